@@ -0,0 +1,650 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::storage::{CheckpointStorage, CheckpointStore};
+use super::{Checkpoint, CheckpointPaths, FileSnapshot, SessionTimeline};
+
+/// Version written to new archives; newer archives are rejected on import
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+
+/// Largest manifest or file list accepted on import
+const METADATA_ENTRY_LIMIT: u64 = 64 * 1024 * 1024;
+
+/// Largest file content accepted on import
+const CONTENT_ENTRY_LIMIT: u64 = 1024 * 1024 * 1024;
+
+/// Largest checkpoint message log accepted on import
+const MESSAGES_ENTRY_LIMIT: u64 = 512 * 1024 * 1024;
+
+/// Which checkpoints an export contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportScope {
+    /// Only the selected checkpoint
+    Checkpoint,
+    /// The selected checkpoint and all of its ancestors
+    Branch,
+}
+
+/// Top-level description of an archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub source_project_id: String,
+    pub source_session_id: String,
+    /// Project directory on the exporting machine, for reference only
+    pub source_project_path: Option<PathBuf>,
+    /// Checkpoints with parents before children; the last one is the head
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+/// File entry of an archived checkpoint; content is stored once per hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchivedFile {
+    path: PathBuf,
+    hash: String,
+    is_deleted: bool,
+    is_binary: bool,
+    permissions: Option<u32>,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+}
+
+/// Result of exporting an archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub archive_path: PathBuf,
+    pub checkpoints: usize,
+    /// Distinct file contents written
+    pub files: usize,
+    /// Size of the archive in bytes
+    pub archive_size: u64,
+}
+
+/// Result of importing an archive as a new session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub project_id: String,
+    pub session_id: String,
+    /// Checkpoint the new session continues from
+    pub head_checkpoint_id: String,
+    pub checkpoints: usize,
+    /// Files written to the project directory; `None` when the head was not restored
+    pub files_restored: Option<usize>,
+}
+
+fn messages_entry(checkpoint_id: &str) -> String {
+    format!("checkpoints/{}/messages.jsonl", checkpoint_id)
+}
+
+fn files_entry(checkpoint_id: &str) -> String {
+    format!("checkpoints/{}/files.json", checkpoint_id)
+}
+
+fn content_entry(hash: &str) -> String {
+    format!("content/{}", hash)
+}
+
+/// Checkpoints to export for `scope`, root first
+fn select_checkpoints(
+    timeline: &SessionTimeline,
+    checkpoint_id: &str,
+    scope: ExportScope,
+) -> Result<Vec<Checkpoint>> {
+    let node = timeline
+        .find_checkpoint(checkpoint_id)
+        .with_context(|| format!("Checkpoint not found: {}", checkpoint_id))?;
+    let mut chain = vec![node.checkpoint.clone()];
+
+    if scope == ExportScope::Branch {
+        while let Some(parent_id) = chain.last().and_then(|c| c.parent_checkpoint_id.clone()) {
+            let Some(parent) = timeline.find_checkpoint(&parent_id) else {
+                break;
+            };
+            chain.push(parent.checkpoint.clone());
+        }
+        chain.reverse();
+    }
+    Ok(chain)
+}
+
+/// Write a checkpoint (or its branch) with messages and file contents to a zip archive
+#[allow(clippy::too_many_arguments)]
+pub fn export_archive(
+    store: &dyn CheckpointStore,
+    timeline: &SessionTimeline,
+    project_id: &str,
+    session_id: &str,
+    project_path: Option<&Path>,
+    checkpoint_id: &str,
+    scope: ExportScope,
+    dest: &Path,
+) -> Result<ExportSummary> {
+    let checkpoints = select_checkpoints(timeline, checkpoint_id, scope)?;
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Zstd);
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).context("Failed to create archive directory")?;
+    }
+    let mut zip = ZipWriter::new(File::create(dest).context("Failed to create archive")?);
+    let mut written_hashes = HashSet::new();
+
+    for checkpoint in &checkpoints {
+        let (_, snapshots, messages) =
+            store.load_checkpoint(project_id, session_id, &checkpoint.id)?;
+
+        zip.start_file(messages_entry(&checkpoint.id), options)?;
+        zip.write_all(messages.as_bytes())?;
+
+        let mut files = Vec::with_capacity(snapshots.len());
+        for snapshot in snapshots {
+            if !snapshot.is_deleted && written_hashes.insert(snapshot.hash.clone()) {
+                zip.start_file(content_entry(&snapshot.hash), options)?;
+                zip.write_all(&snapshot.content)?;
+            }
+            files.push(ArchivedFile {
+                path: snapshot.file_path,
+                hash: snapshot.hash,
+                is_deleted: snapshot.is_deleted,
+                is_binary: snapshot.is_binary,
+                permissions: snapshot.permissions,
+                size: snapshot.size,
+                modified: snapshot.modified,
+            });
+        }
+        zip.start_file(files_entry(&checkpoint.id), options)?;
+        serde_json::to_writer(&mut zip, &files).context("Failed to write file list")?;
+    }
+
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        source_project_id: project_id.to_string(),
+        source_session_id: session_id.to_string(),
+        source_project_path: project_path.map(Path::to_path_buf),
+        checkpoints,
+    };
+    zip.start_file(MANIFEST_ENTRY, options)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).context("Failed to write manifest")?;
+    zip.finish().context("Failed to finish archive")?;
+
+    Ok(ExportSummary {
+        archive_path: dest.to_path_buf(),
+        checkpoints: manifest.checkpoints.len(),
+        files: written_hashes.len(),
+        archive_size: fs::metadata(dest)?.len(),
+    })
+}
+
+/// Read the manifest of an archive without importing it
+pub fn read_manifest(archive_path: &Path) -> Result<ArchiveManifest> {
+    let mut zip = open_archive(archive_path)?;
+    load_manifest(&mut zip)
+}
+
+fn open_archive(archive_path: &Path) -> Result<ZipArchive<File>> {
+    let file = File::open(archive_path).context("Failed to open archive")?;
+    ZipArchive::new(file).context("Not a checkpoint archive")
+}
+
+fn load_manifest(zip: &mut ZipArchive<File>) -> Result<ArchiveManifest> {
+    let manifest: ArchiveManifest =
+        serde_json::from_slice(&read_entry(zip, MANIFEST_ENTRY, METADATA_ENTRY_LIMIT)?)
+            .context("Invalid archive manifest")?;
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        anyhow::bail!(
+            "Archive format version {} is newer than supported version {}",
+            manifest.format_version,
+            ARCHIVE_FORMAT_VERSION
+        );
+    }
+    if manifest.checkpoints.is_empty() {
+        anyhow::bail!("Archive contains no checkpoints");
+    }
+    Ok(manifest)
+}
+
+/// Read an entry, rejecting it once it grows past `limit` bytes
+///
+/// The sizes in the archive headers are not trusted, so nothing is pre-allocated.
+fn read_entry(zip: &mut ZipArchive<File>, name: &str, limit: u64) -> Result<Vec<u8>> {
+    let entry = zip
+        .by_name(name)
+        .with_context(|| format!("Archive entry missing: {}", name))?;
+    let mut content = Vec::new();
+    entry.take(limit + 1).read_to_end(&mut content)?;
+    if content.len() as u64 > limit {
+        anyhow::bail!("Archive entry {} exceeds {} bytes", name, limit);
+    }
+    Ok(content)
+}
+
+/// Reject entries that would escape the project directory or the content pool
+fn validate_file(file: &ArchivedFile) -> Result<()> {
+    let escapes = file
+        .path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes || file.path.as_os_str().is_empty() {
+        anyhow::bail!("Invalid file path in archive: {}", file.path.display());
+    }
+    let valid_hash = file.hash.len() == 64 && file.hash.bytes().all(|b| b.is_ascii_hexdigit());
+    if !file.is_deleted && !valid_hash {
+        anyhow::bail!("Invalid content hash in archive: {}", file.hash);
+    }
+    Ok(())
+}
+
+/// Checkpoint IDs become directory names, so they must be a single plain segment
+fn validate_checkpoint_id(id: &str) -> Result<()> {
+    let valid = !id.is_empty()
+        && id.len() <= 128
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if !valid {
+        anyhow::bail!("Invalid checkpoint ID in archive: {:?}", id);
+    }
+    Ok(())
+}
+
+/// Point a transcript line at the new session and project directory
+fn rebase_message(line: &str, session_id: &str, project_path: &Path) -> String {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(line) else {
+        return line.to_string();
+    };
+    let Some(object) = value.as_object_mut() else {
+        return line.to_string();
+    };
+    if object.contains_key("sessionId") {
+        object.insert("sessionId".to_string(), session_id.into());
+    }
+    if object.contains_key("cwd") {
+        object.insert(
+            "cwd".to_string(),
+            project_path.to_string_lossy().to_string().into(),
+        );
+    }
+    serde_json::to_string(&value).unwrap_or_else(|_| line.to_string())
+}
+
+fn rebase_messages(messages: &str, session_id: &str, project_path: &Path) -> String {
+    messages
+        .lines()
+        .map(|line| rebase_message(line, session_id, project_path))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Import an archive as a new session of the project at `project_path`
+///
+/// Checkpoints keep their IDs and ancestry, and the head checkpoint's messages
+/// become the new session's transcript. Project files are not touched. The
+/// session is assembled in a staging directory and moved into place at the end,
+/// so a failed import leaves nothing behind.
+pub fn import_archive(
+    claude_dir: &Path,
+    archive_path: &Path,
+    project_id: &str,
+    project_path: &Path,
+    session_id: &str,
+) -> Result<ImportSummary> {
+    let mut zip = open_archive(archive_path)?;
+    let manifest = load_manifest(&mut zip)?;
+    for checkpoint in &manifest.checkpoints {
+        validate_checkpoint_id(&checkpoint.id)?;
+    }
+
+    let claude_dir = claude_dir.to_path_buf();
+    let paths = CheckpointPaths::new(&claude_dir, project_id, session_id);
+    let transcript_path = claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(format!("{}.jsonl", session_id));
+    if transcript_path.exists() || paths.timeline_file.exists() {
+        anyhow::bail!("Session already exists: {}", session_id);
+    }
+
+    let staging_id = format!(".import-{}", uuid::Uuid::new_v4());
+    let staging = CheckpointPaths::new(&claude_dir, project_id, &staging_id);
+    let staging_dir = session_dir(&staging)?;
+    let result = stage_import(
+        &mut zip,
+        &manifest,
+        &claude_dir,
+        project_id,
+        project_path,
+        session_id,
+        &staging_id,
+    )
+    .and_then(|head_messages| {
+        fs::rename(&staging_dir, session_dir(&paths)?)
+            .context("Failed to move imported session into place")?;
+        if let Err(e) = fs::write(&transcript_path, head_messages) {
+            let _ = fs::remove_dir_all(session_dir(&paths)?);
+            return Err(e).context("Failed to write session transcript");
+        }
+        Ok(())
+    });
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&staging_dir);
+    }
+    result?;
+
+    Ok(ImportSummary {
+        project_id: project_id.to_string(),
+        session_id: session_id.to_string(),
+        head_checkpoint_id: manifest
+            .checkpoints
+            .last()
+            .map(|c| c.id.clone())
+            .unwrap_or_default(),
+        checkpoints: manifest.checkpoints.len(),
+        files_restored: None,
+    })
+}
+
+fn session_dir(paths: &CheckpointPaths) -> Result<PathBuf> {
+    paths
+        .timeline_file
+        .parent()
+        .map(Path::to_path_buf)
+        .context("Invalid session directory")
+}
+
+/// Write the archived checkpoints under the staging session, returning the transcript
+fn stage_import(
+    zip: &mut ZipArchive<File>,
+    manifest: &ArchiveManifest,
+    claude_dir: &Path,
+    project_id: &str,
+    project_path: &Path,
+    session_id: &str,
+    staging_id: &str,
+) -> Result<String> {
+    let storage = CheckpointStorage::new(claude_dir.to_path_buf());
+    let staging = CheckpointPaths::new(&claude_dir.to_path_buf(), project_id, staging_id);
+    // The timeline is created up front so it carries the final session ID
+    fs::create_dir_all(session_dir(&staging)?)?;
+    storage.save_timeline(
+        &staging.timeline_file,
+        &SessionTimeline::new(session_id.to_string()),
+    )?;
+    storage.init_storage(project_id, staging_id)?;
+
+    let imported: HashSet<&str> = manifest.checkpoints.iter().map(|c| c.id.as_str()).collect();
+    let mut head_messages = String::new();
+    for archived in &manifest.checkpoints {
+        let files: Vec<ArchivedFile> = serde_json::from_slice(&read_entry(
+            zip,
+            &files_entry(&archived.id),
+            METADATA_ENTRY_LIMIT,
+        )?)
+        .context("Invalid file list")?;
+        let mut snapshots = Vec::with_capacity(files.len());
+        for file in files {
+            validate_file(&file)?;
+            let content = if file.is_deleted {
+                Vec::new()
+            } else {
+                let content = read_entry(zip, &content_entry(&file.hash), CONTENT_ENTRY_LIMIT)?;
+                if CheckpointStorage::calculate_file_hash(&content) != file.hash {
+                    anyhow::bail!("Content of {} is corrupted", file.path.display());
+                }
+                content
+            };
+            snapshots.push(FileSnapshot {
+                checkpoint_id: archived.id.clone(),
+                file_path: file.path,
+                content,
+                is_binary: file.is_binary,
+                hash: file.hash,
+                is_deleted: file.is_deleted,
+                permissions: file.permissions,
+                size: file.size,
+                modified: file.modified,
+                reused: false,
+            });
+        }
+
+        let messages = String::from_utf8(read_entry(
+            zip,
+            &messages_entry(&archived.id),
+            MESSAGES_ENTRY_LIMIT,
+        )?)
+        .context("Invalid UTF-8 in messages")?;
+        let messages = rebase_messages(&messages, session_id, project_path);
+
+        let mut checkpoint = archived.clone();
+        checkpoint.session_id = session_id.to_string();
+        checkpoint.project_id = project_id.to_string();
+        checkpoint.parent_checkpoint_id = checkpoint
+            .parent_checkpoint_id
+            .filter(|parent| imported.contains(parent.as_str()));
+        storage.save_checkpoint(project_id, staging_id, &checkpoint, snapshots, &messages)?;
+        head_messages = messages;
+    }
+
+    if !head_messages.is_empty() {
+        head_messages.push('\n');
+    }
+    Ok(head_messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::manager::CheckpointManager;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn exported_branch_imports_as_new_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        let manager = CheckpointManager::new(
+            "source-project".to_string(),
+            "source-session".to_string(),
+            project.clone(),
+            temp_dir.path().join("claude"),
+        )
+        .await
+        .unwrap();
+        manager
+            .track_message(
+                r#"{"type":"user","sessionId":"source-session","cwd":"/old/path","message":{"content":"hi"}}"#
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+
+        fs::write(project.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(project.join("logo.bin"), b"\0\x01\x02").unwrap();
+        manager.create_checkpoint(None, None).await.unwrap();
+        fs::write(project.join("src/main.rs"), "fn main() { run() }\n").unwrap();
+        fs::remove_file(project.join("logo.bin")).unwrap();
+        let head = manager
+            .create_checkpoint(Some("before the build broke".to_string()), None)
+            .await
+            .unwrap()
+            .checkpoint;
+
+        let archive_path = temp_dir.path().join("export/state.zip");
+        let timeline = manager.get_timeline().await;
+        let store = manager.store();
+        let export = |scope| {
+            export_archive(
+                store.as_ref(),
+                &timeline,
+                "source-project",
+                "source-session",
+                Some(&project),
+                &head.id,
+                scope,
+                &archive_path,
+            )
+            .unwrap()
+        };
+        assert_eq!(export(ExportScope::Checkpoint).checkpoints, 1);
+        let summary = export(ExportScope::Branch);
+        assert_eq!(summary.checkpoints, 2);
+        assert_eq!(summary.files, 3);
+
+        let other_claude = temp_dir.path().join("other-claude");
+        let target = PathBuf::from("/home/colleague/project");
+        let imported = import_archive(
+            &other_claude,
+            &archive_path,
+            "-home-colleague-project",
+            &target,
+            "new-session",
+        )
+        .unwrap();
+        assert_eq!(imported.head_checkpoint_id, head.id);
+        assert_eq!(imported.checkpoints, 2);
+
+        let storage = CheckpointStorage::new(other_claude.clone());
+        let paths = CheckpointPaths::new(&other_claude, "-home-colleague-project", "new-session");
+        let timeline = storage.load_timeline(&paths.timeline_file).unwrap();
+        assert_eq!(timeline.total_checkpoints, 2);
+        assert_eq!(
+            timeline.current_checkpoint_id.as_deref(),
+            Some(head.id.as_str())
+        );
+
+        let (checkpoint, snapshots, messages) = storage
+            .load_checkpoint("-home-colleague-project", "new-session", &head.id)
+            .unwrap();
+        assert_eq!(checkpoint.session_id, "new-session");
+        assert_eq!(
+            checkpoint.description.as_deref(),
+            Some("before the build broke")
+        );
+        let main = snapshots
+            .iter()
+            .find(|s| s.file_path == Path::new("src/main.rs"))
+            .unwrap();
+        assert_eq!(main.content, b"fn main() { run() }\n");
+        assert!(snapshots
+            .iter()
+            .any(|s| s.file_path == Path::new("logo.bin") && s.is_deleted));
+        assert!(messages.contains(r#""sessionId":"new-session""#));
+        assert!(messages.contains(r#""cwd":"/home/colleague/project""#));
+
+        let transcript = other_claude.join("projects/-home-colleague-project/new-session.jsonl");
+        assert_eq!(
+            fs::read_to_string(transcript).unwrap(),
+            format!("{}\n", messages)
+        );
+
+        assert!(validate_file(&ArchivedFile {
+            path: PathBuf::from("../outside.txt"),
+            hash: "0".repeat(64),
+            is_deleted: false,
+            is_binary: false,
+            permissions: None,
+            size: 0,
+            modified: None,
+        })
+        .is_err());
+
+        // Importing into an existing session is refused
+        assert!(import_archive(
+            &other_claude,
+            &archive_path,
+            "-home-colleague-project",
+            &target,
+            "new-session",
+        )
+        .is_err());
+
+        // A checkpoint ID that escapes the session directory is rejected before anything is written
+        let mut evil = head.clone();
+        evil.id = "../../../escaped".to_string();
+        let evil_path = temp_dir.path().join("export/evil.zip");
+        let mut zip = ZipWriter::new(File::create(&evil_path).unwrap());
+        zip.start_file(MANIFEST_ENTRY, SimpleFileOptions::default())
+            .unwrap();
+        let manifest = ArchiveManifest {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            source_project_id: "source-project".to_string(),
+            source_session_id: "source-session".to_string(),
+            source_project_path: None,
+            checkpoints: vec![evil],
+        };
+        zip.write_all(&serde_json::to_vec(&manifest).unwrap())
+            .unwrap();
+        zip.finish().unwrap();
+        assert!(import_archive(&other_claude, &evil_path, "p", &target, "evil-session").is_err());
+        assert!(!other_claude.join("projects/p").exists());
+
+        // A failed import leaves no partial session behind, so it can be retried
+        assert!(import_archive(
+            &other_claude,
+            &temp_dir.path().join("export/missing.zip"),
+            "-home-colleague-project",
+            &target,
+            "retry-session",
+        )
+        .is_err());
+        let mut broken = head.clone();
+        broken.id = "missing-entries".to_string();
+        let broken_path = temp_dir.path().join("export/broken.zip");
+        let mut zip = ZipWriter::new(File::create(&broken_path).unwrap());
+        zip.start_file(MANIFEST_ENTRY, SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(
+            &serde_json::to_vec(&ArchiveManifest {
+                checkpoints: vec![broken],
+                ..manifest
+            })
+            .unwrap(),
+        )
+        .unwrap();
+        zip.finish().unwrap();
+        assert!(import_archive(
+            &other_claude,
+            &broken_path,
+            "-home-colleague-project",
+            &target,
+            "retry-session",
+        )
+        .is_err());
+        let timelines = other_claude.join("projects/-home-colleague-project/.timelines");
+        let leftovers: Vec<_> = fs::read_dir(&timelines)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers, vec![std::ffi::OsString::from("new-session")]);
+    }
+
+    #[test]
+    fn oversized_entries_are_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("big.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("entry", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(&[b'x'; 4096]).unwrap();
+        zip.finish().unwrap();
+
+        let mut zip = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(read_entry(&mut zip, "entry", 4096).unwrap().len(), 4096);
+        assert!(read_entry(&mut zip, "entry", 4095).is_err());
+    }
+}
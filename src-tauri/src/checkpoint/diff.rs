@@ -0,0 +1,492 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{
+    ignore, storage::CheckpointStorage, CheckpointDiff, CheckpointFileLimits, DiffHunk, FileDiff,
+    FileSnapshot,
+};
+
+/// Unchanged lines shown around each change
+const CONTEXT_LINES: usize = 3;
+
+/// Edit distance beyond which a file is diffed as a full replacement
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Split text into lines, keeping line terminators so hunks apply byte-for-byte
+fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Shortest edit script between `a` and `b` (Myers' O(ND) algorithm)
+fn edit_script(a: &[&str], b: &[&str]) -> Vec<Op> {
+    // Common prefix and suffix never take part in the search
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops = vec![Op::Equal; prefix];
+    ops.extend(myers_middle(a_mid, b_mid));
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    ops
+}
+
+fn myers_middle(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let limit = max.min(MAX_EDIT_DISTANCE);
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // trace[d] holds v[-d..=d] as it was before step d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=limit as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let idx = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                return backtrack(&trace, n, m);
+            }
+            k += 2;
+        }
+    }
+
+    // Too many edits to search: replace the whole block
+    let mut ops = vec![Op::Delete; a.len()];
+    ops.extend(std::iter::repeat_n(Op::Insert, b.len()));
+    ops
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Op> {
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for d in (1..trace.len() as isize).rev() {
+        let v = &trace[d as usize];
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        ops.push(if prev_k == k + 1 {
+            Op::Insert
+        } else {
+            Op::Delete
+        });
+        x = prev_x;
+        y = prev_y;
+    }
+    while x > 0 && y > 0 {
+        ops.push(Op::Equal);
+        x -= 1;
+        y -= 1;
+    }
+
+    ops.reverse();
+    ops
+}
+
+/// Line-level hunks turning `old` into `new`
+pub fn diff_text(old: &str, new: &str) -> Vec<DiffHunk> {
+    let (a, b) = (split_lines(old), split_lines(new));
+    let ops = edit_script(&a, &b);
+
+    // Position in both files before each op
+    let mut entries = Vec::with_capacity(ops.len());
+    let (mut i, mut j) = (0usize, 0usize);
+    for op in ops {
+        entries.push((op, i, j));
+        match op {
+            Op::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+
+    let changes: Vec<usize> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _, _))| *op != Op::Equal)
+        .map(|(idx, _)| idx)
+        .collect();
+
+    let mut hunks = Vec::new();
+    let mut c = 0;
+    while c < changes.len() {
+        let start = changes[c].saturating_sub(CONTEXT_LINES);
+        let mut end = changes[c];
+        // Merge changes whose context would overlap
+        while c + 1 < changes.len() && changes[c + 1] <= end + 2 * CONTEXT_LINES + 1 {
+            c += 1;
+            end = changes[c];
+        }
+        let stop = (end + CONTEXT_LINES + 1).min(entries.len());
+
+        let mut hunk = DiffHunk {
+            old_start: 0,
+            old_lines: 0,
+            new_start: 0,
+            new_lines: 0,
+            lines: Vec::new(),
+        };
+        for &(op, i, j) in &entries[start..stop] {
+            match op {
+                Op::Equal => {
+                    hunk.old_lines += 1;
+                    hunk.new_lines += 1;
+                    hunk.lines.push(format!(" {}", a[i]));
+                }
+                Op::Delete => {
+                    hunk.old_lines += 1;
+                    hunk.lines.push(format!("-{}", a[i]));
+                }
+                Op::Insert => {
+                    hunk.new_lines += 1;
+                    hunk.lines.push(format!("+{}", b[j]));
+                }
+            }
+        }
+        // Unified diff convention: an empty side starts at the line before it
+        let (_, first_i, first_j) = entries[start];
+        hunk.old_start = first_i + usize::from(hunk.old_lines > 0);
+        hunk.new_start = first_j + usize::from(hunk.new_lines > 0);
+        hunks.push(hunk);
+        c += 1;
+    }
+
+    hunks
+}
+
+/// Number of added and deleted lines across hunks
+pub fn count_changes(hunks: &[DiffHunk]) -> (usize, usize) {
+    hunks
+        .iter()
+        .flat_map(|h| &h.lines)
+        .fold((0, 0), |(add, del), line| match line.as_bytes().first() {
+            Some(b'+') => (add + 1, del),
+            Some(b'-') => (add, del + 1),
+            _ => (add, del),
+        })
+}
+
+/// Render hunks as a unified diff with `a/` and `b/` headers
+pub fn render_unified(
+    path: &Path,
+    old_exists: bool,
+    new_exists: bool,
+    hunks: &[DiffHunk],
+) -> String {
+    let display = path.to_string_lossy().replace('\\', "/");
+    let old_header = if old_exists {
+        format!("a/{}", display)
+    } else {
+        "/dev/null".to_string()
+    };
+    let new_header = if new_exists {
+        format!("b/{}", display)
+    } else {
+        "/dev/null".to_string()
+    };
+
+    let mut out = format!("--- {}\n+++ {}\n", old_header, new_header);
+    for hunk in hunks {
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+        ));
+        for line in &hunk.lines {
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// Apply a subset of the hunks produced by [`diff_text`] for `old`
+pub fn apply_hunks(old: &str, hunks: &[&DiffHunk]) -> Result<String> {
+    let lines = split_lines(old);
+    let mut sorted = hunks.to_vec();
+    sorted.sort_by_key(|h| h.old_start);
+
+    let mut out = String::with_capacity(old.len());
+    let mut cursor = 0usize;
+    for hunk in sorted {
+        let start = if hunk.old_lines == 0 {
+            hunk.old_start
+        } else {
+            hunk.old_start - 1
+        };
+        if start < cursor || start + hunk.old_lines > lines.len() {
+            anyhow::bail!(
+                "Hunk @@ -{},{} does not apply",
+                hunk.old_start,
+                hunk.old_lines
+            );
+        }
+        out.extend(lines[cursor..start].iter().copied());
+
+        let mut pos = start;
+        for line in &hunk.lines {
+            let (marker, text) = line.split_at(1);
+            match marker {
+                " " | "-" => {
+                    if lines.get(pos) != Some(&text) {
+                        anyhow::bail!(
+                            "Hunk @@ -{},{} does not match line {}",
+                            hunk.old_start,
+                            hunk.old_lines,
+                            pos + 1
+                        );
+                    }
+                    if marker == " " {
+                        out.push_str(text);
+                    }
+                    pos += 1;
+                }
+                "+" => out.push_str(text),
+                _ => anyhow::bail!("Invalid hunk line: {:?}", line),
+            }
+        }
+        cursor = pos;
+    }
+    out.extend(lines[cursor..].iter().copied());
+
+    Ok(out)
+}
+
+/// The hunk undoing `hunk`: sides swapped, additions and deletions exchanged
+pub fn reverse_hunk(hunk: &DiffHunk) -> DiffHunk {
+    DiffHunk {
+        old_start: hunk.new_start,
+        old_lines: hunk.new_lines,
+        new_start: hunk.old_start,
+        new_lines: hunk.old_lines,
+        lines: hunk
+            .lines
+            .iter()
+            .map(|line| match line.split_at(1) {
+                ("+", text) => format!("-{}", text),
+                ("-", text) => format!("+{}", text),
+                _ => line.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Diff a single file given the content on each side (`None` if absent)
+pub fn diff_file(path: &Path, old: Option<&[u8]>, new: Option<&[u8]>) -> FileDiff {
+    let is_binary = old.is_some_and(ignore::is_binary) || new.is_some_and(ignore::is_binary);
+    if is_binary {
+        return FileDiff {
+            path: path.to_path_buf(),
+            additions: 0,
+            deletions: 0,
+            diff_content: None,
+            is_binary: true,
+            hunks: Vec::new(),
+        };
+    }
+
+    // Non-binary content is valid UTF-8
+    let as_text = |bytes: Option<&[u8]>| {
+        bytes
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+            .unwrap_or_default()
+    };
+    let hunks = diff_text(&as_text(old), &as_text(new));
+    let (additions, deletions) = count_changes(&hunks);
+
+    FileDiff {
+        path: path.to_path_buf(),
+        additions,
+        deletions,
+        diff_content: (!hunks.is_empty())
+            .then(|| render_unified(path, old.is_some(), new.is_some(), &hunks)),
+        is_binary: false,
+        hunks,
+    }
+}
+
+/// Compare two sets of file snapshots
+///
+/// Snapshots marked as deleted count as absent. `to_checkpoint_id` is `None`
+/// when `to` was read from the working tree.
+pub fn diff_snapshots(
+    from_checkpoint_id: String,
+    to_checkpoint_id: Option<String>,
+    from: &[FileSnapshot],
+    to: &[FileSnapshot],
+    token_delta: i64,
+) -> CheckpointDiff {
+    let (from_map, to_map) = (present_files(from), present_files(to));
+
+    let mut modified_files = Vec::new();
+    let mut deleted_files = Vec::new();
+    for (path, from_file) in &from_map {
+        match to_map.get(path) {
+            Some(to_file) if to_file.hash != from_file.hash => modified_files.push(diff_file(
+                path,
+                Some(&from_file.content),
+                Some(&to_file.content),
+            )),
+            Some(_) => {}
+            None => deleted_files.push(path.clone()),
+        }
+    }
+    let added_files = to_map
+        .keys()
+        .filter(|path| !from_map.contains_key(*path))
+        .cloned()
+        .collect();
+
+    CheckpointDiff {
+        from_checkpoint_id,
+        to_checkpoint_id,
+        modified_files,
+        added_files,
+        deleted_files,
+        token_delta,
+    }
+}
+
+/// Non-deleted snapshots keyed by path
+fn present_files(files: &[FileSnapshot]) -> BTreeMap<PathBuf, &FileSnapshot> {
+    files
+        .iter()
+        .filter(|f| !f.is_deleted)
+        .map(|f| (f.file_path.clone(), f))
+        .collect()
+}
+
+/// Snapshot the current working tree with the same ignore rules and limits as checkpoints
+pub fn working_tree_snapshots(
+    project_path: &Path,
+    limits: &CheckpointFileLimits,
+) -> Result<Vec<FileSnapshot>> {
+    let scan = ignore::scan_project(project_path, limits)?;
+    let mut snapshots = Vec::with_capacity(scan.files.len());
+    for file in scan.files {
+        let content = fs::read(project_path.join(&file.path))?;
+        snapshots.push(FileSnapshot {
+            checkpoint_id: String::new(),
+            is_binary: ignore::is_binary(&content),
+            hash: CheckpointStorage::calculate_file_hash(&content),
+            file_path: file.path,
+            content,
+            is_deleted: false,
+            permissions: file.permissions,
+            size: file.size,
+            modified: Some(file.modified),
+            reused: false,
+        });
+    }
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_all(old: &str, new: &str) -> String {
+        let hunks = diff_text(old, new);
+        apply_hunks(old, &hunks.iter().collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn test_diff_and_apply_roundtrip() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn";
+        let hunks = diff_text(old, new);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(
+            (
+                hunks[0].old_start,
+                hunks[0].old_lines,
+                hunks[0].new_start,
+                hunks[0].new_lines
+            ),
+            (1, 5, 1, 5)
+        );
+        assert_eq!(count_changes(&hunks), (2, 1));
+        assert_eq!(apply_all(old, new), new);
+
+        // Applying only the second hunk keeps the first change out
+        let partial = apply_hunks(old, &[&hunks[1]]).unwrap();
+        assert_eq!(partial, "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn");
+
+        for (old, new) in [("", "x\ny\n"), ("x\ny\n", ""), ("x\r\ny\r\n", "x\r\nz\r\n")] {
+            assert_eq!(apply_all(old, new), new);
+        }
+    }
+
+    #[test]
+    fn test_render_unified() {
+        let hunks = diff_text("one\ntwo\n", "one\nthree");
+        let rendered = render_unified(Path::new("src/a.txt"), true, true, &hunks);
+        assert_eq!(
+            rendered,
+            "--- a/src/a.txt\n+++ b/src/a.txt\n@@ -1,2 +1,2 @@\n one\n-two\n+three\n\\ No newline at end of file\n"
+        );
+
+        let added = render_unified(Path::new("n.txt"), false, true, &diff_text("", "x\n"));
+        assert!(added.starts_with("--- /dev/null\n+++ b/n.txt\n@@ -0,0 +1,1 @@\n"));
+    }
+
+    #[test]
+    fn test_binary_files_have_no_hunks() {
+        let diff = diff_file(
+            Path::new("logo.png"),
+            Some(b"\x89PNG\0"),
+            Some(b"\x89PNG\0\x01"),
+        );
+        assert!(diff.is_binary);
+        assert!(diff.hunks.is_empty());
+        assert!(diff.diff_content.is_none());
+    }
+
+    #[test]
+    fn test_stale_hunk_is_rejected() {
+        let hunks = diff_text("a\nb\n", "a\nc\n");
+        assert!(apply_hunks("a\nx\n", &[&hunks[0]]).is_err());
+    }
+}
@@ -0,0 +1,701 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use super::{
+    storage::{CheckpointStorage, CheckpointStore, GcStats},
+    Checkpoint, CheckpointPaths, CheckpointResult, FileSnapshot,
+};
+
+/// Namespace of the hidden refs holding checkpoint commits
+pub const REF_PREFIX: &str = "refs/ccswitch/checkpoints";
+
+/// Per-checkpoint record mapping the checkpoint onto its commit
+const GIT_RECORD_FILE: &str = "git.json";
+
+/// Identity used for checkpoint commits, independent of the user's git config
+const COMMIT_NAME: &str = "cc-switch";
+const COMMIT_EMAIL: &str = "cc-switch@localhost";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GitRecord {
+    /// Commit holding the checkpoint's file tree
+    commit: String,
+    /// Manifest with the metadata git does not track (hashes, exact modes, mtimes)
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestEntry {
+    path: PathBuf,
+    hash: String,
+    is_deleted: bool,
+    is_binary: bool,
+    permissions: Option<u32>,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+}
+
+impl ManifestEntry {
+    fn from_snapshot(snapshot: &FileSnapshot) -> Self {
+        Self {
+            path: snapshot.file_path.clone(),
+            hash: snapshot.hash.clone(),
+            is_deleted: snapshot.is_deleted,
+            is_binary: snapshot.is_binary,
+            permissions: snapshot.permissions,
+            size: snapshot.size,
+            modified: snapshot.modified,
+        }
+    }
+
+    fn into_snapshot(self, checkpoint_id: &str, content: Vec<u8>) -> FileSnapshot {
+        FileSnapshot {
+            checkpoint_id: checkpoint_id.to_string(),
+            file_path: self.path,
+            content,
+            is_binary: self.is_binary,
+            hash: self.hash,
+            is_deleted: self.is_deleted,
+            permissions: self.permissions,
+            size: self.size,
+            modified: self.modified,
+            reused: false,
+        }
+    }
+}
+
+/// Stores checkpoint file trees as commits in the project's git repository
+///
+/// Each checkpoint becomes a commit on `refs/ccswitch/checkpoints/<session>/<checkpoint>`,
+/// parented on its parent checkpoint's commit, so forks map onto git history and can be
+/// inspected with normal git tooling. Trees are staged in a throwaway index, leaving the
+/// working branch, `HEAD` and the user's index untouched. Metadata, messages and the
+/// timeline stay under the Claude directory like the file-based store.
+pub struct GitCheckpointStorage {
+    storage: Arc<CheckpointStorage>,
+    repo_path: PathBuf,
+}
+
+impl GitCheckpointStorage {
+    /// Create a git-backed store for the repository containing `repo_path`
+    pub fn new(storage: Arc<CheckpointStorage>, repo_path: PathBuf) -> Result<Self> {
+        let store = Self { storage, repo_path };
+        store
+            .git(&["rev-parse", "--git-dir"], None)
+            .with_context(|| format!("{} is not a git repository", store.repo_path.display()))?;
+        Ok(store)
+    }
+
+    /// Hidden ref holding a checkpoint's commit
+    pub fn checkpoint_ref(session_id: &str, checkpoint_id: &str) -> String {
+        format!("{}/{}/{}", REF_PREFIX, session_id, checkpoint_id)
+    }
+
+    fn command(&self, index: Option<&Path>) -> Command {
+        let mut cmd = Command::new("git");
+        cmd.arg("-C")
+            .arg(&self.repo_path)
+            .env("GIT_AUTHOR_NAME", COMMIT_NAME)
+            .env("GIT_AUTHOR_EMAIL", COMMIT_EMAIL)
+            .env("GIT_COMMITTER_NAME", COMMIT_NAME)
+            .env("GIT_COMMITTER_EMAIL", COMMIT_EMAIL);
+        if let Some(index) = index {
+            cmd.env("GIT_INDEX_FILE", index);
+        }
+        cmd
+    }
+
+    fn git(&self, args: &[&str], index: Option<&Path>) -> Result<String> {
+        self.git_with_input(args, index, None)
+    }
+
+    fn git_with_input(
+        &self,
+        args: &[&str],
+        index: Option<&Path>,
+        input: Option<&[u8]>,
+    ) -> Result<String> {
+        let mut child = self
+            .command(index)
+            .args(args)
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run git")?;
+
+        // Write stdin from a separate thread: commands like `hash-object --stdin-paths`
+        // answer line by line and would block on a full stdout pipe otherwise
+        let writer = match (input, child.stdin.take()) {
+            (Some(input), Some(mut stdin)) => {
+                let input = input.to_vec();
+                Some(std::thread::spawn(move || stdin.write_all(&input)))
+            }
+            _ => None,
+        };
+
+        let output = child.wait_with_output().context("Failed to wait for git")?;
+        if let Some(writer) = writer {
+            writer
+                .join()
+                .map_err(|_| anyhow::anyhow!("git stdin writer panicked"))?
+                .context("Failed to write to git stdin")?;
+        }
+        if !output.status.success() {
+            anyhow::bail!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn record_path(paths: &CheckpointPaths, checkpoint_id: &str) -> PathBuf {
+        paths.checkpoint_dir(checkpoint_id).join(GIT_RECORD_FILE)
+    }
+
+    fn load_record(&self, paths: &CheckpointPaths, checkpoint_id: &str) -> Result<GitRecord> {
+        let json = fs::read_to_string(Self::record_path(paths, checkpoint_id))
+            .context("Failed to read git checkpoint record")?;
+        serde_json::from_str(&json).context("Failed to parse git checkpoint record")
+    }
+
+    /// Stage `snapshots` on top of the parent tree and commit the result
+    fn write_commit(
+        &self,
+        index: &Path,
+        checkpoint: &Checkpoint,
+        snapshots: &[FileSnapshot],
+        parent_commit: Option<&str>,
+    ) -> Result<(String, Vec<String>)> {
+        let blob_dir = index.with_file_name("git-blobs");
+        let index = Some(index);
+        match parent_commit {
+            Some(commit) => self.git(&["read-tree", commit], index)?,
+            None => self.git(&["read-tree", "--empty"], index)?,
+        };
+
+        // Drop paths that are no longer part of the manifest
+        let wanted: HashSet<String> = snapshots
+            .iter()
+            .filter(|s| !s.is_deleted)
+            .map(|s| tree_path(&s.file_path))
+            .collect();
+        let staged_output = self.git(&["ls-files", "-z"], index)?;
+        let staged: HashSet<&str> = staged_output
+            .split('\0')
+            .filter(|p| !p.is_empty())
+            .collect();
+        let stale: Vec<&str> = staged
+            .iter()
+            .copied()
+            .filter(|p| !wanted.contains(*p))
+            .collect();
+        if !stale.is_empty() {
+            let input = format!("{}\0", stale.join("\0"));
+            self.git_with_input(
+                &["update-index", "--force-remove", "-z", "--stdin"],
+                index,
+                Some(input.as_bytes()),
+            )?;
+        }
+
+        // Write blobs for changed files with a single `git hash-object`; reused files
+        // keep the parent's tree entry
+        let mut warnings = Vec::new();
+        let mut pending: Vec<(String, &str, PathBuf)> = Vec::new();
+        fs::create_dir_all(&blob_dir).context("Failed to create blob staging directory")?;
+        for snapshot in snapshots.iter().filter(|s| !s.is_deleted) {
+            let path = tree_path(&snapshot.file_path);
+            if snapshot.reused && staged.contains(path.as_str()) {
+                continue;
+            }
+
+            // The parent of a reused file lives in another store, so the unchanged file is
+            // hashed on disk; everything else goes through a staged copy, as does any path
+            // that can't be passed on the line-based `--stdin-paths` input
+            let on_disk = self.repo_path.join(&snapshot.file_path);
+            let source = if snapshot.reused
+                && on_disk.is_file()
+                && on_disk.to_str().is_some_and(|p| !p.contains('\n'))
+            {
+                on_disk
+            } else {
+                let content = if snapshot.reused {
+                    fs::read(&on_disk).map(Cow::Owned)
+                } else {
+                    Ok(Cow::Borrowed(snapshot.content.as_slice()))
+                };
+                let copy = blob_dir.join(pending.len().to_string());
+                if let Err(e) = content.and_then(|content| fs::write(&copy, content)) {
+                    warnings.push(format!("Failed to save {}: {}", path, e));
+                    continue;
+                }
+                copy
+            };
+
+            let executable = snapshot.permissions.is_some_and(|m| m & 0o111 != 0);
+            let mode = if executable { "100755" } else { "100644" };
+            pending.push((path, mode, source));
+        }
+
+        let oids = self.hash_objects(pending.iter().map(|(_, _, source)| source.as_path()));
+        let _ = fs::remove_dir_all(&blob_dir);
+        let mut index_info = String::new();
+        for ((path, mode, _), oid) in pending.iter().zip(oids?) {
+            index_info.push_str(&format!("{} {}\t{}\0", mode, oid, path));
+        }
+        if !index_info.is_empty() {
+            self.git_with_input(
+                &["update-index", "--add", "-z", "--index-info"],
+                index,
+                Some(index_info.as_bytes()),
+            )?;
+        }
+
+        let tree = self.git(&["write-tree"], index)?;
+        let summary = checkpoint
+            .description
+            .as_deref()
+            .or_else(|| checkpoint.metadata.user_prompt.lines().next())
+            .unwrap_or_default();
+        let message = format!("Checkpoint {}\n\n{}", checkpoint.id, summary);
+        let mut args = vec!["commit-tree", tree.as_str(), "-m", message.as_str()];
+        if let Some(parent) = parent_commit {
+            args.extend(["-p", parent]);
+        }
+        let commit = self.git(&args, None)?;
+
+        Ok((commit, warnings))
+    }
+
+    /// Write the files at `sources` as blobs with one `git hash-object --stdin-paths`
+    fn hash_objects<'a>(&self, sources: impl Iterator<Item = &'a Path>) -> Result<Vec<String>> {
+        let mut input = String::new();
+        for source in sources {
+            input.push_str(&source.to_string_lossy());
+            input.push('\n');
+        }
+        if input.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let expected = input.lines().count();
+        let output = self.git_with_input(
+            &["hash-object", "-w", "--no-filters", "--stdin-paths"],
+            None,
+            Some(input.as_bytes()),
+        )?;
+        let oids: Vec<String> = output.lines().map(str::to_string).collect();
+        if oids.len() != expected {
+            anyhow::bail!(
+                "git hash-object returned {} ids for {} files",
+                oids.len(),
+                expected
+            );
+        }
+        Ok(oids)
+    }
+
+    /// Read blob contents for `<commit>:<path>` specs with a single `git cat-file --batch`
+    fn read_blobs(&self, specs: Vec<String>) -> Result<Vec<Vec<u8>>> {
+        if specs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut child = self
+            .command(None)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to run git cat-file")?;
+
+        // Feed requests from a separate thread so large outputs cannot deadlock the pipes
+        let mut stdin = child.stdin.take().context("Missing git stdin")?;
+        let count = specs.len();
+        let writer = std::thread::spawn(move || -> std::io::Result<()> {
+            for spec in specs {
+                writeln!(stdin, "{}", spec)?;
+            }
+            Ok(())
+        });
+
+        let stdout = child.stdout.take().context("Missing git stdout")?;
+        let mut reader = BufReader::new(stdout);
+        let mut blobs = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut header = String::new();
+            reader
+                .read_line(&mut header)
+                .context("Failed to read git cat-file header")?;
+            let size = header
+                .trim_end()
+                .rsplit(' ')
+                .next()
+                .and_then(|s| s.parse::<usize>().ok());
+            match size {
+                Some(size) if !header.ends_with("missing\n") => {
+                    let mut content = vec![0u8; size];
+                    reader
+                        .read_exact(&mut content)
+                        .context("Failed to read git blob")?;
+                    // Each blob is followed by a newline
+                    let mut newline = [0u8; 1];
+                    reader.read_exact(&mut newline)?;
+                    blobs.push(content);
+                }
+                _ => {
+                    log::warn!("Checkpoint blob missing: {}", header.trim_end());
+                    blobs.push(Vec::new());
+                }
+            }
+        }
+
+        let _ = writer.join();
+        let _ = child.wait();
+        Ok(blobs)
+    }
+}
+
+impl CheckpointStore for GitCheckpointStorage {
+    fn save_checkpoint(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint: &Checkpoint,
+        file_snapshots: Vec<FileSnapshot>,
+        messages: &str,
+    ) -> Result<CheckpointResult> {
+        let paths = CheckpointPaths::new(&self.storage.claude_dir, project_id, session_id);
+        self.storage
+            .save_checkpoint_record(&paths, checkpoint, messages)?;
+
+        let parent_commit = checkpoint
+            .parent_checkpoint_id
+            .as_deref()
+            .and_then(|id| self.load_record(&paths, id).ok())
+            .map(|record| record.commit);
+
+        // Stage the tree in a throwaway index so the user's index stays untouched
+        let index = paths.checkpoint_dir(&checkpoint.id).join("git-index");
+        let written = self.write_commit(
+            &index,
+            checkpoint,
+            &file_snapshots,
+            parent_commit.as_deref(),
+        );
+        let _ = fs::remove_file(&index);
+        let (commit, warnings) = written?;
+
+        self.git(
+            &[
+                "update-ref",
+                &Self::checkpoint_ref(session_id, &checkpoint.id),
+                &commit,
+            ],
+            None,
+        )?;
+
+        let record = GitRecord {
+            commit,
+            files: file_snapshots
+                .iter()
+                .map(ManifestEntry::from_snapshot)
+                .collect(),
+        };
+        fs::write(
+            Self::record_path(&paths, &checkpoint.id),
+            serde_json::to_string(&record)?,
+        )
+        .context("Failed to write git checkpoint record")?;
+
+        self.storage.update_timeline_with_checkpoint(
+            &paths.timeline_file,
+            checkpoint,
+            &file_snapshots,
+        )?;
+
+        Ok(CheckpointResult {
+            checkpoint: checkpoint.clone(),
+            files_processed: file_snapshots.len().saturating_sub(warnings.len()),
+            warnings,
+        })
+    }
+
+    fn load_checkpoint(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<(Checkpoint, Vec<FileSnapshot>, String)> {
+        let paths = CheckpointPaths::new(&self.storage.claude_dir, project_id, session_id);
+        let (checkpoint, messages) = self.storage.load_checkpoint_record(&paths, checkpoint_id)?;
+        let record = self.load_record(&paths, checkpoint_id)?;
+
+        let specs = record
+            .files
+            .iter()
+            .filter(|f| !f.is_deleted)
+            .map(|f| format!("{}:{}", record.commit, tree_path(&f.path)))
+            .collect();
+        let mut blobs = self.read_blobs(specs)?.into_iter();
+
+        let snapshots = record
+            .files
+            .into_iter()
+            .map(|entry| {
+                let content = if entry.is_deleted {
+                    Vec::new()
+                } else {
+                    blobs.next().unwrap_or_default()
+                };
+                entry.into_snapshot(checkpoint_id, content)
+            })
+            .collect();
+
+        Ok((checkpoint, snapshots, messages))
+    }
+
+    fn load_file_manifest(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<Vec<FileSnapshot>> {
+        let paths = CheckpointPaths::new(&self.storage.claude_dir, project_id, session_id);
+        let record = self.load_record(&paths, checkpoint_id)?;
+        Ok(record
+            .files
+            .into_iter()
+            .map(|entry| entry.into_snapshot(checkpoint_id, Vec::new()))
+            .collect())
+    }
+
+    fn cleanup_old_checkpoints(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        keep_count: usize,
+    ) -> Result<usize> {
+        let paths = CheckpointPaths::new(&self.storage.claude_dir, project_id, session_id);
+        let mut removed_count = 0;
+
+        // Unreferenced commits and blobs are left for `git gc` to prune
+        for checkpoint in self.storage.checkpoints_beyond(&paths, keep_count)? {
+            let checkpoint_ref = Self::checkpoint_ref(session_id, &checkpoint.id);
+            if let Err(e) = self.git(&["update-ref", "-d", &checkpoint_ref], None) {
+                log::warn!("Failed to delete {}: {}", checkpoint_ref, e);
+            }
+            if self
+                .storage
+                .remove_checkpoint(&paths, &checkpoint.id)
+                .is_ok()
+            {
+                removed_count += 1;
+            }
+        }
+
+        Ok(removed_count)
+    }
+
+    fn remove_checkpoints(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_ids: &[String],
+    ) -> Result<usize> {
+        let paths = CheckpointPaths::new(&self.storage.claude_dir, project_id, session_id);
+        let removed = self.storage.remove_from_timeline(&paths, checkpoint_ids)?;
+        for checkpoint_id in &removed {
+            let checkpoint_ref = Self::checkpoint_ref(session_id, checkpoint_id);
+            if let Err(e) = self.git(&["update-ref", "-d", &checkpoint_ref], None) {
+                log::warn!("Failed to delete {}: {}", checkpoint_ref, e);
+            }
+            self.storage.remove_checkpoint(&paths, checkpoint_id)?;
+        }
+        Ok(removed.len())
+    }
+
+    fn garbage_collect(
+        &self,
+        _project_id: &str,
+        _session_id: &str,
+        _written_before: DateTime<Utc>,
+    ) -> Result<GcStats> {
+        // Objects live in the project repository and are left to the user's `git gc`;
+        // retention reports these sessions as exempt instead of counting them
+        Ok(GcStats::default())
+    }
+}
+
+/// Path of a project file inside a git tree
+fn tree_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::{manager::CheckpointManager, CheckpointBackend, CheckpointStrategy};
+    use tempfile::TempDir;
+
+    fn git(repo: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {:?} failed", args);
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[test]
+    fn test_hash_objects_handles_large_batches() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        let files = project.join("a-fairly-long-directory-name-to-pad-out-paths");
+        fs::create_dir_all(&files).unwrap();
+        git(&project, &["init", "-q"]);
+
+        // Well over 64 KiB of paths in and oids out, more than a pipe buffer holds
+        let sources: Vec<PathBuf> = (0..2000)
+            .map(|i| {
+                let path = files.join(format!("file-{:05}.txt", i));
+                fs::write(&path, i.to_string()).unwrap();
+                path
+            })
+            .collect();
+        let input_len: usize = sources.iter().map(|p| p.as_os_str().len() + 1).sum();
+        assert!(input_len > 64 * 1024);
+
+        let store = GitCheckpointStorage::new(
+            Arc::new(CheckpointStorage::new(temp_dir.path().join("claude"))),
+            project.clone(),
+        )
+        .unwrap();
+        let oids = store
+            .hash_objects(sources.iter().map(PathBuf::as_path))
+            .unwrap();
+        assert_eq!(oids.len(), sources.len());
+        assert_eq!(git(&project, &["cat-file", "-p", &oids[1999]]), "1999");
+    }
+
+    #[tokio::test]
+    async fn test_git_backend_commits_to_hidden_refs() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        git(&project, &["init", "-q"]);
+        fs::write(project.join("src/a.txt"), "one").unwrap();
+        fs::write(project.join("tool.sh"), "#!/bin/sh\n").unwrap();
+
+        let manager = CheckpointManager::new(
+            "test-project".to_string(),
+            "test-session".to_string(),
+            project.clone(),
+            temp_dir.path().join("claude"),
+        )
+        .await
+        .unwrap();
+        manager
+            .update_settings(
+                false,
+                CheckpointStrategy::Manual,
+                None,
+                Some(CheckpointBackend::Git {
+                    repo_path: project.clone(),
+                }),
+            )
+            .await
+            .unwrap();
+
+        let first = manager.create_checkpoint(None, None).await.unwrap();
+        fs::write(project.join("src/a.txt"), "one two").unwrap();
+        fs::remove_file(project.join("tool.sh")).unwrap();
+        let second = manager
+            .create_checkpoint(Some("second".to_string()), None)
+            .await
+            .unwrap();
+        assert!(second.warnings.is_empty());
+
+        // Blobs are staged next to the throwaway index and cleaned up afterwards
+        let paths = CheckpointPaths::new(
+            &temp_dir.path().join("claude"),
+            "test-project",
+            "test-session",
+        );
+        let second_dir = paths.checkpoint_dir(&second.checkpoint.id);
+        assert!(!second_dir.join("git-blobs").exists());
+        assert!(!second_dir.join("git-index").exists());
+
+        // Each checkpoint is a commit on its own hidden ref, chained to its parent
+        let first_ref = GitCheckpointStorage::checkpoint_ref("test-session", &first.checkpoint.id);
+        let second_ref =
+            GitCheckpointStorage::checkpoint_ref("test-session", &second.checkpoint.id);
+        assert_eq!(
+            git(&project, &["rev-parse", &format!("{}^", second_ref)]),
+            git(&project, &["rev-parse", &first_ref])
+        );
+        assert_eq!(
+            git(&project, &["ls-tree", "-r", "--name-only", &second_ref]),
+            "src/a.txt"
+        );
+        assert_eq!(
+            git(&project, &["show", &format!("{}:src/a.txt", second_ref)]),
+            "one two"
+        );
+
+        // The user's index and branch are untouched
+        assert_eq!(git(&project, &["ls-files"]), "");
+        assert!(Command::new("git")
+            .arg("-C")
+            .arg(&project)
+            .args(["rev-parse", "--verify", "-q", "HEAD"])
+            .output()
+            .unwrap()
+            .stdout
+            .is_empty());
+
+        manager
+            .restore_checkpoint(&first.checkpoint.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(project.join("src/a.txt")).unwrap(),
+            "one"
+        );
+        assert_eq!(
+            fs::read_to_string(project.join("tool.sh")).unwrap(),
+            "#!/bin/sh\n"
+        );
+
+        // The backend is fixed once checkpoints exist
+        assert!(manager
+            .update_settings(
+                false,
+                CheckpointStrategy::Manual,
+                None,
+                Some(CheckpointBackend::Files)
+            )
+            .await
+            .is_err());
+    }
+}
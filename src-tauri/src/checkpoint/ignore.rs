@@ -0,0 +1,411 @@
+use chrono::{DateTime, TimeZone, Utc};
+use glob::{MatchOptions, Pattern};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::CheckpointFileLimits;
+
+/// Ignore files honoured while walking a project, in increasing precedence
+pub const IGNORE_FILE_NAMES: [&str; 3] = [".gitignore", ".ignore", ".ccswitchignore"];
+
+/// VCS metadata directories that are never part of a checkpoint
+const ALWAYS_SKIPPED_DIRS: [&str; 3] = [".git", ".hg", ".svn"];
+
+/// Bytes inspected when sniffing whether a file is binary
+const BINARY_SNIFF_LEN: usize = 8000;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// A single parsed line of an ignore file
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// Directory (relative to the project root) that declared the rule
+    base: PathBuf,
+    /// Compiled glob, relative to `base`
+    pattern: Pattern,
+    /// `!pattern` re-includes a previously ignored path
+    negated: bool,
+    /// `pattern/` only matches directories
+    dir_only: bool,
+}
+
+/// Stack of gitignore-style rules collected while descending the project tree
+///
+/// Rules are evaluated last-match-wins, so rules from deeper directories and
+/// from higher-precedence ignore files override earlier ones.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreRules {
+    /// Load every ignore file found directly inside `dir`
+    pub fn load_dir(&mut self, root: &Path, dir: &Path) {
+        let base = dir
+            .strip_prefix(root)
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        for name in IGNORE_FILE_NAMES {
+            let file = dir.join(name);
+            if let Ok(content) = fs::read_to_string(&file) {
+                self.add_patterns(&base, &content);
+            }
+        }
+    }
+
+    /// Parse gitignore-formatted `content` declared in directory `base`
+    pub fn add_patterns(&mut self, base: &Path, content: &str) {
+        for line in content.lines() {
+            if let Some(rule) = Self::parse_line(base, line) {
+                self.rules.push(rule);
+            }
+        }
+    }
+
+    fn parse_line(base: &Path, line: &str) -> Option<IgnoreRule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        // `\#` and `\!` escape a literal leading character
+        let line = line
+            .strip_prefix('\\')
+            .filter(|rest| rest.starts_with('#') || rest.starts_with('!'))
+            .unwrap_or(line);
+
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        if line.is_empty() {
+            return None;
+        }
+
+        // A slash anywhere but the end anchors the pattern to `base`
+        let anchored = line.contains('/');
+        let line = line.trim_start_matches('/');
+        let glob = if anchored {
+            line.to_string()
+        } else {
+            format!("**/{}", line)
+        };
+
+        match Pattern::new(&glob) {
+            Ok(pattern) => Some(IgnoreRule {
+                base: base.to_path_buf(),
+                pattern,
+                negated,
+                dir_only,
+            }),
+            Err(e) => {
+                log::debug!("Skipping invalid ignore pattern {:?}: {}", line, e);
+                None
+            }
+        }
+    }
+
+    /// Whether `rel_path` (relative to the project root) is ignored
+    pub fn is_ignored(&self, rel_path: &Path, is_dir: bool) -> bool {
+        for rule in self.rules.iter().rev() {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let Ok(sub) = rel_path.strip_prefix(&rule.base) else {
+                continue;
+            };
+            let sub = sub.to_string_lossy().replace('\\', "/");
+            if rule.pattern.matches_with(&sub, MATCH_OPTIONS) {
+                return !rule.negated;
+            }
+        }
+        false
+    }
+
+    /// Whether a changed path falls outside what a scan would snapshot
+    ///
+    /// Unlike [`Self::is_ignored`] this also checks every parent directory, since
+    /// change notifications report paths deep inside ignored trees.
+    pub fn excludes_path(&self, rel_path: &Path) -> bool {
+        let mut path = PathBuf::new();
+        let mut components = rel_path.components().peekable();
+        while let Some(component) = components.next() {
+            path.push(component);
+            let is_dir = components.peek().is_some();
+            if ALWAYS_SKIPPED_DIRS
+                .iter()
+                .any(|d| component.as_os_str() == *d)
+                || self.is_ignored(&path, is_dir)
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.rules.truncate(len);
+    }
+}
+
+/// A project file eligible for snapshotting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectFile {
+    /// Path relative to the project root
+    pub path: PathBuf,
+    /// File size in bytes
+    pub size: u64,
+    /// Last modification time
+    pub modified: DateTime<Utc>,
+    /// File permissions (Unix mode)
+    pub permissions: Option<u32>,
+}
+
+impl ProjectFile {
+    fn from_metadata(path: PathBuf, metadata: &fs::Metadata) -> Self {
+        Self {
+            path,
+            size: metadata.len(),
+            modified: modified_at(metadata),
+            permissions: permissions_of(metadata),
+        }
+    }
+}
+
+/// Result of walking a project directory
+#[derive(Debug, Default)]
+pub struct ProjectScan {
+    /// Files that fit within the configured limits, sorted by path
+    pub files: Vec<ProjectFile>,
+    /// Files larger than `max_file_size`
+    pub oversized: Vec<ProjectFile>,
+    /// Files dropped because `max_total_size` was reached
+    pub over_budget: Vec<ProjectFile>,
+    /// Every ignore rule found during the walk, for filtering change notifications
+    pub ignore_rules: IgnoreRules,
+}
+
+impl ProjectScan {
+    /// Human readable warnings for every file left out of the scan
+    pub fn warnings(&self, limits: &CheckpointFileLimits) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for file in &self.oversized {
+            warnings.push(format!(
+                "Skipped {} ({} bytes exceeds max file size of {} bytes)",
+                file.path.display(),
+                file.size,
+                limits.max_file_size
+            ));
+        }
+        if !self.over_budget.is_empty() {
+            warnings.push(format!(
+                "Skipped {} files after reaching max total size of {} bytes",
+                self.over_budget.len(),
+                limits.max_total_size
+            ));
+        }
+        warnings
+    }
+}
+
+/// Walk `root` honouring ignore files and the given size limits
+///
+/// Symlinks are not followed, and VCS metadata directories are always skipped.
+pub fn scan_project(root: &Path, limits: &CheckpointFileLimits) -> std::io::Result<ProjectScan> {
+    let mut rules = IgnoreRules::default();
+    let mut scan = ProjectScan::default();
+    let mut total_size = 0u64;
+    walk_dir(root, root, &mut rules, limits, &mut scan, &mut total_size)?;
+    Ok(scan)
+}
+
+fn walk_dir(
+    root: &Path,
+    dir: &Path,
+    rules: &mut IgnoreRules,
+    limits: &CheckpointFileLimits,
+    scan: &mut ProjectScan,
+    total_size: &mut u64,
+) -> std::io::Result<()> {
+    let rules_len = rules.len();
+    rules.load_dir(root, dir);
+    scan.ignore_rules
+        .rules
+        .extend_from_slice(&rules.rules[rules_len..]);
+
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let Ok(rel) = path.strip_prefix(root) else {
+            continue;
+        };
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            let name = entry.file_name();
+            if ALWAYS_SKIPPED_DIRS.iter().any(|d| name == *d) || rules.is_ignored(rel, true) {
+                continue;
+            }
+            walk_dir(root, &path, rules, limits, scan, total_size)?;
+        } else if file_type.is_file() {
+            if rules.is_ignored(rel, false) {
+                continue;
+            }
+            let file = ProjectFile::from_metadata(rel.to_path_buf(), &entry.metadata()?);
+            if limits.max_file_size > 0 && file.size > limits.max_file_size {
+                scan.oversized.push(file);
+            } else if limits.max_total_size > 0 && *total_size + file.size > limits.max_total_size {
+                scan.over_budget.push(file);
+            } else {
+                *total_size += file.size;
+                scan.files.push(file);
+            }
+        }
+    }
+
+    rules.truncate(rules_len);
+    Ok(())
+}
+
+/// Modification time of a file, falling back to now when unavailable
+pub fn modified_at(metadata: &fs::Metadata) -> DateTime<Utc> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .and_then(|d| {
+            Utc.timestamp_opt(d.as_secs() as i64, d.subsec_nanos())
+                .single()
+        })
+        .unwrap_or_else(Utc::now)
+}
+
+/// Unix permission bits of a file, if the platform has them
+pub fn permissions_of(metadata: &fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Heuristic binary detection: a NUL byte near the start or invalid UTF-8
+pub fn is_binary(content: &[u8]) -> bool {
+    let head = &content[..content.len().min(BINARY_SNIFF_LEN)];
+    head.contains(&0) || std::str::from_utf8(content).is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, rel: &str, content: &[u8]) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn paths(files: &[ProjectFile]) -> Vec<String> {
+        files
+            .iter()
+            .map(|f| f.path.to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    #[test]
+    fn gitignore_semantics() {
+        let mut rules = IgnoreRules::default();
+        rules.add_patterns(
+            Path::new(""),
+            "# comment\n*.log\n!keep.log\n/build\ntarget/\ndocs/*.tmp\n\\#hash\n",
+        );
+
+        assert!(rules.is_ignored(Path::new("a.log"), false));
+        assert!(rules.is_ignored(Path::new("src/deep/a.log"), false));
+        assert!(!rules.is_ignored(Path::new("src/keep.log"), false));
+        assert!(rules.is_ignored(Path::new("build"), true));
+        assert!(!rules.is_ignored(Path::new("src/build"), true));
+        assert!(rules.is_ignored(Path::new("crates/x/target"), true));
+        assert!(!rules.is_ignored(Path::new("target"), false));
+        assert!(rules.is_ignored(Path::new("docs/a.tmp"), false));
+        assert!(!rules.is_ignored(Path::new("docs/sub/a.tmp"), false));
+        assert!(rules.is_ignored(Path::new("#hash"), false));
+    }
+
+    #[test]
+    fn scan_honours_nested_ignore_files_and_limits() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        write(root, ".gitignore", b"node_modules/\n*.bin\n");
+        write(root, ".ccswitchignore", b"!keep.bin\n");
+        write(root, "src/.ignore", b"generated.rs\n");
+        write(root, "src/main.rs", b"fn main() {}");
+        write(root, "src/generated.rs", b"// generated");
+        write(root, "node_modules/pkg/index.js", b"x");
+        write(root, "a.bin", b"\0\0");
+        write(root, "keep.bin", b"\0\0");
+        write(root, ".git/HEAD", b"ref: refs/heads/main");
+        write(root, "big.txt", &[b'a'; 64]);
+        write(root, "z1.txt", &[b'b'; 30]);
+        write(root, "z2.txt", &[b'c'; 30]);
+
+        let limits = CheckpointFileLimits {
+            max_file_size: 32,
+            max_total_size: 100,
+        };
+        let scan = scan_project(root, &limits).unwrap();
+
+        assert_eq!(
+            paths(&scan.files),
+            vec![
+                ".ccswitchignore",
+                ".gitignore",
+                "keep.bin",
+                "src/.ignore",
+                "src/main.rs",
+                "z1.txt"
+            ]
+        );
+        assert_eq!(paths(&scan.oversized), vec!["big.txt"]);
+        assert_eq!(paths(&scan.over_budget), vec!["z2.txt"]);
+        assert_eq!(scan.warnings(&limits).len(), 2);
+
+        // The collected rules filter change notifications the same way
+        let rules = &scan.ignore_rules;
+        assert!(rules.excludes_path(Path::new("node_modules/pkg/index.js")));
+        assert!(rules.excludes_path(Path::new("src/generated.rs")));
+        assert!(rules.excludes_path(Path::new(".git/index")));
+        assert!(rules.excludes_path(Path::new("a.bin")));
+        assert!(!rules.excludes_path(Path::new("keep.bin")));
+        assert!(!rules.excludes_path(Path::new("src/lib.rs")));
+        assert!(!rules.excludes_path(Path::new("generated.rs")));
+    }
+
+    #[test]
+    fn detects_binary_content() {
+        assert!(!is_binary(b"plain text\n"));
+        assert!(!is_binary("多字节文本".as_bytes()));
+        assert!(is_binary(b"PNG\0\x01"));
+        assert!(is_binary(&[0xff, 0xfe, 0x41]));
+    }
+}
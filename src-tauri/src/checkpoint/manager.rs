@@ -1,18 +1,44 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, Utc};
 use log;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
 use tokio::sync::RwLock;
 
 use super::{
-    storage::{self, CheckpointStorage},
-    Checkpoint, CheckpointMetadata, CheckpointPaths, CheckpointResult, CheckpointStrategy,
-    FileSnapshot, FileState, FileTracker, SessionTimeline,
+    archive::{self, ExportScope, ExportSummary},
+    diff,
+    ignore::{self, ProjectFile, ProjectScan},
+    storage::{self, CheckpointStorage, CheckpointStore},
+    transcript::{ClaudeSessionLog, EntryRole, SessionLog, ToolCall},
+    watcher::{self, ProjectWatcher, WatchConfig},
+    Checkpoint, CheckpointBackend, CheckpointDiff, CheckpointFileLimits, CheckpointMetadata,
+    CheckpointPaths, CheckpointResult, CheckpointStrategy, DiffHunk, FileSnapshot, FileState,
+    FileTracker, RestoreAction, RestoreChange, RestoreFileSelection, RestoreReport,
+    SessionTimeline,
 };
 
+/// A file change computed by a selective restore, ready to be written
+struct PlannedRestore {
+    change: RestoreChange,
+    content: Option<Vec<u8>>,
+    permissions: Option<u32>,
+}
+
+/// Whether `path` stays inside the project root (relative, no `..`)
+fn is_project_relative(path: &Path) -> bool {
+    !path.as_os_str().is_empty()
+        && path.components().all(|c| {
+            matches!(
+                c,
+                std::path::Component::Normal(_) | std::path::Component::CurDir
+            )
+        })
+}
+
 /// Manages checkpoint operations for a session
 pub struct CheckpointManager {
     project_id: String,
@@ -20,17 +46,45 @@ pub struct CheckpointManager {
     project_path: PathBuf,
     file_tracker: Arc<RwLock<FileTracker>>,
     pub storage: Arc<CheckpointStorage>,
+    /// Backend for file snapshots, chosen by the timeline's storage backend
+    store: std::sync::RwLock<Arc<dyn CheckpointStore>>,
     timeline: Arc<RwLock<SessionTimeline>>,
     current_messages: Arc<RwLock<Vec<String>>>, // JSONL messages
+    /// Reader for the session log format of the CLI being checkpointed
+    session_log: Arc<dyn SessionLog>,
+    /// Directory watcher, running while the strategy is `Watch`
+    watcher: Mutex<Option<ProjectWatcher>>,
+    /// Bumped after every restore so the watcher ignores the files it rewrote
+    restore_epoch: AtomicU64,
 }
 
 impl CheckpointManager {
-    /// Create a new checkpoint manager
+    /// Create a new checkpoint manager for a Claude Code session
     pub async fn new(
         project_id: String,
         session_id: String,
         project_path: PathBuf,
         claude_dir: PathBuf,
+    ) -> Result<Self> {
+        let session_log = Arc::new(ClaudeSessionLog::new(claude_dir.clone()));
+        Self::with_session_log(
+            project_id,
+            session_id,
+            project_path,
+            claude_dir,
+            session_log,
+        )
+        .await
+    }
+
+    /// Create a checkpoint manager storing its data under `claude_dir` for a
+    /// session whose log is read by `session_log`
+    pub async fn with_session_log(
+        project_id: String,
+        session_id: String,
+        project_path: PathBuf,
+        claude_dir: PathBuf,
+        session_log: Arc<dyn SessionLog>,
     ) -> Result<Self> {
         let storage = Arc::new(CheckpointStorage::new(claude_dir.clone()));
 
@@ -45,9 +99,37 @@ impl CheckpointManager {
             SessionTimeline::new(session_id.clone())
         };
 
-        let file_tracker = FileTracker {
-            tracked_files: HashMap::new(),
-        };
+        let store = storage::open_store(storage.clone(), &timeline.storage_backend)?;
+
+        // Seed the tracker from the current checkpoint so the next one is incremental
+        let mut tracked_files = HashMap::new();
+        if let Some(current_id) = &timeline.current_checkpoint_id {
+            match store.load_file_manifest(&project_id, &session_id, current_id) {
+                Ok(manifest) => {
+                    for snapshot in manifest.into_iter().filter(|s| !s.is_deleted) {
+                        tracked_files.insert(
+                            snapshot.file_path,
+                            FileState {
+                                last_hash: snapshot.hash,
+                                is_modified: false,
+                                // Unknown mtimes force a rehash on the next checkpoint
+                                last_modified: snapshot.modified.unwrap_or(DateTime::UNIX_EPOCH),
+                                exists: true,
+                                size: snapshot.size,
+                                is_binary: snapshot.is_binary,
+                            },
+                        );
+                    }
+                }
+                Err(e) => log::warn!(
+                    "Failed to load manifest of checkpoint {}: {}",
+                    current_id,
+                    e
+                ),
+            }
+        }
+
+        let file_tracker = FileTracker { tracked_files };
 
         Ok(Self {
             project_id,
@@ -55,8 +137,12 @@ impl CheckpointManager {
             project_path,
             file_tracker: Arc::new(RwLock::new(file_tracker)),
             storage,
+            store: std::sync::RwLock::new(store),
             timeline: Arc::new(RwLock::new(timeline)),
             current_messages: Arc::new(RwLock::new(Vec::new())),
+            session_log,
+            watcher: Mutex::new(None),
+            restore_epoch: AtomicU64::new(0),
         })
     }
 
@@ -66,19 +152,9 @@ impl CheckpointManager {
         messages.push(jsonl_message.clone());
 
         // Parse message to check for tool usage
-        if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&jsonl_message) {
-            if let Some(content) = msg.get("message").and_then(|m| m.get("content")) {
-                if let Some(content_array) = content.as_array() {
-                    for item in content_array {
-                        if item.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
-                            if let Some(tool_name) = item.get("name").and_then(|n| n.as_str()) {
-                                if let Some(input) = item.get("input") {
-                                    self.track_tool_operation(tool_name, input).await?;
-                                }
-                            }
-                        }
-                    }
-                }
+        if let Some(entry) = self.session_log.parse_line(&jsonl_message) {
+            for call in &entry.tool_calls {
+                self.track_tool_call(call).await?;
             }
         }
 
@@ -86,20 +162,13 @@ impl CheckpointManager {
     }
 
     /// Track file operations from tool usage
-    async fn track_tool_operation(&self, tool: &str, input: &serde_json::Value) -> Result<()> {
-        match tool.to_lowercase().as_str() {
-            "edit" | "write" | "multiedit" => {
-                if let Some(file_path) = input.get("file_path").and_then(|p| p.as_str()) {
-                    self.track_file_modification(file_path).await?;
-                }
-            }
-            "bash" => {
-                // Try to detect file modifications from bash commands
-                if let Some(command) = input.get("command").and_then(|c| c.as_str()) {
-                    self.track_bash_side_effects(command).await?;
-                }
-            }
-            _ => {}
+    async fn track_tool_call(&self, call: &ToolCall) -> Result<()> {
+        for file_path in &call.files {
+            self.track_file_modification(file_path).await?;
+        }
+        // Try to detect file modifications from shell commands
+        if let Some(command) = &call.command {
+            self.track_bash_side_effects(command).await?;
         }
         Ok(())
     }
@@ -109,28 +178,21 @@ impl CheckpointManager {
         let mut tracker = self.file_tracker.write().await;
         let full_path = self.project_path.join(file_path);
 
-        // Read current file state
-        let (hash, exists, _size, modified) = if full_path.exists() {
-            let content = fs::read_to_string(&full_path).unwrap_or_default();
+        // Read current file state. Stat before reading so a concurrent write leaves a
+        // stale mtime behind, which forces a rehash next time instead of hiding the change.
+        let (hash, exists, size, modified, is_binary) = if full_path.exists() {
             let metadata = fs::metadata(&full_path)?;
-            let modified = metadata
-                .modified()
-                .ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| {
-                    Utc.timestamp_opt(d.as_secs() as i64, d.subsec_nanos())
-                        .unwrap()
-                })
-                .unwrap_or_else(Utc::now);
+            let content = fs::read(&full_path).unwrap_or_default();
 
             (
                 storage::CheckpointStorage::calculate_file_hash(&content),
                 true,
                 metadata.len(),
-                modified,
+                ignore::modified_at(&metadata),
+                ignore::is_binary(&content),
             )
         } else {
-            (String::new(), false, 0, Utc::now())
+            (String::new(), false, 0, Utc::now(), false)
         };
 
         // Check if file has actually changed
@@ -155,6 +217,8 @@ impl CheckpointManager {
                 is_modified,
                 last_modified: modified,
                 exists,
+                size,
+                is_binary,
             },
         );
 
@@ -189,6 +253,25 @@ impl CheckpointManager {
         &self,
         description: Option<String>,
         parent_checkpoint_id: Option<String>,
+    ) -> Result<CheckpointResult> {
+        self.create_checkpoint_with(description, parent_checkpoint_id, false)
+            .await
+    }
+
+    /// Create a checkpoint on behalf of the directory watcher
+    pub(super) async fn create_automatic_checkpoint(
+        &self,
+        description: String,
+    ) -> Result<CheckpointResult> {
+        self.create_checkpoint_with(Some(description), None, true)
+            .await
+    }
+
+    async fn create_checkpoint_with(
+        &self,
+        description: Option<String>,
+        parent_checkpoint_id: Option<String>,
+        automatic: bool,
     ) -> Result<CheckpointResult> {
         let messages = self.current_messages.read().await;
         let message_index = messages.len().saturating_sub(1);
@@ -197,48 +280,54 @@ impl CheckpointManager {
         let (user_prompt, model_used, total_tokens) =
             self.extract_checkpoint_metadata(&messages).await?;
 
-        // Ensure every file in the project is tracked so new checkpoints include all files
-        // Recursively walk the project directory and track each file
-        fn collect_files(
-            dir: &std::path::Path,
-            base: &std::path::Path,
-            files: &mut Vec<std::path::PathBuf>,
-        ) -> Result<(), std::io::Error> {
-            for entry in std::fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    // Skip hidden directories like .git
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if name.starts_with('.') {
-                            continue;
-                        }
-                    }
-                    collect_files(&path, base, files)?;
-                } else if path.is_file() {
-                    // Compute relative path from project root
-                    if let Ok(rel) = path.strip_prefix(base) {
-                        files.push(rel.to_path_buf());
-                    }
-                }
-            }
-            Ok(())
-        }
-        let mut all_files = Vec::new();
-        let project_dir = &self.project_path;
-        let _ = collect_files(project_dir.as_path(), project_dir.as_path(), &mut all_files);
-        for rel in all_files {
-            if let Some(p) = rel.to_str() {
-                // Track each file for snapshot
+        // Collect every eligible project file, honouring ignore files and the size limits
+        let limits = self.file_limits().await;
+        let scan = self.scan_project(&limits);
+        let warnings = scan.warnings(&limits);
+
+        // Only rehash files whose size or mtime changed since the parent checkpoint,
+        // plus tracked files that have disappeared from disk
+        let stale: Vec<PathBuf> = {
+            let tracker = self.file_tracker.read().await;
+            let changed = scan.files.iter().filter(|file| {
+                tracker.tracked_files.get(&file.path).is_none_or(|state| {
+                    !state.exists || state.size != file.size || state.last_modified != file.modified
+                })
+            });
+            let vanished = tracker
+                .tracked_files
+                .iter()
+                .filter(|(path, state)| state.exists && !self.project_path.join(path).exists());
+            changed
+                .map(|file| file.path.clone())
+                .chain(vanished.map(|(path, _)| path.clone()))
+                .collect()
+        };
+        for path in &stale {
+            if let Some(p) = path.to_str() {
                 let _ = self.track_file_modification(p).await;
             }
         }
+        log::debug!(
+            "Rehashed {} of {} project files for checkpoint",
+            stale.len(),
+            scan.files.len()
+        );
+
+        let skipped_files: Vec<PathBuf> = scan
+            .oversized
+            .iter()
+            .chain(&scan.over_budget)
+            .map(|f| f.path.clone())
+            .collect();
 
         // Generate checkpoint ID early so snapshots reference it
         let checkpoint_id = storage::CheckpointStorage::generate_checkpoint_id();
 
         // Create file snapshots
-        let file_snapshots = self.create_file_snapshots(&checkpoint_id).await?;
+        let file_snapshots = self
+            .create_file_snapshots(&checkpoint_id, &scan.files)
+            .await?;
 
         // Generate checkpoint struct
         let checkpoint = Checkpoint {
@@ -261,23 +350,27 @@ impl CheckpointManager {
                 total_tokens,
                 model_used,
                 user_prompt,
-                file_changes: file_snapshots.len(),
+                file_changes: file_snapshots.iter().filter(|s| !s.reused).count(),
                 snapshot_size: storage::CheckpointStorage::estimate_checkpoint_size(
                     &messages.join("\n"),
                     &file_snapshots,
                 ),
+                skipped_files,
+                automatic,
             },
+            pinned: false,
         };
 
         // Save checkpoint
         let messages_content = messages.join("\n");
-        let result = self.storage.save_checkpoint(
+        let mut result = self.store().save_checkpoint(
             &self.project_id,
             &self.session_id,
             &checkpoint,
             file_snapshots,
             &messages_content,
         )?;
+        result.warnings.extend(warnings);
 
         // Reload timeline from disk so in-memory timeline has updated nodes and total_checkpoints
         let claude_dir = self.storage.claude_dir.clone();
@@ -292,8 +385,9 @@ impl CheckpointManager {
         let mut timeline = self.timeline.write().await;
         timeline.current_checkpoint_id = Some(checkpoint_id);
 
-        // Reset file tracker
+        // Reset file tracker; recorded deletions no longer need tracking
         let mut tracker = self.file_tracker.write().await;
+        tracker.tracked_files.retain(|_, state| state.exists);
         for (_, state) in tracker.tracked_files.iter_mut() {
             state.is_modified = false;
         }
@@ -301,150 +395,128 @@ impl CheckpointManager {
         Ok(result)
     }
 
+    /// Store that holds this session's file snapshots
+    pub fn store(&self) -> Arc<dyn CheckpointStore> {
+        self.store
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Current file size limits for this session
+    pub(super) async fn file_limits(&self) -> CheckpointFileLimits {
+        self.timeline.read().await.file_limits.clone()
+    }
+
+    /// Walk the project directory, falling back to an empty scan on I/O errors
+    fn scan_project(&self, limits: &CheckpointFileLimits) -> ProjectScan {
+        ignore::scan_project(&self.project_path, limits).unwrap_or_else(|e| {
+            log::warn!(
+                "Failed to scan project {}: {}",
+                self.project_path.display(),
+                e
+            );
+            ProjectScan::default()
+        })
+    }
+
     /// Extract metadata from messages for checkpoint
     async fn extract_checkpoint_metadata(
         &self,
         messages: &[String],
     ) -> Result<(String, String, u64)> {
-        let mut user_prompt = String::new();
-        let mut model_used = String::from("unknown");
+        let mut user_prompt = None;
+        let mut model_used = None;
         let mut total_tokens = 0u64;
 
-        // Iterate through messages in reverse to find the last user prompt
-        for msg_str in messages.iter().rev() {
-            if let Ok(msg) = serde_json::from_str::<serde_json::Value>(msg_str) {
-                // Check for user message
-                if msg.get("type").and_then(|t| t.as_str()) == Some("user") {
-                    if let Some(content) = msg
-                        .get("message")
-                        .and_then(|m| m.get("content"))
-                        .and_then(|c| c.as_array())
-                    {
-                        for item in content {
-                            if item.get("type").and_then(|t| t.as_str()) == Some("text") {
-                                if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                    user_prompt = text.to_string();
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // Extract model info
-                if let Some(model) = msg.get("model").and_then(|m| m.as_str()) {
-                    model_used = model.to_string();
-                }
-
-                // Also check for model in message.model (assistant messages)
-                if let Some(message) = msg.get("message") {
-                    if let Some(model) = message.get("model").and_then(|m| m.as_str()) {
-                        model_used = model.to_string();
-                    }
-                }
-
-                // Count tokens - check both top-level and nested usage
-                // First check for usage in message.usage (assistant messages)
-                if let Some(message) = msg.get("message") {
-                    if let Some(usage) = message.get("usage") {
-                        if let Some(input) = usage.get("input_tokens").and_then(|t| t.as_u64()) {
-                            total_tokens += input;
-                        }
-                        if let Some(output) = usage.get("output_tokens").and_then(|t| t.as_u64()) {
-                            total_tokens += output;
-                        }
-                        // Also count cache tokens
-                        if let Some(cache_creation) = usage
-                            .get("cache_creation_input_tokens")
-                            .and_then(|t| t.as_u64())
-                        {
-                            total_tokens += cache_creation;
-                        }
-                        if let Some(cache_read) = usage
-                            .get("cache_read_input_tokens")
-                            .and_then(|t| t.as_u64())
-                        {
-                            total_tokens += cache_read;
-                        }
-                    }
-                }
-
-                // Then check for top-level usage (result messages)
-                if let Some(usage) = msg.get("usage") {
-                    if let Some(input) = usage.get("input_tokens").and_then(|t| t.as_u64()) {
-                        total_tokens += input;
-                    }
-                    if let Some(output) = usage.get("output_tokens").and_then(|t| t.as_u64()) {
-                        total_tokens += output;
-                    }
-                    // Also count cache tokens
-                    if let Some(cache_creation) = usage
-                        .get("cache_creation_input_tokens")
-                        .and_then(|t| t.as_u64())
-                    {
-                        total_tokens += cache_creation;
-                    }
-                    if let Some(cache_read) = usage
-                        .get("cache_read_input_tokens")
-                        .and_then(|t| t.as_u64())
-                    {
-                        total_tokens += cache_read;
-                    }
-                }
+        // Iterate through messages in reverse to find the last user prompt and model
+        for entry in messages
+            .iter()
+            .rev()
+            .filter_map(|msg| self.session_log.parse_line(msg))
+        {
+            if user_prompt.is_none() {
+                user_prompt = entry.prompt;
             }
+            if model_used.is_none() {
+                model_used = entry.model;
+            }
+            total_tokens += entry.tokens;
         }
 
-        Ok((user_prompt, model_used, total_tokens))
+        Ok((
+            user_prompt.unwrap_or_default(),
+            model_used.unwrap_or_else(|| "unknown".to_string()),
+            total_tokens,
+        ))
     }
 
-    /// Create file snapshots for all tracked modified files
-    async fn create_file_snapshots(&self, checkpoint_id: &str) -> Result<Vec<FileSnapshot>> {
+    /// Create the file manifest of a checkpoint
+    ///
+    /// Modified files are read into new snapshots, unchanged ones reuse the parent's
+    /// content by hash, and tracked files that were deleted are recorded as deletions.
+    /// Tracked paths outside `files` (ignored or over the size limits) are skipped.
+    async fn create_file_snapshots(
+        &self,
+        checkpoint_id: &str,
+        files: &[ProjectFile],
+    ) -> Result<Vec<FileSnapshot>> {
         let tracker = self.file_tracker.read().await;
         let mut snapshots = Vec::new();
 
-        for (rel_path, state) in &tracker.tracked_files {
-            // Skip files that haven't been modified
+        for file in files {
+            let Some(state) = tracker.tracked_files.get(&file.path) else {
+                continue;
+            };
+
             if !state.is_modified {
+                snapshots.push(FileSnapshot {
+                    checkpoint_id: checkpoint_id.to_string(),
+                    file_path: file.path.clone(),
+                    content: Vec::new(),
+                    is_binary: state.is_binary,
+                    hash: state.last_hash.clone(),
+                    is_deleted: false,
+                    permissions: file.permissions,
+                    size: state.size,
+                    modified: Some(state.last_modified),
+                    reused: true,
+                });
                 continue;
             }
 
-            let full_path = self.project_path.join(rel_path);
-
-            let (content, exists, permissions, size, current_hash) = if full_path.exists() {
-                let content = fs::read_to_string(&full_path).unwrap_or_default();
-                let current_hash = storage::CheckpointStorage::calculate_file_hash(&content);
-
-                // Don't skip based on hash - if is_modified is true, we should snapshot it
-                // The hash check in track_file_modification already determined if it changed
-
-                let metadata = fs::metadata(&full_path)?;
-                let permissions = {
-                    #[cfg(unix)]
-                    {
-                        use std::os::unix::fs::PermissionsExt;
-                        Some(metadata.permissions().mode())
-                    }
-                    #[cfg(not(unix))]
-                    {
-                        None
-                    }
-                };
-                (content, true, permissions, metadata.len(), current_hash)
-            } else {
-                (String::new(), false, None, 0, String::new())
-            };
-
+            let content = fs::read(self.project_path.join(&file.path)).unwrap_or_default();
             snapshots.push(FileSnapshot {
                 checkpoint_id: checkpoint_id.to_string(),
-                file_path: rel_path.clone(),
+                file_path: file.path.clone(),
+                is_binary: ignore::is_binary(&content),
+                hash: storage::CheckpointStorage::calculate_file_hash(&content),
+                size: content.len() as u64,
                 content,
-                hash: current_hash,
-                is_deleted: !exists,
-                permissions,
-                size,
+                is_deleted: false,
+                permissions: file.permissions,
+                modified: Some(state.last_modified),
+                reused: false,
             });
         }
 
+        for (rel_path, state) in &tracker.tracked_files {
+            if state.is_modified && !state.exists {
+                snapshots.push(FileSnapshot {
+                    checkpoint_id: checkpoint_id.to_string(),
+                    file_path: rel_path.clone(),
+                    content: Vec::new(),
+                    is_binary: false,
+                    hash: String::new(),
+                    is_deleted: true,
+                    permissions: None,
+                    size: 0,
+                    modified: None,
+                    reused: false,
+                });
+            }
+        }
+
         Ok(snapshots)
     }
 
@@ -452,39 +524,20 @@ impl CheckpointManager {
     pub async fn restore_checkpoint(&self, checkpoint_id: &str) -> Result<CheckpointResult> {
         // Load checkpoint data
         let (checkpoint, file_snapshots, messages) =
-            self.storage
+            self.store()
                 .load_checkpoint(&self.project_id, &self.session_id, checkpoint_id)?;
 
-        // First, collect all files currently in the project to handle deletions
-        fn collect_all_project_files(
-            dir: &std::path::Path,
-            base: &std::path::Path,
-            files: &mut Vec<std::path::PathBuf>,
-        ) -> Result<(), std::io::Error> {
-            for entry in std::fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    // Skip hidden directories like .git
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if name.starts_with('.') {
-                            continue;
-                        }
-                    }
-                    collect_all_project_files(&path, base, files)?;
-                } else if path.is_file() {
-                    // Compute relative path from project root
-                    if let Ok(rel) = path.strip_prefix(base) {
-                        files.push(rel.to_path_buf());
-                    }
-                }
-            }
-            Ok(())
-        }
-
-        let mut current_files = Vec::new();
-        let _ =
-            collect_all_project_files(&self.project_path, &self.project_path, &mut current_files);
+        // First, collect all eligible files currently in the project to handle deletions.
+        // Ignored files and files skipped by the size limits are left untouched.
+        let limits = self.file_limits().await;
+        let skipped: HashSet<&PathBuf> = checkpoint.metadata.skipped_files.iter().collect();
+        let current_files: Vec<PathBuf> = self
+            .scan_project(&limits)
+            .files
+            .into_iter()
+            .map(|f| f.path)
+            .filter(|path| !skipped.contains(path))
+            .collect();
 
         // Create a set of files that should exist after restore
         let mut checkpoint_files = std::collections::HashSet::new();
@@ -497,6 +550,7 @@ impl CheckpointManager {
         // Delete files that exist now but shouldn't exist in the checkpoint
         let mut warnings = Vec::new();
         let mut files_processed = 0;
+        let mut deleted_files = Vec::new();
 
         for current_file in current_files {
             if !checkpoint_files.contains(&current_file) {
@@ -506,6 +560,7 @@ impl CheckpointManager {
                     Ok(_) => {
                         files_processed += 1;
                         log::info!("Deleted file not in checkpoint: {:?}", current_file);
+                        deleted_files.push(current_file);
                     }
                     Err(e) => {
                         warnings.push(format!(
@@ -518,39 +573,11 @@ impl CheckpointManager {
             }
         }
 
-        // Clean up empty directories
-        fn remove_empty_dirs(
-            dir: &std::path::Path,
-            base: &std::path::Path,
-        ) -> Result<bool, std::io::Error> {
-            if dir == base {
-                return Ok(false); // Don't remove the base directory
-            }
-
-            let mut is_empty = true;
-            for entry in fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    if !remove_empty_dirs(&path, base)? {
-                        is_empty = false;
-                    }
-                } else {
-                    is_empty = false;
-                }
-            }
-
-            if is_empty {
-                fs::remove_dir(dir)?;
-                Ok(true)
-            } else {
-                Ok(false)
-            }
+        // Clean up any directories left empty by the deletions above
+        for file in &deleted_files {
+            Self::remove_empty_parents(&self.project_path, file);
         }
 
-        // Clean up any empty directories left after file deletion
-        let _ = remove_empty_dirs(&self.project_path, &self.project_path);
-
         // Restore files from checkpoint
         for snapshot in &file_snapshots {
             match self.restore_file_snapshot(snapshot).await {
@@ -579,18 +606,26 @@ impl CheckpointManager {
         tracker.tracked_files.clear();
         for snapshot in &file_snapshots {
             if !snapshot.is_deleted {
+                // Record the restored file's own mtime so the next checkpoint can reuse it
+                let metadata = fs::metadata(self.project_path.join(&snapshot.file_path)).ok();
                 tracker.tracked_files.insert(
                     snapshot.file_path.clone(),
                     FileState {
                         last_hash: snapshot.hash.clone(),
                         is_modified: false,
-                        last_modified: Utc::now(),
+                        last_modified: metadata
+                            .as_ref()
+                            .map_or(DateTime::UNIX_EPOCH, ignore::modified_at),
                         exists: true,
+                        size: metadata.map_or(snapshot.size, |m| m.len()),
+                        is_binary: snapshot.is_binary,
                     },
                 );
             }
         }
 
+        self.restore_epoch.fetch_add(1, Ordering::SeqCst);
+
         Ok(CheckpointResult {
             checkpoint: checkpoint.clone(),
             files_processed,
@@ -598,6 +633,244 @@ impl CheckpointManager {
         })
     }
 
+    /// Remove empty ancestor directories of `rel_path`, stopping at the project root
+    fn remove_empty_parents(root: &Path, rel_path: &Path) {
+        let mut dir = rel_path.parent();
+        while let Some(rel_dir) = dir {
+            if rel_dir.as_os_str().is_empty() {
+                break;
+            }
+            // remove_dir fails on non-empty directories, which ends the walk
+            if fs::remove_dir(root.join(rel_dir)).is_err() {
+                break;
+            }
+            dir = rel_dir.parent();
+        }
+    }
+
+    /// Diff a checkpoint against another checkpoint, or the working tree when `to` is `None`
+    pub async fn diff_checkpoints(
+        &self,
+        from_checkpoint_id: &str,
+        to_checkpoint_id: Option<&str>,
+    ) -> Result<CheckpointDiff> {
+        let store = self.store();
+        let (from_checkpoint, from_files, _) =
+            store.load_checkpoint(&self.project_id, &self.session_id, from_checkpoint_id)?;
+
+        let (to_files, to_tokens) = match to_checkpoint_id {
+            Some(id) => {
+                let (to_checkpoint, files, _) =
+                    store.load_checkpoint(&self.project_id, &self.session_id, id)?;
+                (files, to_checkpoint.metadata.total_tokens)
+            }
+            None => {
+                let limits = self.file_limits().await;
+                let messages = self.current_messages.read().await;
+                let (_, _, tokens) = self.extract_checkpoint_metadata(&messages).await?;
+                (
+                    diff::working_tree_snapshots(&self.project_path, &limits)?,
+                    tokens,
+                )
+            }
+        };
+
+        Ok(diff::diff_snapshots(
+            from_checkpoint_id.to_string(),
+            to_checkpoint_id.map(str::to_string),
+            &from_files,
+            &to_files,
+            to_tokens as i64 - from_checkpoint.metadata.total_tokens as i64,
+        ))
+    }
+
+    /// Restore selected files or hunks from a checkpoint into the working tree
+    ///
+    /// `files: None` restores every file that differs, deleting eligible files missing
+    /// from the checkpoint, like [`Self::restore_checkpoint`] but without touching the
+    /// messages or timeline. With `dry_run` nothing is written and the report lists the
+    /// pending changes.
+    pub async fn restore_files(
+        &self,
+        checkpoint_id: &str,
+        files: Option<Vec<RestoreFileSelection>>,
+        dry_run: bool,
+    ) -> Result<RestoreReport> {
+        let (checkpoint, snapshots, _) =
+            self.store()
+                .load_checkpoint(&self.project_id, &self.session_id, checkpoint_id)?;
+        let snapshot_map: HashMap<&PathBuf, &FileSnapshot> = snapshots
+            .iter()
+            .filter(|s| !s.is_deleted)
+            .map(|s| (&s.file_path, s))
+            .collect();
+
+        // Only files the checkpoint would have snapshotted may be deleted; ignored
+        // files and files skipped by the size limits are left untouched
+        let limits = self.file_limits().await;
+        let skipped: HashSet<&PathBuf> = checkpoint.metadata.skipped_files.iter().collect();
+        let eligible: BTreeSet<PathBuf> = self
+            .scan_project(&limits)
+            .files
+            .into_iter()
+            .map(|f| f.path)
+            .filter(|path| !skipped.contains(path))
+            .collect();
+
+        let selections = match files {
+            Some(files) => files,
+            None => snapshot_map
+                .keys()
+                .map(|p| (*p).clone())
+                .chain(eligible.iter().cloned())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|path| RestoreFileSelection { path, hunks: None })
+                .collect(),
+        };
+
+        let mut changes = Vec::new();
+        let mut warnings = Vec::new();
+        for selection in &selections {
+            let snapshot = snapshot_map.get(&selection.path).copied();
+            let planned = if !is_project_relative(&selection.path) {
+                Err(anyhow::anyhow!("Path must be relative to the project"))
+            } else if snapshot.is_none() && !eligible.contains(&selection.path) {
+                Err(anyhow::anyhow!("File is not part of the checkpoint"))
+            } else {
+                self.plan_file_restore(selection, snapshot)
+            };
+            let planned = match planned {
+                Ok(Some(planned)) => planned,
+                Ok(None) => continue,
+                Err(e) => {
+                    warnings.push(format!(
+                        "Failed to restore {}: {}",
+                        selection.path.display(),
+                        e
+                    ));
+                    continue;
+                }
+            };
+
+            if !dry_run {
+                if let Err(e) = self.write_planned_restore(&planned) {
+                    warnings.push(format!(
+                        "Failed to restore {}: {}",
+                        selection.path.display(),
+                        e
+                    ));
+                    continue;
+                }
+                if let Some(p) = planned.change.path.to_str() {
+                    let _ = self.track_file_modification(p).await;
+                }
+            }
+            changes.push(planned.change);
+        }
+        if !dry_run && !changes.is_empty() {
+            self.restore_epoch.fetch_add(1, Ordering::SeqCst);
+        }
+
+        Ok(RestoreReport {
+            checkpoint_id: checkpoint_id.to_string(),
+            dry_run,
+            changes,
+            warnings,
+        })
+    }
+
+    /// Work out the restored content of one file; `None` if it already matches
+    fn plan_file_restore(
+        &self,
+        selection: &RestoreFileSelection,
+        snapshot: Option<&FileSnapshot>,
+    ) -> Result<Option<PlannedRestore>> {
+        let full_path = self.project_path.join(&selection.path);
+        let current = if full_path.is_file() {
+            Some(fs::read(&full_path).context("Failed to read current file")?)
+        } else {
+            None
+        };
+
+        let target = match (&selection.hunks, snapshot) {
+            (None, snapshot) => snapshot.map(|s| s.content.clone()),
+            (Some(indices), Some(snapshot)) => {
+                let current_bytes = current.as_deref().unwrap_or_default();
+                if snapshot.is_binary || ignore::is_binary(current_bytes) {
+                    anyhow::bail!("Hunk selection is not supported for binary files");
+                }
+                let current_text = std::str::from_utf8(current_bytes)?;
+                let target_text = std::str::from_utf8(&snapshot.content)?;
+
+                // Hunk indices refer to the checkpoint -> working tree diff shown to
+                // the user; undoing the selected hunks restores those parts
+                let hunks = diff::diff_text(target_text, current_text);
+                let reversed = indices
+                    .iter()
+                    .map(|&i| {
+                        hunks
+                            .get(i)
+                            .map(diff::reverse_hunk)
+                            .ok_or_else(|| anyhow::anyhow!("Hunk {} does not exist", i))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let selected: Vec<&DiffHunk> = reversed.iter().collect();
+                Some(diff::apply_hunks(current_text, &selected)?.into_bytes())
+            }
+            (Some(_), None) => anyhow::bail!("File is not part of the checkpoint"),
+        };
+
+        let action = match (&current, &target) {
+            (None, None) => return Ok(None),
+            (Some(current), Some(target)) if current == target => return Ok(None),
+            (Some(_), None) => RestoreAction::Delete,
+            (None, Some(_)) => RestoreAction::Create,
+            (Some(_), Some(_)) => RestoreAction::Modify,
+        };
+        let file_diff = diff::diff_file(&selection.path, current.as_deref(), target.as_deref());
+
+        Ok(Some(PlannedRestore {
+            change: RestoreChange {
+                path: selection.path.clone(),
+                action,
+                additions: file_diff.additions,
+                deletions: file_diff.deletions,
+                diff_content: file_diff.diff_content,
+            },
+            content: target,
+            permissions: snapshot.and_then(|s| s.permissions),
+        }))
+    }
+
+    /// Write a planned restore to the working tree
+    fn write_planned_restore(&self, planned: &PlannedRestore) -> Result<()> {
+        let rel_path = &planned.change.path;
+        let full_path = self.project_path.join(rel_path);
+
+        match &planned.content {
+            None => {
+                fs::remove_file(&full_path).context("Failed to delete file")?;
+                Self::remove_empty_parents(&self.project_path, rel_path);
+            }
+            Some(content) => {
+                if let Some(parent) = full_path.parent() {
+                    fs::create_dir_all(parent).context("Failed to create parent directories")?;
+                }
+                fs::write(&full_path, content).context("Failed to write file")?;
+
+                #[cfg(unix)]
+                if let Some(mode) = planned.permissions {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(&full_path, std::fs::Permissions::from_mode(mode))
+                        .context("Failed to set file permissions")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Restore a single file from snapshot
     async fn restore_file_snapshot(&self, snapshot: &FileSnapshot) -> Result<()> {
         let full_path = self.project_path.join(&snapshot.file_path);
@@ -665,7 +938,7 @@ impl CheckpointManager {
     ) -> Result<CheckpointResult> {
         // Load the checkpoint to fork from
         let (_base_checkpoint, _, _) =
-            self.storage
+            self.store()
                 .load_checkpoint(&self.project_id, &self.session_id, checkpoint_id)?;
 
         // Restore to that checkpoint first
@@ -679,6 +952,26 @@ impl CheckpointManager {
             .await
     }
 
+    /// Export a checkpoint, or its whole branch, to a portable archive
+    pub async fn export_archive(
+        &self,
+        checkpoint_id: &str,
+        scope: ExportScope,
+        dest: &Path,
+    ) -> Result<ExportSummary> {
+        let timeline = self.timeline.read().await;
+        archive::export_archive(
+            self.store().as_ref(),
+            &timeline,
+            &self.project_id,
+            &self.session_id,
+            Some(&self.project_path),
+            checkpoint_id,
+            scope,
+            dest,
+        )
+    }
+
     /// Check if auto-checkpoint should be triggered
     pub async fn should_auto_checkpoint(&self, message: &str) -> bool {
         let timeline = self.timeline.read().await;
@@ -687,61 +980,20 @@ impl CheckpointManager {
             return false;
         }
 
+        let Some(entry) = self.session_log.parse_line(message) else {
+            return false;
+        };
+
         match timeline.checkpoint_strategy {
             CheckpointStrategy::Manual => false,
-            CheckpointStrategy::PerPrompt => {
-                // Check if message is a user prompt
-                if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
-                    msg.get("type").and_then(|t| t.as_str()) == Some("user")
-                } else {
-                    false
-                }
-            }
-            CheckpointStrategy::PerToolUse => {
-                // Check if message contains tool use
-                if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
-                    if let Some(content) = msg
-                        .get("message")
-                        .and_then(|m| m.get("content"))
-                        .and_then(|c| c.as_array())
-                    {
-                        content.iter().any(|item| {
-                            item.get("type").and_then(|t| t.as_str()) == Some("tool_use")
-                        })
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            CheckpointStrategy::Smart => {
-                // Smart strategy: checkpoint after destructive operations
-                if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
-                    if let Some(content) = msg
-                        .get("message")
-                        .and_then(|m| m.get("content"))
-                        .and_then(|c| c.as_array())
-                    {
-                        content.iter().any(|item| {
-                            if item.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
-                                let tool_name =
-                                    item.get("name").and_then(|n| n.as_str()).unwrap_or("");
-                                matches!(
-                                    tool_name.to_lowercase().as_str(),
-                                    "write" | "edit" | "multiedit" | "bash" | "rm" | "delete"
-                                )
-                            } else {
-                                false
-                            }
-                        })
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
+            // Check if message is a user prompt
+            CheckpointStrategy::PerPrompt => entry.role == EntryRole::User,
+            // Check if message contains tool use
+            CheckpointStrategy::PerToolUse => !entry.tool_calls.is_empty(),
+            // Changes are picked up by the directory watcher instead
+            CheckpointStrategy::Watch => false,
+            // Smart strategy: checkpoint after destructive operations
+            CheckpointStrategy::Smart => entry.tool_calls.iter().any(|call| call.destructive),
         }
     }
 
@@ -750,10 +1002,26 @@ impl CheckpointManager {
         &self,
         auto_checkpoint_enabled: bool,
         checkpoint_strategy: CheckpointStrategy,
+        file_limits: Option<CheckpointFileLimits>,
+        storage_backend: Option<CheckpointBackend>,
     ) -> Result<()> {
         let mut timeline = self.timeline.write().await;
+
+        // Switching backends would strand existing snapshots in the old store
+        if let Some(backend) = storage_backend.filter(|b| *b != timeline.storage_backend) {
+            if timeline.total_checkpoints > 0 {
+                anyhow::bail!("Storage backend can only be changed before the first checkpoint");
+            }
+            let store = storage::open_store(self.storage.clone(), &backend)?;
+            *self.store.write().unwrap_or_else(PoisonError::into_inner) = store;
+            timeline.storage_backend = backend;
+        }
+
         timeline.auto_checkpoint_enabled = auto_checkpoint_enabled;
         timeline.checkpoint_strategy = checkpoint_strategy;
+        if let Some(limits) = file_limits {
+            timeline.file_limits = limits;
+        }
 
         // Save updated timeline
         let claude_dir = self.storage.claude_dir.clone();
@@ -764,6 +1032,90 @@ impl CheckpointManager {
         Ok(())
     }
 
+    /// Pin or unpin a checkpoint so retention keeps it
+    pub async fn set_checkpoint_pinned(
+        &self,
+        checkpoint_id: &str,
+        pinned: bool,
+    ) -> Result<Checkpoint> {
+        let mut timeline = self.timeline.write().await;
+        let node = timeline
+            .find_checkpoint_mut(checkpoint_id)
+            .with_context(|| format!("Checkpoint not found: {}", checkpoint_id))?;
+        node.checkpoint.pinned = pinned;
+        let checkpoint = node.checkpoint.clone();
+
+        let paths =
+            CheckpointPaths::new(&self.storage.claude_dir, &self.project_id, &self.session_id);
+        self.storage
+            .update_checkpoint_metadata(&paths, &checkpoint)?;
+        self.storage
+            .save_timeline(&paths.timeline_file, &timeline)?;
+
+        Ok(checkpoint)
+    }
+
+    /// Start or stop the directory watcher to match the current settings
+    pub async fn sync_watcher(self: &Arc<Self>) {
+        let watch = {
+            let timeline = self.timeline.read().await;
+            timeline.auto_checkpoint_enabled
+                && matches!(timeline.checkpoint_strategy, CheckpointStrategy::Watch)
+        };
+
+        let mut watcher = self.watcher.lock().unwrap_or_else(PoisonError::into_inner);
+        if !watch {
+            *watcher = None;
+        } else if watcher.is_none() {
+            log::info!(
+                "Watching {} for session {}",
+                self.project_path.display(),
+                self.session_id
+            );
+            *watcher = Some(ProjectWatcher::spawn(self, WatchConfig::default()));
+        }
+    }
+
+    /// Stop the directory watcher, if it is running
+    pub fn stop_watching(&self) {
+        *self.watcher.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    pub(super) fn project_path(&self) -> &Path {
+        &self.project_path
+    }
+
+    pub(super) fn restore_epoch(&self) -> u64 {
+        self.restore_epoch.load(Ordering::SeqCst)
+    }
+
+    /// Most recent tool call, preferring the on-disk session log over tracked messages
+    pub(super) async fn latest_tool_call(&self) -> Option<String> {
+        let log = Arc::clone(&self.session_log);
+        let project_id = self.project_id.clone();
+        let session_id = self.session_id.clone();
+        let from_transcript = tokio::task::spawn_blocking(move || {
+            log.locate(&project_id, &session_id)
+                .and_then(|path| watcher::latest_tool_call_in_transcript(log.as_ref(), &path))
+        })
+        .await
+        .ok()
+        .flatten();
+        if from_transcript.is_some() {
+            return from_transcript;
+        }
+        let messages = self.current_messages.read().await;
+        watcher::describe_latest_tool_call(
+            self.session_log.as_ref(),
+            messages.iter().map(String::as_str),
+        )
+    }
+
+    /// Reader for this session's log format
+    pub fn session_log(&self) -> Arc<dyn SessionLog> {
+        Arc::clone(&self.session_log)
+    }
+
     /// Get files modified since a given timestamp
     pub async fn get_files_modified_since(&self, since: DateTime<Utc>) -> Vec<PathBuf> {
         let tracker = self.file_tracker.read().await;
@@ -785,3 +1137,150 @@ impl CheckpointManager {
             .max()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn manager(temp_dir: &TempDir) -> CheckpointManager {
+        CheckpointManager::new(
+            "test-project".to_string(),
+            "test-session".to_string(),
+            temp_dir.path().join("project"),
+            temp_dir.path().join("claude"),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_incremental_checkpoints_reuse_unchanged_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join("src/a.txt"), "one").unwrap();
+        fs::write(project.join("logo.bin"), [0u8, 159, 146, 150]).unwrap();
+
+        let manager1 = manager(&temp_dir).await;
+        let first = manager1.create_checkpoint(None, None).await.unwrap();
+        assert_eq!(first.checkpoint.metadata.file_changes, 2);
+
+        fs::write(project.join("src/a.txt"), "one two").unwrap();
+        let second = manager1.create_checkpoint(None, None).await.unwrap();
+        assert_eq!(second.checkpoint.metadata.file_changes, 1);
+        assert!(second.warnings.is_empty());
+
+        // The manifest still lists every file, unchanged ones by reference
+        let manifest = manager1
+            .storage
+            .load_file_manifest("test-project", "test-session", &second.checkpoint.id)
+            .unwrap();
+        assert_eq!(manifest.len(), 2);
+
+        // A fresh manager picks up the tracker state from the current checkpoint
+        fs::remove_file(project.join("logo.bin")).unwrap();
+        let manager2 = manager(&temp_dir).await;
+        let third = manager2.create_checkpoint(None, None).await.unwrap();
+        assert_eq!(third.checkpoint.metadata.file_changes, 1);
+
+        manager2
+            .restore_checkpoint(&first.checkpoint.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(project.join("src/a.txt")).unwrap(),
+            "one"
+        );
+        assert_eq!(
+            fs::read(project.join("logo.bin")).unwrap(),
+            vec![0u8, 159, 146, 150]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_selective_restore_with_dry_run_and_hunks() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(&project).unwrap();
+        let original = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        fs::write(project.join("a.txt"), original).unwrap();
+
+        let manager = manager(&temp_dir).await;
+        let checkpoint = manager.create_checkpoint(None, None).await.unwrap();
+        let checkpoint_id = checkpoint.checkpoint.id;
+
+        let edited = "one\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\ntwelve\n";
+        fs::write(project.join("a.txt"), edited).unwrap();
+        fs::write(project.join("new.txt"), "new").unwrap();
+
+        let diff = manager
+            .diff_checkpoints(&checkpoint_id, None)
+            .await
+            .unwrap();
+        assert_eq!(diff.added_files, vec![PathBuf::from("new.txt")]);
+        assert_eq!(diff.modified_files[0].hunks.len(), 2);
+        assert!(diff.to_checkpoint_id.is_none());
+
+        // A dry run reports the full restore without writing anything
+        let preview = manager
+            .restore_files(&checkpoint_id, None, true)
+            .await
+            .unwrap();
+        let actions: Vec<_> = preview.changes.iter().map(|c| c.action).collect();
+        assert_eq!(actions, vec![RestoreAction::Modify, RestoreAction::Delete]);
+        assert!(project.join("new.txt").exists());
+
+        // Restore only the second hunk of a.txt
+        let report = manager
+            .restore_files(
+                &checkpoint_id,
+                Some(vec![RestoreFileSelection {
+                    path: PathBuf::from("a.txt"),
+                    hunks: Some(vec![1]),
+                }]),
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(report.warnings.is_empty());
+        assert_eq!(
+            fs::read_to_string(project.join("a.txt")).unwrap(),
+            "one\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n"
+        );
+        assert!(project.join("new.txt").exists());
+
+        // Paths outside the project and files the checkpoint never saw are refused
+        fs::write(project.join(".gitignore"), "build.log\n").unwrap();
+        fs::write(project.join("build.log"), "ignored").unwrap();
+        let report = manager
+            .restore_files(
+                &checkpoint_id,
+                Some(
+                    ["../outside.txt", "/etc/hosts", "build.log"]
+                        .into_iter()
+                        .map(|path| RestoreFileSelection {
+                            path: PathBuf::from(path),
+                            hunks: None,
+                        })
+                        .collect(),
+                ),
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(report.changes.is_empty());
+        assert_eq!(report.warnings.len(), 3);
+        assert!(project.join("build.log").exists());
+    }
+
+    #[test]
+    fn test_reversed_hunks_undo_the_diff() {
+        let checkpoint = "a\nb\nc\n";
+        let working = "a\nB\nc\nd\n";
+        let hunks = diff::diff_text(checkpoint, working);
+        let reversed: Vec<DiffHunk> = hunks.iter().map(diff::reverse_hunk).collect();
+        let selected: Vec<&DiffHunk> = reversed.iter().collect();
+        assert_eq!(diff::apply_hunks(working, &selected).unwrap(), checkpoint);
+    }
+}
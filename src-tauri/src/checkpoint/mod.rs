@@ -3,9 +3,16 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+pub mod archive;
+pub mod diff;
+pub mod git_store;
+pub mod ignore;
 pub mod manager;
+pub mod retention;
 pub mod state;
 pub mod storage;
+pub mod transcript;
+pub mod watcher;
 
 /// Represents a checkpoint in the session timeline
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +34,9 @@ pub struct Checkpoint {
     pub parent_checkpoint_id: Option<String>,
     /// Metadata about the checkpoint
     pub metadata: CheckpointMetadata,
+    /// Pinned checkpoints are never removed by retention
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 /// Metadata associated with a checkpoint
@@ -43,6 +53,12 @@ pub struct CheckpointMetadata {
     pub file_changes: usize,
     /// Size of all file snapshots in bytes
     pub snapshot_size: u64,
+    /// Files left out because of size limits; restore leaves them untouched
+    #[serde(default)]
+    pub skipped_files: Vec<PathBuf>,
+    /// Created by the directory watcher rather than on request
+    #[serde(default)]
+    pub automatic: bool,
 }
 
 /// Represents a snapshot of a file at a checkpoint
@@ -53,8 +69,11 @@ pub struct FileSnapshot {
     pub checkpoint_id: String,
     /// Relative path from project root
     pub file_path: PathBuf,
-    /// Full content of the file (will be compressed)
-    pub content: String,
+    /// Raw content of the file (will be compressed)
+    pub content: Vec<u8>,
+    /// Whether the content is binary rather than UTF-8 text
+    #[serde(default)]
+    pub is_binary: bool,
     /// SHA-256 hash for integrity verification
     pub hash: String,
     /// Whether this file was deleted at this checkpoint
@@ -63,6 +82,12 @@ pub struct FileSnapshot {
     pub permissions: Option<u32>,
     /// File size in bytes
     pub size: u64,
+    /// Last modification time when the snapshot was taken
+    #[serde(default)]
+    pub modified: Option<DateTime<Utc>>,
+    /// Content is unchanged since the parent checkpoint and reused from the content pool
+    #[serde(default)]
+    pub reused: bool,
 }
 
 /// Represents a node in the timeline tree
@@ -93,6 +118,36 @@ pub struct SessionTimeline {
     pub checkpoint_strategy: CheckpointStrategy,
     /// Total number of checkpoints in timeline
     pub total_checkpoints: usize,
+    /// Size limits applied when collecting project files
+    #[serde(default)]
+    pub file_limits: CheckpointFileLimits,
+    /// Backend that stores file snapshots
+    #[serde(default)]
+    pub storage_backend: CheckpointBackend,
+}
+
+/// Storage backend for checkpoint file snapshots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CheckpointBackend {
+    /// zstd-compressed content pool under the Claude directory
+    #[default]
+    Files,
+    /// Commits on hidden refs in the project's git repository
+    Git {
+        #[serde(rename = "repoPath")]
+        repo_path: PathBuf,
+    },
+}
+
+/// Size limits for files collected into a checkpoint (0 disables a limit)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointFileLimits {
+    /// Files larger than this are skipped
+    pub max_file_size: u64,
+    /// Stop collecting once the snapshot reaches this many bytes
+    pub max_total_size: u64,
 }
 
 /// Strategy for automatic checkpoint creation
@@ -107,6 +162,8 @@ pub enum CheckpointStrategy {
     PerToolUse,
     /// Create checkpoint after destructive operations
     Smart,
+    /// Create checkpoint after bursts of changes in the project directory
+    Watch,
 }
 
 /// Tracks the state of files for checkpointing
@@ -127,6 +184,10 @@ pub struct FileState {
     pub last_modified: DateTime<Utc>,
     /// Whether the file currently exists
     pub exists: bool,
+    /// File size in bytes when last hashed
+    pub size: u64,
+    /// Whether the file content is binary
+    pub is_binary: bool,
 }
 
 /// Result of a checkpoint operation
@@ -142,11 +203,12 @@ pub struct CheckpointResult {
 
 /// Diff between two checkpoints
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CheckpointDiff {
     /// Source checkpoint ID
     pub from_checkpoint_id: String,
-    /// Target checkpoint ID  
-    pub to_checkpoint_id: String,
+    /// Target checkpoint ID, or `None` for the working tree
+    pub to_checkpoint_id: Option<String>,
     /// Files that were modified
     pub modified_files: Vec<FileDiff>,
     /// Files that were added
@@ -159,6 +221,7 @@ pub struct CheckpointDiff {
 
 /// Diff for a single file
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FileDiff {
     /// File path
     pub path: PathBuf,
@@ -168,6 +231,79 @@ pub struct FileDiff {
     pub deletions: usize,
     /// Unified diff content (optional)
     pub diff_content: Option<String>,
+    /// Whether either side is binary, in which case no hunks are produced
+    #[serde(default)]
+    pub is_binary: bool,
+    /// Line-level hunks, indexed for selective restore
+    #[serde(default)]
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// A hunk of a unified diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    /// First line in the old file (1-based)
+    pub old_start: usize,
+    /// Number of old lines covered by the hunk
+    pub old_lines: usize,
+    /// First line in the new file (1-based)
+    pub new_start: usize,
+    /// Number of new lines covered by the hunk
+    pub new_lines: usize,
+    /// Lines prefixed with ' ', '-' or '+', including their line terminators
+    pub lines: Vec<String>,
+}
+
+/// Files (and optionally hunks) to restore from a checkpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreFileSelection {
+    /// File path relative to the project root
+    pub path: PathBuf,
+    /// Indices of hunks in the checkpoint -> working tree diff (as returned by
+    /// `get_checkpoint_diff` without a target); `None` restores the whole file
+    #[serde(default)]
+    pub hunks: Option<Vec<usize>>,
+}
+
+/// What a restore does to a single file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreAction {
+    Create,
+    Modify,
+    Delete,
+}
+
+/// Planned or applied change for a single file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreChange {
+    /// File path relative to the project root
+    pub path: PathBuf,
+    /// Change applied to the working tree
+    pub action: RestoreAction,
+    /// Lines added to the working tree file
+    pub additions: usize,
+    /// Lines removed from the working tree file
+    pub deletions: usize,
+    /// Unified diff from the working tree to the restored content
+    pub diff_content: Option<String>,
+}
+
+/// Result of a selective restore
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    /// Checkpoint the files were restored from
+    pub checkpoint_id: String,
+    /// Whether this was a dry run that wrote nothing
+    pub dry_run: bool,
+    /// Per-file changes; files that already match are omitted
+    pub changes: Vec<RestoreChange>,
+    /// Any warnings during the operation
+    pub warnings: Vec<String>,
 }
 
 impl Default for CheckpointStrategy {
@@ -176,6 +312,15 @@ impl Default for CheckpointStrategy {
     }
 }
 
+impl Default for CheckpointFileLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: 10 * 1024 * 1024,
+            max_total_size: 512 * 1024 * 1024,
+        }
+    }
+}
+
 impl SessionTimeline {
     /// Create a new empty timeline
     pub fn new(session_id: String) -> Self {
@@ -186,6 +331,8 @@ impl SessionTimeline {
             auto_checkpoint_enabled: false,
             checkpoint_strategy: CheckpointStrategy::default(),
             total_checkpoints: 0,
+            file_limits: CheckpointFileLimits::default(),
+            storage_backend: CheckpointBackend::default(),
         }
     }
 
@@ -209,6 +356,90 @@ impl SessionTimeline {
 
         None
     }
+
+    /// Find a checkpoint by ID for modification
+    pub fn find_checkpoint_mut(&mut self, checkpoint_id: &str) -> Option<&mut TimelineNode> {
+        self.root_node
+            .as_mut()
+            .and_then(|root| Self::find_in_tree_mut(root, checkpoint_id))
+    }
+
+    fn find_in_tree_mut<'a>(
+        node: &'a mut TimelineNode,
+        checkpoint_id: &str,
+    ) -> Option<&'a mut TimelineNode> {
+        if node.checkpoint.id == checkpoint_id {
+            return Some(node);
+        }
+
+        node.children
+            .iter_mut()
+            .find_map(|child| Self::find_in_tree_mut(child, checkpoint_id))
+    }
+
+    /// All checkpoints in the timeline, in tree order
+    pub fn checkpoints(&self) -> Vec<&Checkpoint> {
+        fn collect<'a>(node: &'a TimelineNode, out: &mut Vec<&'a Checkpoint>) {
+            out.push(&node.checkpoint);
+            for child in &node.children {
+                collect(child, out);
+            }
+        }
+
+        let mut checkpoints = Vec::new();
+        if let Some(root) = &self.root_node {
+            collect(root, &mut checkpoints);
+        }
+        checkpoints
+    }
+
+    /// Remove a checkpoint from the tree, reattaching its children to its parent
+    ///
+    /// A root with several children cannot be removed; returns whether the
+    /// checkpoint was removed.
+    pub fn remove_checkpoint(&mut self, checkpoint_id: &str) -> bool {
+        let Some(root) = self.root_node.as_mut() else {
+            return false;
+        };
+
+        if root.checkpoint.id == checkpoint_id {
+            if root.children.len() > 1 {
+                return false;
+            }
+            let mut new_root = root.children.pop();
+            if let Some(node) = &mut new_root {
+                node.checkpoint.parent_checkpoint_id = None;
+            }
+            self.root_node = new_root;
+        } else if !Self::remove_from_tree(root, checkpoint_id) {
+            return false;
+        }
+
+        self.total_checkpoints = self.total_checkpoints.saturating_sub(1);
+        if self.current_checkpoint_id.as_deref() == Some(checkpoint_id) {
+            self.current_checkpoint_id = None;
+        }
+        true
+    }
+
+    fn remove_from_tree(node: &mut TimelineNode, checkpoint_id: &str) -> bool {
+        if let Some(pos) = node
+            .children
+            .iter()
+            .position(|child| child.checkpoint.id == checkpoint_id)
+        {
+            let removed = node.children.remove(pos);
+            for (offset, mut child) in removed.children.into_iter().enumerate() {
+                child.checkpoint.parent_checkpoint_id = Some(node.checkpoint.id.clone());
+                node.children.insert(pos + offset, child);
+            }
+            return true;
+        }
+
+        node.children
+            .iter_mut()
+            .any(|child| Self::remove_from_tree(child, checkpoint_id))
+    }
 }
 
 /// Checkpoint storage paths
@@ -245,7 +476,6 @@ impl CheckpointPaths {
         self.checkpoint_dir(checkpoint_id).join("messages.jsonl")
     }
 
-    #[allow(dead_code)]
     pub fn file_snapshot_path(&self, _checkpoint_id: &str, file_hash: &str) -> PathBuf {
         // In content-addressable storage, files are stored by hash in the content pool
         self.files_dir.join("content_pool").join(file_hash)
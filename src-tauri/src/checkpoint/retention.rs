@@ -0,0 +1,769 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+use super::ignore;
+use super::state::CheckpointState;
+use super::storage::{self, CheckpointStorage, CheckpointStore};
+use super::transcript;
+use super::{Checkpoint, CheckpointBackend, CheckpointPaths, SessionTimeline};
+
+/// File under `<claude_dir>/projects` holding the retention policy
+pub const RETENTION_POLICY_FILE: &str = "checkpoint-retention.json";
+
+/// How often the background task applies the retention policy
+const RETENTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Sessions whose timeline changed this recently are treated as in use
+const ACTIVE_SESSION_GRACE_MINUTES: i64 = 10;
+
+/// Content written this recently may belong to a checkpoint still being saved
+const GC_GRACE_MINUTES: i64 = 10;
+
+/// Event emitted with a [`RetentionReport`] whenever the background task frees space
+pub const RETENTION_REPORT_EVENT: &str = "checkpoint-retention-report";
+
+/// Policy deciding which checkpoints are kept
+///
+/// Ages are measured from the time the policy is applied. Pinned checkpoints,
+/// checkpoints with a user-provided description and each session's current
+/// checkpoint are always kept, even when a quota is exceeded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionPolicy {
+    /// Whether retention runs at all; off until a policy is saved
+    #[serde(default = "saved_policy_enabled")]
+    pub enabled: bool,
+    /// Keep every checkpoint younger than this many hours
+    pub keep_all_hours: u64,
+    /// Up to this age in hours, keep the newest checkpoint of each hour
+    pub keep_hourly_hours: u64,
+    /// Up to this age in days, keep the newest checkpoint of each day
+    pub keep_daily_days: u64,
+    /// Disk quota per project in bytes (0 = unlimited)
+    pub max_project_bytes: u64,
+    /// Disk quota across all projects in bytes (0 = unlimited)
+    pub max_total_bytes: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keep_all_hours: 24,
+            keep_hourly_hours: 72,
+            keep_daily_days: 30,
+            max_project_bytes: 1024 * 1024 * 1024,
+            max_total_bytes: 5 * 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// Policies saved before `enabled` existed were opted into explicitly
+fn saved_policy_enabled() -> bool {
+    true
+}
+
+impl RetentionPolicy {
+    fn path(claude_dir: &Path) -> PathBuf {
+        claude_dir.join("projects").join(RETENTION_POLICY_FILE)
+    }
+
+    /// Load the saved policy, falling back to the defaults
+    pub fn load(claude_dir: &Path) -> Self {
+        let path = Self::path(claude_dir);
+        match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::warn!("Invalid retention policy {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Persist the policy
+    pub fn save(&self, claude_dir: &Path) -> Result<()> {
+        let path = Self::path(claude_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create projects directory")?;
+        }
+        let json =
+            serde_json::to_string_pretty(self).context("Failed to serialize retention policy")?;
+        fs::write(&path, json).context("Failed to write retention policy")
+    }
+}
+
+/// Outcome of applying a retention policy
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionReport {
+    /// Sessions the policy was applied to
+    pub sessions_scanned: usize,
+    /// Sessions left alone because they are in use
+    pub sessions_skipped: usize,
+    /// Sessions using the git backend, whose file contents live in the project
+    /// repository and are neither counted towards quotas nor pruned
+    pub git_sessions_exempt: usize,
+    /// Checkpoints removed by thinning or quotas
+    pub checkpoints_removed: usize,
+    /// Content files removed by garbage collection
+    pub content_files_removed: usize,
+    /// Checkpoint storage size before the run
+    pub bytes_before: u64,
+    /// Checkpoint storage size after the run
+    pub bytes_after: u64,
+    /// Space freed by the run
+    pub bytes_reclaimed: u64,
+    /// Problems encountered along the way
+    pub warnings: Vec<String>,
+}
+
+/// Checkpoints retention must never remove
+fn protected_ids(timeline: &SessionTimeline) -> HashSet<String> {
+    let mut protected: HashSet<String> = timeline
+        .checkpoints()
+        .into_iter()
+        .filter(|c| {
+            c.pinned
+                || (!c.metadata.automatic
+                    && c.description
+                        .as_deref()
+                        .is_some_and(|d| !d.trim().is_empty()))
+        })
+        .map(|c| c.id.clone())
+        .collect();
+    protected.extend(timeline.current_checkpoint_id.clone());
+    // Removing a branching root would split the tree
+    if let Some(root) = timeline.root_node.as_ref().filter(|r| r.children.len() > 1) {
+        protected.insert(root.checkpoint.id.clone());
+    }
+    protected
+}
+
+/// IDs of the checkpoints the age-based tiers of `policy` drop
+pub fn select_expired(
+    checkpoints: &[&Checkpoint],
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    protected: &HashSet<String>,
+) -> Vec<String> {
+    let keep_all = Duration::hours(policy.keep_all_hours as i64);
+    let keep_hourly = Duration::hours(policy.keep_hourly_hours as i64);
+    let keep_daily = Duration::days(policy.keep_daily_days as i64);
+
+    let mut newest_first = checkpoints.to_vec();
+    newest_first.sort_by_key(|c| std::cmp::Reverse(c.timestamp));
+
+    let mut hours_seen = HashSet::new();
+    let mut days_seen = HashSet::new();
+    let mut expired = Vec::new();
+    for checkpoint in newest_first {
+        if protected.contains(&checkpoint.id) {
+            continue;
+        }
+        let age = now - checkpoint.timestamp;
+        let ts = checkpoint.timestamp;
+        let keep = if age < keep_all {
+            true
+        } else if age < keep_hourly {
+            hours_seen.insert((ts.date_naive(), ts.hour()))
+        } else if age < keep_daily {
+            days_seen.insert(ts.date_naive())
+        } else {
+            false
+        };
+        if !keep {
+            expired.push(checkpoint.id.clone());
+        }
+    }
+    expired
+}
+
+/// A session's checkpoint storage as seen by a retention run
+struct SessionEntry {
+    project_id: String,
+    session_id: String,
+    dir: PathBuf,
+    /// Size on disk, minus what the planned removals will free
+    bytes: u64,
+    /// `None` for sessions that are skipped
+    store: Option<Arc<dyn CheckpointStore>>,
+    /// Checkpoints quotas may still remove, oldest first
+    candidates: VecDeque<QuotaCandidate>,
+    /// Reference count and size of each content pool entry of the remaining checkpoints
+    content: HashMap<String, (usize, u64)>,
+    /// Checkpoints the quotas decided to remove
+    planned: Vec<String>,
+}
+
+/// A checkpoint quotas may remove, with the storage only it accounts for
+struct QuotaCandidate {
+    id: String,
+    timestamp: DateTime<Utc>,
+    /// Metadata, messages and file references of the checkpoint
+    own_bytes: u64,
+    /// Content pool entries the checkpoint references
+    hashes: Vec<String>,
+}
+
+impl SessionEntry {
+    /// Plan removing the oldest candidate, returning the bytes that will free
+    fn plan_removal(&mut self) -> u64 {
+        let Some(candidate) = self.candidates.pop_front() else {
+            return 0;
+        };
+        let mut freed = candidate.own_bytes;
+        for hash in &candidate.hashes {
+            if let Some((count, size)) = self.content.get_mut(hash) {
+                *count -= 1;
+                if *count == 0 {
+                    freed += *size;
+                }
+            }
+        }
+        self.bytes = self.bytes.saturating_sub(freed);
+        self.planned.push(candidate.id);
+        freed
+    }
+}
+
+/// Apply `policy` to every checkpointed session under `claude_dir`
+///
+/// Sessions in `active_sessions`, or whose timeline changed recently, are only
+/// counted towards the quotas.
+pub fn apply_retention(
+    claude_dir: &Path,
+    policy: &RetentionPolicy,
+    active_sessions: &HashSet<String>,
+    now: DateTime<Utc>,
+) -> Result<RetentionReport> {
+    let mut report = RetentionReport::default();
+    let gc_cutoff = now - Duration::minutes(GC_GRACE_MINUTES);
+    let active_cutoff = now - Duration::minutes(ACTIVE_SESSION_GRACE_MINUTES);
+    let has_quota = policy.max_project_bytes > 0 || policy.max_total_bytes > 0;
+
+    let mut entries = Vec::new();
+    for (project_id, session_id, dir) in discover_sessions(claude_dir)? {
+        let mut entry = SessionEntry {
+            project_id,
+            session_id,
+            bytes: dir_size(&dir),
+            dir,
+            store: None,
+            candidates: VecDeque::new(),
+            content: HashMap::new(),
+            planned: Vec::new(),
+        };
+        report.bytes_before += entry.bytes;
+
+        let recently_changed = fs::metadata(entry.dir.join("timeline.json"))
+            .map(|m| ignore::modified_at(&m) >= active_cutoff)
+            .unwrap_or(false);
+        if active_sessions.contains(&entry.session_id) || recently_changed {
+            report.sessions_skipped += 1;
+        } else {
+            report.sessions_scanned += 1;
+            if let Err(e) = thin_session(
+                claude_dir,
+                &mut entry,
+                policy,
+                now,
+                gc_cutoff,
+                has_quota,
+                &mut report,
+            ) {
+                report.warnings.push(format!(
+                    "Failed to apply retention to session {}: {}",
+                    entry.session_id, e
+                ));
+            }
+        }
+        entries.push(entry);
+    }
+
+    if policy.max_project_bytes > 0 {
+        let projects: BTreeSet<String> = entries.iter().map(|e| e.project_id.clone()).collect();
+        for project_id in projects {
+            let mut group: Vec<&mut SessionEntry> = entries
+                .iter_mut()
+                .filter(|e| e.project_id == project_id)
+                .collect();
+            enforce_quota(
+                &mut group,
+                policy.max_project_bytes,
+                &format!("Project {}", project_id),
+                &mut report,
+            );
+        }
+    }
+    if policy.max_total_bytes > 0 {
+        let mut group: Vec<&mut SessionEntry> = entries.iter_mut().collect();
+        enforce_quota(
+            &mut group,
+            policy.max_total_bytes,
+            "Checkpoint storage",
+            &mut report,
+        );
+    }
+
+    for entry in entries.iter_mut().filter(|e| !e.planned.is_empty()) {
+        remove_planned(entry, gc_cutoff, &mut report);
+    }
+
+    report.bytes_after = entries.iter().map(|e| e.bytes).sum();
+    report.bytes_reclaimed = report.bytes_before.saturating_sub(report.bytes_after);
+    Ok(report)
+}
+
+/// Drop the checkpoints the age tiers expire and collect their content
+///
+/// With `has_quota`, also measures what each remaining checkpoint holds so the
+/// quotas can be planned without touching the disk again.
+fn thin_session(
+    claude_dir: &Path,
+    entry: &mut SessionEntry,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    gc_cutoff: DateTime<Utc>,
+    has_quota: bool,
+    report: &mut RetentionReport,
+) -> Result<()> {
+    let store = storage::open_session_store(
+        claude_dir.to_path_buf(),
+        &entry.project_id,
+        &entry.session_id,
+    )?;
+    let paths = CheckpointPaths::new(
+        &claude_dir.to_path_buf(),
+        &entry.project_id,
+        &entry.session_id,
+    );
+    let timeline =
+        CheckpointStorage::new(claude_dir.to_path_buf()).load_timeline(&paths.timeline_file)?;
+    let protected = protected_ids(&timeline);
+    let checkpoints = timeline.checkpoints();
+
+    let expired = select_expired(&checkpoints, policy, now, &protected);
+    if !expired.is_empty() {
+        report.checkpoints_removed +=
+            store.remove_checkpoints(&entry.project_id, &entry.session_id, &expired)?;
+    }
+    let gc = store.garbage_collect(&entry.project_id, &entry.session_id, gc_cutoff)?;
+    report.content_files_removed += gc.files_removed;
+
+    entry.bytes = dir_size(&entry.dir);
+
+    // Git-backed content lives in the project repository, out of reach of the quotas
+    if matches!(timeline.storage_backend, CheckpointBackend::Git { .. }) {
+        report.git_sessions_exempt += 1;
+        return Ok(());
+    }
+    entry.store = Some(store.clone());
+    if !has_quota {
+        return Ok(());
+    }
+
+    let expired: HashSet<&String> = expired.iter().collect();
+    let mut remaining: Vec<&Checkpoint> = checkpoints
+        .into_iter()
+        .filter(|c| !expired.contains(&c.id))
+        .collect();
+    remaining.sort_by_key(|c| c.timestamp);
+    for checkpoint in remaining {
+        let hashes: Vec<String> = store
+            .load_file_manifest(&entry.project_id, &entry.session_id, &checkpoint.id)?
+            .into_iter()
+            .filter(|s| !s.is_deleted)
+            .map(|s| s.hash)
+            .collect();
+        for hash in &hashes {
+            let (count, _) = entry.content.entry(hash.clone()).or_insert_with(|| {
+                let size = fs::metadata(paths.file_snapshot_path(&checkpoint.id, hash))
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+                (0, size)
+            });
+            *count += 1;
+        }
+        if !protected.contains(&checkpoint.id) {
+            entry.candidates.push_back(QuotaCandidate {
+                id: checkpoint.id.clone(),
+                timestamp: checkpoint.timestamp,
+                own_bytes: dir_size(&paths.checkpoint_dir(&checkpoint.id))
+                    + dir_size(&paths.files_dir.join("refs").join(&checkpoint.id)),
+                hashes,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Plan removing the oldest removable checkpoints in `group` until it fits in `quota`
+fn enforce_quota(
+    group: &mut [&mut SessionEntry],
+    quota: u64,
+    scope: &str,
+    report: &mut RetentionReport,
+) {
+    let mut total: u64 = group.iter().map(|e| e.bytes).sum();
+    while total > quota {
+        let oldest = group
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.candidates.front().map(|c| (i, c.timestamp)))
+            .min_by_key(|(_, timestamp)| *timestamp)
+            .map(|(i, _)| i);
+        let Some(index) = oldest else {
+            report.warnings.push(format!(
+                "{} uses {} bytes, over its quota of {} bytes, but has no removable checkpoints left",
+                scope, total, quota
+            ));
+            return;
+        };
+        total = total.saturating_sub(group[index].plan_removal());
+    }
+}
+
+/// Remove the checkpoints the quotas planned for a session and collect their content once
+fn remove_planned(
+    entry: &mut SessionEntry,
+    gc_cutoff: DateTime<Utc>,
+    report: &mut RetentionReport,
+) {
+    let Some(store) = entry.store.clone() else {
+        return;
+    };
+    match store.remove_checkpoints(&entry.project_id, &entry.session_id, &entry.planned) {
+        Ok(removed) => report.checkpoints_removed += removed,
+        Err(e) => report.warnings.push(format!(
+            "Failed to remove checkpoints of session {}: {}",
+            entry.session_id, e
+        )),
+    }
+    match store.garbage_collect(&entry.project_id, &entry.session_id, gc_cutoff) {
+        Ok(gc) => report.content_files_removed += gc.files_removed,
+        Err(e) => report.warnings.push(format!(
+            "Failed to garbage collect session {}: {}",
+            entry.session_id, e
+        )),
+    }
+    entry.bytes = dir_size(&entry.dir);
+}
+
+/// Every `(project_id, session_id, timeline dir)` with checkpoint storage
+fn discover_sessions(claude_dir: &Path) -> Result<Vec<(String, String, PathBuf)>> {
+    let projects_dir = claude_dir.join("projects");
+    let mut sessions = Vec::new();
+    if !projects_dir.exists() {
+        return Ok(sessions);
+    }
+
+    for project in fs::read_dir(&projects_dir)? {
+        let project = project?;
+        let timelines_dir = project.path().join(".timelines");
+        let Ok(session_dirs) = fs::read_dir(&timelines_dir) else {
+            continue;
+        };
+        let project_id = project.file_name().to_string_lossy().to_string();
+        for session in session_dirs {
+            let session = session?;
+            let dir = session.path();
+            if dir.join("timeline.json").is_file() {
+                let session_id = session.file_name().to_string_lossy().to_string();
+                sessions.push((project_id.clone(), session_id, dir));
+            }
+        }
+    }
+    sessions.sort();
+    Ok(sessions)
+}
+
+/// Total size of the files below `dir`
+fn dir_size(dir: &Path) -> u64 {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Apply `policy` to the checkpoints of every supported CLI
+///
+/// Each app keeps its checkpoints in its own directory, so `max_total_bytes`
+/// is enforced per app.
+pub fn apply_retention_to_all_apps(
+    claude_dir: &Path,
+    policy: &RetentionPolicy,
+    active_sessions: &HashSet<String>,
+    now: DateTime<Utc>,
+) -> Result<RetentionReport> {
+    let mut report = RetentionReport::default();
+    for root in transcript::checkpoint_roots(claude_dir) {
+        let part = apply_retention(&root, policy, active_sessions, now)
+            .with_context(|| format!("Failed to apply retention in {}", root.display()))?;
+        report.sessions_scanned += part.sessions_scanned;
+        report.sessions_skipped += part.sessions_skipped;
+        report.git_sessions_exempt += part.git_sessions_exempt;
+        report.checkpoints_removed += part.checkpoints_removed;
+        report.content_files_removed += part.content_files_removed;
+        report.bytes_before += part.bytes_before;
+        report.bytes_after += part.bytes_after;
+        report.bytes_reclaimed += part.bytes_reclaimed;
+        report.warnings.extend(part.warnings);
+    }
+    Ok(report)
+}
+
+/// Periodically apply the saved retention policy, skipping sessions with an active manager
+///
+/// Nothing is removed until the user saves an enabled policy.
+pub fn spawn_retention_scheduler(app: AppHandle, state: CheckpointState) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(claude_dir) = state.claude_dir().await else {
+                continue;
+            };
+            let active: HashSet<String> = state.list_active_sessions().await.into_iter().collect();
+
+            let policy = RetentionPolicy::load(&claude_dir);
+            if !policy.enabled {
+                continue;
+            }
+
+            let result = tokio::task::spawn_blocking(move || {
+                apply_retention_to_all_apps(&claude_dir, &policy, &active, Utc::now())
+            })
+            .await;
+            match result {
+                Ok(Ok(report)) => {
+                    for warning in &report.warnings {
+                        log::warn!("Checkpoint retention: {}", warning);
+                    }
+                    if report.checkpoints_removed > 0 || report.content_files_removed > 0 {
+                        log::info!(
+                            "Checkpoint retention removed {} checkpoints and {} content files, reclaiming {} bytes",
+                            report.checkpoints_removed,
+                            report.content_files_removed,
+                            report.bytes_reclaimed
+                        );
+                        let _ = app.emit(RETENTION_REPORT_EVENT, &report);
+                    }
+                }
+                Ok(Err(e)) => log::warn!("Checkpoint retention failed: {}", e),
+                Err(e) => log::warn!("Checkpoint retention task panicked: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::manager::CheckpointManager;
+    use crate::checkpoint::CheckpointMetadata;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn checkpoint(id: &str, timestamp: DateTime<Utc>) -> Checkpoint {
+        Checkpoint {
+            id: id.to_string(),
+            session_id: "s".to_string(),
+            project_id: "p".to_string(),
+            message_index: 0,
+            timestamp,
+            description: None,
+            parent_checkpoint_id: None,
+            metadata: CheckpointMetadata {
+                total_tokens: 0,
+                model_used: String::new(),
+                user_prompt: String::new(),
+                file_changes: 0,
+                snapshot_size: 0,
+                skipped_files: Vec::new(),
+                automatic: false,
+            },
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn thins_checkpoints_by_age_tier() {
+        let now = Utc.with_ymd_and_hms(2026, 1, 10, 12, 0, 0).unwrap();
+        let ago = |minutes: i64| now - Duration::minutes(minutes);
+        let checkpoints = [
+            checkpoint("recent-1", ago(60)),
+            checkpoint("recent-2", ago(61)),
+            // 05:50 and 05:20 on the 9th share an hour
+            checkpoint("hourly-keep", ago(30 * 60 + 10)),
+            checkpoint("hourly-drop", ago(30 * 60 + 40)),
+            // 12:00 and 10:00 on the 5th share a day
+            checkpoint("daily-keep", ago(5 * 24 * 60)),
+            checkpoint("daily-drop", ago(5 * 24 * 60 + 120)),
+            checkpoint("ancient", ago(40 * 24 * 60)),
+            checkpoint("ancient-pinned", ago(41 * 24 * 60)),
+        ];
+        let refs: Vec<&Checkpoint> = checkpoints.iter().collect();
+        let protected = HashSet::from(["ancient-pinned".to_string()]);
+
+        let mut expired = select_expired(&refs, &RetentionPolicy::default(), now, &protected);
+        expired.sort();
+        assert_eq!(expired, vec!["ancient", "daily-drop", "hourly-drop"]);
+    }
+
+    async fn checkpointed_session(temp_dir: &TempDir, count: usize) -> CheckpointManager {
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(&project).unwrap();
+        let manager = CheckpointManager::new(
+            "test-project".to_string(),
+            "test-session".to_string(),
+            project.clone(),
+            temp_dir.path().join("claude"),
+        )
+        .await
+        .unwrap();
+        for i in 0..count {
+            fs::write(
+                project.join("file.txt"),
+                format!("version {}\n", i).repeat(100),
+            )
+            .unwrap();
+            manager.create_checkpoint(None, None).await.unwrap();
+        }
+        manager
+    }
+
+    #[tokio::test]
+    async fn expires_checkpoints_and_reclaims_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = checkpointed_session(&temp_dir, 3).await;
+        let checkpoints = manager.list_checkpoints().await;
+        manager
+            .set_checkpoint_pinned(&checkpoints[0].id, true)
+            .await
+            .unwrap();
+
+        let claude_dir = temp_dir.path().join("claude");
+        let later = Utc::now() + Duration::days(60);
+        let report = apply_retention(
+            &claude_dir,
+            &RetentionPolicy::default(),
+            &HashSet::new(),
+            later,
+        )
+        .unwrap();
+
+        assert_eq!(report.sessions_scanned, 1);
+        assert_eq!(report.checkpoints_removed, 1);
+        assert_eq!(report.content_files_removed, 1);
+        assert!(report.bytes_reclaimed > 0);
+
+        let paths = CheckpointPaths::new(&claude_dir, "test-project", "test-session");
+        let timeline = CheckpointStorage::new(claude_dir.clone())
+            .load_timeline(&paths.timeline_file)
+            .unwrap();
+        let remaining: Vec<&str> = timeline
+            .checkpoints()
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect();
+        assert_eq!(
+            remaining,
+            vec![checkpoints[0].id.as_str(), checkpoints[2].id.as_str()]
+        );
+        assert_eq!(timeline.total_checkpoints, 2);
+        assert!(!paths.checkpoint_dir(&checkpoints[1].id).exists());
+        manager
+            .store()
+            .load_checkpoint("test-project", "test-session", &checkpoints[2].id)
+            .unwrap();
+
+        // Active sessions are left alone
+        let active = HashSet::from(["test-session".to_string()]);
+        let report =
+            apply_retention(&claude_dir, &RetentionPolicy::default(), &active, later).unwrap();
+        assert_eq!(report.sessions_skipped, 1);
+        assert_eq!(report.checkpoints_removed, 0);
+    }
+
+    #[tokio::test]
+    async fn enforces_project_quota_oldest_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = checkpointed_session(&temp_dir, 4).await;
+        let checkpoints = manager.list_checkpoints().await;
+
+        let claude_dir = temp_dir.path().join("claude");
+        let policy = RetentionPolicy {
+            max_project_bytes: 1,
+            ..RetentionPolicy::default()
+        };
+        let report = apply_retention(
+            &claude_dir,
+            &policy,
+            &HashSet::new(),
+            Utc::now() + Duration::hours(1),
+        )
+        .unwrap();
+
+        // Everything but the current checkpoint goes, and the quota still can't be met
+        assert_eq!(report.checkpoints_removed, 3);
+        assert_eq!(report.warnings.len(), 1);
+        let paths = CheckpointPaths::new(&claude_dir, "test-project", "test-session");
+        let timeline = CheckpointStorage::new(claude_dir.clone())
+            .load_timeline(&paths.timeline_file)
+            .unwrap();
+        assert_eq!(timeline.root_node.unwrap().checkpoint.id, checkpoints[3].id);
+    }
+
+    #[tokio::test]
+    async fn plans_quota_removals_from_checkpoint_sizes() {
+        let temp_dir = TempDir::new().unwrap();
+        let _manager = checkpointed_session(&temp_dir, 4).await;
+        let claude_dir = temp_dir.path().join("claude");
+        let paths = CheckpointPaths::new(&claude_dir, "test-project", "test-session");
+        let session_dir = paths.timeline_file.parent().unwrap().to_path_buf();
+
+        // One byte over the quota: only the oldest checkpoint has to go
+        let policy = RetentionPolicy {
+            max_project_bytes: dir_size(&session_dir) - 1,
+            ..RetentionPolicy::default()
+        };
+        let report = apply_retention(
+            &claude_dir,
+            &policy,
+            &HashSet::new(),
+            Utc::now() + Duration::hours(1),
+        )
+        .unwrap();
+
+        assert_eq!(report.checkpoints_removed, 1);
+        assert_eq!(report.content_files_removed, 1);
+        assert!(report.warnings.is_empty());
+        assert!(report.bytes_after <= policy.max_project_bytes);
+    }
+
+    #[test]
+    fn retention_is_off_until_a_policy_is_saved() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(!RetentionPolicy::load(temp_dir.path()).enabled);
+
+        // Policies saved before the switch existed stay in effect
+        fs::create_dir_all(temp_dir.path().join("projects")).unwrap();
+        fs::write(
+            RetentionPolicy::path(temp_dir.path()),
+            r#"{"keepAllHours": 12}"#,
+        )
+        .unwrap();
+        let policy = RetentionPolicy::load(temp_dir.path());
+        assert!(policy.enabled);
+        assert_eq!(policy.keep_all_hours, 12);
+    }
+}
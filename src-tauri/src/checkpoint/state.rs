@@ -5,6 +5,8 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use super::manager::CheckpointManager;
+use super::transcript;
+use crate::app_config::AppType;
 
 /// Manages checkpoint managers for active sessions
 ///
@@ -37,6 +39,11 @@ impl CheckpointState {
         *dir = Some(claude_dir);
     }
 
+    /// Gets the Claude directory path, if it has been set
+    pub async fn claude_dir(&self) -> Option<PathBuf> {
+        self.claude_dir.read().await.clone()
+    }
+
     /// Gets or creates a CheckpointManager for a session
     ///
     /// If a manager already exists for the session, it returns the existing one.
@@ -54,6 +61,21 @@ impl CheckpointState {
         session_id: String,
         project_id: String,
         project_path: PathBuf,
+    ) -> Result<Arc<CheckpointManager>> {
+        self.get_or_create_app_manager(AppType::Claude, session_id, project_id, project_path)
+            .await
+    }
+
+    /// Gets or creates a CheckpointManager for a session of the given CLI
+    ///
+    /// The app decides how the session log is read and where checkpoint data is
+    /// stored. Session IDs are unique across apps, so managers share one map.
+    pub async fn get_or_create_app_manager(
+        &self,
+        app: AppType,
+        session_id: String,
+        project_id: String,
+        project_path: PathBuf,
     ) -> Result<Arc<CheckpointManager>> {
         let mut managers = self.managers.write().await;
 
@@ -71,11 +93,17 @@ impl CheckpointState {
         };
 
         // Create new manager
-        let manager =
-            CheckpointManager::new(project_id, session_id.clone(), project_path, claude_dir)
-                .await?;
+        let manager = CheckpointManager::with_session_log(
+            project_id,
+            session_id.clone(),
+            project_path,
+            transcript::checkpoint_root(&app, &claude_dir),
+            transcript::session_log(&app, &claude_dir),
+        )
+        .await?;
 
         let manager_arc = Arc::new(manager);
+        manager_arc.sync_watcher().await;
         managers.insert(session_id, Arc::clone(&manager_arc));
 
         Ok(manager_arc)
@@ -84,7 +112,6 @@ impl CheckpointState {
     /// Gets an existing CheckpointManager for a session
     ///
     /// Returns None if no manager exists for the session
-    #[allow(dead_code)]
     pub async fn get_manager(&self, session_id: &str) -> Option<Arc<CheckpointManager>> {
         let managers = self.managers.read().await;
         managers.get(session_id).map(Arc::clone)
@@ -95,7 +122,11 @@ impl CheckpointState {
     /// This should be called when a session ends to free resources
     pub async fn remove_manager(&self, session_id: &str) -> Option<Arc<CheckpointManager>> {
         let mut managers = self.managers.write().await;
-        managers.remove(session_id)
+        let removed = managers.remove(session_id);
+        if let Some(manager) = &removed {
+            manager.stop_watching();
+        }
+        removed
     }
 
     /// Clears all managers
@@ -104,6 +135,9 @@ impl CheckpointState {
     #[allow(dead_code)]
     pub async fn clear_all(&self) {
         let mut managers = self.managers.write().await;
+        for manager in managers.values() {
+            manager.stop_watching();
+        }
         managers.clear();
     }
 
@@ -1,14 +1,118 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use uuid::Uuid;
 use zstd::stream::{decode_all, encode_all};
 
 use super::{
-    Checkpoint, CheckpointPaths, CheckpointResult, FileSnapshot, SessionTimeline, TimelineNode,
+    git_store::GitCheckpointStorage, Checkpoint, CheckpointBackend, CheckpointPaths,
+    CheckpointResult, FileSnapshot, SessionTimeline, TimelineNode,
 };
 
+/// Where checkpoint file content is persisted
+///
+/// Timelines, checkpoint metadata and messages always live under the Claude
+/// directory; implementations differ in how file snapshots are stored.
+pub trait CheckpointStore: Send + Sync {
+    /// Save a checkpoint with its file snapshots and messages
+    fn save_checkpoint(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint: &Checkpoint,
+        file_snapshots: Vec<FileSnapshot>,
+        messages: &str,
+    ) -> Result<CheckpointResult>;
+
+    /// Load a checkpoint with file contents and messages
+    fn load_checkpoint(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<(Checkpoint, Vec<FileSnapshot>, String)>;
+
+    /// Load the file manifest of a checkpoint without reading file contents
+    fn load_file_manifest(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<Vec<FileSnapshot>>;
+
+    /// Remove all but the `keep_count` most recent checkpoints
+    fn cleanup_old_checkpoints(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        keep_count: usize,
+    ) -> Result<usize>;
+
+    /// Remove the given checkpoints and splice them out of the timeline
+    ///
+    /// Returns the number of checkpoints actually removed; a root with several
+    /// children is kept.
+    fn remove_checkpoints(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_ids: &[String],
+    ) -> Result<usize>;
+
+    /// Delete stored content that no checkpoint references any more
+    ///
+    /// Content written at or after `written_before` is kept, so snapshots that are
+    /// still being saved are never collected.
+    fn garbage_collect(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        written_before: DateTime<Utc>,
+    ) -> Result<GcStats>;
+}
+
+/// Outcome of a content garbage collection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    /// Content files removed
+    pub files_removed: usize,
+    /// Bytes freed on disk
+    pub bytes_reclaimed: u64,
+}
+
+/// Open the store for the given backend
+pub fn open_store(
+    storage: Arc<CheckpointStorage>,
+    backend: &CheckpointBackend,
+) -> Result<Arc<dyn CheckpointStore>> {
+    Ok(match backend {
+        CheckpointBackend::Files => storage,
+        CheckpointBackend::Git { repo_path } => {
+            Arc::new(GitCheckpointStorage::new(storage, repo_path.clone())?)
+        }
+    })
+}
+
+/// Open the store configured in a session's timeline
+pub fn open_session_store(
+    claude_dir: PathBuf,
+    project_id: &str,
+    session_id: &str,
+) -> Result<Arc<dyn CheckpointStore>> {
+    let storage = Arc::new(CheckpointStorage::new(claude_dir));
+    let paths = CheckpointPaths::new(&storage.claude_dir, project_id, session_id);
+    let backend = if paths.timeline_file.exists() {
+        storage.load_timeline(&paths.timeline_file)?.storage_backend
+    } else {
+        CheckpointBackend::default()
+    };
+    open_store(storage, &backend)
+}
+
 /// Manages checkpoint storage operations
 pub struct CheckpointStorage {
     pub claude_dir: PathBuf,
@@ -52,23 +156,7 @@ impl CheckpointStorage {
         messages: &str, // JSONL content up to checkpoint
     ) -> Result<CheckpointResult> {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
-        let checkpoint_dir = paths.checkpoint_dir(&checkpoint.id);
-
-        // Create checkpoint directory
-        fs::create_dir_all(&checkpoint_dir).context("Failed to create checkpoint directory")?;
-
-        // Save checkpoint metadata
-        let metadata_path = paths.checkpoint_metadata_file(&checkpoint.id);
-        let metadata_json = serde_json::to_string_pretty(checkpoint)
-            .context("Failed to serialize checkpoint metadata")?;
-        fs::write(&metadata_path, metadata_json).context("Failed to write checkpoint metadata")?;
-
-        // Save messages (compressed)
-        let messages_path = paths.checkpoint_messages_file(&checkpoint.id);
-        let compressed_messages = encode_all(messages.as_bytes(), self.compression_level)
-            .context("Failed to compress messages")?;
-        fs::write(&messages_path, compressed_messages)
-            .context("Failed to write compressed messages")?;
+        self.save_checkpoint_record(&paths, checkpoint, messages)?;
 
         // Save file snapshots
         let mut warnings = Vec::new();
@@ -95,6 +183,34 @@ impl CheckpointStorage {
         })
     }
 
+    /// Save checkpoint metadata and compressed messages
+    pub(super) fn save_checkpoint_record(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint: &Checkpoint,
+        messages: &str,
+    ) -> Result<()> {
+        let checkpoint_dir = paths.checkpoint_dir(&checkpoint.id);
+
+        // Create checkpoint directory
+        fs::create_dir_all(&checkpoint_dir).context("Failed to create checkpoint directory")?;
+
+        // Save checkpoint metadata
+        let metadata_path = paths.checkpoint_metadata_file(&checkpoint.id);
+        let metadata_json = serde_json::to_string_pretty(checkpoint)
+            .context("Failed to serialize checkpoint metadata")?;
+        fs::write(&metadata_path, metadata_json).context("Failed to write checkpoint metadata")?;
+
+        // Save messages (compressed)
+        let messages_path = paths.checkpoint_messages_file(&checkpoint.id);
+        let compressed_messages = encode_all(messages.as_bytes(), self.compression_level)
+            .context("Failed to compress messages")?;
+        fs::write(&messages_path, compressed_messages)
+            .context("Failed to write compressed messages")?;
+
+        Ok(())
+    }
+
     /// Save a single file snapshot
     fn save_file_snapshot(&self, paths: &CheckpointPaths, snapshot: &FileSnapshot) -> Result<()> {
         // Use content-addressable storage: store files by their hash
@@ -107,10 +223,13 @@ impl CheckpointStorage {
 
         // Only write the content if it doesn't already exist
         if !content_file.exists() {
+            // Reused snapshots carry no content and rely on the parent's pool entry
+            if snapshot.reused {
+                anyhow::bail!("Content missing from pool for hash: {}", snapshot.hash);
+            }
             // Compress and save file content
-            let compressed_content =
-                encode_all(snapshot.content.as_bytes(), self.compression_level)
-                    .context("Failed to compress file content")?;
+            let compressed_content = encode_all(&snapshot.content[..], self.compression_level)
+                .context("Failed to compress file content")?;
             fs::write(&content_file, compressed_content)
                 .context("Failed to write file content to pool")?;
         }
@@ -125,8 +244,10 @@ impl CheckpointStorage {
             "path": snapshot.file_path,
             "hash": snapshot.hash,
             "is_deleted": snapshot.is_deleted,
+            "is_binary": snapshot.is_binary,
             "permissions": snapshot.permissions,
             "size": snapshot.size,
+            "modified": snapshot.modified,
         });
 
         // Use a sanitized filename for the reference
@@ -151,7 +272,20 @@ impl CheckpointStorage {
         checkpoint_id: &str,
     ) -> Result<(Checkpoint, Vec<FileSnapshot>, String)> {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let (checkpoint, messages) = self.load_checkpoint_record(&paths, checkpoint_id)?;
+
+        // Load file snapshots
+        let file_snapshots = self.load_file_snapshots(&paths, checkpoint_id, true)?;
 
+        Ok((checkpoint, file_snapshots, messages))
+    }
+
+    /// Load checkpoint metadata and decompressed messages
+    pub(super) fn load_checkpoint_record(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+    ) -> Result<(Checkpoint, String)> {
         // Load checkpoint metadata
         let metadata_path = paths.checkpoint_metadata_file(checkpoint_id);
         let metadata_json =
@@ -168,17 +302,26 @@ impl CheckpointStorage {
         )
         .context("Invalid UTF-8 in messages")?;
 
-        // Load file snapshots
-        let file_snapshots = self.load_file_snapshots(&paths, checkpoint_id)?;
+        Ok((checkpoint, messages))
+    }
 
-        Ok((checkpoint, file_snapshots, messages))
+    /// Load the file references of a checkpoint without reading their content
+    pub fn load_file_manifest(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<Vec<FileSnapshot>> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        self.load_file_snapshots(&paths, checkpoint_id, false)
     }
 
-    /// Load all file snapshots for a checkpoint
+    /// Load all file snapshots for a checkpoint, optionally with their content
     fn load_file_snapshots(
         &self,
         paths: &CheckpointPaths,
         checkpoint_id: &str,
+        with_content: bool,
     ) -> Result<Vec<FileSnapshot>> {
         let refs_dir = paths.files_dir.join("refs").join(checkpoint_id);
         if !refs_dir.exists() {
@@ -207,20 +350,20 @@ impl CheckpointStorage {
                 .as_str()
                 .ok_or_else(|| anyhow::anyhow!("Missing hash in reference"))?;
 
-            // Load content from pool
+            let is_deleted = ref_metadata["is_deleted"].as_bool().unwrap_or(false);
+
+            // Load content from pool; deletion records have none
             let content_file = content_pool_dir.join(hash);
-            let content = if content_file.exists() {
+            let content = if !with_content || is_deleted {
+                Vec::new()
+            } else if content_file.exists() {
                 let compressed_content =
                     fs::read(&content_file).context("Failed to read file content from pool")?;
-                String::from_utf8(
-                    decode_all(&compressed_content[..])
-                        .context("Failed to decompress file content")?,
-                )
-                .context("Invalid UTF-8 in file content")?
+                decode_all(&compressed_content[..]).context("Failed to decompress file content")?
             } else {
                 // Handle missing content gracefully
                 log::warn!("Content file missing for hash: {}", hash);
-                String::new()
+                Vec::new()
             };
 
             snapshots.push(FileSnapshot {
@@ -228,9 +371,12 @@ impl CheckpointStorage {
                 file_path: PathBuf::from(ref_metadata["path"].as_str().unwrap_or("")),
                 content,
                 hash: hash.to_string(),
-                is_deleted: ref_metadata["is_deleted"].as_bool().unwrap_or(false),
+                is_deleted,
+                is_binary: ref_metadata["is_binary"].as_bool().unwrap_or(false),
                 permissions: ref_metadata["permissions"].as_u64().map(|p| p as u32),
                 size: ref_metadata["size"].as_u64().unwrap_or(0),
+                modified: serde_json::from_value(ref_metadata["modified"].clone()).ok(),
+                reused: false,
             });
         }
 
@@ -254,7 +400,7 @@ impl CheckpointStorage {
     }
 
     /// Update timeline with a new checkpoint
-    fn update_timeline_with_checkpoint(
+    pub(super) fn update_timeline_with_checkpoint(
         &self,
         timeline_path: &Path,
         checkpoint: &Checkpoint,
@@ -313,9 +459,9 @@ impl CheckpointStorage {
     }
 
     /// Calculate hash of file content
-    pub fn calculate_file_hash(content: &str) -> String {
+    pub fn calculate_file_hash(content: &[u8]) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(content.as_bytes());
+        hasher.update(content);
         format!("{:x}", hasher.finalize())
     }
 
@@ -341,22 +487,9 @@ impl CheckpointStorage {
         keep_count: usize,
     ) -> Result<usize> {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
-        let timeline = self.load_timeline(&paths.timeline_file)?;
-
-        // Collect all checkpoint IDs in chronological order
-        let mut all_checkpoints = Vec::new();
-        if let Some(root) = &timeline.root_node {
-            Self::collect_checkpoints(root, &mut all_checkpoints);
-        }
-
-        // Sort by timestamp (oldest first)
-        all_checkpoints.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
-
-        // Keep only the most recent checkpoints
-        let to_remove = all_checkpoints.len().saturating_sub(keep_count);
         let mut removed_count = 0;
 
-        for checkpoint in all_checkpoints.into_iter().take(to_remove) {
+        for checkpoint in self.checkpoints_beyond(&paths, keep_count)? {
             if self.remove_checkpoint(&paths, &checkpoint.id).is_ok() {
                 removed_count += 1;
             }
@@ -377,6 +510,29 @@ impl CheckpointStorage {
         Ok(removed_count)
     }
 
+    /// Checkpoints older than the `keep_count` most recent ones, oldest first
+    pub(super) fn checkpoints_beyond(
+        &self,
+        paths: &CheckpointPaths,
+        keep_count: usize,
+    ) -> Result<Vec<Checkpoint>> {
+        let timeline = self.load_timeline(&paths.timeline_file)?;
+
+        // Collect all checkpoint IDs in chronological order
+        let mut all_checkpoints = Vec::new();
+        if let Some(root) = &timeline.root_node {
+            Self::collect_checkpoints(root, &mut all_checkpoints);
+        }
+
+        // Sort by timestamp (oldest first)
+        all_checkpoints.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+        // Keep only the most recent checkpoints
+        let to_remove = all_checkpoints.len().saturating_sub(keep_count);
+        all_checkpoints.truncate(to_remove);
+        Ok(all_checkpoints)
+    }
+
     /// Collect all checkpoints from the tree in order
     fn collect_checkpoints(node: &TimelineNode, checkpoints: &mut Vec<Checkpoint>) {
         checkpoints.push(node.checkpoint.clone());
@@ -386,7 +542,11 @@ impl CheckpointStorage {
     }
 
     /// Remove a checkpoint and its associated files
-    fn remove_checkpoint(&self, paths: &CheckpointPaths, checkpoint_id: &str) -> Result<()> {
+    pub(super) fn remove_checkpoint(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+    ) -> Result<()> {
         // Remove checkpoint metadata directory
         let checkpoint_dir = paths.checkpoint_dir(checkpoint_id);
         if checkpoint_dir.exists() {
@@ -408,15 +568,26 @@ impl CheckpointStorage {
     /// Garbage collect unreferenced content from the content pool
     pub fn garbage_collect_content(&self, project_id: &str, session_id: &str) -> Result<usize> {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        Ok(self
+            .collect_unreferenced_content(&paths, None)?
+            .files_removed)
+    }
+
+    /// Remove content pool entries no ref points to, optionally only those written before a cutoff
+    pub(super) fn collect_unreferenced_content(
+        &self,
+        paths: &CheckpointPaths,
+        written_before: Option<DateTime<Utc>>,
+    ) -> Result<GcStats> {
         let content_pool_dir = paths.files_dir.join("content_pool");
         let refs_dir = paths.files_dir.join("refs");
 
         if !content_pool_dir.exists() {
-            return Ok(0);
+            return Ok(GcStats::default());
         }
 
         // Collect all referenced hashes
-        let mut referenced_hashes = std::collections::HashSet::new();
+        let mut referenced_hashes = HashSet::new();
 
         if refs_dir.exists() {
             for checkpoint_entry in fs::read_dir(&refs_dir)? {
@@ -441,20 +612,135 @@ impl CheckpointStorage {
         }
 
         // Remove unreferenced content
-        let mut removed_count = 0;
+        let mut stats = GcStats::default();
         for entry in fs::read_dir(&content_pool_dir)? {
             let content_file = entry?.path();
-            if content_file.is_file() {
-                if let Some(hash) = content_file.file_name().and_then(|n| n.to_str()) {
-                    if !referenced_hashes.contains(hash) {
-                        if fs::remove_file(&content_file).is_ok() {
-                            removed_count += 1;
-                        }
-                    }
+            let Ok(metadata) = fs::metadata(&content_file) else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            if let Some(cutoff) = written_before {
+                if super::ignore::modified_at(&metadata) >= cutoff {
+                    continue;
+                }
+            }
+            if let Some(hash) = content_file.file_name().and_then(|n| n.to_str()) {
+                if !referenced_hashes.contains(hash) && fs::remove_file(&content_file).is_ok() {
+                    stats.files_removed += 1;
+                    stats.bytes_reclaimed += metadata.len();
                 }
             }
         }
 
-        Ok(removed_count)
+        Ok(stats)
+    }
+
+    /// Splice checkpoints out of the saved timeline, returning the ones that were removed
+    ///
+    /// The timeline is updated before any data is deleted, so an interrupted removal
+    /// leaves orphaned data for the next garbage collection rather than dangling nodes.
+    pub(super) fn remove_from_timeline(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_ids: &[String],
+    ) -> Result<Vec<String>> {
+        let mut timeline = self.load_timeline(&paths.timeline_file)?;
+        let removed: Vec<String> = checkpoint_ids
+            .iter()
+            .filter(|id| timeline.remove_checkpoint(id))
+            .cloned()
+            .collect();
+        if !removed.is_empty() {
+            self.save_timeline(&paths.timeline_file, &timeline)?;
+        }
+        Ok(removed)
+    }
+
+    /// Rewrite the metadata file of an existing checkpoint
+    pub(super) fn update_checkpoint_metadata(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint: &Checkpoint,
+    ) -> Result<()> {
+        let metadata_json = serde_json::to_string_pretty(checkpoint)
+            .context("Failed to serialize checkpoint metadata")?;
+        fs::write(
+            paths.checkpoint_metadata_file(&checkpoint.id),
+            metadata_json,
+        )
+        .context("Failed to write checkpoint metadata")
+    }
+}
+
+impl CheckpointStore for CheckpointStorage {
+    fn save_checkpoint(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint: &Checkpoint,
+        file_snapshots: Vec<FileSnapshot>,
+        messages: &str,
+    ) -> Result<CheckpointResult> {
+        CheckpointStorage::save_checkpoint(
+            self,
+            project_id,
+            session_id,
+            checkpoint,
+            file_snapshots,
+            messages,
+        )
+    }
+
+    fn load_checkpoint(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<(Checkpoint, Vec<FileSnapshot>, String)> {
+        CheckpointStorage::load_checkpoint(self, project_id, session_id, checkpoint_id)
+    }
+
+    fn load_file_manifest(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<Vec<FileSnapshot>> {
+        CheckpointStorage::load_file_manifest(self, project_id, session_id, checkpoint_id)
+    }
+
+    fn cleanup_old_checkpoints(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        keep_count: usize,
+    ) -> Result<usize> {
+        CheckpointStorage::cleanup_old_checkpoints(self, project_id, session_id, keep_count)
+    }
+
+    fn remove_checkpoints(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_ids: &[String],
+    ) -> Result<usize> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let removed = self.remove_from_timeline(&paths, checkpoint_ids)?;
+        for checkpoint_id in &removed {
+            self.remove_checkpoint(&paths, checkpoint_id)?;
+        }
+        Ok(removed.len())
+    }
+
+    fn garbage_collect(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        written_before: DateTime<Utc>,
+    ) -> Result<GcStats> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        self.collect_unreferenced_content(&paths, Some(written_before))
     }
 }
@@ -0,0 +1,744 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde_json::Value;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+use crate::app_config::AppType;
+
+/// Who produced a transcript entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntryRole {
+    User,
+    Assistant,
+    #[default]
+    Other,
+}
+
+/// A tool call normalized across CLIs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolCall {
+    pub name: String,
+    /// The most telling input, e.g. a file path or command line
+    pub summary: Option<String>,
+    /// Project files the call writes
+    pub files: Vec<String>,
+    /// Shell command the call runs
+    pub command: Option<String>,
+    /// Whether the call can change the project, used by the smart strategy
+    pub destructive: bool,
+}
+
+/// What checkpointing needs to know about one transcript line
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptEntry {
+    pub role: EntryRole,
+    /// Text of a user prompt
+    pub prompt: Option<String>,
+    pub model: Option<String>,
+    pub tokens: u64,
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// Reads and writes the session logs of one CLI
+///
+/// Checkpoints store a session as newline separated message lines. Line-oriented
+/// logs are stored as-is; other formats convert to one JSON message per line.
+pub trait SessionLog: Send + Sync {
+    /// Path of a session log, if it can be found
+    fn locate(&self, project_id: &str, session_id: &str) -> Option<PathBuf>;
+
+    /// Parse one message line
+    fn parse_line(&self, line: &str) -> Option<TranscriptEntry>;
+
+    /// Copy a session log for a forked session, returning the new path
+    fn fork(&self, source: &Path, new_session_id: &str) -> Result<PathBuf>;
+
+    /// Read a session log as message lines
+    fn read_lines(&self, path: &Path) -> Result<Vec<String>> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read session log {}", path.display()))?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Read the message lines within the last `max_bytes` of a session log
+    fn read_tail_lines(&self, path: &Path, max_bytes: u64) -> Result<Vec<String>> {
+        let mut file = fs::File::open(path)
+            .with_context(|| format!("Failed to open session log {}", path.display()))?;
+        let len = file.metadata()?.len();
+        let start = len.saturating_sub(max_bytes);
+        file.seek(SeekFrom::Start(start))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)
+            .with_context(|| format!("Failed to read session log {}", path.display()))?;
+
+        let content = String::from_utf8_lossy(&tail);
+        let mut lines = content.lines();
+        if start > 0 {
+            // The first line is most likely cut off
+            lines.next();
+        }
+        Ok(lines
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Overwrite a session log with message lines from a checkpoint
+    fn write_lines(&self, path: &Path, messages: &str) -> Result<()> {
+        fs::write(path, messages)
+            .with_context(|| format!("Failed to write session log {}", path.display()))
+    }
+}
+
+/// Session log reader for an app
+pub fn session_log(app: &AppType, claude_dir: &Path) -> Arc<dyn SessionLog> {
+    match app {
+        AppType::Claude => Arc::new(ClaudeSessionLog::new(claude_dir.to_path_buf())),
+        AppType::Codex => Arc::new(CodexSessionLog::new(
+            crate::codex_config::get_codex_config_dir(),
+        )),
+        AppType::Gemini => Arc::new(GeminiSessionLog::new(crate::gemini_config::get_gemini_dir())),
+    }
+}
+
+/// Directory holding an app's checkpoint data
+///
+/// Claude checkpoints live next to its own projects; the other CLIs get a
+/// directory under the app config dir with the same layout.
+pub fn checkpoint_root(app: &AppType, claude_dir: &Path) -> PathBuf {
+    match app {
+        AppType::Claude => claude_dir.to_path_buf(),
+        _ => crate::config::get_app_config_dir()
+            .join("checkpoints")
+            .join(app.as_str()),
+    }
+}
+
+/// Checkpoint directories of every app
+pub fn checkpoint_roots(claude_dir: &Path) -> Vec<PathBuf> {
+    [AppType::Claude, AppType::Codex, AppType::Gemini]
+        .iter()
+        .map(|app| checkpoint_root(app, claude_dir))
+        .collect()
+}
+
+/// First string value found under `keys`
+fn first_str<'a>(input: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| input.get(*key).and_then(|v| v.as_str()))
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+/// Claude Code transcripts: `<claude_dir>/projects/<project_id>/<session_id>.jsonl`
+pub struct ClaudeSessionLog {
+    claude_dir: PathBuf,
+}
+
+impl ClaudeSessionLog {
+    pub fn new(claude_dir: PathBuf) -> Self {
+        Self { claude_dir }
+    }
+
+    fn tool_call(name: &str, input: &Value) -> ToolCall {
+        let lower = name.to_lowercase();
+        let mut call = ToolCall {
+            name: name.to_string(),
+            summary: first_str(
+                input,
+                &[
+                    "file_path",
+                    "notebook_path",
+                    "command",
+                    "path",
+                    "pattern",
+                    "url",
+                ],
+            )
+            .map(str::to_string),
+            destructive: matches!(
+                lower.as_str(),
+                "write" | "edit" | "multiedit" | "bash" | "rm" | "delete"
+            ),
+            ..Default::default()
+        };
+        match lower.as_str() {
+            "edit" | "write" | "multiedit" => {
+                call.files.extend(str_field(input, "file_path"));
+            }
+            "bash" => call.command = str_field(input, "command"),
+            _ => {}
+        }
+        call
+    }
+
+    fn add_usage(usage: Option<&Value>, tokens: &mut u64) {
+        let Some(usage) = usage else {
+            return;
+        };
+        for key in [
+            "input_tokens",
+            "output_tokens",
+            "cache_creation_input_tokens",
+            "cache_read_input_tokens",
+        ] {
+            *tokens += usage.get(key).and_then(|t| t.as_u64()).unwrap_or(0);
+        }
+    }
+}
+
+impl SessionLog for ClaudeSessionLog {
+    fn locate(&self, project_id: &str, session_id: &str) -> Option<PathBuf> {
+        Some(
+            self.claude_dir
+                .join("projects")
+                .join(project_id)
+                .join(format!("{}.jsonl", session_id)),
+        )
+    }
+
+    fn parse_line(&self, line: &str) -> Option<TranscriptEntry> {
+        let msg: Value = serde_json::from_str(line).ok()?;
+        let message = msg.get("message");
+        let content = message.and_then(|m| m.get("content"));
+        let mut entry = TranscriptEntry::default();
+
+        match msg.get("type").and_then(|t| t.as_str()) {
+            Some("user") => {
+                entry.role = EntryRole::User;
+                entry.prompt = content.and_then(|c| c.as_array()).and_then(|items| {
+                    items
+                        .iter()
+                        .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("text"))
+                        .find_map(|item| str_field(item, "text"))
+                });
+            }
+            Some("assistant") => entry.role = EntryRole::Assistant,
+            _ => {}
+        }
+
+        entry.model = message
+            .and_then(|m| str_field(m, "model"))
+            .or_else(|| str_field(&msg, "model"));
+
+        // Assistant messages carry usage in message.usage, result messages at the top level
+        Self::add_usage(message.and_then(|m| m.get("usage")), &mut entry.tokens);
+        Self::add_usage(msg.get("usage"), &mut entry.tokens);
+
+        if let Some(items) = content.and_then(|c| c.as_array()) {
+            entry.tool_calls = items
+                .iter()
+                .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+                .filter_map(|item| {
+                    let name = item.get("name")?.as_str()?;
+                    Some(Self::tool_call(
+                        name,
+                        item.get("input").unwrap_or(&Value::Null),
+                    ))
+                })
+                .collect();
+        }
+
+        Some(entry)
+    }
+
+    fn fork(&self, source: &Path, new_session_id: &str) -> Result<PathBuf> {
+        let target = source.with_file_name(format!("{}.jsonl", new_session_id));
+        fs::copy(source, &target).context("Failed to copy session file")?;
+        Ok(target)
+    }
+}
+
+/// Codex rollouts: `<codex_dir>/sessions/YYYY/MM/DD/rollout-<time>-<session_id>.jsonl`
+///
+/// Each line wraps a payload in `{type, payload}`, where the type is one of
+/// `session_meta`, `turn_context`, `response_item` or `event_msg`.
+pub struct CodexSessionLog {
+    codex_dir: PathBuf,
+}
+
+impl CodexSessionLog {
+    pub fn new(codex_dir: PathBuf) -> Self {
+        Self { codex_dir }
+    }
+
+    fn tool_call(name: &str, args: &Value) -> ToolCall {
+        let mut call = ToolCall {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        match name {
+            "shell" | "local_shell" | "container.exec" | "exec_command" => {
+                call.command = Self::shell_command(args);
+                call.summary = call.command.clone();
+                call.destructive = true;
+            }
+            "apply_patch" => {
+                let patch = args
+                    .as_str()
+                    .or_else(|| args.get("input").and_then(|v| v.as_str()))
+                    .unwrap_or("");
+                call.files = patch_files(patch);
+                call.summary = call.files.first().cloned();
+                call.destructive = true;
+            }
+            _ => {
+                call.summary =
+                    first_str(args, &["path", "file_path", "pattern", "url"]).map(str::to_string)
+            }
+        }
+        call
+    }
+
+    /// Command line of a shell call, unwrapping `bash -lc <script>`
+    fn shell_command(args: &Value) -> Option<String> {
+        if let Some(cmd) = first_str(args, &["cmd", "command"]) {
+            return Some(cmd.to_string());
+        }
+        let argv: Vec<&str> = args
+            .get("command")?
+            .as_array()?
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect();
+        match argv.as_slice() {
+            [.., flag, script] if matches!(*flag, "-c" | "-lc") => Some(script.to_string()),
+            [] => None,
+            _ => Some(argv.join(" ")),
+        }
+    }
+}
+
+/// Paths touched by a Codex `apply_patch` envelope
+fn patch_files(patch: &str) -> Vec<String> {
+    patch
+        .lines()
+        .filter_map(|line| {
+            [
+                "*** Add File: ",
+                "*** Update File: ",
+                "*** Delete File: ",
+                "*** Move to: ",
+            ]
+            .iter()
+            .find_map(|prefix| line.strip_prefix(prefix))
+        })
+        .map(|path| path.trim().to_string())
+        .collect()
+}
+
+impl SessionLog for CodexSessionLog {
+    fn locate(&self, _project_id: &str, session_id: &str) -> Option<PathBuf> {
+        let suffix = format!("-{}.jsonl", session_id);
+        WalkDir::new(self.codex_dir.join("sessions"))
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .find(|entry| {
+                entry.file_type().is_file()
+                    && entry.file_name().to_string_lossy().ends_with(&suffix)
+            })
+            .map(|entry| entry.into_path())
+    }
+
+    fn parse_line(&self, line: &str) -> Option<TranscriptEntry> {
+        let msg: Value = serde_json::from_str(line).ok()?;
+        let payload = msg.get("payload")?;
+        let mut entry = TranscriptEntry::default();
+
+        match (
+            msg.get("type").and_then(|t| t.as_str()),
+            payload.get("type").and_then(|t| t.as_str()),
+        ) {
+            // The prompt also appears as a response item, next to injected context
+            // messages; the event is only emitted for what the user typed
+            (Some("event_msg"), Some("user_message")) => {
+                entry.role = EntryRole::User;
+                entry.prompt = str_field(payload, "message");
+            }
+            (Some("event_msg"), Some("token_count")) => {
+                entry.tokens = payload
+                    .pointer("/info/last_token_usage/total_tokens")
+                    .and_then(|t| t.as_u64())
+                    .unwrap_or(0);
+            }
+            (Some("turn_context"), _) => entry.model = str_field(payload, "model"),
+            (Some("response_item"), Some("message"))
+                if payload.get("role").and_then(|r| r.as_str()) == Some("assistant") =>
+            {
+                entry.role = EntryRole::Assistant;
+            }
+            (Some("response_item"), Some("function_call")) => {
+                entry.role = EntryRole::Assistant;
+                let name = payload.get("name").and_then(|n| n.as_str())?;
+                // Arguments arrive as a JSON encoded string
+                let args = payload
+                    .get("arguments")
+                    .and_then(|a| a.as_str())
+                    .and_then(|a| serde_json::from_str(a).ok())
+                    .unwrap_or(Value::Null);
+                entry.tool_calls.push(Self::tool_call(name, &args));
+            }
+            (Some("response_item"), Some("custom_tool_call")) => {
+                entry.role = EntryRole::Assistant;
+                let name = payload.get("name").and_then(|n| n.as_str())?;
+                let input = payload.get("input").cloned().unwrap_or(Value::Null);
+                entry.tool_calls.push(Self::tool_call(name, &input));
+            }
+            (Some("response_item"), Some("local_shell_call")) => {
+                entry.role = EntryRole::Assistant;
+                let action = payload.get("action").unwrap_or(&Value::Null);
+                entry
+                    .tool_calls
+                    .push(Self::tool_call("local_shell", action));
+            }
+            _ => {}
+        }
+
+        Some(entry)
+    }
+
+    fn fork(&self, source: &Path, new_session_id: &str) -> Result<PathBuf> {
+        let mut lines = self.read_lines(source)?;
+        // The session id lives in the leading session_meta record
+        if let Some(first) = lines.first_mut() {
+            if let Ok(mut meta) = serde_json::from_str::<Value>(first) {
+                if meta.get("type").and_then(|t| t.as_str()) == Some("session_meta") {
+                    if let Some(payload) = meta.get_mut("payload").and_then(|p| p.as_object_mut()) {
+                        payload.insert("id".to_string(), Value::from(new_session_id));
+                        *first = meta.to_string();
+                    }
+                }
+            }
+        }
+
+        let target = source.with_file_name(format!(
+            "rollout-{}-{}.jsonl",
+            Utc::now().format("%Y-%m-%dT%H-%M-%S"),
+            new_session_id
+        ));
+        let mut content = lines.join("\n");
+        content.push('\n');
+        fs::write(&target, content).context("Failed to write forked session file")?;
+        Ok(target)
+    }
+}
+
+/// Gemini CLI chats: `<gemini_dir>/tmp/<project_hash>/chats/session-<time>-<id8>.json`
+///
+/// A chat is a single JSON document whose `messages` array becomes one line per
+/// message; `user` messages hold the prompt and `gemini` messages tokens and tool calls.
+pub struct GeminiSessionLog {
+    gemini_dir: PathBuf,
+}
+
+impl GeminiSessionLog {
+    pub fn new(gemini_dir: PathBuf) -> Self {
+        Self { gemini_dir }
+    }
+
+    fn tool_call(name: &str, args: &Value) -> ToolCall {
+        let mut call = ToolCall {
+            name: name.to_string(),
+            summary: first_str(
+                args,
+                &[
+                    "file_path",
+                    "absolute_path",
+                    "command",
+                    "path",
+                    "pattern",
+                    "url",
+                ],
+            )
+            .map(str::to_string),
+            ..Default::default()
+        };
+        match name {
+            "write_file" | "replace" | "edit" => {
+                call.files.extend(str_field(args, "file_path"));
+                call.destructive = true;
+            }
+            "run_shell_command" => {
+                call.command = str_field(args, "command");
+                call.destructive = true;
+            }
+            _ => {}
+        }
+        call
+    }
+
+    fn read_chat(path: &Path) -> Result<Value> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read session log {}", path.display()))?;
+        serde_json::from_str(&content).context("Failed to parse Gemini chat")
+    }
+
+    fn write_chat(path: &Path, chat: &Value) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(chat)?)
+            .with_context(|| format!("Failed to write session log {}", path.display()))
+    }
+}
+
+impl SessionLog for GeminiSessionLog {
+    fn locate(&self, _project_id: &str, session_id: &str) -> Option<PathBuf> {
+        // File names only carry the first 8 characters of the session id
+        let short_id: String = session_id.chars().take(8).collect();
+        WalkDir::new(self.gemini_dir.join("tmp"))
+            .max_depth(3)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy();
+                entry.file_type().is_file()
+                    && name.starts_with("session-")
+                    && name.ends_with(".json")
+                    && name.contains(&short_id)
+            })
+            .map(|entry| entry.into_path())
+            .find(|path| {
+                Self::read_chat(path)
+                    .map(|chat| chat.get("sessionId").and_then(|s| s.as_str()) == Some(session_id))
+                    .unwrap_or(false)
+            })
+    }
+
+    fn parse_line(&self, line: &str) -> Option<TranscriptEntry> {
+        let msg: Value = serde_json::from_str(line).ok()?;
+        let mut entry = TranscriptEntry::default();
+
+        match msg.get("type").and_then(|t| t.as_str()) {
+            Some("user") => {
+                entry.role = EntryRole::User;
+                entry.prompt = match msg.get("content") {
+                    Some(Value::String(text)) => Some(text.clone()),
+                    Some(Value::Array(parts)) => parts.iter().find_map(|p| str_field(p, "text")),
+                    _ => None,
+                };
+            }
+            Some("gemini") => {
+                entry.role = EntryRole::Assistant;
+                entry.model = str_field(&msg, "model");
+                entry.tokens = msg
+                    .pointer("/tokens/total")
+                    .and_then(|t| t.as_u64())
+                    .unwrap_or(0);
+                if let Some(calls) = msg.get("toolCalls").and_then(|c| c.as_array()) {
+                    entry.tool_calls = calls
+                        .iter()
+                        .filter_map(|call| {
+                            let name = call.get("name")?.as_str()?;
+                            Some(Self::tool_call(
+                                name,
+                                call.get("args").unwrap_or(&Value::Null),
+                            ))
+                        })
+                        .collect();
+                }
+            }
+            _ => {}
+        }
+
+        Some(entry)
+    }
+
+    fn fork(&self, source: &Path, new_session_id: &str) -> Result<PathBuf> {
+        let mut chat = Self::read_chat(source)?;
+        if let Some(obj) = chat.as_object_mut() {
+            obj.insert("sessionId".to_string(), Value::from(new_session_id));
+        }
+        let short_id: String = new_session_id.chars().take(8).collect();
+        let target = source.with_file_name(format!(
+            "session-{}-{}.json",
+            Utc::now().format("%Y-%m-%dT%H-%M"),
+            short_id
+        ));
+        Self::write_chat(&target, &chat)?;
+        Ok(target)
+    }
+
+    fn read_lines(&self, path: &Path) -> Result<Vec<String>> {
+        let chat = Self::read_chat(path)?;
+        Ok(chat
+            .get("messages")
+            .and_then(|m| m.as_array())
+            .map(|messages| messages.iter().map(Value::to_string).collect())
+            .unwrap_or_default())
+    }
+
+    fn read_tail_lines(&self, path: &Path, _max_bytes: u64) -> Result<Vec<String>> {
+        // A chat is a single JSON document, which can only be read whole
+        self.read_lines(path)
+    }
+
+    fn write_lines(&self, path: &Path, messages: &str) -> Result<()> {
+        let messages: Vec<Value> = messages
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        // Keep the chat header (session id, project hash, start time) when it exists
+        let mut chat = Self::read_chat(path).unwrap_or_else(|_| serde_json::json!({}));
+        if let Some(obj) = chat.as_object_mut() {
+            obj.insert("messages".to_string(), Value::Array(messages));
+            obj.insert(
+                "lastUpdated".to_string(),
+                Value::from(Utc::now().to_rfc3339()),
+            );
+        }
+        Self::write_chat(path, &chat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parses_codex_rollout() {
+        let temp_dir = TempDir::new().unwrap();
+        let day_dir = temp_dir.path().join("sessions/2025/10/01");
+        fs::create_dir_all(&day_dir).unwrap();
+        let lines = [
+            r#"{"type":"session_meta","payload":{"id":"abc-123","cwd":"/work"}}"#,
+            r#"{"type":"turn_context","payload":{"cwd":"/work","model":"gpt-5-codex"}}"#,
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"<environment_context>"}]}}"#,
+            r#"{"type":"event_msg","payload":{"type":"user_message","message":"fix the build"}}"#,
+            r#"{"type":"response_item","payload":{"type":"function_call","name":"shell","arguments":"{\"command\":[\"bash\",\"-lc\",\"cargo build\"]}"}}"#,
+            r#"{"type":"response_item","payload":{"type":"custom_tool_call","name":"apply_patch","input":"*** Begin Patch\n*** Update File: src/lib.rs\n@@\n-a\n+b\n*** Add File: src/new.rs\n+x\n*** End Patch"}}"#,
+            r#"{"type":"event_msg","payload":{"type":"token_count","info":{"total_token_usage":{"total_tokens":150},"last_token_usage":{"total_tokens":150}}}}"#,
+        ];
+        let path = day_dir.join("rollout-2025-10-01T10-00-00-abc-123.jsonl");
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        let log = CodexSessionLog::new(temp_dir.path().to_path_buf());
+        assert_eq!(log.locate("any", "abc-123"), Some(path.clone()));
+        assert_eq!(log.locate("any", "abc-999"), None);
+
+        let entries: Vec<TranscriptEntry> = log
+            .read_lines(&path)
+            .unwrap()
+            .iter()
+            .filter_map(|line| log.parse_line(line))
+            .collect();
+        let prompts: Vec<_> = entries.iter().filter_map(|e| e.prompt.as_deref()).collect();
+        assert_eq!(prompts, vec!["fix the build"]);
+        assert_eq!(entries[1].model.as_deref(), Some("gpt-5-codex"));
+        assert_eq!(entries.iter().map(|e| e.tokens).sum::<u64>(), 150);
+
+        let shell = &entries[4].tool_calls[0];
+        assert_eq!(shell.command.as_deref(), Some("cargo build"));
+        assert!(shell.destructive);
+        let patch = &entries[5].tool_calls[0];
+        assert_eq!(patch.files, vec!["src/lib.rs", "src/new.rs"]);
+        assert_eq!(patch.summary.as_deref(), Some("src/lib.rs"));
+
+        let forked = log.fork(&path, "def-456").unwrap();
+        assert_eq!(log.locate("any", "def-456"), Some(forked.clone()));
+        let meta: Value = serde_json::from_str(&log.read_lines(&forked).unwrap()[0]).unwrap();
+        assert_eq!(meta["payload"]["id"], "def-456");
+    }
+
+    #[test]
+    fn parses_and_rewrites_gemini_chat() {
+        let temp_dir = TempDir::new().unwrap();
+        let chats_dir = temp_dir.path().join("tmp/0123abcd/chats");
+        fs::create_dir_all(&chats_dir).unwrap();
+        let path = chats_dir.join("session-2025-10-01T10-00-5f2c9a1e.json");
+        let chat = serde_json::json!({
+            "sessionId": "5f2c9a1e-0000-4000-8000-000000000000",
+            "projectHash": "0123abcd",
+            "messages": [
+                {"id": "1", "type": "user", "content": "add a readme"},
+                {"id": "2", "type": "gemini", "content": "", "model": "gemini-2.5-pro",
+                 "tokens": {"input": 90, "output": 10, "total": 100},
+                 "toolCalls": [{"name": "write_file", "args": {"file_path": "/work/README.md", "content": "hi"}}]},
+                {"id": "3", "type": "gemini", "content": "done", "tokens": {"total": 20}}
+            ]
+        });
+        fs::write(&path, chat.to_string()).unwrap();
+
+        let log = GeminiSessionLog::new(temp_dir.path().to_path_buf());
+        let session_id = "5f2c9a1e-0000-4000-8000-000000000000";
+        assert_eq!(log.locate("any", session_id), Some(path.clone()));
+
+        let lines = log.read_lines(&path).unwrap();
+        assert_eq!(lines.len(), 3);
+        let entries: Vec<TranscriptEntry> =
+            lines.iter().filter_map(|l| log.parse_line(l)).collect();
+        assert_eq!(entries[0].role, EntryRole::User);
+        assert_eq!(entries[0].prompt.as_deref(), Some("add a readme"));
+        assert_eq!(entries[1].model.as_deref(), Some("gemini-2.5-pro"));
+        assert_eq!(entries.iter().map(|e| e.tokens).sum::<u64>(), 120);
+        let call = &entries[1].tool_calls[0];
+        assert_eq!(call.files, vec!["/work/README.md"]);
+        assert!(call.destructive);
+
+        // Restoring the first two messages keeps the chat header
+        log.write_lines(&path, &lines[..2].join("\n")).unwrap();
+        let restored = GeminiSessionLog::read_chat(&path).unwrap();
+        assert_eq!(restored["projectHash"], "0123abcd");
+        assert_eq!(restored["messages"].as_array().unwrap().len(), 2);
+
+        let forked = log
+            .fork(&path, "9d8e7f6a-1111-4000-8000-000000000000")
+            .unwrap();
+        assert_eq!(
+            log.locate("any", "9d8e7f6a-1111-4000-8000-000000000000"),
+            Some(forked)
+        );
+    }
+
+    #[test]
+    fn parses_claude_transcript() {
+        let log = ClaudeSessionLog::new(PathBuf::from("/claude"));
+        let entry = log
+            .parse_line(
+                r#"{"type":"assistant","message":{"model":"claude-sonnet-4","usage":{"input_tokens":10,"output_tokens":5,"cache_read_input_tokens":100},"content":[{"type":"tool_use","name":"Edit","input":{"file_path":"src/main.rs"}},{"type":"tool_use","name":"Read","input":{"file_path":"Cargo.toml"}}]}}"#,
+            )
+            .unwrap();
+        assert_eq!(entry.role, EntryRole::Assistant);
+        assert_eq!(entry.model.as_deref(), Some("claude-sonnet-4"));
+        assert_eq!(entry.tokens, 115);
+        assert_eq!(entry.tool_calls[0].files, vec!["src/main.rs"]);
+        assert!(entry.tool_calls[0].destructive);
+        assert!(entry.tool_calls[1].files.is_empty());
+        assert!(!entry.tool_calls[1].destructive);
+
+        let prompt = log
+            .parse_line(r#"{"type":"user","message":{"content":[{"type":"text","text":"hello"}]}}"#)
+            .unwrap();
+        assert_eq!(prompt.prompt.as_deref(), Some("hello"));
+        assert_eq!(
+            log.locate("p", "s"),
+            Some(PathBuf::from("/claude/projects/p/s.jsonl"))
+        );
+    }
+
+    #[test]
+    fn reads_only_the_tail_of_a_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("s.jsonl");
+        fs::write(&path, "first line\nsecond line\n\nthird line\n").unwrap();
+        let log = ClaudeSessionLog::new(temp_dir.path().to_path_buf());
+
+        // The line cut off at the start of the window is skipped
+        assert_eq!(
+            log.read_tail_lines(&path, 20).unwrap(),
+            vec!["third line".to_string()]
+        );
+        assert_eq!(
+            log.read_tail_lines(&path, 1024).unwrap(),
+            log.read_lines(&path).unwrap()
+        );
+    }
+}
@@ -0,0 +1,534 @@
+use chrono::{DateTime, Utc};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::ignore::{self, IgnoreRules, ProjectScan};
+use super::manager::CheckpointManager;
+use super::transcript::{SessionLog, ToolCall};
+
+/// Longest tool input summary kept in an automatic checkpoint description
+const MAX_SUMMARY_LEN: usize = 80;
+
+/// How much of the end of a session log is searched for the latest tool call
+const TRANSCRIPT_TAIL_BYTES: u64 = 256 * 1024;
+
+/// Polling never spends more than 1/N of the time scanning the project
+const POLL_SCAN_DUTY_FACTOR: u32 = 20;
+
+/// Timing of the watcher behind [`super::CheckpointStrategy::Watch`]
+///
+/// The watcher listens for OS change notifications and only rescans the project to
+/// confirm a debounced burst. When notifications are unavailable it falls back to
+/// polling, stretching `poll_interval` so scans of large trees stay a small share
+/// of wall time.
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    /// How often a pending burst is re-checked, and the shortest polling interval
+    pub poll_interval: Duration,
+    /// Quiet period required after the last change before checkpointing
+    pub debounce: Duration,
+    /// Upper bound on how long a continuous burst can postpone a checkpoint
+    pub max_delay: Duration,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            debounce: Duration::from_secs(2),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Size and mtime of every eligible project file, keyed by relative path
+pub type Fingerprint = BTreeMap<PathBuf, (u64, DateTime<Utc>)>;
+
+/// Fingerprint the files a checkpoint would snapshot
+pub fn fingerprint(scan: &ProjectScan) -> Fingerprint {
+    scan.files
+        .iter()
+        .map(|f| (f.path.clone(), (f.size, f.modified)))
+        .collect()
+}
+
+/// Collapses bursts of changes into a single trigger
+///
+/// Fires once the tree has differed from the last checkpoint and stayed unchanged
+/// for `debounce`, or once `max_delay` has passed since the burst began.
+#[derive(Debug)]
+pub struct Debouncer {
+    debounce: Duration,
+    max_delay: Duration,
+    burst_started: Option<Instant>,
+    last_change: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(debounce: Duration, max_delay: Duration) -> Self {
+        Self {
+            debounce,
+            max_delay,
+            burst_started: None,
+            last_change: None,
+        }
+    }
+
+    /// Feed one poll result; returns `true` when a checkpoint should be created
+    ///
+    /// `dirty` means the tree differs from the last checkpoint, `changed` that it
+    /// differs from the previous poll.
+    pub fn poll(&mut self, dirty: bool, changed: bool, now: Instant) -> bool {
+        if !dirty {
+            self.reset();
+            return false;
+        }
+
+        if changed || self.last_change.is_none() {
+            self.last_change = Some(now);
+        }
+        let burst_started = *self.burst_started.get_or_insert(now);
+        let last_change = self.last_change.unwrap_or(now);
+
+        let settled = now.duration_since(last_change) >= self.debounce;
+        let overdue = now.duration_since(burst_started) >= self.max_delay;
+        if settled || overdue {
+            self.reset();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.burst_started = None;
+        self.last_change = None;
+    }
+}
+
+/// Background task polling a project directory for a [`CheckpointManager`]
+///
+/// The task only holds a weak reference to the manager and is aborted on drop, so
+/// it never outlives the session it belongs to.
+pub struct ProjectWatcher {
+    handle: JoinHandle<()>,
+}
+
+impl ProjectWatcher {
+    pub fn spawn(manager: &Arc<CheckpointManager>, config: WatchConfig) -> Self {
+        let handle = tokio::spawn(run(Arc::downgrade(manager), config));
+        Self { handle }
+    }
+}
+
+impl Drop for ProjectWatcher {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Checkpoint baseline the watcher compares against
+struct Baseline {
+    restore_epoch: u64,
+    fingerprint: Fingerprint,
+}
+
+/// State shared by the notification and polling loops
+struct WatchState {
+    manager: Weak<CheckpointManager>,
+    debouncer: Debouncer,
+    baseline: Option<Baseline>,
+    previous: Option<Fingerprint>,
+}
+
+/// What the watcher should do after looking at the project
+enum Step {
+    /// The session is gone
+    Stop,
+    /// The scan failed; try again later
+    Retry,
+    /// The scan succeeded; carries its ignore rules and how long it took
+    Scanned(IgnoreRules, Duration),
+}
+
+impl WatchState {
+    /// Scan the project, compare against the baseline and checkpoint once settled
+    ///
+    /// `burst_settled` is `Some` when the caller debounces change notifications
+    /// itself; otherwise consecutive scans are compared to detect bursts.
+    async fn check(&mut self, burst_settled: Option<bool>) -> Step {
+        let Some(manager) = self.manager.upgrade() else {
+            return Step::Stop;
+        };
+
+        let root = manager.project_path().to_path_buf();
+        let limits = manager.file_limits().await;
+        let started = Instant::now();
+        let scan =
+            match tokio::task::spawn_blocking(move || ignore::scan_project(&root, &limits)).await {
+                Ok(Ok(scan)) => scan,
+                Ok(Err(e)) => {
+                    log::debug!("Checkpoint watcher failed to scan project: {}", e);
+                    return Step::Retry;
+                }
+                Err(_) => return Step::Retry,
+            };
+        let elapsed = started.elapsed();
+        let current = fingerprint(&scan);
+        let rules = scan.ignore_rules;
+
+        // Restores rewrite the tree themselves; start over from what they left behind
+        let restore_epoch = manager.restore_epoch();
+        let base = match &self.baseline {
+            Some(base) if base.restore_epoch == restore_epoch => base,
+            _ => {
+                self.debouncer.reset();
+                self.previous = Some(current.clone());
+                self.baseline = Some(Baseline {
+                    restore_epoch,
+                    fingerprint: current,
+                });
+                return Step::Scanned(rules, elapsed);
+            }
+        };
+
+        let dirty = current != base.fingerprint;
+        let fire = match burst_settled {
+            Some(settled) => dirty && settled,
+            None => {
+                let changed = self.previous.as_ref() != Some(&current);
+                self.previous = Some(current.clone());
+                self.debouncer.poll(dirty, changed, Instant::now())
+            }
+        };
+        if !fire {
+            return Step::Scanned(rules, elapsed);
+        }
+
+        let description = match manager.latest_tool_call().await {
+            Some(call) => format!("Auto checkpoint after {}", call),
+            None => "Auto checkpoint after file changes".to_string(),
+        };
+        match manager.create_automatic_checkpoint(description).await {
+            Ok(result) => log::info!(
+                "Watcher created checkpoint {} ({} files)",
+                result.checkpoint.id,
+                result.files_processed
+            ),
+            Err(e) => log::warn!("Watcher failed to create checkpoint: {}", e),
+        }
+        // Changes made while checkpointing show up as dirty on the next check
+        self.baseline = Some(Baseline {
+            restore_epoch: manager.restore_epoch(),
+            fingerprint: current,
+        });
+        Step::Scanned(rules, elapsed)
+    }
+}
+
+async fn run(manager: Weak<CheckpointManager>, config: WatchConfig) {
+    let Some(root) = manager.upgrade().map(|m| m.project_path().to_path_buf()) else {
+        return;
+    };
+    let mut state = WatchState {
+        manager,
+        debouncer: Debouncer::new(config.debounce, config.max_delay),
+        baseline: None,
+        previous: None,
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            for path in event.paths {
+                let _ = tx.send(path);
+            }
+        }
+    })
+    .and_then(|mut watcher| {
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+        Ok(watcher)
+    });
+
+    match watcher {
+        Ok(watcher) => watch_notifications(state, config, root, watcher, rx).await,
+        Err(e) => {
+            log::warn!(
+                "Change notifications unavailable for {}, polling instead: {}",
+                root.display(),
+                e
+            );
+            // Take the baseline before the first poll
+            if let Step::Stop = state.check(None).await {
+                return;
+            }
+            watch_polling(state, config).await
+        }
+    }
+}
+
+/// Wait for change notifications and rescan only to confirm a debounced burst
+async fn watch_notifications(
+    mut state: WatchState,
+    config: WatchConfig,
+    root: PathBuf,
+    _watcher: RecommendedWatcher,
+    mut events: mpsc::UnboundedReceiver<PathBuf>,
+) {
+    let mut rules = match state.check(Some(false)).await {
+        Step::Stop => return,
+        Step::Retry => IgnoreRules::default(),
+        Step::Scanned(rules, _) => rules,
+    };
+    let mut pending = false;
+    let mut changed = false;
+    let mut interval = tokio::time::interval(config.poll_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            path = events.recv() => {
+                let Some(path) = path else { break };
+                let relevant = path
+                    .strip_prefix(&root)
+                    .is_ok_and(|rel| !rules.excludes_path(rel));
+                if relevant {
+                    if !pending {
+                        interval.reset();
+                    }
+                    pending = true;
+                    changed = true;
+                }
+            }
+            _ = interval.tick(), if pending => {
+                // Until the burst settles the debouncer only needs to know whether
+                // anything happened; the full scan confirms the tree really differs
+                let fire = state.debouncer.poll(true, changed, Instant::now());
+                changed = false;
+                if !fire {
+                    continue;
+                }
+                match state.check(Some(true)).await {
+                    Step::Stop => break,
+                    Step::Retry => {}
+                    Step::Scanned(scanned, _) => {
+                        rules = scanned;
+                        pending = false;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rescan the whole project periodically, backing off on large trees
+async fn watch_polling(mut state: WatchState, config: WatchConfig) {
+    let mut delay = config.poll_interval;
+    loop {
+        tokio::time::sleep(delay).await;
+        match state.check(None).await {
+            Step::Stop => break,
+            Step::Retry => {}
+            Step::Scanned(_, elapsed) => {
+                delay = config.poll_interval.max(elapsed * POLL_SCAN_DUTY_FACTOR);
+            }
+        }
+    }
+}
+
+/// Describe the last tool call in a batch of session log lines, e.g. `Edit src/main.rs`
+pub fn describe_latest_tool_call<'a>(
+    log: &dyn SessionLog,
+    lines: impl DoubleEndedIterator<Item = &'a str>,
+) -> Option<String> {
+    lines.rev().find_map(|line| {
+        let entry = log.parse_line(line)?;
+        entry.tool_calls.last().map(describe_tool_call)
+    })
+}
+
+fn describe_tool_call(call: &ToolCall) -> String {
+    match &call.summary {
+        Some(summary) => format!(
+            "{} {}",
+            call.name,
+            truncate(summary.lines().next().unwrap_or(""))
+        ),
+        None => call.name.clone(),
+    }
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_SUMMARY_LEN {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(MAX_SUMMARY_LEN - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Latest tool call recorded near the end of a session log file
+pub fn latest_tool_call_in_transcript(log: &dyn SessionLog, path: &Path) -> Option<String> {
+    let lines = log.read_tail_lines(path, TRANSCRIPT_TAIL_BYTES).ok()?;
+    describe_latest_tool_call(log, lines.iter().map(String::as_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::transcript::ClaudeSessionLog;
+    use crate::checkpoint::CheckpointStrategy;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn debouncer_waits_for_quiet_period_and_caps_bursts() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut debouncer = Debouncer::new(Duration::from_millis(100), Duration::from_millis(500));
+
+        assert!(!debouncer.poll(false, false, at(0)));
+        assert!(!debouncer.poll(true, true, at(10)));
+        assert!(!debouncer.poll(true, true, at(60)));
+        assert!(!debouncer.poll(true, false, at(120)));
+        assert!(debouncer.poll(true, false, at(160)));
+
+        // A burst that never settles still fires after `max_delay`
+        let mut fired = Vec::new();
+        for ms in (200..=800).step_by(50) {
+            if debouncer.poll(true, true, at(ms)) {
+                fired.push(ms);
+            }
+        }
+        assert_eq!(fired, vec![700]);
+
+        // Reverting the changes cancels the pending trigger
+        assert!(!debouncer.poll(true, true, at(900)));
+        assert!(!debouncer.poll(false, true, at(950)));
+        assert!(!debouncer.poll(false, false, at(1100)));
+    }
+
+    #[test]
+    fn describes_most_recent_tool_call() {
+        let lines = [
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Edit","input":{"file_path":"src/main.rs"}}]}}"#,
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","content":"ok"}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"running"},{"type":"tool_use","name":"Bash","input":{"command":"cargo fmt\ncargo test"}}]}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"done"}]}}"#,
+            "not json",
+        ];
+        let log = ClaudeSessionLog::new(PathBuf::from("/claude"));
+
+        assert_eq!(
+            describe_latest_tool_call(&log, lines.iter().copied()).as_deref(),
+            Some("Bash cargo fmt")
+        );
+        assert_eq!(
+            describe_latest_tool_call(&log, lines[..2].iter().copied()).as_deref(),
+            Some("Edit src/main.rs")
+        );
+        assert_eq!(
+            describe_latest_tool_call(&log, lines[3..].iter().copied()),
+            None
+        );
+        assert_eq!(truncate(&"x".repeat(100)).chars().count(), MAX_SUMMARY_LEN);
+    }
+
+    #[tokio::test]
+    async fn watcher_checkpoints_external_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("a.txt"), "a").unwrap();
+
+        let manager = Arc::new(
+            CheckpointManager::new(
+                "test-project".to_string(),
+                "test-session".to_string(),
+                project.clone(),
+                temp_dir.path().join("claude"),
+            )
+            .await
+            .unwrap(),
+        );
+        manager
+            .track_message(
+                r#"{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Write","input":{"file_path":"b.txt"}}]}}"#
+                    .to_string(),
+            )
+            .await
+            .unwrap();
+        manager
+            .update_settings(true, CheckpointStrategy::Watch, None, None)
+            .await
+            .unwrap();
+        let _watcher = ProjectWatcher::spawn(
+            &manager,
+            WatchConfig {
+                poll_interval: Duration::from_millis(20),
+                debounce: Duration::from_millis(60),
+                max_delay: Duration::from_secs(5),
+            },
+        );
+
+        // Let the watcher take its baseline before touching the tree
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(manager.get_timeline().await.total_checkpoints, 0);
+        fs::write(project.join("b.txt"), "b").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while manager.get_timeline().await.total_checkpoints == 0 {
+            assert!(Instant::now() < deadline, "watcher never checkpointed");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let checkpoints = manager.list_checkpoints().await;
+        assert_eq!(
+            checkpoints[0].description.as_deref(),
+            Some("Auto checkpoint after Write b.txt")
+        );
+        assert!(checkpoints[0].metadata.automatic);
+    }
+
+    #[tokio::test]
+    async fn watcher_ignores_changes_in_ignored_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(project.join("node_modules")).unwrap();
+        fs::write(project.join(".gitignore"), "node_modules/\n").unwrap();
+
+        let manager = Arc::new(
+            CheckpointManager::new(
+                "test-project".to_string(),
+                "test-session".to_string(),
+                project.clone(),
+                temp_dir.path().join("claude"),
+            )
+            .await
+            .unwrap(),
+        );
+        manager
+            .update_settings(true, CheckpointStrategy::Watch, None, None)
+            .await
+            .unwrap();
+        let _watcher = ProjectWatcher::spawn(
+            &manager,
+            WatchConfig {
+                poll_interval: Duration::from_millis(20),
+                debounce: Duration::from_millis(60),
+                max_delay: Duration::from_secs(5),
+            },
+        );
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        for i in 0..20 {
+            fs::write(project.join(format!("node_modules/{}.js", i)), "x").unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(manager.get_timeline().await.total_checkpoints, 0);
+    }
+}
@@ -0,0 +1,1077 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike, Utc};
+use log::{error, info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Listener, Manager, State};
+use tokio::sync::Notify;
+
+use super::agents::{
+    agent_run_from_row, enqueue_agent_run, is_pid_running, load_agent, start_queued_run, AgentDb,
+    AgentRun, AGENT_RUN_COLUMNS,
+};
+
+/// How often the dispatcher re-checks the queue without being woken
+const QUEUE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often due schedules are evaluated
+const SCHEDULE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Occurrences older than this when noticed are treated as missed (app closed or asleep)
+const MISSED_RUN_GRACE_MINUTES: i64 = 5;
+
+/// Key of the queue limits in `app_settings`
+const QUEUE_LIMITS_KEY: &str = "agent_queue_limits";
+
+const SCHEDULE_COLUMNS: &str = "id, agent_id, project_path, task, model, cron, enabled, missed_run_policy, next_run_at, last_run_at, created_at, updated_at";
+
+/// A five-field cron expression: `minute hour day-of-month month day-of-week`
+///
+/// Supports `*`, lists, ranges, steps, month and weekday names, and the `@hourly`,
+/// `@daily`, `@weekly`, `@monthly` and `@yearly` shortcuts. When both day fields are
+/// restricted a day matches either of them, as in Vixie cron.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let expanded = match expression.trim().to_ascii_lowercase().as_str() {
+            "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+            "@monthly" => "0 0 1 * *".to_string(),
+            "@weekly" => "0 0 * * 0".to_string(),
+            "@daily" | "@midnight" => "0 0 * * *".to_string(),
+            "@hourly" => "0 * * * *".to_string(),
+            other if other.starts_with('@') => {
+                return Err(format!("Unknown cron shortcut '{}'", other))
+            }
+            other => other.to_string(),
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            return Err(format!(
+                "Cron expression '{}' must have 5 fields, found {}",
+                expression,
+                fields.len()
+            ));
+        };
+
+        let mut days_of_week = parse_field(dow, 0, 7, WEEKDAY_NAMES)?;
+        // Both 0 and 7 mean Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[])?,
+            hours: parse_field(hour, 0, 23, &[])?,
+            days_of_month: parse_field(dom, 1, 31, &[])?,
+            months: parse_field(month, 1, 12, MONTH_NAMES)?,
+            days_of_week,
+            dom_restricted: !dom.starts_with('*'),
+            dow_restricted: !dow.starts_with('*'),
+        })
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("Invalid step '{}' in '{}'", step, field))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (
+                parse_value(a, min, max, names)?,
+                parse_value(b, min, max, names)?,
+            )
+        } else {
+            let value = parse_value(range, min, max, names)?;
+            // `5/15` means "from 5 to the end, every 15"
+            if part.contains('/') {
+                (value, max)
+            } else {
+                (value, value)
+            }
+        };
+
+        if start > end {
+            return Err(format!("Invalid range '{}' in '{}'", range, field));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, String> {
+    let parsed = match value.parse::<u32>() {
+        Ok(v) => v,
+        Err(_) => names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(value))
+            .map(|i| i as u32 + min)
+            .ok_or_else(|| format!("Invalid cron value '{}'", value))?,
+    };
+    if parsed < min || parsed > max {
+        return Err(format!("Cron value {} is outside {}-{}", parsed, min, max));
+    }
+    Ok(parsed)
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+impl CronSchedule {
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let dom = has(self.days_of_month, date.day());
+        let dow = has(self.days_of_week, date.weekday().num_days_from_sunday());
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+
+    /// Next occurrence strictly after `after`, in local time
+    pub fn next_after(&self, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.next_after_in(after, &Local)
+    }
+
+    /// Next occurrence strictly after `after`, evaluating the fields in `tz`
+    ///
+    /// Local times skipped by a DST change never match; repeated ones match once.
+    pub fn next_after_in<Tz: TimeZone>(
+        &self,
+        after: &DateTime<Utc>,
+        tz: &Tz,
+    ) -> Option<DateTime<Utc>> {
+        let local = after.with_timezone(tz).naive_local();
+        let mut t =
+            local.date().and_hms_opt(local.hour(), local.minute(), 0)? + Duration::minutes(1);
+        // Long enough for Feb 29 across a skipped leap year (e.g. 2100)
+        let limit = t + Duration::days(366 * 9);
+
+        while t < limit {
+            if !has(self.months, t.month()) {
+                let (year, month) = if t.month() == 12 {
+                    (t.year() + 1, 1)
+                } else {
+                    (t.year(), t.month() + 1)
+                };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.day_matches(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !has(self.hours, t.hour()) {
+                t = t.date().and_hms_opt(t.hour(), 0, 0)? + Duration::hours(1);
+                continue;
+            }
+            if !has(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+                continue;
+            }
+            if let Some(at) = tz.from_local_datetime(&t).earliest() {
+                let at = at.with_timezone(&Utc);
+                if at > *after {
+                    return Some(at);
+                }
+            }
+            t += Duration::minutes(1);
+        }
+        None
+    }
+}
+
+/// What to do about occurrences that passed while the app was not running
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRunPolicy {
+    /// Drop missed occurrences and wait for the next one
+    Skip,
+    /// Run once to catch up, however many occurrences were missed
+    #[default]
+    RunOnce,
+}
+
+impl MissedRunPolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::RunOnce => "run_once",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "skip" => Self::Skip,
+            _ => Self::RunOnce,
+        }
+    }
+}
+
+/// A cron schedule that enqueues an agent run on a project
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentSchedule {
+    pub id: Option<i64>,
+    pub agent_id: i64,
+    pub project_path: String,
+    /// Task to run; falls back to the agent's default task
+    pub task: Option<String>,
+    /// Model override; falls back to the agent's model
+    pub model: Option<String>,
+    pub cron: String,
+    pub enabled: bool,
+    pub missed_run_policy: MissedRunPolicy,
+    pub next_run_at: Option<String>,
+    pub last_run_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn schedule_from_row(row: &rusqlite::Row) -> rusqlite::Result<AgentSchedule> {
+    Ok(AgentSchedule {
+        id: Some(row.get(0)?),
+        agent_id: row.get(1)?,
+        project_path: row.get(2)?,
+        task: row.get(3)?,
+        model: row.get(4)?,
+        cron: row.get(5)?,
+        enabled: row.get(6)?,
+        missed_run_policy: MissedRunPolicy::parse(&row.get::<_, String>(7)?),
+        next_run_at: row.get(8)?,
+        last_run_at: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+    })
+}
+
+/// Create the agent_schedules table
+pub(crate) fn init_schedule_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_schedules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            project_path TEXT NOT NULL,
+            task TEXT,
+            model TEXT,
+            cron TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            missed_run_policy TEXT NOT NULL DEFAULT 'run_once',
+            next_run_at TEXT,
+            last_run_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Concurrency caps applied by the run queue
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AgentQueueLimits {
+    /// Runs executing at once across all agents
+    pub max_concurrent_runs: usize,
+    /// Runs executing at once for any single agent
+    pub max_runs_per_agent: usize,
+    /// Per-agent overrides of `max_runs_per_agent`, keyed by agent id
+    pub agent_limits: HashMap<i64, usize>,
+}
+
+impl Default for AgentQueueLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent_runs: 2,
+            max_runs_per_agent: 1,
+            agent_limits: HashMap::new(),
+        }
+    }
+}
+
+impl AgentQueueLimits {
+    fn limit_for(&self, agent_id: i64) -> usize {
+        self.agent_limits
+            .get(&agent_id)
+            .copied()
+            .unwrap_or(self.max_runs_per_agent)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent_runs == 0 || self.max_runs_per_agent == 0 {
+            return Err("Concurrency limits must be at least 1".to_string());
+        }
+        if self.agent_limits.values().any(|limit| *limit == 0) {
+            return Err("Per-agent limits must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn load_limits(conn: &Connection) -> AgentQueueLimits {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![QUEUE_LIMITS_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| serde_json::from_str(&value).ok())
+    .unwrap_or_default()
+}
+
+/// Pick queued runs (oldest first) that fit under the limits
+///
+/// `queued` holds `(run_id, agent_id)` pairs; `active` counts pending and running
+/// runs per agent. A run blocked by its agent's cap does not block other agents.
+fn select_runs_to_start(
+    queued: &[(i64, i64)],
+    active: &HashMap<i64, usize>,
+    limits: &AgentQueueLimits,
+) -> Vec<i64> {
+    let mut active = active.clone();
+    let mut total: usize = active.values().sum();
+    let mut selected = Vec::new();
+
+    for (run_id, agent_id) in queued {
+        if total >= limits.max_concurrent_runs {
+            break;
+        }
+        let count = active.entry(*agent_id).or_insert(0);
+        if *count < limits.limit_for(*agent_id) {
+            *count += 1;
+            total += 1;
+            selected.push(*run_id);
+        }
+    }
+    selected
+}
+
+/// Move the runs that may start now from `queued` to `pending`
+fn claim_runs(conn: &Connection) -> Result<Vec<i64>, String> {
+    let limits = load_limits(conn);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT agent_id, COUNT(*) FROM agent_runs WHERE status IN ('pending', 'running') GROUP BY agent_id",
+        )
+        .map_err(|e| e.to_string())?;
+    let active = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as usize))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, agent_id FROM agent_runs WHERE status = 'queued' ORDER BY queued_at, id",
+        )
+        .map_err(|e| e.to_string())?;
+    let queued = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut claimed = Vec::new();
+    for run_id in select_runs_to_start(&queued, &active, &limits) {
+        let updated = conn
+            .execute(
+                "UPDATE agent_runs SET status = 'pending' WHERE id = ?1 AND status = 'queued'",
+                params![run_id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated > 0 {
+            claimed.push(run_id);
+        }
+    }
+    Ok(claimed)
+}
+
+/// Reset runs left over from a previous app session
+///
+/// Pending runs never got a process and go back to the queue. Running runs whose
+/// process is gone are marked failed; runs whose process is still alive are left
+/// running for `cleanup_finished_processes` to settle once they exit.
+fn recover_interrupted_runs(conn: &Connection) -> SqliteResult<(usize, usize)> {
+    let requeued = conn.execute(
+        "UPDATE agent_runs SET status = 'queued' WHERE status = 'pending'",
+        [],
+    )?;
+
+    let mut stmt = conn.prepare("SELECT id, pid FROM agent_runs WHERE status = 'running'")?;
+    let running = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?))
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    drop(stmt);
+
+    let mut failed = 0;
+    for (run_id, pid) in running {
+        if pid.is_some_and(is_pid_running) {
+            info!(
+                "Agent run {} is still running after restart, keeping it",
+                run_id
+            );
+            continue;
+        }
+        failed += conn.execute(
+            "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
+            params![run_id],
+        )?;
+    }
+    Ok((requeued, failed))
+}
+
+/// Whether a due occurrence should start a run
+///
+/// Returns `None` while the occurrence is still in the future.
+fn due_occurrence_runs(
+    occurrence: &DateTime<Utc>,
+    now: &DateTime<Utc>,
+    policy: MissedRunPolicy,
+) -> Option<bool> {
+    if occurrence > now {
+        return None;
+    }
+    let missed = *now - *occurrence > Duration::minutes(MISSED_RUN_GRACE_MINUTES);
+    Some(!missed || policy == MissedRunPolicy::RunOnce)
+}
+
+/// Enqueue runs for every enabled schedule that is due, returning how many were queued
+fn run_due_schedules(conn: &Connection, now: &DateTime<Utc>) -> Result<usize, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_schedules WHERE enabled = 1",
+            SCHEDULE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let schedules = stmt
+        .query_map([], schedule_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut queued = 0;
+    for schedule in schedules {
+        let Some(id) = schedule.id else { continue };
+        let cron = match CronSchedule::from_str(&schedule.cron) {
+            Ok(cron) => cron,
+            Err(e) => {
+                warn!("Schedule {} has an invalid cron expression: {}", id, e);
+                continue;
+            }
+        };
+        let following = cron.next_after(now).map(|t| t.to_rfc3339());
+
+        let next = schedule
+            .next_run_at
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc));
+        let Some(next) = next else {
+            // Never computed (or unreadable): start counting from now
+            conn.execute(
+                "UPDATE agent_schedules SET next_run_at = ?1 WHERE id = ?2",
+                params![following, id],
+            )
+            .map_err(|e| e.to_string())?;
+            continue;
+        };
+
+        let Some(should_run) = due_occurrence_runs(&next, now, schedule.missed_run_policy) else {
+            continue;
+        };
+
+        let previous_run = active_run_for_schedule(conn, id).map_err(|e| e.to_string())?;
+        let mut enqueued = false;
+        if let (true, Some(run_id)) = (should_run, previous_run) {
+            info!(
+                "Schedule {} skipped its run at {}: run {} has not finished",
+                id, next, run_id
+            );
+        } else if should_run {
+            match enqueue_scheduled_run(conn, &schedule, &next.to_rfc3339()) {
+                Ok(run_id) => {
+                    info!("Schedule {} queued agent run {}", id, run_id);
+                    queued += 1;
+                    enqueued = true;
+                }
+                Err(e) => error!("Schedule {} failed to queue a run: {}", id, e),
+            }
+        } else {
+            info!(
+                "Schedule {} missed its run at {}, skipping to {:?}",
+                id, next, following
+            );
+        }
+
+        // Always move on to the next occurrence, but only record runs that were queued
+        let last_run_at = enqueued.then(|| next.to_rfc3339());
+        conn.execute(
+            "UPDATE agent_schedules SET last_run_at = COALESCE(?1, last_run_at), next_run_at = ?2 WHERE id = ?3",
+            params![last_run_at, following, id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(queued)
+}
+
+fn enqueue_scheduled_run(
+    conn: &Connection,
+    schedule: &AgentSchedule,
+    scheduled_for: &str,
+) -> Result<i64, String> {
+    let agent = load_agent(conn, schedule.agent_id).map_err(|e| e.to_string())?;
+    let task = schedule
+        .task
+        .clone()
+        .or_else(|| agent.default_task.clone())
+        .filter(|t| !t.trim().is_empty())
+        .ok_or("Schedule has no task and the agent has no default task")?;
+    enqueue_agent_run(
+        conn,
+        &agent,
+        &schedule.project_path,
+        &task,
+        schedule.model.clone(),
+        schedule.id.map(|id| (id, scheduled_for)),
+    )
+}
+
+/// Handle for waking the agent run dispatcher
+#[derive(Clone, Default)]
+pub struct AgentRunQueue {
+    notify: Arc<Notify>,
+}
+
+impl AgentRunQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the dispatcher to look at the queue and tell the UI it changed
+    pub fn wake(&self, app: &AppHandle) {
+        self.notify.notify_one();
+        let _ = app.emit("agent-queue-updated", ());
+    }
+}
+
+/// Start as many queued runs as the limits allow
+async fn dispatch_queued_runs(app: &AppHandle) -> Result<(), String> {
+    let claimed = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        claim_runs(&conn)?
+    };
+
+    for run_id in claimed {
+        if let Err(e) = start_queued_run(app, run_id).await {
+            error!("Failed to start queued agent run {}: {}", run_id, e);
+            {
+                let db = app.state::<AgentDb>();
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                conn.execute(
+                    "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                    params![run_id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            let _ = app.emit("agent-complete", false);
+            let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+        }
+        let _ = app.emit("agent-queue-updated", ());
+    }
+    Ok(())
+}
+
+/// Spawn the background dispatcher that starts queued runs and fires schedules
+///
+/// Requires `AgentDb`, `ProcessRegistryState` and `AgentRunQueue` to be managed.
+/// Occurrences missed while the app was closed are handled on the first tick.
+pub fn spawn_agent_run_queue(app: AppHandle) {
+    let queue = app.state::<AgentRunQueue>().inner().clone();
+
+    // Finished runs free a slot
+    let notify = queue.notify.clone();
+    app.listen_any("agent-complete", move |_| notify.notify_one());
+
+    tauri::async_runtime::spawn(async move {
+        {
+            let db = app.state::<AgentDb>();
+            let recovered =
+                db.0.lock()
+                    .map_err(|e| e.to_string())
+                    .and_then(|conn| recover_interrupted_runs(&conn).map_err(|e| e.to_string()));
+            match recovered {
+                Ok((requeued, failed)) if requeued + failed > 0 => info!(
+                    "Recovered agent runs after restart: {} requeued, {} marked failed",
+                    requeued, failed
+                ),
+                Ok(_) => {}
+                Err(e) => error!("Failed to recover interrupted agent runs: {}", e),
+            }
+        }
+
+        let mut poll = tokio::time::interval(QUEUE_POLL_INTERVAL);
+        let mut schedule_check = tokio::time::interval(SCHEDULE_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = poll.tick() => {}
+                _ = queue.notify.notified() => {}
+                _ = schedule_check.tick() => {
+                    let db = app.state::<AgentDb>();
+                    let result = db
+                        .0
+                        .lock()
+                        .map_err(|e| e.to_string())
+                        .and_then(|conn| run_due_schedules(&conn, &Utc::now()));
+                    match result {
+                        Ok(0) => {}
+                        Ok(_) => {
+                            let _ = app.emit("agent-queue-updated", ());
+                        }
+                        Err(e) => error!("Failed to evaluate agent schedules: {}", e),
+                    }
+                }
+            }
+
+            if let Err(e) = dispatch_queued_runs(&app).await {
+                error!("Agent run dispatch failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Snapshot of the run queue for the UI
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentQueueStatus {
+    /// Runs waiting for a slot, oldest first
+    pub queued: Vec<AgentRun>,
+    /// Runs currently pending or running
+    pub active: usize,
+    pub limits: AgentQueueLimits,
+}
+
+/// Get the queued runs, active run count and limits
+#[tauri::command]
+pub async fn get_agent_queue_status(db: State<'_, AgentDb>) -> Result<AgentQueueStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_runs WHERE status = 'queued' ORDER BY queued_at, id",
+            AGENT_RUN_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let queued = stmt
+        .query_map([], agent_run_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let active = conn
+        .query_row(
+            "SELECT COUNT(*) FROM agent_runs WHERE status IN ('pending', 'running')",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(|e| e.to_string())? as usize;
+
+    Ok(AgentQueueStatus {
+        queued,
+        active,
+        limits: load_limits(&conn),
+    })
+}
+
+/// Get the run queue concurrency limits
+#[tauri::command]
+pub async fn get_agent_queue_limits(db: State<'_, AgentDb>) -> Result<AgentQueueLimits, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_limits(&conn))
+}
+
+/// Save the run queue concurrency limits
+#[tauri::command]
+pub async fn set_agent_queue_limits(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    queue: State<'_, AgentRunQueue>,
+    limits: AgentQueueLimits,
+) -> Result<(), String> {
+    limits.validate()?;
+    let value = serde_json::to_string(&limits).map_err(|e| e.to_string())?;
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = ?2",
+            params![QUEUE_LIMITS_KEY, value],
+        )
+        .map_err(|e| format!("Failed to save agent queue limits: {}", e))?;
+    }
+
+    // Raised limits may let queued runs start
+    queue.wake(&app);
+    Ok(())
+}
+
+/// List agent schedules (optionally filtered by agent_id)
+#[tauri::command]
+pub async fn list_agent_schedules(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+) -> Result<Vec<AgentSchedule>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_schedules WHERE ?1 IS NULL OR agent_id = ?1 ORDER BY id",
+            SCHEDULE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let schedules = stmt
+        .query_map(params![agent_id], schedule_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(schedules)
+}
+
+/// Check the schedule fields and compute its first occurrence
+fn prepare_schedule(
+    conn: &Connection,
+    agent_id: i64,
+    project_path: &str,
+    cron: &str,
+    task: Option<&str>,
+) -> Result<Option<String>, String> {
+    let cron = CronSchedule::from_str(cron)?;
+    let agent = load_agent(conn, agent_id).map_err(|e| e.to_string())?;
+    if project_path.trim().is_empty() {
+        return Err("Schedule needs a project path".to_string());
+    }
+    let has_task = task
+        .or(agent.default_task.as_deref())
+        .is_some_and(|t| !t.trim().is_empty());
+    if !has_task {
+        return Err("Schedule needs a task or an agent default task".to_string());
+    }
+    let next = cron
+        .next_after(&Utc::now())
+        .ok_or("Cron expression never matches a date")?;
+    Ok(Some(next.to_rfc3339()))
+}
+
+fn load_schedule(conn: &Connection, id: i64) -> Result<AgentSchedule, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM agent_schedules WHERE id = ?1",
+            SCHEDULE_COLUMNS
+        ),
+        params![id],
+        schedule_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Create a cron schedule for an agent
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_agent_schedule(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    project_path: String,
+    cron: String,
+    task: Option<String>,
+    model: Option<String>,
+    missed_run_policy: Option<MissedRunPolicy>,
+    enabled: Option<bool>,
+) -> Result<AgentSchedule, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let next_run_at = prepare_schedule(&conn, agent_id, &project_path, &cron, task.as_deref())?;
+
+    conn.execute(
+        "INSERT INTO agent_schedules (agent_id, project_path, task, model, cron, enabled, missed_run_policy, next_run_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            agent_id,
+            project_path,
+            task,
+            model,
+            cron.trim(),
+            enabled.unwrap_or(true),
+            missed_run_policy.unwrap_or_default().as_str(),
+            next_run_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    load_schedule(&conn, conn.last_insert_rowid())
+}
+
+/// Update a schedule; its next occurrence is recomputed from now
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn update_agent_schedule(
+    db: State<'_, AgentDb>,
+    id: i64,
+    project_path: String,
+    cron: String,
+    task: Option<String>,
+    model: Option<String>,
+    missed_run_policy: MissedRunPolicy,
+    enabled: bool,
+) -> Result<AgentSchedule, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let existing = load_schedule(&conn, id)?;
+    let next_run_at = prepare_schedule(
+        &conn,
+        existing.agent_id,
+        &project_path,
+        &cron,
+        task.as_deref(),
+    )?;
+
+    conn.execute(
+        "UPDATE agent_schedules SET project_path = ?1, task = ?2, model = ?3, cron = ?4, enabled = ?5, missed_run_policy = ?6, next_run_at = ?7, updated_at = CURRENT_TIMESTAMP WHERE id = ?8",
+        params![
+            project_path,
+            task,
+            model,
+            cron.trim(),
+            enabled,
+            missed_run_policy.as_str(),
+            next_run_at,
+            id
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    load_schedule(&conn, id)
+}
+
+/// Delete a schedule; runs it already queued are kept
+#[tauri::command]
+pub async fn delete_agent_schedule(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM agent_schedules WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Preview the next occurrences of a cron expression (RFC 3339, UTC)
+#[tauri::command]
+pub async fn preview_agent_schedule(
+    cron: String,
+    count: Option<usize>,
+) -> Result<Vec<String>, String> {
+    let schedule = CronSchedule::from_str(&cron)?;
+    let mut occurrences = Vec::new();
+    let mut cursor = Utc::now();
+    for _ in 0..count.unwrap_or(5).min(50) {
+        let Some(next) = schedule.next_after(&cursor) else {
+            break;
+        };
+        occurrences.push(next.to_rfc3339());
+        cursor = next;
+    }
+    Ok(occurrences)
+}
+
+/// A run of this schedule that is still queued or executing, if any
+fn active_run_for_schedule(conn: &Connection, schedule_id: i64) -> SqliteResult<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM agent_runs WHERE schedule_id = ?1 AND status IN ('queued', 'pending', 'running') LIMIT 1",
+        params![schedule_id],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(cron: &str, after: &str) -> String {
+        CronSchedule::from_str(cron)
+            .unwrap()
+            .next_after_in(&utc(after), &Utc)
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    #[test]
+    fn parses_cron_fields_and_shortcuts() {
+        assert_eq!(
+            next("30 2 * * *", "2026-10-19T03:00:00Z"),
+            "2026-10-20T02:30:00+00:00"
+        );
+        assert_eq!(
+            next("*/15 * * * *", "2026-10-19T03:07:10Z"),
+            "2026-10-19T03:15:00+00:00"
+        );
+        assert_eq!(
+            next("0 9 * * mon-fri", "2026-10-24T10:00:00Z"),
+            "2026-10-26T09:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 1 jan *", "2026-10-19T00:00:00Z"),
+            "2027-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("@weekly", "2026-10-19T00:00:00Z"),
+            "2026-10-25T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 12 * * 7", "2026-10-19T00:00:00Z"),
+            "2026-10-25T12:00:00+00:00"
+        );
+        // Strictly after: an exact match moves on to the next occurrence
+        assert_eq!(
+            next("@hourly", "2026-10-19T05:00:00Z"),
+            "2026-10-19T06:00:00+00:00"
+        );
+        // Both day fields restricted: either matches
+        assert_eq!(
+            next("0 0 13 * fri", "2026-10-19T00:00:00Z"),
+            "2026-10-23T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2026-10-19T00:00:00Z"),
+            "2028-02-29T00:00:00+00:00"
+        );
+
+        for bad in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "@often",
+            "0 0 * foo *",
+        ] {
+            assert!(
+                CronSchedule::from_str(bad).is_err(),
+                "{bad} should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn selects_runs_within_global_and_per_agent_limits() {
+        let limits = AgentQueueLimits {
+            max_concurrent_runs: 3,
+            max_runs_per_agent: 1,
+            agent_limits: HashMap::from([(2, 2)]),
+        };
+        let queued = [(10, 1), (11, 1), (12, 2), (13, 2), (14, 3)];
+
+        // Agent 1 is capped at one run, agent 2 may run two, the global cap stops agent 3
+        let selected = select_runs_to_start(&queued, &HashMap::new(), &limits);
+        assert_eq!(selected, vec![10, 12, 13]);
+
+        // Already-active runs count towards both caps
+        let active = HashMap::from([(1, 1), (2, 1)]);
+        assert_eq!(select_runs_to_start(&queued, &active, &limits), vec![12]);
+    }
+
+    #[test]
+    fn recovery_keeps_runs_whose_process_is_alive() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agent_runs (id INTEGER PRIMARY KEY, status TEXT, pid INTEGER, completed_at TEXT);
+             INSERT INTO agent_runs (id, status, pid) VALUES (1, 'pending', NULL), (2, 'running', NULL);",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO agent_runs (id, status, pid) VALUES (3, 'running', ?1)",
+            params![std::process::id() as i64],
+        )
+        .unwrap();
+
+        assert_eq!(recover_interrupted_runs(&conn).unwrap(), (1, 1));
+
+        let status = |id: i64| -> String {
+            conn.query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(status(1), "queued");
+        assert_eq!(status(2), "failed");
+        assert_eq!(status(3), "running");
+    }
+
+    #[test]
+    fn missed_occurrences_follow_the_policy() {
+        let occurrence = utc("2026-10-19T02:00:00Z");
+        let on_time = utc("2026-10-19T02:00:30Z");
+        let after_restart = utc("2026-10-19T09:00:00Z");
+
+        assert_eq!(
+            due_occurrence_runs(
+                &occurrence,
+                &utc("2026-10-19T01:59:00Z"),
+                MissedRunPolicy::Skip
+            ),
+            None
+        );
+        assert_eq!(
+            due_occurrence_runs(&occurrence, &on_time, MissedRunPolicy::Skip),
+            Some(true)
+        );
+        assert_eq!(
+            due_occurrence_runs(&occurrence, &after_restart, MissedRunPolicy::Skip),
+            Some(false)
+        );
+        assert_eq!(
+            due_occurrence_runs(&occurrence, &after_restart, MissedRunPolicy::RunOnce),
+            Some(true)
+        );
+    }
+}
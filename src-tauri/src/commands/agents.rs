@@ -3,7 +3,7 @@ use chrono;
 use dirs;
 use log::{debug, error, info, warn};
 use reqwest;
-use rusqlite::{params, Connection, Result as SqliteResult};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::io::{BufRead, BufReader};
@@ -32,6 +32,15 @@ pub struct Agent {
     pub enable_file_read: bool,
     pub enable_file_write: bool,
     pub enable_network: bool,
+    /// Extra tools granted on top of the flags, in `--allowedTools` syntax
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Tools always denied, overriding the flags and `allowed_tools`
+    #[serde(default)]
+    pub disallowed_tools: Vec<String>,
+    /// Directories outside the project the agent may access
+    #[serde(default)]
+    pub allowed_directories: Vec<String>,
     pub hooks: Option<String>, // JSON string of hooks configuration
     pub created_at: String,
     pub updated_at: String,
@@ -48,11 +57,177 @@ pub struct AgentRun {
     pub model: String,
     pub project_path: String,
     pub session_id: String, // UUID session ID from Claude Code
-    pub status: String,     // 'pending', 'running', 'completed', 'failed', 'cancelled'
+    pub status: String,     // 'queued', 'pending', 'running', 'completed', 'failed', 'cancelled'
     pub pid: Option<u32>,
     pub process_started_at: Option<String>,
     pub created_at: String,
     pub completed_at: Option<String>,
+    /// Permissions the run was launched with, for auditing
+    pub permission_policy: Option<AgentPermissionPolicy>,
+    /// When the run entered the queue
+    pub queued_at: Option<String>,
+    /// Schedule that enqueued the run, if any
+    pub schedule_id: Option<i64>,
+    /// Occurrence the scheduled run stands in for
+    pub scheduled_for: Option<String>,
+}
+
+/// Tools covered by `enable_file_read`
+const FILE_READ_TOOLS: &[&str] = &["Read", "Glob", "Grep", "LS", "NotebookRead"];
+/// Tools covered by `enable_file_write`
+const FILE_WRITE_TOOLS: &[&str] = &["Edit", "MultiEdit", "Write", "NotebookEdit"];
+/// Tools covered by `enable_network`
+const NETWORK_TOOLS: &[&str] = &["WebFetch", "WebSearch"];
+/// Tools that touch neither the file system nor the network
+const BASE_TOOLS: &[&str] = &["Task", "TodoWrite"];
+
+/// Effective Claude Code permissions of an agent run
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AgentPermissionPolicy {
+    pub allowed_tools: Vec<String>,
+    pub disallowed_tools: Vec<String>,
+    pub additional_directories: Vec<String>,
+}
+
+impl AgentPermissionPolicy {
+    /// Translate an agent's permission flags and tool lists into a policy
+    ///
+    /// Bash can both write files and reach the network, so the flags only grant it
+    /// when both are enabled; narrower rules such as `Bash(git status:*)` can still
+    /// be granted through `allowed_tools`. Explicitly disallowed tools always win.
+    pub fn for_agent(agent: &Agent) -> Self {
+        let mut granted: Vec<&str> = BASE_TOOLS.to_vec();
+        let mut denied: Vec<&str> = Vec::new();
+        for (enabled, tools) in [
+            (agent.enable_file_read, FILE_READ_TOOLS),
+            (agent.enable_file_write, FILE_WRITE_TOOLS),
+            (agent.enable_network, NETWORK_TOOLS),
+            (agent.enable_file_write && agent.enable_network, &["Bash"]),
+        ] {
+            if enabled {
+                granted.extend(tools);
+            } else {
+                denied.extend(tools);
+            }
+        }
+
+        let explicit_deny = |tool: &str| agent.disallowed_tools.iter().any(|t| t == tool);
+        // A rule like `Bash(git:*)` needs its base tool to stay available
+        let explicit_allow = |tool: &str| {
+            agent
+                .allowed_tools
+                .iter()
+                .any(|t| t == tool || t.split('(').next() == Some(tool))
+        };
+
+        let mut allowed_tools: Vec<String> = Vec::new();
+        for tool in granted
+            .into_iter()
+            .chain(agent.allowed_tools.iter().map(String::as_str))
+        {
+            if !explicit_deny(tool) && !allowed_tools.iter().any(|t| t == tool) {
+                allowed_tools.push(tool.to_string());
+            }
+        }
+
+        let mut disallowed_tools: Vec<String> = Vec::new();
+        for tool in denied
+            .into_iter()
+            .filter(|tool| !explicit_allow(tool))
+            .chain(agent.disallowed_tools.iter().map(String::as_str))
+        {
+            if !disallowed_tools.iter().any(|t| t == tool) {
+                disallowed_tools.push(tool.to_string());
+            }
+        }
+
+        Self {
+            allowed_tools,
+            disallowed_tools,
+            additional_directories: agent.allowed_directories.clone(),
+        }
+    }
+
+    /// Claude Code command line flags enforcing the policy
+    pub fn to_cli_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (flag, values) in [
+            ("--allowedTools", &self.allowed_tools),
+            ("--disallowedTools", &self.disallowed_tools),
+            ("--add-dir", &self.additional_directories),
+        ] {
+            if !values.is_empty() {
+                args.push(flag.to_string());
+                args.extend(values.iter().cloned());
+            }
+        }
+        args
+    }
+}
+
+/// Columns read by [`agent_from_row`]
+const AGENT_COLUMNS: &str = "id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at, allowed_tools, disallowed_tools, allowed_directories";
+
+/// Columns read by [`agent_run_from_row`]
+pub(crate) const AGENT_RUN_COLUMNS: &str = "id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at, permission_policy, queued_at, schedule_id, scheduled_for";
+
+/// Decode a JSON string list column, treating NULL or bad JSON as empty
+fn json_list(value: Option<String>) -> Vec<String> {
+    value
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn agent_from_row(row: &rusqlite::Row) -> rusqlite::Result<Agent> {
+    Ok(Agent {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        icon: row.get(2)?,
+        system_prompt: row.get(3)?,
+        default_task: row.get(4)?,
+        model: row
+            .get::<_, String>(5)
+            .unwrap_or_else(|_| "sonnet".to_string()),
+        enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
+        enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
+        enable_network: row.get::<_, bool>(8).unwrap_or(false),
+        allowed_tools: json_list(row.get(12)?),
+        disallowed_tools: json_list(row.get(13)?),
+        allowed_directories: json_list(row.get(14)?),
+        hooks: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+    })
+}
+
+pub(crate) fn agent_run_from_row(row: &rusqlite::Row) -> rusqlite::Result<AgentRun> {
+    Ok(AgentRun {
+        id: Some(row.get(0)?),
+        agent_id: row.get(1)?,
+        agent_name: row.get(2)?,
+        agent_icon: row.get(3)?,
+        task: row.get(4)?,
+        model: row.get(5)?,
+        project_path: row.get(6)?,
+        session_id: row.get(7)?,
+        status: row
+            .get::<_, String>(8)
+            .unwrap_or_else(|_| "pending".to_string()),
+        pid: row
+            .get::<_, Option<i64>>(9)
+            .ok()
+            .flatten()
+            .map(|p| p as u32),
+        process_started_at: row.get(10)?,
+        created_at: row.get(11)?,
+        completed_at: row.get(12)?,
+        permission_policy: row
+            .get::<_, Option<String>>(13)?
+            .and_then(|p| serde_json::from_str(&p).ok()),
+        queued_at: row.get(14)?,
+        schedule_id: row.get(15)?,
+        scheduled_for: row.get(16)?,
+    })
 }
 
 /// Represents runtime metrics calculated from JSONL
@@ -89,9 +264,45 @@ pub struct AgentData {
     pub system_prompt: String,
     pub default_task: Option<String>,
     pub model: String,
+    /// Permission flags; exports without them get the defaults of a new agent
+    #[serde(default = "default_true")]
+    pub enable_file_read: bool,
+    #[serde(default = "default_true")]
+    pub enable_file_write: bool,
+    #[serde(default)]
+    pub enable_network: bool,
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    #[serde(default)]
+    pub disallowed_tools: Vec<String>,
+    #[serde(default)]
+    pub allowed_directories: Vec<String>,
     pub hooks: Option<String>,
 }
 
+fn default_true() -> bool {
+    true
+}
+
+impl From<Agent> for AgentData {
+    fn from(agent: Agent) -> Self {
+        Self {
+            name: agent.name,
+            icon: agent.icon,
+            system_prompt: agent.system_prompt,
+            default_task: agent.default_task,
+            model: agent.model,
+            enable_file_read: agent.enable_file_read,
+            enable_file_write: agent.enable_file_write,
+            enable_network: agent.enable_network,
+            allowed_tools: agent.allowed_tools,
+            disallowed_tools: agent.disallowed_tools,
+            allowed_directories: agent.allowed_directories,
+            hooks: agent.hooks,
+        }
+    }
+}
+
 /// Database connection state
 pub struct AgentDb(pub Mutex<Connection>);
 
@@ -237,6 +448,9 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
             enable_file_write BOOLEAN NOT NULL DEFAULT 1,
             enable_network BOOLEAN NOT NULL DEFAULT 0,
             hooks TEXT,
+            allowed_tools TEXT,
+            disallowed_tools TEXT,
+            allowed_directories TEXT,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
             updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
         )",
@@ -262,6 +476,9 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agents ADD COLUMN enable_network BOOLEAN DEFAULT 0",
         [],
     );
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN allowed_tools TEXT", []);
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN disallowed_tools TEXT", []);
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN allowed_directories TEXT", []);
 
     // Create agent_runs table
     conn.execute(
@@ -279,6 +496,10 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
             process_started_at TEXT,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
             completed_at TEXT,
+            permission_policy TEXT,
+            queued_at TEXT,
+            schedule_id INTEGER,
+            scheduled_for TEXT,
             FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
         )",
         [],
@@ -295,6 +516,13 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agent_runs ADD COLUMN process_started_at TEXT",
         [],
     );
+    let _ = conn.execute(
+        "ALTER TABLE agent_runs ADD COLUMN permission_policy TEXT",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN queued_at TEXT", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN schedule_id INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN scheduled_for TEXT", []);
 
     // Drop old columns that are no longer needed (data is now read from JSONL files)
     // Note: SQLite doesn't support DROP COLUMN, so we'll ignore errors for existing columns
@@ -342,6 +570,9 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     )?;
 
+    // Create agent_schedules table
+    super::agent_queue::init_schedule_table(&conn)?;
+
     Ok(conn)
 }
 
@@ -351,28 +582,14 @@ pub async fn list_agents(db: State<'_, AgentDb>) -> Result<Vec<Agent>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = conn
-        .prepare("SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents ORDER BY created_at DESC")
+        .prepare(&format!(
+            "SELECT {} FROM agents ORDER BY created_at DESC",
+            AGENT_COLUMNS
+        ))
         .map_err(|e| e.to_string())?;
 
     let agents = stmt
-        .query_map([], |row| {
-            Ok(Agent {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                icon: row.get(2)?,
-                system_prompt: row.get(3)?,
-                default_task: row.get(4)?,
-                model: row
-                    .get::<_, String>(5)
-                    .unwrap_or_else(|_| "sonnet".to_string()),
-                enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
-                enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
-                enable_network: row.get::<_, bool>(8).unwrap_or(false),
-                hooks: row.get(9)?,
-                created_at: row.get(10)?,
-                updated_at: row.get(11)?,
-            })
-        })
+        .query_map([], agent_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
@@ -392,6 +609,9 @@ pub async fn create_agent(
     enable_file_read: Option<bool>,
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
+    allowed_tools: Option<Vec<String>>,
+    disallowed_tools: Option<Vec<String>>,
+    allowed_directories: Option<Vec<String>>,
     hooks: Option<String>,
 ) -> Result<Agent, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
@@ -399,10 +619,16 @@ pub async fn create_agent(
     let enable_file_read = enable_file_read.unwrap_or(true);
     let enable_file_write = enable_file_write.unwrap_or(true);
     let enable_network = enable_network.unwrap_or(false);
+    let allowed_tools =
+        serde_json::to_string(&allowed_tools.unwrap_or_default()).map_err(|e| e.to_string())?;
+    let disallowed_tools =
+        serde_json::to_string(&disallowed_tools.unwrap_or_default()).map_err(|e| e.to_string())?;
+    let allowed_directories = serde_json::to_string(&allowed_directories.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
 
     conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks],
+        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, allowed_tools, disallowed_tools, allowed_directories, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, allowed_tools, disallowed_tools, allowed_directories, hooks],
     )
     .map_err(|e| e.to_string())?;
 
@@ -411,24 +637,9 @@ pub async fn create_agent(
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -448,6 +659,9 @@ pub async fn update_agent(
     enable_file_read: Option<bool>,
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
+    allowed_tools: Option<Vec<String>>,
+    disallowed_tools: Option<Vec<String>>,
+    allowed_directories: Option<Vec<String>>,
     hooks: Option<String>,
 ) -> Result<Agent, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
@@ -482,6 +696,19 @@ pub async fn update_agent(
         query.push_str(&format!(", enable_network = ?{}", param_count));
         params_vec.push(Box::new(en));
     }
+    for (column, list) in [
+        ("allowed_tools", allowed_tools),
+        ("disallowed_tools", disallowed_tools),
+        ("allowed_directories", allowed_directories),
+    ] {
+        if let Some(list) = list {
+            param_count += 1;
+            query.push_str(&format!(", {} = ?{}", column, param_count));
+            params_vec.push(Box::new(
+                serde_json::to_string(&list).map_err(|e| e.to_string())?,
+            ));
+        }
+    }
 
     param_count += 1;
     query.push_str(&format!(" WHERE id = ?{}", param_count));
@@ -496,24 +723,9 @@ pub async fn update_agent(
     // Fetch the updated agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -535,31 +747,16 @@ pub async fn delete_agent(db: State<'_, AgentDb>, id: i64) -> Result<(), String>
 #[tauri::command]
 pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_agent(&conn, id).map_err(|e| e.to_string())
+}
 
-    let agent = conn
-        .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get::<_, String>(5).unwrap_or_else(|_| "sonnet".to_string()),
-                    enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
-                    enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
-                    enable_network: row.get::<_, bool>(8).unwrap_or(false),
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
-        )
-        .map_err(|e| e.to_string())?;
-
-    Ok(agent)
+/// Load an agent row with an already locked connection
+pub(crate) fn load_agent(conn: &Connection, id: i64) -> rusqlite::Result<Agent> {
+    conn.query_row(
+        &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
+        params![id],
+        agent_from_row,
+    )
 }
 
 /// List agent runs (optionally filtered by agent_id)
@@ -571,43 +768,23 @@ pub async fn list_agent_runs(
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let query = if agent_id.is_some() {
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-         FROM agent_runs WHERE agent_id = ?1 ORDER BY created_at DESC"
+        format!(
+            "SELECT {} FROM agent_runs WHERE agent_id = ?1 ORDER BY created_at DESC",
+            AGENT_RUN_COLUMNS
+        )
     } else {
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-         FROM agent_runs ORDER BY created_at DESC"
+        format!(
+            "SELECT {} FROM agent_runs ORDER BY created_at DESC",
+            AGENT_RUN_COLUMNS
+        )
     };
 
-    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
-
-    let run_mapper = |row: &rusqlite::Row| -> rusqlite::Result<AgentRun> {
-        Ok(AgentRun {
-            id: Some(row.get(0)?),
-            agent_id: row.get(1)?,
-            agent_name: row.get(2)?,
-            agent_icon: row.get(3)?,
-            task: row.get(4)?,
-            model: row.get(5)?,
-            project_path: row.get(6)?,
-            session_id: row.get(7)?,
-            status: row
-                .get::<_, String>(8)
-                .unwrap_or_else(|_| "pending".to_string()),
-            pid: row
-                .get::<_, Option<i64>>(9)
-                .ok()
-                .flatten()
-                .map(|p| p as u32),
-            process_started_at: row.get(10)?,
-            created_at: row.get(11)?,
-            completed_at: row.get(12)?,
-        })
-    };
+    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
 
     let runs = if let Some(aid) = agent_id {
-        stmt.query_map(params![aid], run_mapper)
+        stmt.query_map(params![aid], agent_run_from_row)
     } else {
-        stmt.query_map(params![], run_mapper)
+        stmt.query_map(params![], agent_run_from_row)
     }
     .map_err(|e| e.to_string())?
     .collect::<Result<Vec<_>, _>>()
@@ -623,26 +800,9 @@ pub async fn get_agent_run(db: State<'_, AgentDb>, id: i64) -> Result<AgentRun,
 
     let run = conn
         .query_row(
-            "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-             FROM agent_runs WHERE id = ?1",
+            &format!("SELECT {} FROM agent_runs WHERE id = ?1", AGENT_RUN_COLUMNS),
             params![id],
-            |row| {
-                Ok(AgentRun {
-                    id: Some(row.get(0)?),
-                    agent_id: row.get(1)?,
-                    agent_name: row.get(2)?,
-                    agent_icon: row.get(3)?,
-                    task: row.get(4)?,
-                    model: row.get(5)?,
-                    project_path: row.get(6)?,
-                    session_id: row.get(7)?,
-                    status: row.get::<_, String>(8).unwrap_or_else(|_| "pending".to_string()),
-                    pid: row.get::<_, Option<i64>>(9).ok().flatten().map(|p| p as u32),
-                    process_started_at: row.get(10)?,
-                    created_at: row.get(11)?,
-                    completed_at: row.get(12)?,
-                })
-            },
+            agent_run_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -676,7 +836,7 @@ pub async fn list_agent_runs_with_metrics(
     Ok(runs_with_metrics)
 }
 
-/// Execute a CC agent with streaming output
+/// Queue a CC agent run; the run queue starts it once a concurrency slot is free
 #[tauri::command]
 pub async fn execute_agent(
     app: AppHandle,
@@ -685,67 +845,129 @@ pub async fn execute_agent(
     task: String,
     model: Option<String>,
     db: State<'_, AgentDb>,
-    registry: State<'_, crate::process::ProcessRegistryState>,
+    queue: State<'_, super::agent_queue::AgentRunQueue>,
 ) -> Result<i64, String> {
-    info!("Executing agent {} with task: {}", agent_id, task);
+    info!("Queueing agent {} with task: {}", agent_id, task);
+
+    let run_id = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let agent = load_agent(&conn, agent_id).map_err(|e| e.to_string())?;
+        enqueue_agent_run(&conn, &agent, &project_path, &task, model, None)?
+    };
+
+    queue.wake(&app);
+    Ok(run_id)
+}
 
-    // Get the agent from database
-    let agent = get_agent(db.clone(), agent_id).await?;
+/// Insert a `queued` run for an agent, recording the permissions it will run with
+///
+/// `schedule` carries the schedule id and the occurrence time for scheduled runs.
+pub(crate) fn enqueue_agent_run(
+    conn: &Connection,
+    agent: &Agent,
+    project_path: &str,
+    task: &str,
+    model: Option<String>,
+    schedule: Option<(i64, &str)>,
+) -> Result<i64, String> {
+    let agent_id = agent.id.ok_or("Agent has no id")?;
     let execution_model = model.unwrap_or(agent.model.clone());
 
-    // Create .claude/settings.json with agent hooks if it doesn't exist
-    if let Some(hooks_json) = &agent.hooks {
-        let claude_dir = std::path::Path::new(&project_path).join(".claude");
-        let settings_path = claude_dir.join("settings.json");
+    // Resolve what the agent may do and record it with the run
+    let policy = AgentPermissionPolicy::for_agent(agent);
+    info!(
+        "Agent '{}' permissions: allowed {:?}, disallowed {:?}, extra dirs {:?}",
+        agent.name, policy.allowed_tools, policy.disallowed_tools, policy.additional_directories
+    );
+    let policy_json = serde_json::to_string(&policy).map_err(|e| e.to_string())?;
+    let (schedule_id, scheduled_for) = schedule.unzip();
 
-        // Create .claude directory if it doesn't exist
-        if !claude_dir.exists() {
-            std::fs::create_dir_all(&claude_dir)
-                .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
-            info!("Created .claude directory at: {:?}", claude_dir);
-        }
+    conn.execute(
+        "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, permission_policy, queued_at, schedule_id, scheduled_for) VALUES (?1, ?2, ?3, ?4, ?5, ?6, '', 'queued', ?7, ?8, ?9, ?10)",
+        params![
+            agent_id,
+            agent.name,
+            agent.icon,
+            task,
+            execution_model,
+            project_path,
+            policy_json,
+            chrono::Utc::now().to_rfc3339(),
+            schedule_id,
+            scheduled_for
+        ],
+    )
+    .map_err(|e| e.to_string())?;
 
-        // Check if settings.json already exists
-        if !settings_path.exists() {
-            // Parse the hooks JSON
-            let hooks: serde_json::Value = serde_json::from_str(hooks_json)
-                .map_err(|e| format!("Failed to parse agent hooks: {}", e))?;
+    Ok(conn.last_insert_rowid())
+}
 
-            // Create a settings object with just the hooks
-            let settings = serde_json::json!({
-                "hooks": hooks
-            });
+/// Create .claude/settings.json with the agent hooks if it doesn't exist
+fn write_agent_hooks(agent: &Agent, project_path: &str) -> Result<(), String> {
+    let Some(hooks_json) = &agent.hooks else {
+        return Ok(());
+    };
 
-            // Write the settings file
-            let settings_content = serde_json::to_string_pretty(&settings)
-                .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let claude_dir = std::path::Path::new(project_path).join(".claude");
+    let settings_path = claude_dir.join("settings.json");
 
-            std::fs::write(&settings_path, settings_content)
-                .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+    // Create .claude directory if it doesn't exist
+    if !claude_dir.exists() {
+        std::fs::create_dir_all(&claude_dir)
+            .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+        info!("Created .claude directory at: {:?}", claude_dir);
+    }
 
-            info!(
-                "Created settings.json with agent hooks at: {:?}",
-                settings_path
-            );
-        } else {
-            info!("settings.json already exists at: {:?}", settings_path);
-        }
+    // Check if settings.json already exists
+    if !settings_path.exists() {
+        // Parse the hooks JSON
+        let hooks: serde_json::Value = serde_json::from_str(hooks_json)
+            .map_err(|e| format!("Failed to parse agent hooks: {}", e))?;
+
+        // Create a settings object with just the hooks
+        let settings = serde_json::json!({
+            "hooks": hooks
+        });
+
+        // Write the settings file
+        let settings_content = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+        std::fs::write(&settings_path, settings_content)
+            .map_err(|e| format!("Failed to write settings.json: {}", e))?;
+
+        info!(
+            "Created settings.json with agent hooks at: {:?}",
+            settings_path
+        );
+    } else {
+        info!("settings.json already exists at: {:?}", settings_path);
     }
 
-    // Create a new run record
-    let run_id = {
+    Ok(())
+}
+
+/// Launch a run that the run queue has moved from `queued` to `pending`
+pub(crate) async fn start_queued_run(app: &AppHandle, run_id: i64) -> Result<i64, String> {
+    let db = app.state::<AgentDb>();
+    let (run, agent) = {
         let conn = db.0.lock().map_err(|e| e.to_string())?;
-        conn.execute(
-            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![agent_id, agent.name, agent.icon, task, execution_model, project_path, ""],
-        )
-        .map_err(|e| e.to_string())?;
-        conn.last_insert_rowid()
+        let run = conn
+            .query_row(
+                &format!("SELECT {} FROM agent_runs WHERE id = ?1", AGENT_RUN_COLUMNS),
+                params![run_id],
+                agent_run_from_row,
+            )
+            .map_err(|e| e.to_string())?;
+        let agent = load_agent(&conn, run.agent_id).map_err(|e| e.to_string())?;
+        (run, agent)
     };
 
+    write_agent_hooks(&agent, &run.project_path)?;
+
     // Find Claude binary
-    info!("Running agent '{}'", agent.name);
-    let claude_path = match find_claude_binary(&app) {
+    info!("Running agent '{}' (run {})", agent.name, run_id);
+    let claude_path = match find_claude_binary(app) {
         Ok(path) => path,
         Err(e) => {
             error!("Failed to find claude binary: {}", e);
@@ -753,33 +975,39 @@ pub async fn execute_agent(
         }
     };
 
+    // Use the policy recorded at enqueue time so the audit trail matches the launch
+    let policy = run
+        .permission_policy
+        .clone()
+        .unwrap_or_else(|| AgentPermissionPolicy::for_agent(&agent));
+
     // Build arguments
-    let args = vec![
+    let mut args = vec![
         "-p".to_string(),
-        task.clone(),
+        run.task.clone(),
         "--system-prompt".to_string(),
         agent.system_prompt.clone(),
         "--model".to_string(),
-        execution_model.clone(),
+        run.model.clone(),
         "--output-format".to_string(),
         "stream-json".to_string(),
         "--verbose".to_string(),
-        "--dangerously-skip-permissions".to_string(),
     ];
+    args.extend(policy.to_cli_args());
 
     // Always use system binary execution (sidecar removed)
     spawn_agent_system(
-        app,
+        app.clone(),
         run_id,
-        agent_id,
+        run.agent_id,
         agent.name.clone(),
         claude_path,
         args,
-        project_path,
-        task,
-        execution_model,
+        run.project_path,
+        run.task,
+        run.model,
         db,
-        registry,
+        app.state::<crate::process::ProcessRegistryState>(),
     )
     .await
 }
@@ -1146,35 +1374,15 @@ pub async fn list_running_sessions(
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     // First get all running sessions from the database
-    let mut stmt = conn.prepare(
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-         FROM agent_runs WHERE status = 'running' ORDER BY process_started_at DESC"
-    ).map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_runs WHERE status = 'running' ORDER BY process_started_at DESC",
+            AGENT_RUN_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
 
     let mut runs = stmt
-        .query_map([], |row| {
-            Ok(AgentRun {
-                id: Some(row.get(0)?),
-                agent_id: row.get(1)?,
-                agent_name: row.get(2)?,
-                agent_icon: row.get(3)?,
-                task: row.get(4)?,
-                model: row.get(5)?,
-                project_path: row.get(6)?,
-                session_id: row.get(7)?,
-                status: row
-                    .get::<_, String>(8)
-                    .unwrap_or_else(|_| "pending".to_string()),
-                pid: row
-                    .get::<_, Option<i64>>(9)
-                    .ok()
-                    .flatten()
-                    .map(|p| p as u32),
-                process_started_at: row.get(10)?,
-                created_at: row.get(11)?,
-                completed_at: row.get(12)?,
-            })
-        })
+        .query_map([], agent_run_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
@@ -1237,7 +1445,9 @@ pub async fn kill_agent_session(
                 params![run_id],
                 |row| row.get::<_, Option<i64>>(0),
             )
+            .optional()
             .map_err(|e| e.to_string())?
+            .flatten()
         };
 
         if let Some(pid) = pid_result {
@@ -1246,10 +1456,10 @@ pub async fn kill_agent_session(
         }
     }
 
-    // Update the database to mark as cancelled
+    // Update the database to mark as cancelled (queued runs never started a process)
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     let updated = conn.execute(
-        "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
+        "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status IN ('queued', 'pending', 'running')",
         params![run_id],
     ).map_err(|e| e.to_string())?;
 
@@ -1300,31 +1510,7 @@ pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i6
 
     for (run_id, pid) in running_processes {
         // Check if the process is still running
-        let is_running = if cfg!(target_os = "windows") {
-            // On Windows, use tasklist to check if process exists
-            match std::process::Command::new("tasklist")
-                .args(["/FI", &format!("PID eq {}", pid)])
-                .args(["/FO", "CSV"])
-                .output()
-            {
-                Ok(output) => {
-                    let output_str = String::from_utf8_lossy(&output.stdout);
-                    output_str.lines().count() > 1 // Header + process line if exists
-                }
-                Err(_) => false,
-            }
-        } else {
-            // On Unix-like systems, use kill -0 to check if process exists
-            match std::process::Command::new("kill")
-                .args(["-0", &pid.to_string()])
-                .output()
-            {
-                Ok(output) => output.status.success(),
-                Err(_) => false,
-            }
-        };
-
-        if !is_running {
+        if !is_pid_running(pid) {
             // Process has finished, update status
             let updated = conn.execute(
                 "UPDATE agent_runs SET status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
@@ -1344,6 +1530,33 @@ pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i6
     Ok(cleaned_up)
 }
 
+/// Check whether a process with the given PID still exists
+pub(crate) fn is_pid_running(pid: i64) -> bool {
+    if cfg!(target_os = "windows") {
+        // On Windows, use tasklist to check if process exists
+        match std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid)])
+            .args(["/FO", "CSV"])
+            .output()
+        {
+            Ok(output) => {
+                let output_str = String::from_utf8_lossy(&output.stdout);
+                output_str.lines().count() > 1 // Header + process line if exists
+            }
+            Err(_) => false,
+        }
+    } else {
+        // On Unix-like systems, use kill -0 to check if process exists
+        match std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .output()
+        {
+            Ok(output) => output.status.success(),
+            Err(_) => false,
+        }
+    }
+}
+
 /// Get live output from a running process
 #[tauri::command]
 pub async fn get_live_session_output(
@@ -1541,27 +1754,18 @@ pub async fn export_agent(db: State<'_, AgentDb>, id: i64) -> Result<String, Str
     // Fetch the agent
     let agent = conn
         .query_row(
-            "SELECT name, icon, system_prompt, default_task, model, hooks FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(serde_json::json!({
-                    "name": row.get::<_, String>(0)?,
-                    "icon": row.get::<_, String>(1)?,
-                    "system_prompt": row.get::<_, String>(2)?,
-                    "default_task": row.get::<_, Option<String>>(3)?,
-                    "model": row.get::<_, String>(4)?,
-                    "hooks": row.get::<_, Option<String>>(5)?
-                }))
-            },
+            agent_from_row,
         )
         .map_err(|e| format!("Failed to fetch agent: {}", e))?;
 
     // Create the export wrapper
-    let export_data = serde_json::json!({
-        "version": 1,
-        "exported_at": chrono::Utc::now().to_rfc3339(),
-        "agent": agent
-    });
+    let export_data = AgentExport {
+        version: 1,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        agent: agent.into(),
+    };
 
     // Convert to pretty JSON string
     serde_json::to_string_pretty(&export_data)
@@ -1740,15 +1944,28 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
         agent_data.name
     };
 
+    let allowed_tools =
+        serde_json::to_string(&agent_data.allowed_tools).map_err(|e| e.to_string())?;
+    let disallowed_tools =
+        serde_json::to_string(&agent_data.disallowed_tools).map_err(|e| e.to_string())?;
+    let allowed_directories =
+        serde_json::to_string(&agent_data.allowed_directories).map_err(|e| e.to_string())?;
+
     // Create the agent
     conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, 1, 1, 0, ?6)",
+        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, allowed_tools, disallowed_tools, allowed_directories, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
         params![
             final_name,
             agent_data.icon,
             agent_data.system_prompt,
             agent_data.default_task,
             agent_data.model,
+            agent_data.enable_file_read,
+            agent_data.enable_file_write,
+            agent_data.enable_network,
+            allowed_tools,
+            disallowed_tools,
+            allowed_directories,
             agent_data.hooks
         ],
     )
@@ -1759,24 +1976,9 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| format!("Failed to fetch created agent: {}", e))?;
 
@@ -2001,3 +2203,92 @@ pub async fn load_agent_session_history(
         Err(format!("Session file not found: {}", session_id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(read: bool, write: bool, network: bool) -> Agent {
+        Agent {
+            id: Some(1),
+            name: "reviewer".to_string(),
+            icon: "bot".to_string(),
+            system_prompt: String::new(),
+            default_task: None,
+            model: "sonnet".to_string(),
+            enable_file_read: read,
+            enable_file_write: write,
+            enable_network: network,
+            allowed_tools: Vec::new(),
+            disallowed_tools: Vec::new(),
+            allowed_directories: Vec::new(),
+            hooks: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn permission_flags_map_to_tool_lists() {
+        let policy = AgentPermissionPolicy::for_agent(&agent(true, false, false));
+        assert!(policy.allowed_tools.iter().any(|t| t == "Read"));
+        for tool in ["Edit", "Write", "WebFetch", "Bash"] {
+            assert!(
+                policy.disallowed_tools.iter().any(|t| t == tool),
+                "{}",
+                tool
+            );
+            assert!(!policy.allowed_tools.iter().any(|t| t == tool), "{}", tool);
+        }
+
+        let policy = AgentPermissionPolicy::for_agent(&agent(true, true, true));
+        assert!(policy.allowed_tools.iter().any(|t| t == "Bash"));
+        assert!(policy.disallowed_tools.is_empty());
+    }
+
+    #[test]
+    fn explicit_tool_lists_refine_the_flags() {
+        let mut agent = agent(true, true, false);
+        agent.allowed_tools = vec!["Bash(git status:*)".to_string()];
+        agent.disallowed_tools = vec!["Write".to_string()];
+        agent.allowed_directories = vec!["/shared/docs".to_string()];
+        let policy = AgentPermissionPolicy::for_agent(&agent);
+
+        // The narrow Bash rule survives the flag-derived denial of Bash
+        assert!(policy
+            .allowed_tools
+            .iter()
+            .any(|t| t == "Bash(git status:*)"));
+        assert!(!policy.disallowed_tools.iter().any(|t| t == "Bash"));
+        assert!(!policy.allowed_tools.iter().any(|t| t == "Write"));
+        assert!(policy.disallowed_tools.iter().any(|t| t == "Write"));
+
+        let args = policy.to_cli_args();
+        let flag = |name: &str| args.iter().position(|a| a == name).unwrap();
+        assert!(flag("--allowedTools") < flag("--disallowedTools"));
+        assert_eq!(args[flag("--add-dir") + 1], "/shared/docs");
+        assert!(!args.iter().any(|a| a == "--dangerously-skip-permissions"));
+    }
+
+    #[test]
+    fn exports_keep_permissions() {
+        let mut source = agent(true, false, true);
+        source.disallowed_tools = vec!["Write".to_string()];
+        source.allowed_directories = vec!["/shared/docs".to_string()];
+        let json = serde_json::to_string(&AgentData::from(source)).unwrap();
+        let data: AgentData = serde_json::from_str(&json).unwrap();
+        assert!(!data.enable_file_write);
+        assert!(data.enable_network);
+        assert_eq!(data.disallowed_tools, vec!["Write"]);
+        assert_eq!(data.allowed_directories, vec!["/shared/docs"]);
+
+        // Older exports without the fields get the defaults of a new agent
+        let legacy: AgentData = serde_json::from_str(
+            r#"{"name":"a","icon":"bot","system_prompt":"","model":"sonnet"}"#,
+        )
+        .unwrap();
+        assert!(legacy.enable_file_read && legacy.enable_file_write);
+        assert!(!legacy.enable_network);
+        assert!(legacy.allowed_tools.is_empty());
+    }
+}
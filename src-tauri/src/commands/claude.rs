@@ -4,12 +4,15 @@ use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter, Manager};
 use tokio::process::{Child, Command};
 use tokio::sync::Mutex;
 
+use crate::app_config::AppType;
+
 /// Global state to track current Claude process
 pub struct ClaudeProcessState {
     pub current_process: Arc<Mutex<Option<Child>>>,
@@ -1283,6 +1286,7 @@ async fn spawn_claude_process(
         let mut lines = stdout_reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
             log::debug!("Claude stdout: {}", line);
+            let mut started_session = None;
 
             // Parse the line to check for init message with session ID
             if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&line) {
@@ -1291,6 +1295,7 @@ async fn spawn_claude_process(
                         let mut session_id_guard = session_id_holder_clone.lock().unwrap();
                         if session_id_guard.is_none() {
                             *session_id_guard = Some(claude_session_id.to_string());
+                            started_session = Some(claude_session_id.to_string());
                             log::info!("Extracted Claude session ID: {}", claude_session_id);
 
                             // Now register with ProcessRegistry using Claude's session ID
@@ -1315,6 +1320,14 @@ async fn spawn_claude_process(
                 }
             }
 
+            // A resumed session restarts the directory watcher stopped when it last exited
+            if let Some(session_id) = started_session {
+                let checkpoints = app_handle.state::<crate::checkpoint::state::CheckpointState>();
+                if let Some(manager) = checkpoints.get_manager(&session_id).await {
+                    manager.sync_watcher().await;
+                }
+            }
+
             // Store live output in registry if we have a run_id
             if let Some(run_id) = *run_id_holder_clone.lock().unwrap() {
                 let _ = registry_clone.append_live_output(run_id, &line);
@@ -1390,6 +1403,16 @@ async fn spawn_claude_process(
 
         // Clear the process from state
         *current_process = None;
+        drop(current_process);
+
+        // Nothing edits the project once the session exits, so stop polling it
+        let session_id = session_id_holder_clone3.lock().unwrap().clone();
+        if let Some(session_id) = session_id {
+            let checkpoints = app_handle_wait.state::<crate::checkpoint::state::CheckpointState>();
+            if let Some(manager) = checkpoints.get_manager(&session_id).await {
+                manager.stop_watching();
+            }
+        }
     });
 
     Ok(())
@@ -1589,6 +1612,14 @@ fn search_files_recursive(
     Ok(())
 }
 
+/// Resolves the CLI a checkpoint command targets, defaulting to Claude Code
+fn checkpoint_app(app_type: Option<String>) -> Result<AppType, String> {
+    match app_type {
+        Some(app_type) => AppType::from_str(&app_type).map_err(|e| e.to_string()),
+        None => Ok(AppType::Claude),
+    }
+}
+
 /// Creates a checkpoint for the current session state
 #[tauri::command]
 pub async fn create_checkpoint(
@@ -1596,6 +1627,7 @@ pub async fn create_checkpoint(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
     message_index: Option<usize>,
     description: Option<String>,
 ) -> Result<crate::checkpoint::CheckpointResult, String> {
@@ -1606,7 +1638,8 @@ pub async fn create_checkpoint(
     );
 
     let manager = app
-        .get_or_create_manager(
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
             session_id.clone(),
             project_id.clone(),
             PathBuf::from(&project_path),
@@ -1614,32 +1647,21 @@ pub async fn create_checkpoint(
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
-    // Always load current session messages from the JSONL file
-    let session_path = get_claude_dir()
-        .map_err(|e| e.to_string())?
-        .join("projects")
-        .join(&project_id)
-        .join(format!("{}.jsonl", session_id));
-
-    if session_path.exists() {
-        let file = fs::File::open(&session_path)
+    // Always load current session messages from the session log
+    let session_log = manager.session_log();
+    if let Some(session_path) = session_log
+        .locate(&project_id, &session_id)
+        .filter(|path| path.exists())
+    {
+        let lines = session_log
+            .read_lines(&session_path)
             .map_err(|e| format!("Failed to open session file: {}", e))?;
-        let reader = BufReader::new(file);
-
-        let mut line_count = 0;
-        for line in reader.lines() {
-            if let Some(index) = message_index {
-                if line_count > index {
-                    break;
-                }
-            }
-            if let Ok(line) = line {
-                manager
-                    .track_message(line)
-                    .await
-                    .map_err(|e| format!("Failed to track message: {}", e))?;
-            }
-            line_count += 1;
+        let limit = message_index.map_or(usize::MAX, |index| index.saturating_add(1));
+        for line in lines.into_iter().take(limit) {
+            manager
+                .track_message(line)
+                .await
+                .map_err(|e| format!("Failed to track message: {}", e))?;
         }
     }
 
@@ -1657,6 +1679,7 @@ pub async fn restore_checkpoint(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
 ) -> Result<crate::checkpoint::CheckpointResult, String> {
     log::info!(
         "Restoring checkpoint: {} for session: {}",
@@ -1665,7 +1688,8 @@ pub async fn restore_checkpoint(
     );
 
     let manager = app
-        .get_or_create_manager(
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
             session_id.clone(),
             project_id.clone(),
             PathBuf::from(&project_path),
@@ -1678,21 +1702,20 @@ pub async fn restore_checkpoint(
         .await
         .map_err(|e| format!("Failed to restore checkpoint: {}", e))?;
 
-    // Update the session JSONL file with restored messages
-    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
-    let session_path = claude_dir
-        .join("projects")
-        .join(&result.checkpoint.project_id)
-        .join(format!("{}.jsonl", session_id));
-
     // The manager has already restored the messages internally,
     // but we need to update the actual session file
+    let session_log = manager.session_log();
+    let Some(session_path) = session_log.locate(&result.checkpoint.project_id, &session_id) else {
+        log::warn!("Session log for {} not found, skipping update", session_id);
+        return Ok(result);
+    };
     let (_, _, messages) = manager
-        .storage
+        .store()
         .load_checkpoint(&result.checkpoint.project_id, &session_id, &checkpoint_id)
         .map_err(|e| format!("Failed to load checkpoint data: {}", e))?;
 
-    fs::write(&session_path, messages)
+    session_log
+        .write_lines(&session_path, &messages)
         .map_err(|e| format!("Failed to update session file: {}", e))?;
 
     Ok(result)
@@ -1705,6 +1728,7 @@ pub async fn list_checkpoints(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
 ) -> Result<Vec<crate::checkpoint::Checkpoint>, String> {
     log::info!(
         "Listing checkpoints for session: {} in project: {}",
@@ -1713,7 +1737,12 @@ pub async fn list_checkpoints(
     );
 
     let manager = app
-        .get_or_create_manager(session_id, project_id, PathBuf::from(&project_path))
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id,
+            project_id,
+            PathBuf::from(&project_path),
+        )
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
@@ -1722,12 +1751,14 @@ pub async fn list_checkpoints(
 
 /// Forks a new timeline branch from a checkpoint
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn fork_from_checkpoint(
     app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
     checkpoint_id: String,
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
     new_session_id: String,
     description: Option<String>,
 ) -> Result<crate::checkpoint::CheckpointResult, String> {
@@ -1737,26 +1768,24 @@ pub async fn fork_from_checkpoint(
         new_session_id
     );
 
+    let app_type = checkpoint_app(app_type)?;
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
 
     // First, copy the session file to the new session
-    let source_session_path = claude_dir
-        .join("projects")
-        .join(&project_id)
-        .join(format!("{}.jsonl", session_id));
-    let new_session_path = claude_dir
-        .join("projects")
-        .join(&project_id)
-        .join(format!("{}.jsonl", new_session_id));
-
-    if source_session_path.exists() {
-        fs::copy(&source_session_path, &new_session_path)
+    let session_log = crate::checkpoint::transcript::session_log(&app_type, &claude_dir);
+    if let Some(source_session_path) = session_log
+        .locate(&project_id, &session_id)
+        .filter(|path| path.exists())
+    {
+        session_log
+            .fork(&source_session_path, &new_session_id)
             .map_err(|e| format!("Failed to copy session file: {}", e))?;
     }
 
     // Create manager for the new session
     let manager = app
-        .get_or_create_manager(
+        .get_or_create_app_manager(
+            app_type,
             new_session_id.clone(),
             project_id,
             PathBuf::from(&project_path),
@@ -1770,6 +1799,110 @@ pub async fn fork_from_checkpoint(
         .map_err(|e| format!("Failed to fork checkpoint: {}", e))
 }
 
+/// Exports a checkpoint, or its branch of the timeline, to a portable archive
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_checkpoint_archive(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    checkpoint_id: String,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    app_type: Option<String>,
+    include_ancestors: bool,
+    output_path: String,
+) -> Result<crate::checkpoint::archive::ExportSummary, String> {
+    use crate::checkpoint::archive::ExportScope;
+
+    log::info!(
+        "Exporting checkpoint {} of session {} to {}",
+        checkpoint_id,
+        session_id,
+        output_path
+    );
+
+    let manager = app
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id,
+            project_id,
+            PathBuf::from(project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    let scope = if include_ancestors {
+        ExportScope::Branch
+    } else {
+        ExportScope::Checkpoint
+    };
+    manager
+        .export_archive(&checkpoint_id, scope, &PathBuf::from(output_path))
+        .await
+        .map_err(|e| format!("Failed to export checkpoint: {}", e))
+}
+
+/// Reads the manifest of a checkpoint archive without importing it
+#[tauri::command]
+pub async fn inspect_checkpoint_archive(
+    archive_path: String,
+) -> Result<crate::checkpoint::archive::ArchiveManifest, String> {
+    crate::checkpoint::archive::read_manifest(&PathBuf::from(archive_path))
+        .map_err(|e| format!("Failed to read checkpoint archive: {}", e))
+}
+
+/// Imports a checkpoint archive as a new session of a local project,
+/// optionally restoring the project files to the archived head checkpoint
+#[tauri::command]
+pub async fn import_checkpoint_archive(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    archive_path: String,
+    project_path: String,
+    restore_files: bool,
+) -> Result<crate::checkpoint::archive::ImportSummary, String> {
+    log::info!(
+        "Importing checkpoint archive {} into {}",
+        archive_path,
+        project_path
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    // Same encoding as create_project
+    let project_id = project_path.replace('/', "-");
+    let session_id = uuid::Uuid::new_v4().to_string();
+
+    let mut summary = {
+        let (project_id, session_id) = (project_id.clone(), session_id.clone());
+        let project_path = PathBuf::from(&project_path);
+        tokio::task::spawn_blocking(move || {
+            crate::checkpoint::archive::import_archive(
+                &claude_dir,
+                &PathBuf::from(archive_path),
+                &project_id,
+                &project_path,
+                &session_id,
+            )
+        })
+        .await
+        .map_err(|e| format!("Import task failed: {}", e))?
+        .map_err(|e| format!("Failed to import checkpoint archive: {}", e))?
+    };
+
+    if restore_files {
+        let manager = app
+            .get_or_create_manager(session_id, project_id, PathBuf::from(&project_path))
+            .await
+            .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+        let result = manager
+            .restore_checkpoint(&summary.head_checkpoint_id)
+            .await
+            .map_err(|e| format!("Failed to restore imported checkpoint: {}", e))?;
+        summary.files_restored = Some(result.files_processed);
+    }
+
+    Ok(summary)
+}
+
 /// Gets the timeline for a session
 #[tauri::command]
 pub async fn get_session_timeline(
@@ -1777,6 +1910,7 @@ pub async fn get_session_timeline(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
 ) -> Result<crate::checkpoint::SessionTimeline, String> {
     log::info!(
         "Getting timeline for session: {} in project: {}",
@@ -1785,7 +1919,12 @@ pub async fn get_session_timeline(
     );
 
     let manager = app
-        .get_or_create_manager(session_id, project_id, PathBuf::from(&project_path))
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id,
+            project_id,
+            PathBuf::from(&project_path),
+        )
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
@@ -1794,15 +1933,20 @@ pub async fn get_session_timeline(
 
 /// Updates checkpoint settings for a session
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_checkpoint_settings(
     app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
     auto_checkpoint_enabled: bool,
     checkpoint_strategy: String,
+    max_file_size: Option<u64>,
+    max_total_size: Option<u64>,
+    storage_backend: Option<String>,
 ) -> Result<(), String> {
-    use crate::checkpoint::CheckpointStrategy;
+    use crate::checkpoint::{CheckpointBackend, CheckpointFileLimits, CheckpointStrategy};
 
     log::info!("Updating checkpoint settings for session: {}", session_id);
 
@@ -1811,6 +1955,7 @@ pub async fn update_checkpoint_settings(
         "per_prompt" => CheckpointStrategy::PerPrompt,
         "per_tool_use" => CheckpointStrategy::PerToolUse,
         "smart" => CheckpointStrategy::Smart,
+        "watch" => CheckpointStrategy::Watch,
         _ => {
             return Err(format!(
                 "Invalid checkpoint strategy: {}",
@@ -1819,102 +1964,147 @@ pub async fn update_checkpoint_settings(
         }
     };
 
+    let storage_backend = match storage_backend.as_deref() {
+        None => None,
+        Some("files") => Some(CheckpointBackend::Files),
+        Some("git") => Some(CheckpointBackend::Git {
+            repo_path: PathBuf::from(&project_path),
+        }),
+        Some(other) => return Err(format!("Invalid checkpoint storage backend: {}", other)),
+    };
+
     let manager = app
-        .get_or_create_manager(session_id, project_id, PathBuf::from(&project_path))
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id,
+            project_id,
+            PathBuf::from(&project_path),
+        )
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
+    // Only override the limits that were provided, keeping the rest as-is
+    let file_limits = if max_file_size.is_some() || max_total_size.is_some() {
+        let current = manager.get_timeline().await.file_limits;
+        Some(CheckpointFileLimits {
+            max_file_size: max_file_size.unwrap_or(current.max_file_size),
+            max_total_size: max_total_size.unwrap_or(current.max_total_size),
+        })
+    } else {
+        None
+    };
+
     manager
-        .update_settings(auto_checkpoint_enabled, strategy)
+        .update_settings(auto_checkpoint_enabled, strategy, file_limits, storage_backend)
         .await
-        .map_err(|e| format!("Failed to update settings: {}", e))
+        .map_err(|e| format!("Failed to update settings: {}", e))?;
+
+    // Start or stop the directory watcher for the `watch` strategy
+    manager.sync_watcher().await;
+    Ok(())
 }
 
-/// Gets diff between two checkpoints
+/// Gets diff between two checkpoints, or between a checkpoint and the working tree
+/// when `to_checkpoint_id` is omitted
 #[tauri::command]
 pub async fn get_checkpoint_diff(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
     from_checkpoint_id: String,
-    to_checkpoint_id: String,
+    to_checkpoint_id: Option<String>,
     session_id: String,
     project_id: String,
+    project_path: Option<String>,
+    app_type: Option<String>,
 ) -> Result<crate::checkpoint::CheckpointDiff, String> {
-    use crate::checkpoint::storage::CheckpointStorage;
+    use crate::checkpoint::{diff, storage};
 
     log::info!(
         "Getting diff between checkpoints: {} -> {}",
         from_checkpoint_id,
-        to_checkpoint_id
+        to_checkpoint_id.as_deref().unwrap_or("working tree")
     );
 
-    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
-    let storage = CheckpointStorage::new(claude_dir);
-
-    // Load both checkpoints
-    let (from_checkpoint, from_files, _) = storage
-        .load_checkpoint(&project_id, &session_id, &from_checkpoint_id)
-        .map_err(|e| format!("Failed to load source checkpoint: {}", e))?;
-    let (to_checkpoint, to_files, _) = storage
-        .load_checkpoint(&project_id, &session_id, &to_checkpoint_id)
-        .map_err(|e| format!("Failed to load target checkpoint: {}", e))?;
-
-    // Build file maps
-    let mut from_map: std::collections::HashMap<PathBuf, &crate::checkpoint::FileSnapshot> =
-        std::collections::HashMap::new();
-    for file in &from_files {
-        from_map.insert(file.file_path.clone(), file);
-    }
-
-    let mut to_map: std::collections::HashMap<PathBuf, &crate::checkpoint::FileSnapshot> =
-        std::collections::HashMap::new();
-    for file in &to_files {
-        to_map.insert(file.file_path.clone(), file);
-    }
-
-    // Calculate differences
-    let mut modified_files = Vec::new();
-    let mut added_files = Vec::new();
-    let mut deleted_files = Vec::new();
-
-    // Check for modified and deleted files
-    for (path, from_file) in &from_map {
-        if let Some(to_file) = to_map.get(path) {
-            if from_file.hash != to_file.hash {
-                // File was modified
-                let additions = to_file.content.lines().count();
-                let deletions = from_file.content.lines().count();
-
-                modified_files.push(crate::checkpoint::FileDiff {
-                    path: path.clone(),
-                    additions,
-                    deletions,
-                    diff_content: None, // TODO: Generate actual diff
-                });
-            }
-        } else {
-            // File was deleted
-            deleted_files.push(path.clone());
+    let app_type = checkpoint_app(app_type)?;
+    match (to_checkpoint_id, project_path) {
+        (to_checkpoint_id, Some(project_path)) => {
+            let manager = app
+                .get_or_create_app_manager(
+                    app_type,
+                    session_id,
+                    project_id,
+                    PathBuf::from(project_path),
+                )
+                .await
+                .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+            manager
+                .diff_checkpoints(&from_checkpoint_id, to_checkpoint_id.as_deref())
+                .await
+                .map_err(|e| format!("Failed to diff checkpoints: {}", e))
         }
-    }
-
-    // Check for added files
-    for (path, _) in &to_map {
-        if !from_map.contains_key(path) {
-            added_files.push(path.clone());
+        (Some(to_checkpoint_id), None) => {
+            let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+            let root = crate::checkpoint::transcript::checkpoint_root(&app_type, &claude_dir);
+            let storage = storage::open_session_store(root, &project_id, &session_id)
+                .map_err(|e| format!("Failed to open checkpoint storage: {}", e))?;
+
+            // Load both checkpoints
+            let (from_checkpoint, from_files, _) = storage
+                .load_checkpoint(&project_id, &session_id, &from_checkpoint_id)
+                .map_err(|e| format!("Failed to load source checkpoint: {}", e))?;
+            let (to_checkpoint, to_files, _) = storage
+                .load_checkpoint(&project_id, &session_id, &to_checkpoint_id)
+                .map_err(|e| format!("Failed to load target checkpoint: {}", e))?;
+
+            // Calculate token delta
+            let token_delta = (to_checkpoint.metadata.total_tokens as i64)
+                - (from_checkpoint.metadata.total_tokens as i64);
+
+            Ok(diff::diff_snapshots(
+                from_checkpoint_id,
+                Some(to_checkpoint_id),
+                &from_files,
+                &to_files,
+                token_delta,
+            ))
         }
+        (None, None) => Err("A project path is required to diff against the working tree".into()),
     }
+}
+
+/// Restores selected files or hunks from a checkpoint, optionally as a dry run
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn restore_checkpoint_files(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    checkpoint_id: String,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    app_type: Option<String>,
+    files: Option<Vec<crate::checkpoint::RestoreFileSelection>>,
+    dry_run: bool,
+) -> Result<crate::checkpoint::RestoreReport, String> {
+    log::info!(
+        "Restoring files from checkpoint: {} (dry run: {})",
+        checkpoint_id,
+        dry_run
+    );
 
-    // Calculate token delta
-    let token_delta = (to_checkpoint.metadata.total_tokens as i64)
-        - (from_checkpoint.metadata.total_tokens as i64);
+    let manager = app
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id,
+            project_id,
+            PathBuf::from(project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
-    Ok(crate::checkpoint::CheckpointDiff {
-        from_checkpoint_id,
-        to_checkpoint_id,
-        modified_files,
-        added_files,
-        deleted_files,
-        token_delta,
-    })
+    manager
+        .restore_files(&checkpoint_id, files, dry_run)
+        .await
+        .map_err(|e| format!("Failed to restore checkpoint files: {}", e))
 }
 
 /// Tracks a message for checkpointing
@@ -1924,12 +2114,18 @@ pub async fn track_checkpoint_message(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
     message: String,
 ) -> Result<(), String> {
     log::info!("Tracking message for session: {}", session_id);
 
     let manager = app
-        .get_or_create_manager(session_id, project_id, PathBuf::from(project_path))
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id,
+            project_id,
+            PathBuf::from(project_path),
+        )
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
@@ -1946,12 +2142,18 @@ pub async fn check_auto_checkpoint(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
     message: String,
 ) -> Result<bool, String> {
     log::info!("Checking auto-checkpoint for session: {}", session_id);
 
     let manager = app
-        .get_or_create_manager(session_id.clone(), project_id, PathBuf::from(project_path))
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id.clone(),
+            project_id,
+            PathBuf::from(project_path),
+        )
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
@@ -1965,6 +2167,7 @@ pub async fn cleanup_old_checkpoints(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
     keep_count: usize,
 ) -> Result<usize, String> {
     log::info!(
@@ -1974,7 +2177,8 @@ pub async fn cleanup_old_checkpoints(
     );
 
     let manager = app
-        .get_or_create_manager(
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
             session_id.clone(),
             project_id.clone(),
             PathBuf::from(project_path),
@@ -1983,11 +2187,94 @@ pub async fn cleanup_old_checkpoints(
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
     manager
-        .storage
+        .store()
         .cleanup_old_checkpoints(&project_id, &session_id, keep_count)
         .map_err(|e| format!("Failed to cleanup checkpoints: {}", e))
 }
 
+/// Pins or unpins a checkpoint so retention never removes it
+#[tauri::command]
+pub async fn set_checkpoint_pinned(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    checkpoint_id: String,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    app_type: Option<String>,
+    pinned: bool,
+) -> Result<crate::checkpoint::Checkpoint, String> {
+    log::info!(
+        "Setting pinned={} on checkpoint {} of session {}",
+        pinned,
+        checkpoint_id,
+        session_id
+    );
+
+    let manager = app
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id,
+            project_id,
+            PathBuf::from(project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    manager
+        .set_checkpoint_pinned(&checkpoint_id, pinned)
+        .await
+        .map_err(|e| format!("Failed to update checkpoint: {}", e))
+}
+
+/// Gets the checkpoint retention policy
+#[tauri::command]
+pub async fn get_checkpoint_retention_policy(
+) -> Result<crate::checkpoint::retention::RetentionPolicy, String> {
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    Ok(crate::checkpoint::retention::RetentionPolicy::load(
+        &claude_dir,
+    ))
+}
+
+/// Saves the checkpoint retention policy
+#[tauri::command]
+pub async fn update_checkpoint_retention_policy(
+    policy: crate::checkpoint::retention::RetentionPolicy,
+) -> Result<(), String> {
+    log::info!("Updating checkpoint retention policy: {:?}", policy);
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    policy
+        .save(&claude_dir)
+        .map_err(|e| format!("Failed to save retention policy: {}", e))
+}
+
+/// Applies the retention policy now and reports the reclaimed space
+#[tauri::command]
+pub async fn apply_checkpoint_retention(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+) -> Result<crate::checkpoint::retention::RetentionReport, String> {
+    use crate::checkpoint::retention::{apply_retention_to_all_apps, RetentionPolicy};
+
+    log::info!("Applying checkpoint retention policy");
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let active: std::collections::HashSet<String> =
+        app.list_active_sessions().await.into_iter().collect();
+
+    let policy = RetentionPolicy::load(&claude_dir);
+    if !policy.enabled {
+        return Err("Checkpoint retention is disabled".to_string());
+    }
+
+    tokio::task::spawn_blocking(move || {
+        apply_retention_to_all_apps(&claude_dir, &policy, &active, chrono::Utc::now())
+    })
+    .await
+    .map_err(|e| format!("Retention task failed: {}", e))?
+    .map_err(|e| format!("Failed to apply retention policy: {}", e))
+}
+
 /// Gets checkpoint settings for a session
 #[tauri::command]
 pub async fn get_checkpoint_settings(
@@ -1995,11 +2282,17 @@ pub async fn get_checkpoint_settings(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
 ) -> Result<serde_json::Value, String> {
     log::info!("Getting checkpoint settings for session: {}", session_id);
 
     let manager = app
-        .get_or_create_manager(session_id, project_id, PathBuf::from(project_path))
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id,
+            project_id,
+            PathBuf::from(project_path),
+        )
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
@@ -2046,6 +2339,7 @@ pub async fn get_recently_modified_files(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
     minutes: i64,
 ) -> Result<Vec<String>, String> {
     use chrono::{Duration, Utc};
@@ -2057,7 +2351,12 @@ pub async fn get_recently_modified_files(
     );
 
     let manager = app
-        .get_or_create_manager(session_id, project_id, PathBuf::from(project_path))
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
+            session_id,
+            project_id,
+            PathBuf::from(project_path),
+        )
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
@@ -2082,6 +2381,7 @@ pub async fn track_session_messages(
     session_id: String,
     project_id: String,
     project_path: String,
+    app_type: Option<String>,
     messages: Vec<String>,
 ) -> Result<(), String> {
     log::info!(
@@ -2091,7 +2391,8 @@ pub async fn track_session_messages(
     );
 
     let manager = state
-        .get_or_create_manager(
+        .get_or_create_app_manager(
+            checkpoint_app(app_type)?,
             session_id.clone(),
             project_id.clone(),
             PathBuf::from(&project_path),
@@ -19,6 +19,7 @@ mod usage;
 // Claude Code Session and Agent modules (from opcode)
 pub mod claude;
 pub mod agents;
+pub mod agent_queue;
 
 pub use config::*;
 pub use deeplink::*;
@@ -39,3 +40,4 @@ pub use usage::*;
 // Re-export Claude Code commands
 pub use claude::*;
 pub use agents::*;
+pub use agent_queue::*;
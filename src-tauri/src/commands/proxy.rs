@@ -215,6 +215,58 @@ pub async fn get_circuit_breaker_stats(
     Ok(None)
 }
 
+// ==================== 影子流量相关命令 ====================
+
+/// 获取应用的影子流量配置
+#[tauri::command]
+pub async fn get_shadow_config(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<Option<ShadowConfig>, String> {
+    state
+        .db
+        .get_shadow_config(&app_type)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 设置应用的影子流量配置
+#[tauri::command]
+pub async fn set_shadow_config(
+    state: tauri::State<'_, AppState>,
+    config: ShadowConfig,
+) -> Result<(), String> {
+    if config.sample_percent > 100 {
+        return Err("采样比例必须在 0-100 之间".to_string());
+    }
+
+    let db = &state.db;
+    if db
+        .get_provider_by_id(&config.provider_id, &config.app_type)
+        .map_err(|e| e.to_string())?
+        .is_none()
+    {
+        return Err(format!("Provider {} not found", config.provider_id));
+    }
+
+    db.set_shadow_config(&config)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 删除应用的影子流量配置
+#[tauri::command]
+pub async fn delete_shadow_config(
+    state: tauri::State<'_, AppState>,
+    app_type: String,
+) -> Result<(), String> {
+    state
+        .db
+        .delete_shadow_config(&app_type)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// 测试供应商连接是否正常
 #[tauri::command]
 pub async fn test_provider_connection(
@@ -1,8 +1,26 @@
 //! 使用统计相关命令
 
+use crate::app_config::AppType;
 use crate::error::AppError;
+use crate::services::currency::ExchangeRate;
+use crate::services::pricing_import::{
+    ModelAlias, PricingCatalog, PricingEntry, PricingFormat, PricingImportPreview,
+    PricingImportResult, PricingImportService, PricingSource,
+};
+use crate::services::session_usage_import::SessionUsageImportResult;
+use crate::services::usage_analytics::{ErrorBreakdown, LatencyStats, StatsGroupBy};
+use crate::services::usage_anomaly::{AnomalyRules, PauseKind, UsageAlert, UsagePause};
+use crate::services::usage_export::{
+    ExportFormat, ExportResult, ReportFrequency, UsageReportConfig,
+};
+use crate::services::usage_forecast::BudgetForecast;
+use crate::services::usage_retention::{
+    DatabaseSizeInfo, LogMaintenanceResult, LogRetentionConfig,
+};
 use crate::services::usage_stats::*;
+use crate::services::ProviderService;
 use crate::store::AppState;
+use std::collections::HashMap;
 use tauri::State;
 
 /// 获取使用量汇总
@@ -11,8 +29,11 @@ pub fn get_usage_summary(
     state: State<'_, AppState>,
     start_date: Option<i64>,
     end_date: Option<i64>,
+    currency: Option<String>,
 ) -> Result<UsageSummary, AppError> {
-    state.db.get_usage_summary(start_date, end_date)
+    state
+        .db
+        .get_usage_summary(start_date, end_date, currency.as_deref())
 }
 
 /// 获取每日趋势
@@ -20,14 +41,28 @@ pub fn get_usage_summary(
 pub fn get_usage_trends(
     state: State<'_, AppState>,
     days: u32,
+    currency: Option<String>,
 ) -> Result<Vec<DailyStats>, AppError> {
-    state.db.get_daily_trends(days)
+    state.db.get_daily_trends(days, currency.as_deref())
+}
+
+/// 获取最近若干小时的逐小时趋势
+#[tauri::command]
+pub fn get_usage_hourly_trends(
+    state: State<'_, AppState>,
+    hours: u32,
+    currency: Option<String>,
+) -> Result<Vec<DailyStats>, AppError> {
+    state.db.get_hourly_trends(hours, currency.as_deref())
 }
 
 /// 获取 Provider 统计
 #[tauri::command]
-pub fn get_provider_stats(state: State<'_, AppState>) -> Result<Vec<ProviderStats>, AppError> {
-    state.db.get_provider_stats()
+pub fn get_provider_stats(
+    state: State<'_, AppState>,
+    currency: Option<String>,
+) -> Result<Vec<ProviderStats>, AppError> {
+    state.db.get_provider_stats(currency.as_deref())
 }
 
 /// 获取模型统计
@@ -36,6 +71,71 @@ pub fn get_model_stats(state: State<'_, AppState>) -> Result<Vec<ModelStats>, Ap
     state.db.get_model_stats()
 }
 
+/// 获取延迟分位数统计（p50 / p90 / p99）
+#[tauri::command]
+pub fn get_latency_stats(
+    state: State<'_, AppState>,
+    group_by: StatsGroupBy,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+) -> Result<Vec<LatencyStats>, AppError> {
+    state.db.get_latency_stats(group_by, start_date, end_date)
+}
+
+/// 获取错误分布与热力图
+#[tauri::command]
+pub fn get_error_breakdown(
+    state: State<'_, AppState>,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    utc_offset_minutes: Option<i32>,
+) -> Result<ErrorBreakdown, AppError> {
+    state
+        .db
+        .get_error_breakdown(start_date, end_date, utc_offset_minutes)
+}
+
+/// 获取项目统计（按客户端工作目录归集）
+#[tauri::command]
+pub fn get_project_stats(
+    state: State<'_, AppState>,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    currency: Option<String>,
+) -> Result<Vec<ProjectStats>, AppError> {
+    state
+        .db
+        .get_project_stats(start_date, end_date, currency.as_deref())
+}
+
+/// 获取会话统计（可按项目目录过滤）
+#[tauri::command]
+pub fn get_session_stats(
+    state: State<'_, AppState>,
+    project_path: Option<String>,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+    currency: Option<String>,
+) -> Result<Vec<SessionStats>, AppError> {
+    state.db.get_session_stats(
+        project_path.as_deref(),
+        start_date,
+        end_date,
+        currency.as_deref(),
+    )
+}
+
+/// 从 Claude Code / Codex / Gemini CLI 会话日志增量导入未经代理的用量
+#[tauri::command]
+pub async fn import_session_usage(
+    state: State<'_, AppState>,
+) -> Result<SessionUsageImportResult, AppError> {
+    let db = state.db.clone();
+    tauri::async_runtime::spawn_blocking(move || db.import_session_usage())
+        .await
+        .map_err(|e| AppError::Message(format!("导入会话日志用量失败: {e}")))?
+}
+
 /// 获取请求日志列表
 #[tauri::command]
 pub fn get_request_logs(
@@ -47,6 +147,165 @@ pub fn get_request_logs(
     state.db.get_request_logs(&filters, page, page_size)
 }
 
+/// 按过滤条件导出请求日志（CSV / JSON Lines / Parquet）
+#[tauri::command]
+pub async fn export_request_logs(
+    state: State<'_, AppState>,
+    filters: LogFilters,
+    format: ExportFormat,
+    path: String,
+) -> Result<ExportResult, AppError> {
+    let db = state.db.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        db.export_request_logs(&filters, format, std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| AppError::Message(format!("导出请求日志失败: {e}")))?
+}
+
+/// 获取定期报表配置
+#[tauri::command]
+pub fn get_usage_report_config(state: State<'_, AppState>) -> Result<UsageReportConfig, AppError> {
+    state.db.get_usage_report_config()
+}
+
+/// 保存定期报表配置
+#[tauri::command]
+pub fn set_usage_report_config(
+    state: State<'_, AppState>,
+    config: UsageReportConfig,
+) -> Result<(), AppError> {
+    state.db.set_usage_report_config(&config)
+}
+
+/// 立即生成最近一个完整周期的报表，返回写入的文件路径
+#[tauri::command]
+pub async fn generate_usage_report(
+    state: State<'_, AppState>,
+    frequency: ReportFrequency,
+) -> Result<Vec<String>, AppError> {
+    let db = state.db.clone();
+    let paths = tauri::async_runtime::spawn_blocking(move || db.generate_usage_report(frequency))
+        .await
+        .map_err(|e| AppError::Message(format!("生成用量报表失败: {e}")))??;
+    Ok(paths.into_iter().map(|p| p.display().to_string()).collect())
+}
+
+/// 预测本月月末花费与限额 / 余额耗尽时间
+///
+/// `include_balances` 为 true 时会执行已启用的用量脚本获取预付余额
+#[tauri::command]
+pub async fn get_budget_forecast(
+    state: State<'_, AppState>,
+    currency: Option<String>,
+    include_balances: Option<bool>,
+) -> Result<BudgetForecast, AppError> {
+    let mut balances = HashMap::new();
+    if include_balances.unwrap_or(false) {
+        for app_type in [AppType::Claude, AppType::Codex, AppType::Gemini] {
+            for (id, provider) in state.db.get_all_providers(app_type.as_str())? {
+                let script_enabled = provider
+                    .meta
+                    .as_ref()
+                    .and_then(|m| m.usage_script.as_ref())
+                    .is_some_and(|s| s.enabled);
+                if !script_enabled {
+                    continue;
+                }
+                match ProviderService::query_usage(state.inner(), app_type.clone(), &id).await {
+                    Ok(result) => {
+                        if let Some(data) = result
+                            .data
+                            .and_then(|list| list.into_iter().find(|d| d.remaining.is_some()))
+                        {
+                            balances.insert(format!("{}:{id}", app_type.as_str()), data);
+                        }
+                    }
+                    Err(e) => log::warn!("查询供应商 {id} 余额失败: {e}"),
+                }
+            }
+        }
+    }
+    state.db.get_budget_forecast(currency.as_deref(), &balances)
+}
+
+/// 获取日志保留配置
+#[tauri::command]
+pub fn get_log_retention_config(
+    state: State<'_, AppState>,
+) -> Result<LogRetentionConfig, AppError> {
+    state.db.get_log_retention_config()
+}
+
+/// 保存日志保留配置
+#[tauri::command]
+pub fn set_log_retention_config(
+    state: State<'_, AppState>,
+    config: LogRetentionConfig,
+) -> Result<(), AppError> {
+    state.db.set_log_retention_config(&config)
+}
+
+/// 立即执行一次日志聚合清理与增量 VACUUM
+#[tauri::command]
+pub async fn run_log_maintenance(
+    state: State<'_, AppState>,
+) -> Result<LogMaintenanceResult, AppError> {
+    let db = state.db.clone();
+    tauri::async_runtime::spawn_blocking(move || db.run_log_maintenance())
+        .await
+        .map_err(|e| AppError::Message(format!("日志维护失败: {e}")))?
+}
+
+/// 获取数据库占用信息
+#[tauri::command]
+pub fn get_database_size(state: State<'_, AppState>) -> Result<DatabaseSizeInfo, AppError> {
+    state.db.get_database_size()
+}
+
+/// 获取用量异常告警规则
+#[tauri::command]
+pub fn get_anomaly_rules(state: State<'_, AppState>) -> Result<AnomalyRules, AppError> {
+    state.db.get_anomaly_rules()
+}
+
+/// 保存用量异常告警规则
+#[tauri::command]
+pub fn set_anomaly_rules(state: State<'_, AppState>, rules: AnomalyRules) -> Result<(), AppError> {
+    state.db.set_anomaly_rules(&rules)
+}
+
+/// 获取用量异常告警历史
+#[tauri::command]
+pub fn get_usage_alerts(
+    state: State<'_, AppState>,
+    limit: Option<u32>,
+) -> Result<Vec<UsageAlert>, AppError> {
+    state.db.get_usage_alerts(limit)
+}
+
+/// 标记告警为已读
+#[tauri::command]
+pub fn acknowledge_usage_alert(state: State<'_, AppState>, id: i64) -> Result<(), AppError> {
+    state.db.acknowledge_usage_alert(id)
+}
+
+/// 获取因用量异常暂停的供应商与会话
+#[tauri::command]
+pub fn get_usage_pauses(state: State<'_, AppState>) -> Result<Vec<UsagePause>, AppError> {
+    state.db.get_usage_pauses()
+}
+
+/// 解除暂停
+#[tauri::command]
+pub fn resume_usage_pause(
+    state: State<'_, AppState>,
+    kind: PauseKind,
+    target: String,
+) -> Result<bool, AppError> {
+    state.db.resume_usage_pause(kind, &target)
+}
+
 /// 获取单个请求详情
 #[tauri::command]
 pub fn get_request_detail(
@@ -160,11 +419,149 @@ pub fn delete_model_pricing(state: State<'_, AppState>, model_id: String) -> Res
         rusqlite::params![model_id],
     )
     .map_err(|e| AppError::Database(format!("删除模型定价失败: {e}")))?;
+    conn.execute(
+        "DELETE FROM model_pricing_rules WHERE model_id = ?1",
+        rusqlite::params![model_id],
+    )
+    .map_err(|e| AppError::Database(format!("删除定价规则失败: {e}")))?;
 
     log::info!("已删除模型定价: {model_id}");
     Ok(())
 }
 
+/// 获取定价规则
+#[tauri::command]
+pub fn get_pricing_rules(
+    state: State<'_, AppState>,
+    model_id: Option<String>,
+) -> Result<Vec<ModelPricingRuleInfo>, AppError> {
+    state.db.get_pricing_rules(model_id.as_deref())
+}
+
+/// 新增或更新定价规则
+#[tauri::command]
+pub fn save_pricing_rule(
+    state: State<'_, AppState>,
+    rule: ModelPricingRuleInfo,
+) -> Result<i64, AppError> {
+    state.db.save_pricing_rule(&rule)
+}
+
+/// 删除定价规则
+#[tauri::command]
+pub fn delete_pricing_rule(state: State<'_, AppState>, id: i64) -> Result<(), AppError> {
+    state.db.delete_pricing_rule(id)
+}
+
+/// 按当前定价重算指定时间范围内的请求成本
+#[tauri::command]
+pub fn recalculate_costs(
+    state: State<'_, AppState>,
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+) -> Result<RecalculateCostsResult, AppError> {
+    state.db.recalculate_costs(start_date, end_date)
+}
+
+/// 读取定价目录并预览与当前定价的差异
+#[tauri::command]
+pub async fn preview_pricing_import(
+    state: State<'_, AppState>,
+    source: PricingSource,
+    format: Option<PricingFormat>,
+) -> Result<PricingImportPreview, AppError> {
+    PricingImportService::preview(&state.db, &source, format).await
+}
+
+/// 应用用户确认后的定价和别名
+#[tauri::command]
+pub fn apply_pricing_import(
+    state: State<'_, AppState>,
+    entries: Vec<PricingEntry>,
+    aliases: Vec<ModelAlias>,
+) -> Result<PricingImportResult, AppError> {
+    state.db.apply_pricing_import(&entries, &aliases)
+}
+
+/// 导出当前定价目录（JSON 格式，可再次导入）
+#[tauri::command]
+pub fn export_pricing_catalog(state: State<'_, AppState>) -> Result<PricingCatalog, AppError> {
+    state.db.export_pricing_catalog()
+}
+
+/// 获取定价目录 URL
+#[tauri::command]
+pub fn get_pricing_catalog_url(state: State<'_, AppState>) -> Result<Option<String>, AppError> {
+    state.db.get_pricing_catalog_url()
+}
+
+/// 设置定价目录 URL
+#[tauri::command]
+pub fn set_pricing_catalog_url(
+    state: State<'_, AppState>,
+    url: Option<String>,
+) -> Result<(), AppError> {
+    state.db.set_pricing_catalog_url(url.as_deref())
+}
+
+/// 获取模型别名
+#[tauri::command]
+pub fn get_model_aliases(state: State<'_, AppState>) -> Result<Vec<ModelAlias>, AppError> {
+    state.db.get_model_aliases()
+}
+
+/// 新增或更新模型别名
+#[tauri::command]
+pub fn set_model_alias(state: State<'_, AppState>, alias: ModelAlias) -> Result<(), AppError> {
+    state.db.set_model_alias(&alias)
+}
+
+/// 删除模型别名
+#[tauri::command]
+pub fn delete_model_alias(state: State<'_, AppState>, alias: String) -> Result<(), AppError> {
+    state.db.delete_model_alias(&alias)
+}
+
+/// 获取汇率列表
+#[tauri::command]
+pub fn get_exchange_rates(
+    state: State<'_, AppState>,
+    currency: Option<String>,
+) -> Result<Vec<ExchangeRate>, AppError> {
+    state.db.get_exchange_rates(currency.as_deref())
+}
+
+/// 新增或更新汇率
+#[tauri::command]
+pub fn set_exchange_rate(state: State<'_, AppState>, rate: ExchangeRate) -> Result<(), AppError> {
+    state.db.set_exchange_rate(&rate)
+}
+
+/// 删除汇率
+#[tauri::command]
+pub fn delete_exchange_rate(
+    state: State<'_, AppState>,
+    currency: String,
+    effective_date: String,
+) -> Result<(), AppError> {
+    state.db.delete_exchange_rate(&currency, &effective_date)
+}
+
+/// 获取默认报表币种
+#[tauri::command]
+pub fn get_reporting_currency(state: State<'_, AppState>) -> Result<String, AppError> {
+    state.db.get_reporting_currency()
+}
+
+/// 设置默认报表币种
+#[tauri::command]
+pub fn set_reporting_currency(
+    state: State<'_, AppState>,
+    currency: String,
+) -> Result<(), AppError> {
+    state.db.set_reporting_currency(&currency)
+}
+
 /// 模型定价信息
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -17,7 +17,8 @@ impl Database {
             let conn = lock_conn!(self.conn);
             conn.query_row(
                 "SELECT enabled, listen_address, listen_port, max_retries,
-                        request_timeout, enable_logging, live_takeover_active
+                        request_timeout, enable_logging, live_takeover_active,
+                        max_concurrent_requests, priority_lanes, gemini_token_endpoint
                  FROM proxy_config WHERE id = 1",
                 [],
                 |row| {
@@ -29,6 +30,14 @@ impl Database {
                         request_timeout: row.get::<_, i32>(4)? as u64,
                         enable_logging: row.get::<_, i32>(5)? != 0,
                         live_takeover_active: row.get::<_, i32>(6).unwrap_or(0) != 0,
+                        max_concurrent_requests: row.get::<_, i64>(7).unwrap_or(0).max(0) as usize,
+                        priority_lanes: row
+                            .get::<_, Option<String>>(8)
+                            .ok()
+                            .flatten()
+                            .and_then(|json| serde_json::from_str(&json).ok())
+                            .unwrap_or_default(),
+                        gemini_token_endpoint: row.get::<_, Option<String>>(9).ok().flatten(),
                     })
                 },
             )
@@ -48,12 +57,15 @@ impl Database {
 
     /// 更新代理配置
     pub async fn update_proxy_config(&self, config: ProxyConfig) -> Result<(), AppError> {
+        let priority_lanes = serde_json::to_string(&config.priority_lanes)
+            .map_err(|e| AppError::Database(e.to_string()))?;
         let conn = lock_conn!(self.conn);
 
         conn.execute(
             "INSERT OR REPLACE INTO proxy_config
-             (id, enabled, listen_address, listen_port, max_retries, request_timeout, enable_logging, live_takeover_active, target_app, created_at, updated_at)
-             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8,
+             (id, enabled, listen_address, listen_port, max_retries, request_timeout, enable_logging, live_takeover_active, target_app,
+              max_concurrent_requests, priority_lanes, gemini_token_endpoint, created_at, updated_at)
+             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11,
                      COALESCE((SELECT created_at FROM proxy_config WHERE id = 1), datetime('now')),
                      datetime('now'))",
             rusqlite::params![
@@ -65,6 +77,9 @@ impl Database {
                 if config.enable_logging { 1 } else { 0 },
                 if config.live_takeover_active { 1 } else { 0 },
                 "claude", // 兼容旧字段，写入默认值
+                config.max_concurrent_requests as i64,
+                priority_lanes,
+                config.gemini_token_endpoint,
             ],
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
@@ -340,4 +355,68 @@ impl Database {
         log::info!("已删除所有 Live 配置备份");
         Ok(())
     }
+
+    // ==================== Shadow Traffic ====================
+
+    /// 获取应用的影子流量配置
+    pub async fn get_shadow_config(
+        &self,
+        app_type: &str,
+    ) -> Result<Option<ShadowConfig>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let result = conn.query_row(
+            "SELECT app_type, provider_id, sample_percent, enabled
+             FROM proxy_shadow_config WHERE app_type = ?1",
+            rusqlite::params![app_type],
+            |row| {
+                Ok(ShadowConfig {
+                    app_type: row.get(0)?,
+                    provider_id: row.get(1)?,
+                    sample_percent: row.get::<_, i64>(2)?.clamp(0, 100) as u8,
+                    enabled: row.get::<_, i32>(3)? != 0,
+                })
+            },
+        );
+
+        match result {
+            Ok(config) => Ok(Some(config)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(AppError::Database(e.to_string())),
+        }
+    }
+
+    /// 保存应用的影子流量配置
+    pub async fn set_shadow_config(&self, config: &ShadowConfig) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.execute(
+            "INSERT OR REPLACE INTO proxy_shadow_config
+             (app_type, provider_id, sample_percent, enabled, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                config.app_type,
+                config.provider_id,
+                config.sample_percent.min(100) as i64,
+                if config.enabled { 1 } else { 0 },
+                chrono::Utc::now().timestamp(),
+            ],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 删除应用的影子流量配置
+    pub async fn delete_shadow_config(&self, app_type: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+
+        conn.execute(
+            "DELETE FROM proxy_shadow_config WHERE app_type = ?1",
+            rusqlite::params![app_type],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
 }
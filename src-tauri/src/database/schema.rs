@@ -319,6 +319,22 @@ impl Database {
             [],
         );
 
+        // 尝试添加优先级通道相关列到 proxy_config 表
+        let _ = conn.execute(
+            "ALTER TABLE proxy_config ADD COLUMN max_concurrent_requests INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE proxy_config ADD COLUMN priority_lanes TEXT",
+            [],
+        );
+
+        // 尝试添加 Gemini OAuth token 端点列到 proxy_config 表
+        let _ = conn.execute(
+            "ALTER TABLE proxy_config ADD COLUMN gemini_token_endpoint TEXT",
+            [],
+        );
+
         // 尝试添加 enabled 列到 circuit_breaker_config 表
         let _ = conn.execute(
             "ALTER TABLE circuit_breaker_config ADD COLUMN enabled INTEGER NOT NULL DEFAULT 1",
@@ -401,6 +417,242 @@ impl Database {
         )
         .map_err(|e| AppError::Database(e.to_string()))?;
 
+        // 21. Proxy Shadow Config 表 (影子流量配置，每个应用一条)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_shadow_config (
+                app_type TEXT PRIMARY KEY,
+                provider_id TEXT NOT NULL,
+                sample_percent INTEGER NOT NULL DEFAULT 10,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                updated_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 22. Proxy Shadow Logs 表 (影子请求日志，与 proxy_request_logs 字段一致，不计入主统计)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS proxy_shadow_logs (
+                request_id TEXT PRIMARY KEY,
+                provider_id TEXT NOT NULL,
+                app_type TEXT NOT NULL,
+                model TEXT NOT NULL,
+                input_tokens INTEGER NOT NULL DEFAULT 0,
+                output_tokens INTEGER NOT NULL DEFAULT 0,
+                cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+                cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+                input_cost_usd TEXT NOT NULL DEFAULT '0',
+                output_cost_usd TEXT NOT NULL DEFAULT '0',
+                cache_read_cost_usd TEXT NOT NULL DEFAULT '0',
+                cache_creation_cost_usd TEXT NOT NULL DEFAULT '0',
+                total_cost_usd TEXT NOT NULL DEFAULT '0',
+                latency_ms INTEGER NOT NULL,
+                first_token_ms INTEGER,
+                duration_ms INTEGER,
+                status_code INTEGER NOT NULL,
+                error_message TEXT,
+                session_id TEXT,
+                provider_type TEXT,
+                is_streaming INTEGER NOT NULL DEFAULT 0,
+                cost_multiplier TEXT NOT NULL DEFAULT '1.0',
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_shadow_logs_provider
+             ON proxy_shadow_logs(provider_id, app_type)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 23. Model Pricing Rules 表 (阶梯 / 生效日期 / 时段定价规则，命中时替代基础定价)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS model_pricing_rules (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                model_id TEXT NOT NULL,
+                min_prompt_tokens INTEGER NOT NULL DEFAULT 0,
+                effective_from INTEGER,
+                effective_to INTEGER,
+                time_start_minute INTEGER,
+                time_end_minute INTEGER,
+                input_cost_per_million TEXT NOT NULL,
+                output_cost_per_million TEXT NOT NULL,
+                cache_read_cost_per_million TEXT NOT NULL DEFAULT '0',
+                cache_creation_cost_per_million TEXT NOT NULL DEFAULT '0'
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_pricing_rules_model
+             ON model_pricing_rules(model_id)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 24. Model Pricing Aliases 表 (请求模型名 → model_pricing.model_id)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS model_pricing_aliases (
+                alias TEXT PRIMARY KEY,
+                model_id TEXT NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 25. Exchange Rates 表 (1 USD 折合 rate 单位的 currency，自 effective_date 起生效)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS exchange_rates (
+                currency TEXT NOT NULL,
+                effective_date TEXT NOT NULL,
+                rate TEXT NOT NULL,
+                PRIMARY KEY (currency, effective_date)
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 尝试添加结算币种相关列到请求日志表（结算成本按供应商币种记录，无汇率时为 NULL）
+        for table in ["proxy_request_logs", "proxy_shadow_logs"] {
+            let _ = conn.execute(
+                &format!(
+                    "ALTER TABLE {table} ADD COLUMN billing_currency TEXT NOT NULL DEFAULT 'USD'"
+                ),
+                [],
+            );
+            let _ = conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN billing_rate TEXT"),
+                [],
+            );
+            let _ = conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN billing_cost TEXT"),
+                [],
+            );
+        }
+
+        // 尝试添加项目目录列到请求日志表（客户端工作目录，用于按项目归集成本）
+        for table in ["proxy_request_logs", "proxy_shadow_logs"] {
+            let _ = conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN project_path TEXT"),
+                [],
+            );
+        }
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_request_logs_project
+             ON proxy_request_logs(project_path)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 尝试添加日志来源列到请求日志表（proxy: 代理流量，session_log: 会话日志导入）
+        for table in ["proxy_request_logs", "proxy_shadow_logs"] {
+            let _ = conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN source TEXT NOT NULL DEFAULT 'proxy'"),
+                [],
+            );
+        }
+
+        // 26. Session Log Offsets 表 (会话日志增量导入进度)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS session_log_offsets (
+                path TEXT PRIMARY KEY,
+                app_type TEXT NOT NULL,
+                offset INTEGER NOT NULL DEFAULT 0,
+                modified_at INTEGER NOT NULL DEFAULT 0,
+                context TEXT,
+                updated_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 27. Usage Daily Rollups 表 (超出保留期的请求日志按天聚合，created_at 为当日 UTC 零点)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_daily_rollups (
+                created_at INTEGER NOT NULL,
+                is_shadow INTEGER NOT NULL DEFAULT 0,
+                provider_id TEXT NOT NULL,
+                app_type TEXT NOT NULL,
+                model TEXT NOT NULL,
+                source TEXT NOT NULL DEFAULT 'proxy',
+                billing_currency TEXT NOT NULL DEFAULT 'USD',
+                request_count INTEGER NOT NULL DEFAULT 0,
+                success_count INTEGER NOT NULL DEFAULT 0,
+                input_tokens INTEGER NOT NULL DEFAULT 0,
+                output_tokens INTEGER NOT NULL DEFAULT 0,
+                cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+                cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+                total_cost_usd TEXT NOT NULL DEFAULT '0',
+                billing_cost TEXT,
+                latency_ms_sum INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (created_at, is_shadow, provider_id, app_type, model, source, billing_currency)
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 尝试添加请求指纹列到请求日志表（模型 + 最后一条消息的哈希，用于检测重复请求）
+        for table in ["proxy_request_logs", "proxy_shadow_logs"] {
+            let _ = conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN request_hash TEXT"),
+                [],
+            );
+        }
+
+        // 28. Usage Alerts 表 (用量异常告警历史)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_alerts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                app_type TEXT,
+                provider_id TEXT,
+                session_id TEXT,
+                message TEXT NOT NULL,
+                observed REAL NOT NULL,
+                threshold REAL NOT NULL,
+                paused INTEGER NOT NULL DEFAULT 0,
+                acknowledged INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_alerts_created_at
+             ON usage_alerts(created_at)",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 29. Usage Pauses 表 (因用量异常暂停的供应商 / 会话，target 为 app_type:provider_id 或会话 ID)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_pauses (
+                kind TEXT NOT NULL,
+                target TEXT NOT NULL,
+                alert_id INTEGER,
+                reason TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (kind, target)
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 30. Rolled Up Session Records 表 (已聚合删除的会话日志记录 ID，防止重新导入)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS rolled_up_session_records (
+                request_id TEXT PRIMARY KEY
+            )",
+            [],
+        )
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
         Ok(())
     }
 
@@ -526,6 +526,46 @@ pub fn run() {
                 }
             }
 
+            // 定期使用报表
+            crate::services::usage_export::spawn_usage_report_scheduler(
+                app.state::<AppState>().db.clone(),
+            );
+
+            // 请求日志保留与数据库维护
+            crate::services::usage_retention::spawn_log_maintenance_scheduler(
+                app.state::<AppState>().db.clone(),
+            );
+
+            // 检查点状态与保留策略后台任务
+            let checkpoint_state = crate::checkpoint::state::CheckpointState::new();
+            if let Some(home) = dirs::home_dir() {
+                futures::executor::block_on(
+                    checkpoint_state.set_claude_dir(home.join(".claude")),
+                );
+            }
+            app.manage(checkpoint_state.clone());
+            crate::checkpoint::retention::spawn_retention_scheduler(
+                app.handle().clone(),
+                checkpoint_state,
+            );
+
+            // 进程注册表、Agent 数据库与运行队列
+            app.manage(crate::process::ProcessRegistryState::default());
+            match commands::init_database(app.handle()) {
+                Ok(conn) => {
+                    app.manage(commands::AgentDb(std::sync::Mutex::new(conn)));
+                    app.manage(commands::AgentRunQueue::new());
+                    commands::spawn_agent_run_queue(app.handle().clone());
+                }
+                Err(e) => log::error!("初始化 Agent 数据库失败: {e}"),
+            }
+
+            // 用量异常检测
+            crate::services::usage_anomaly::spawn_usage_anomaly_watcher(
+                app.handle().clone(),
+                app.state::<AppState>().db.clone(),
+            );
+
             // 自动启动代理服务器
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -671,6 +711,10 @@ pub fn run() {
             commands::update_circuit_breaker_config,
             commands::get_circuit_breaker_stats,
             commands::test_provider_connection,
+            // Shadow traffic
+            commands::get_shadow_config,
+            commands::set_shadow_config,
+            commands::delete_shadow_config,
             // Failover queue management
             commands::get_failover_queue,
             commands::get_available_providers_for_failover,
@@ -683,11 +727,49 @@ pub fn run() {
             commands::get_usage_trends,
             commands::get_provider_stats,
             commands::get_model_stats,
+            commands::get_usage_hourly_trends,
+            commands::get_latency_stats,
+            commands::get_error_breakdown,
+            commands::get_project_stats,
+            commands::get_session_stats,
+            commands::import_session_usage,
             commands::get_request_logs,
             commands::get_request_detail,
+            commands::export_request_logs,
+            commands::get_usage_report_config,
+            commands::set_usage_report_config,
+            commands::generate_usage_report,
+            commands::get_budget_forecast,
+            commands::get_log_retention_config,
+            commands::set_log_retention_config,
+            commands::run_log_maintenance,
+            commands::get_database_size,
+            commands::get_anomaly_rules,
+            commands::set_anomaly_rules,
+            commands::get_usage_alerts,
+            commands::acknowledge_usage_alert,
+            commands::get_usage_pauses,
+            commands::resume_usage_pause,
             commands::get_model_pricing,
             commands::update_model_pricing,
             commands::delete_model_pricing,
+            commands::get_pricing_rules,
+            commands::save_pricing_rule,
+            commands::delete_pricing_rule,
+            commands::recalculate_costs,
+            commands::preview_pricing_import,
+            commands::apply_pricing_import,
+            commands::export_pricing_catalog,
+            commands::get_pricing_catalog_url,
+            commands::set_pricing_catalog_url,
+            commands::get_model_aliases,
+            commands::set_model_alias,
+            commands::delete_model_alias,
+            commands::get_exchange_rates,
+            commands::set_exchange_rate,
+            commands::delete_exchange_rate,
+            commands::get_reporting_currency,
+            commands::set_reporting_currency,
             commands::check_provider_limits,
             // Stream health check
             commands::stream_check_provider,
@@ -721,14 +803,22 @@ pub fn run() {
             // Checkpoint commands
             commands::create_checkpoint,
             commands::restore_checkpoint,
+            commands::restore_checkpoint_files,
             commands::list_checkpoints,
             commands::fork_from_checkpoint,
+            commands::export_checkpoint_archive,
+            commands::inspect_checkpoint_archive,
+            commands::import_checkpoint_archive,
             commands::get_session_timeline,
             commands::update_checkpoint_settings,
             commands::get_checkpoint_diff,
             commands::track_checkpoint_message,
             commands::check_auto_checkpoint,
             commands::cleanup_old_checkpoints,
+            commands::set_checkpoint_pinned,
+            commands::get_checkpoint_retention_policy,
+            commands::update_checkpoint_retention_policy,
+            commands::apply_checkpoint_retention,
             commands::get_checkpoint_settings,
             commands::clear_checkpoint_manager,
             commands::get_checkpoint_state_stats,
@@ -749,6 +839,14 @@ pub fn run() {
             commands::get_agent_run_with_real_time_metrics,
             commands::list_agent_runs_with_metrics,
             commands::execute_agent,
+            commands::get_agent_queue_status,
+            commands::get_agent_queue_limits,
+            commands::set_agent_queue_limits,
+            commands::list_agent_schedules,
+            commands::create_agent_schedule,
+            commands::update_agent_schedule,
+            commands::delete_agent_schedule,
+            commands::preview_agent_schedule,
             commands::list_running_sessions,
             commands::kill_agent_session,
             commands::get_session_status,
@@ -159,12 +159,21 @@ pub struct ProviderMeta {
     /// 成本倍数（用于计算实际成本）
     #[serde(rename = "costMultiplier", skip_serializing_if = "Option::is_none")]
     pub cost_multiplier: Option<String>,
+    /// 结算币种（ISO 4217，如 CNY；未设置时为 USD）
+    #[serde(rename = "billingCurrency", skip_serializing_if = "Option::is_none")]
+    pub billing_currency: Option<String>,
+    /// 固定结算汇率（1 USD 折合多少结算币种；未设置时按汇率表换算）
+    #[serde(rename = "billingRate", skip_serializing_if = "Option::is_none")]
+    pub billing_rate: Option<String>,
     /// 每日消费限额（USD）
     #[serde(rename = "limitDailyUsd", skip_serializing_if = "Option::is_none")]
     pub limit_daily_usd: Option<String>,
     /// 每月消费限额（USD）
     #[serde(rename = "limitMonthlyUsd", skip_serializing_if = "Option::is_none")]
     pub limit_monthly_usd: Option<String>,
+    /// 是否修复上游返回的畸形工具调用（仅格式转换模式生效）
+    #[serde(rename = "toolCallRepair", skip_serializing_if = "Option::is_none")]
+    pub tool_call_repair: Option<bool>,
 }
 
 impl ProviderManager {
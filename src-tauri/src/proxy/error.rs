@@ -60,11 +60,42 @@ pub enum ProxyError {
     #[error("认证失败: {0}")]
     AuthError(String),
 
+    /// 用量异常告警触发的自动暂停
+    #[error("已因用量异常暂停: {0}")]
+    UsagePaused(String),
+
     #[allow(dead_code)]
     #[error("内部错误: {0}")]
     Internal(String),
 }
 
+impl ProxyError {
+    /// 返回给客户端的 HTTP 状态码
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ProxyError::UpstreamError { status, .. } => {
+                StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY)
+            }
+            ProxyError::AlreadyRunning => StatusCode::CONFLICT,
+            ProxyError::NotRunning => StatusCode::SERVICE_UNAVAILABLE,
+            ProxyError::BindFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ProxyError::ForwardFailed(_) => StatusCode::BAD_GATEWAY,
+            ProxyError::NoAvailableProvider => StatusCode::SERVICE_UNAVAILABLE,
+            ProxyError::ProviderUnhealthy(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ProxyError::MaxRetriesExceeded => StatusCode::SERVICE_UNAVAILABLE,
+            ProxyError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ProxyError::ConfigError(_) => StatusCode::BAD_REQUEST,
+            ProxyError::TransformError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ProxyError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            ProxyError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ProxyError::StreamIdleTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ProxyError::AuthError(_) => StatusCode::UNAUTHORIZED,
+            ProxyError::UsagePaused(_) => StatusCode::FORBIDDEN,
+            ProxyError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
 impl IntoResponse for ProxyError {
     fn into_response(self) -> Response {
         let (status, body) = match &self {
@@ -72,8 +103,7 @@ impl IntoResponse for ProxyError {
                 status: upstream_status,
                 body: upstream_body,
             } => {
-                let http_status =
-                    StatusCode::from_u16(*upstream_status).unwrap_or(StatusCode::BAD_GATEWAY);
+                let http_status = self.status_code();
 
                 // 尝试解析上游响应体为 JSON，如果失败则包装为字符串
                 let error_body = if let Some(body_str) = upstream_body {
@@ -101,40 +131,8 @@ impl IntoResponse for ProxyError {
                 (http_status, error_body)
             }
             _ => {
-                let (http_status, message) = match &self {
-                    ProxyError::AlreadyRunning => (StatusCode::CONFLICT, self.to_string()),
-                    ProxyError::NotRunning => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
-                    ProxyError::BindFailed(_) => {
-                        (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
-                    }
-                    ProxyError::ForwardFailed(_) => (StatusCode::BAD_GATEWAY, self.to_string()),
-                    ProxyError::NoAvailableProvider => {
-                        (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
-                    }
-                    ProxyError::ProviderUnhealthy(_) => {
-                        (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
-                    }
-                    ProxyError::MaxRetriesExceeded => {
-                        (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
-                    }
-                    ProxyError::DatabaseError(_) => {
-                        (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
-                    }
-                    ProxyError::ConfigError(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-                    ProxyError::TransformError(_) => {
-                        (StatusCode::UNPROCESSABLE_ENTITY, self.to_string())
-                    }
-                    ProxyError::InvalidRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
-                    ProxyError::Timeout(_) => (StatusCode::GATEWAY_TIMEOUT, self.to_string()),
-                    ProxyError::StreamIdleTimeout(_) => {
-                        (StatusCode::GATEWAY_TIMEOUT, self.to_string())
-                    }
-                    ProxyError::AuthError(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
-                    ProxyError::Internal(_) => {
-                        (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
-                    }
-                    ProxyError::UpstreamError { .. } => unreachable!(),
-                };
+                let http_status = self.status_code();
+                let message = self.to_string();
 
                 let error_body = json!({
                     "error": {
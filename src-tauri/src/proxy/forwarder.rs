@@ -4,6 +4,7 @@
 
 use super::{
     error::*,
+    gemini_oauth::GeminiTokenManager,
     provider_router::ProviderRouter as NewProviderRouter,
     providers::{get_adapter, ProviderAdapter},
     types::ProxyStatus,
@@ -12,17 +13,21 @@ use super::{
 use crate::{app_config::AppType, database::Database, provider::Provider};
 use reqwest::{Client, Response};
 use serde_json::Value;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 pub struct RequestForwarder {
     client: Client,
+    db: Arc<Database>,
     router: Arc<NewProviderRouter>,
+    gemini_tokens: Arc<GeminiTokenManager>,
     #[allow(dead_code)]
     max_retries: u8,
     status: Arc<RwLock<ProxyStatus>>,
     current_providers: Arc<RwLock<std::collections::HashMap<String, (String, String)>>>,
+    /// 最后一个转发失败的供应商 ID（故障转移后用于记录失败日志）
+    failed_provider_id: Mutex<Option<String>>,
 }
 
 impl RequestForwarder {
@@ -32,6 +37,7 @@ impl RequestForwarder {
         max_retries: u8,
         status: Arc<RwLock<ProxyStatus>>,
         current_providers: Arc<RwLock<std::collections::HashMap<String, (String, String)>>>,
+        gemini_tokens: Arc<GeminiTokenManager>,
     ) -> Self {
         let mut client_builder = Client::builder();
         if timeout_secs > 0 {
@@ -44,13 +50,24 @@ impl RequestForwarder {
 
         Self {
             client,
-            router: Arc::new(NewProviderRouter::new(db)),
+            router: Arc::new(NewProviderRouter::new(db.clone())),
+            db,
+            gemini_tokens,
             max_retries,
             status,
             current_providers,
+            failed_provider_id: Mutex::new(None),
         }
     }
 
+    /// 最后一个转发失败的供应商 ID
+    pub fn failed_provider_id(&self) -> Option<String> {
+        self.failed_provider_id
+            .lock()
+            .ok()
+            .and_then(|id| id.clone())
+    }
+
     /// 转发请求（带故障转移）
     pub async fn forward_with_retry(
         &self,
@@ -74,6 +91,36 @@ impl RequestForwarder {
             return Err(ProxyError::NoAvailableProvider);
         }
 
+        // 跳过因用量异常被暂停的供应商（检查失败时放行）
+        let mut pause_reason = None;
+        let providers: Vec<Provider> = providers
+            .into_iter()
+            .filter(
+                |provider| match self.db.find_usage_pause(app_type_str, &provider.id, None) {
+                    Ok(Some(pause)) => {
+                        log::info!(
+                            "[{}] 跳过已暂停的 Provider: {}",
+                            app_type_str,
+                            provider.name
+                        );
+                        pause_reason.get_or_insert(pause.reason);
+                        false
+                    }
+                    Ok(None) => true,
+                    Err(e) => {
+                        log::warn!("检查用量暂停状态失败: {e}");
+                        true
+                    }
+                },
+            )
+            .collect();
+
+        if providers.is_empty() {
+            return Err(pause_reason
+                .map(ProxyError::UsagePaused)
+                .unwrap_or(ProxyError::NoAvailableProvider));
+        }
+
         log::info!(
             "[{}] 故障转移链: {} 个可用供应商",
             app_type_str,
@@ -110,7 +157,14 @@ impl RequestForwarder {
 
             // 转发请求
             match self
-                .forward(provider, endpoint, &body, &headers, adapter.as_ref())
+                .forward_with_oauth_refresh(
+                    app_type,
+                    provider,
+                    endpoint,
+                    &body,
+                    &headers,
+                    adapter.as_ref(),
+                )
                 .await
             {
                 Ok(response) => {
@@ -167,6 +221,9 @@ impl RequestForwarder {
                 }
                 Err(e) => {
                     let latency = start.elapsed().as_millis() as u64;
+                    if let Ok(mut failed) = self.failed_provider_id.lock() {
+                        *failed = Some(provider.id.clone());
+                    }
 
                     // 失败：记录失败并更新熔断器
                     if let Err(record_err) = self
@@ -246,6 +303,84 @@ impl RequestForwarder {
         Err(last_error.unwrap_or(ProxyError::MaxRetriesExceeded))
     }
 
+    /// 转发影子请求
+    ///
+    /// 不经过 ProviderRouter，不更新熔断器和代理状态统计
+    pub(crate) async fn forward_shadow(
+        &self,
+        app_type: &AppType,
+        provider: &Provider,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+    ) -> Result<Response, ProxyError> {
+        let adapter = get_adapter(app_type);
+        self.forward(provider, endpoint, body, headers, adapter.as_ref())
+            .await
+    }
+
+    /// 转发单个请求，Gemini OAuth 供应商会自动刷新 token
+    ///
+    /// token 即将过期时先刷新；上游返回 401 时刷新一次并重试，
+    /// 重试仍失败才交由调用方计入熔断器
+    async fn forward_with_oauth_refresh(
+        &self,
+        app_type: &AppType,
+        provider: &Provider,
+        endpoint: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+        adapter: &dyn ProviderAdapter,
+    ) -> Result<Response, ProxyError> {
+        if *app_type != AppType::Gemini {
+            return self
+                .forward(provider, endpoint, body, headers, adapter)
+                .await;
+        }
+
+        let provider = match self.gemini_tokens.ensure_fresh(&self.db, provider).await {
+            Ok(p) => p,
+            Err(e) => {
+                log::warn!("[Gemini] 预先刷新 OAuth token 失败，继续使用旧 token: {e}");
+                provider.clone()
+            }
+        };
+
+        match self
+            .forward(&provider, endpoint, body, headers, adapter)
+            .await
+        {
+            Err(ProxyError::UpstreamError {
+                status: 401,
+                body: err_body,
+            }) => {
+                match self
+                    .gemini_tokens
+                    .refresh_after_unauthorized(&self.db, &provider)
+                    .await
+                {
+                    Ok(Some(refreshed)) => {
+                        log::info!("[Gemini] OAuth token 已刷新，重试请求: {}", provider.name);
+                        self.forward(&refreshed, endpoint, body, headers, adapter)
+                            .await
+                    }
+                    Ok(None) => Err(ProxyError::UpstreamError {
+                        status: 401,
+                        body: err_body,
+                    }),
+                    Err(e) => {
+                        log::warn!("[Gemini] 401 后刷新 OAuth token 失败: {e}");
+                        Err(ProxyError::UpstreamError {
+                            status: 401,
+                            body: err_body,
+                        })
+                    }
+                }
+            }
+            result => result,
+        }
+    }
+
     /// 转发单个请求（使用适配器）
     async fn forward(
         &self,
@@ -0,0 +1,429 @@
+//! Gemini CLI OAuth token 刷新
+//!
+//! Gemini CLI 的 access_token 有效期约 1 小时。代理在转发前检查过期时间，
+//! 即将过期或上游返回 401 时使用 refresh_token 换取新 token，并写回 Provider 配置。
+//!
+//! 同一 Provider 的并发刷新只会发起一次请求（single-flight）：
+//! 后到的请求在锁上等待，拿到锁后先从数据库重新读取凭证，若已被刷新则直接复用。
+
+use super::providers::{GeminiAdapter, OAuthCredentials, ProviderType};
+use super::ProxyError;
+use crate::database::Database;
+use crate::provider::Provider;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex as AsyncMutex;
+
+/// Google OAuth 默认 token 端点
+pub const DEFAULT_TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+
+/// token 端点响应
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<i64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    token_type: Option<String>,
+    #[serde(default)]
+    id_token: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// Gemini OAuth token 管理器（在代理服务器生命周期内共享）
+pub struct GeminiTokenManager {
+    client: Client,
+    /// 强制使用的 token 端点（为空时依次使用凭证中的 token_uri 和默认端点），可随代理配置热更新
+    token_endpoint: RwLock<Option<String>>,
+    /// 每个 Provider 一把刷新锁
+    refresh_locks: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+impl GeminiTokenManager {
+    pub fn new() -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
+            token_endpoint: RwLock::new(None),
+            refresh_locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 使用代理配置中的 token 端点（自建 OAuth 代理等场景）
+    pub fn with_token_endpoint(endpoint: Option<String>) -> Self {
+        let manager = Self::new();
+        manager.set_token_endpoint(endpoint);
+        manager
+    }
+
+    /// 更新 token 端点（空字符串视为未配置）
+    pub fn set_token_endpoint(&self, endpoint: Option<String>) {
+        let endpoint = endpoint
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty());
+        *self
+            .token_endpoint
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = endpoint;
+    }
+
+    /// 转发前调用：token 即将过期时刷新
+    ///
+    /// 非 OAuth Provider 或无法刷新时原样返回
+    pub async fn ensure_fresh(
+        &self,
+        db: &Database,
+        provider: &Provider,
+    ) -> Result<Provider, ProxyError> {
+        let Some(creds) = refreshable_credentials(provider) else {
+            return Ok(provider.clone());
+        };
+        if !creds.needs_refresh() {
+            return Ok(provider.clone());
+        }
+
+        log::info!(
+            "[Gemini] OAuth token 即将过期，刷新 Provider: {}",
+            provider.name
+        );
+        self.refresh_single_flight(db, provider, |latest| latest.needs_refresh())
+            .await
+    }
+
+    /// 上游返回 401 后调用：强制刷新
+    ///
+    /// 返回 `Some(provider)` 表示已获得新 token 可以重试；
+    /// 若其他请求已经刷新过，则直接复用其结果而不再请求 token 端点
+    pub async fn refresh_after_unauthorized(
+        &self,
+        db: &Database,
+        provider: &Provider,
+    ) -> Result<Option<Provider>, ProxyError> {
+        let Some(creds) = refreshable_credentials(provider) else {
+            return Ok(None);
+        };
+
+        log::info!("[Gemini] 收到 401，尝试刷新 OAuth token: {}", provider.name);
+        let stale_token = creds.access_token;
+        let refreshed = self
+            .refresh_single_flight(db, provider, |latest| latest.access_token == stale_token)
+            .await?;
+        Ok(Some(refreshed))
+    }
+
+    /// 单飞刷新
+    ///
+    /// `still_stale` 用于在拿到锁后判断最新凭证是否仍需刷新
+    async fn refresh_single_flight(
+        &self,
+        db: &Database,
+        provider: &Provider,
+        still_stale: impl Fn(&OAuthCredentials) -> bool,
+    ) -> Result<Provider, ProxyError> {
+        let lock = {
+            let mut locks = self.refresh_locks.lock().unwrap_or_else(|e| e.into_inner());
+            locks
+                .entry(provider.id.clone())
+                .or_insert_with(|| Arc::new(AsyncMutex::new(())))
+                .clone()
+        };
+        let _guard = lock.lock().await;
+
+        // 重新读取，其他请求可能已经完成刷新
+        let latest = db
+            .get_provider_by_id(&provider.id, "gemini")
+            .map_err(|e| ProxyError::DatabaseError(e.to_string()))?
+            .unwrap_or_else(|| provider.clone());
+        let Some(creds) = refreshable_credentials(&latest) else {
+            return Ok(latest);
+        };
+        if !still_stale(&creds) {
+            log::debug!("[Gemini] OAuth token 已被其他请求刷新，直接复用");
+            return Ok(latest);
+        }
+
+        let token = self.exchange_refresh_token(&creds).await?;
+        let updated = apply_token_response(&latest, &token);
+
+        if let Err(e) =
+            db.update_provider_settings_config("gemini", &updated.id, &updated.settings_config)
+        {
+            // 写回失败不影响本次请求，下次请求会再次刷新
+            log::warn!("[Gemini] 写回刷新后的 OAuth 凭证失败: {e}");
+        } else {
+            log::info!("[Gemini] OAuth token 刷新成功: {}", updated.name);
+        }
+
+        Ok(updated)
+    }
+
+    /// 调用 token 端点，用 refresh_token 换取新的 access_token
+    async fn exchange_refresh_token(
+        &self,
+        creds: &OAuthCredentials,
+    ) -> Result<TokenResponse, ProxyError> {
+        let configured = self
+            .token_endpoint
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let endpoint = configured
+            .as_deref()
+            .or(creds.token_uri.as_deref())
+            .unwrap_or(DEFAULT_TOKEN_ENDPOINT);
+
+        let params = [
+            ("grant_type", "refresh_token"),
+            (
+                "refresh_token",
+                creds.refresh_token.as_deref().unwrap_or(""),
+            ),
+            ("client_id", creds.client_id.as_deref().unwrap_or("")),
+            (
+                "client_secret",
+                creds.client_secret.as_deref().unwrap_or(""),
+            ),
+        ];
+
+        let response = self
+            .client
+            .post(endpoint)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| ProxyError::AuthError(format!("刷新 OAuth token 失败: {e}")))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProxyError::AuthError(format!(
+                "刷新 OAuth token 失败 ({status}): {body}"
+            )));
+        }
+
+        response
+            .json::<TokenResponse>()
+            .await
+            .map_err(|e| ProxyError::AuthError(format!("解析 OAuth token 响应失败: {e}")))
+    }
+}
+
+impl Default for GeminiTokenManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 提取可刷新的 OAuth 凭证（仅 GeminiCli 且带 refresh_token）
+fn refreshable_credentials(provider: &Provider) -> Option<OAuthCredentials> {
+    let adapter = GeminiAdapter::new();
+    if adapter.provider_type(provider) != ProviderType::GeminiCli {
+        return None;
+    }
+    let key = adapter.extract_key_raw(provider)?;
+    let creds = adapter.parse_oauth_credentials(&key)?.with_default_client();
+    creds.can_refresh().then_some(creds)
+}
+
+/// 将 token 响应合并进原有的凭证 JSON，保留其余字段
+fn apply_token_response(provider: &Provider, token: &TokenResponse) -> Provider {
+    let adapter = GeminiAdapter::new();
+    let raw = adapter.extract_key_raw(provider).unwrap_or_default();
+    let mut creds: Value = serde_json::from_str(&raw).unwrap_or_else(|_| json!({}));
+
+    creds["access_token"] = json!(token.access_token);
+    if let Some(expires_in) = token.expires_in {
+        creds["expiry_date"] = json!(chrono::Utc::now().timestamp_millis() + expires_in * 1000);
+    }
+    if let Some(refresh_token) = &token.refresh_token {
+        creds["refresh_token"] = json!(refresh_token);
+    }
+    if let Some(token_type) = &token.token_type {
+        creds["token_type"] = json!(token_type);
+    }
+    if let Some(id_token) = &token.id_token {
+        creds["id_token"] = json!(id_token);
+    }
+    if let Some(scope) = &token.scope {
+        creds["scope"] = json!(scope);
+    }
+
+    adapter.with_replaced_key(provider, creds.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 启动本地 token 端点桩，返回 (端点 URL, 调用计数)
+    async fn spawn_token_stub() -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/token",
+            post(move |body: String| {
+                let counter = counter.clone();
+                async move {
+                    let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                    assert!(body.contains("grant_type=refresh_token"));
+                    assert!(body.contains("refresh_token=1%2F%2Frefresh"));
+                    // 模拟较慢的端点，让并发请求在锁上排队
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    axum::Json(json!({
+                        "access_token": format!("ya29.refreshed-{n}"),
+                        "expires_in": 3599,
+                        "token_type": "Bearer"
+                    }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{addr}/token"), hits)
+    }
+
+    fn oauth_provider(expiry_date: i64) -> Provider {
+        let creds = json!({
+            "access_token": "ya29.stale",
+            "refresh_token": "1//refresh",
+            "expiry_date": expiry_date,
+            "scope": "https://www.googleapis.com/auth/cloud-platform"
+        });
+        Provider::with_id(
+            "gemini-cli".to_string(),
+            "Gemini CLI".to_string(),
+            json!({
+                "env": {
+                    "GOOGLE_GEMINI_API_KEY": creds.to_string(),
+                    "GOOGLE_GEMINI_BASE_URL": "https://generativelanguage.googleapis.com"
+                }
+            }),
+            None,
+        )
+    }
+
+    fn stored_credentials(db: &Database) -> Value {
+        let provider = db
+            .get_provider_by_id("gemini-cli", "gemini")
+            .unwrap()
+            .unwrap();
+        let raw = provider.settings_config["env"]["GOOGLE_GEMINI_API_KEY"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_refresh_is_single_flight() {
+        let (endpoint, hits) = spawn_token_stub().await;
+        let manager = Arc::new(GeminiTokenManager::with_token_endpoint(Some(
+            endpoint.to_string(),
+        )));
+        let db = Arc::new(Database::memory().unwrap());
+        let provider = oauth_provider(chrono::Utc::now().timestamp_millis() - 1000);
+        db.save_provider("gemini", &provider).unwrap();
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let manager = manager.clone();
+                let db = db.clone();
+                let provider = provider.clone();
+                tokio::spawn(async move { manager.ensure_fresh(&db, &provider).await })
+            })
+            .collect();
+
+        for task in tasks {
+            let refreshed = task.await.unwrap().unwrap();
+            let auth = GeminiAdapter::new().parse_oauth_credentials(
+                refreshed.settings_config["env"]["GOOGLE_GEMINI_API_KEY"]
+                    .as_str()
+                    .unwrap(),
+            );
+            assert_eq!(auth.unwrap().access_token, "ya29.refreshed-1");
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // 写回数据库，保留原有字段
+        let stored = stored_credentials(&db);
+        assert_eq!(stored["access_token"], "ya29.refreshed-1");
+        assert_eq!(stored["refresh_token"], "1//refresh");
+        assert_eq!(
+            stored["scope"],
+            "https://www.googleapis.com/auth/cloud-platform"
+        );
+        assert!(stored["expiry_date"].as_i64().unwrap() > chrono::Utc::now().timestamp_millis());
+    }
+
+    #[tokio::test]
+    async fn test_valid_token_is_not_refreshed() {
+        let (endpoint, hits) = spawn_token_stub().await;
+        let manager = GeminiTokenManager::with_token_endpoint(Some(endpoint.to_string()));
+        let db = Database::memory().unwrap();
+        let provider = oauth_provider(chrono::Utc::now().timestamp_millis() + 3_600_000);
+        db.save_provider("gemini", &provider).unwrap();
+
+        let result = manager.ensure_fresh(&db, &provider).await.unwrap();
+        assert_eq!(result.settings_config, provider.settings_config);
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_refresh_reuses_concurrent_result() {
+        let (endpoint, hits) = spawn_token_stub().await;
+        let manager = GeminiTokenManager::with_token_endpoint(Some(endpoint.to_string()));
+        let db = Database::memory().unwrap();
+        // 未过期但被上游拒绝（例如被撤销）
+        let provider = oauth_provider(chrono::Utc::now().timestamp_millis() + 3_600_000);
+        db.save_provider("gemini", &provider).unwrap();
+
+        let first = manager
+            .refresh_after_unauthorized(&db, &provider)
+            .await
+            .unwrap();
+        assert!(first.is_some());
+        // 另一个持有旧 token 的请求也收到 401：直接复用已刷新的凭证
+        let second = manager
+            .refresh_after_unauthorized(&db, &provider)
+            .await
+            .unwrap();
+        assert!(second.is_some());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert_eq!(stored_credentials(&db)["access_token"], "ya29.refreshed-1");
+    }
+
+    #[tokio::test]
+    async fn test_api_key_provider_is_ignored() {
+        let manager =
+            GeminiTokenManager::with_token_endpoint(Some("http://127.0.0.1:9/token".to_string()));
+        let db = Database::memory().unwrap();
+        let provider = Provider::with_id(
+            "gemini-key".to_string(),
+            "Gemini".to_string(),
+            json!({"env": {"GEMINI_API_KEY": "AIza-test"}}),
+            None,
+        );
+
+        assert!(manager
+            .refresh_after_unauthorized(&db, &provider)
+            .await
+            .unwrap()
+            .is_none());
+        let same = manager.ensure_fresh(&db, &provider).await.unwrap();
+        assert_eq!(same.settings_config, provider.settings_config);
+    }
+}
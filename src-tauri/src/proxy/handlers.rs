@@ -4,9 +4,9 @@
 
 use super::{
     forwarder::RequestForwarder,
-    providers::{get_adapter, transform, ProviderType},
+    providers::{get_adapter, tool_repair::ToolCallRepairer, transform, ProviderType},
     server::ProxyState,
-    session::ProxySession,
+    session::{ClientSession, ProxySession},
     types::*,
     usage::{logger::UsageLogger, parser::TokenUsage},
     ProxyError,
@@ -97,6 +97,8 @@ async fn log_usage_with_session(
         first_token_ms,
         status_code,
         Some(session.session_id.clone()),
+        None,
+        None,
         provider_type_str,
         session.is_streaming,
     ) {
@@ -116,6 +118,7 @@ async fn log_usage(
     first_token_ms: Option<u64>,
     is_streaming: bool,
     status_code: u16,
+    client: &ClientSession,
 ) {
     let logger = UsageLogger::new(&state.db);
 
@@ -147,7 +150,9 @@ async fn log_usage(
         latency_ms,
         first_token_ms,
         status_code,
-        None,
+        client.session_id.clone(),
+        client.project_path.clone(),
+        client.request_hash.clone(),
         None, // provider_type
         is_streaming,
     ) {
@@ -155,6 +160,67 @@ async fn log_usage(
     }
 }
 
+/// 异步记录转发失败的请求（状态码取返回给客户端的状态码）
+///
+/// 因用量异常暂停而被拒绝的请求未到达供应商，不计入失败统计
+fn log_failure(
+    state: &ProxyState,
+    provider_id: &str,
+    app_type: &str,
+    model: &str,
+    error: &ProxyError,
+    start_time: std::time::Instant,
+    client: &ClientSession,
+) {
+    if matches!(error, ProxyError::UsagePaused(_)) {
+        return;
+    }
+
+    let state = state.clone();
+    let provider_id = provider_id.to_string();
+    let app_type = app_type.to_string();
+    let model = model.to_string();
+    let status_code = error.status_code().as_u16();
+    let error_message = error.to_string();
+    let latency_ms = start_time.elapsed().as_millis() as u64;
+    let client = client.clone();
+
+    tokio::spawn(async move {
+        let logger = UsageLogger::new(&state.db);
+        if let Err(e) = logger.log_error(
+            uuid::Uuid::new_v4().to_string(),
+            provider_id,
+            app_type,
+            model,
+            status_code,
+            error_message,
+            latency_ms,
+            client.session_id,
+            client.project_path,
+            client.request_hash,
+        ) {
+            log::warn!("记录失败请求失败: {e}");
+        }
+    });
+}
+
+/// 检查会话是否因用量异常被暂停（检查失败时放行）
+///
+/// 被暂停的供应商不在此拒绝，而是由 forwarder 从故障转移链中跳过
+fn ensure_not_paused(state: &ProxyState, client: &ClientSession) -> Result<(), ProxyError> {
+    let Some(session_id) = client.session_id.as_deref() else {
+        return Ok(());
+    };
+    match state.db.find_session_pause(session_id) {
+        Ok(Some(pause)) => Err(ProxyError::UsagePaused(pause.reason)),
+        Ok(None) => Ok(()),
+        Err(e) => {
+            log::warn!("检查用量暂停状态失败: {e}");
+            Ok(())
+        }
+    }
+}
+
 type UsageCallbackWithTiming = Arc<dyn Fn(Vec<Value>, Option<u64>) + Send + Sync + 'static>;
 
 #[derive(Clone)]
@@ -297,7 +363,9 @@ pub async fn health_check() -> (StatusCode, Json<Value>) {
 
 /// 获取服务状态
 pub async fn get_status(State(state): State<ProxyState>) -> Result<Json<ProxyStatus>, ProxyError> {
-    let status = state.status.read().await.clone();
+    let mut status = state.status.read().await.clone();
+    status.lanes = state.lanes.snapshot();
+    status.queued_requests = status.lanes.iter().map(|l| l.queued).sum();
     Ok(Json(status))
 }
 
@@ -343,17 +411,61 @@ pub async fn handle_messages(
         is_stream
     );
 
+    // 工具调用修复（需在 body 移交给 forwarder 前提取 tools）
+    let tool_repairer = if needs_transform
+        && provider
+            .meta
+            .as_ref()
+            .and_then(|m| m.tool_call_repair)
+            .unwrap_or(false)
+    {
+        ToolCallRepairer::from_request(&body)
+    } else {
+        None
+    };
+
     let forwarder = RequestForwarder::new(
         state.db.clone(),
         config.request_timeout,
         config.max_retries,
         state.status.clone(),
         state.current_providers.clone(),
+        state.gemini_tokens.clone(),
     );
 
+    // 识别客户端会话与项目目录（用于按会话 / 项目归集成本）
+    let client = state.sessions.identify(&headers, &body).await;
+    ensure_not_paused(&state, &client)?;
+
+    // 影子流量镜像（后台执行，不影响本次响应）
+    state
+        .shadow
+        .mirror(
+            &state,
+            &AppType::Claude,
+            "/v1/messages",
+            &provider.id,
+            &body,
+            &headers,
+        )
+        .await;
+
     let response = forwarder
         .forward_with_retry(&AppType::Claude, "/v1/messages", body, headers)
-        .await?;
+        .await
+        .inspect_err(|e| {
+            log_failure(
+                &state,
+                &forwarder
+                    .failed_provider_id()
+                    .unwrap_or_else(|| provider.id.clone()),
+                "claude",
+                &request_model,
+                e,
+                start_time,
+                &client,
+            );
+        })?;
 
     let status = response.status();
     log::info!("[Claude] 上游响应状态: {status}");
@@ -365,11 +477,13 @@ pub async fn handle_messages(
             log::info!("[Claude] 开始流式响应转换 (OpenAI SSE → Anthropic SSE)");
 
             let stream = response.bytes_stream();
-            let sse_stream = super::providers::streaming::create_anthropic_sse_stream(stream);
+            let sse_stream =
+                super::providers::streaming::create_anthropic_sse_stream(stream, tool_repairer);
 
             let usage_collector = {
                 let state = state.clone();
                 let provider_id = provider.id.clone();
+                let client = client.clone();
                 let model = request_model.clone();
                 let status_code = status.as_u16();
                 let start_time_clone = start_time;
@@ -378,6 +492,7 @@ pub async fn handle_messages(
                         let latency_ms = start_time_clone.elapsed().as_millis() as u64;
                         let state = state.clone();
                         let provider_id = provider_id.clone();
+                        let client = client.clone();
                         let model = model.clone();
                         tokio::spawn(async move {
                             log_usage(
@@ -390,6 +505,7 @@ pub async fn handle_messages(
                                 first_token_ms,
                                 true, // is_streaming
                                 status_code,
+                                &client,
                             )
                             .await;
                         });
@@ -439,12 +555,19 @@ pub async fn handle_messages(
             log::debug!("[Claude] OpenAI 原始响应: {body_str}");
 
             // 解析并转换
-            let openai_response: Value = serde_json::from_slice(&body_bytes).map_err(|e| {
+            let mut openai_response: Value = serde_json::from_slice(&body_bytes).map_err(|e| {
                 log::error!("[Claude] 解析 OpenAI 响应失败: {e}, body: {body_str}");
                 ProxyError::TransformError(format!("Failed to parse OpenAI response: {e}"))
             })?;
 
             log::info!("[Claude] 解析 OpenAI 响应成功");
+
+            if let Some(repairer) = &tool_repairer {
+                let repairs = repairer.repair_openai_response(&mut openai_response);
+                if repairs > 0 {
+                    log::info!("[Claude] 工具调用已修复 ({repairs} 处)");
+                }
+            }
             log::info!(
                 "[Claude] <<< OpenAI 响应 JSON:\n{}",
                 serde_json::to_string_pretty(&openai_response).unwrap_or_default()
@@ -473,6 +596,7 @@ pub async fn handle_messages(
                 tokio::spawn({
                     let state = state.clone();
                     let provider_id = provider.id.clone();
+                    let client = client.clone();
                     let model = model.to_string();
                     async move {
                         log_usage(
@@ -485,6 +609,7 @@ pub async fn handle_messages(
                             None,
                             false,
                             status.as_u16(),
+                            &client,
                         )
                         .await;
                     }
@@ -548,6 +673,7 @@ pub async fn handle_messages(
         let usage_collector = {
             let state = state.clone();
             let provider_id = provider.id.clone();
+            let client = client.clone();
             let model = request_model.clone();
             let status_code = status.as_u16();
             let start_time_clone = start_time;
@@ -556,6 +682,7 @@ pub async fn handle_messages(
                     let latency_ms = start_time_clone.elapsed().as_millis() as u64;
                     let state = state.clone();
                     let provider_id = provider_id.clone();
+                    let client = client.clone();
                     let model = model.clone();
                     tokio::spawn(async move {
                         log_usage(
@@ -568,6 +695,7 @@ pub async fn handle_messages(
                             first_token_ms,
                             true,
                             status_code,
+                            &client,
                         )
                         .await;
                     });
@@ -610,6 +738,7 @@ pub async fn handle_messages(
                 tokio::spawn({
                     let state = state.clone();
                     let provider_id = provider.id.clone();
+                    let client = client.clone();
                     let model = model.to_string();
                     async move {
                         log_usage(
@@ -622,6 +751,7 @@ pub async fn handle_messages(
                             None,
                             false,
                             status.as_u16(),
+                            &client,
                         )
                         .await;
                     }
@@ -672,6 +802,7 @@ pub async fn handle_gemini(
         config.max_retries,
         state.status.clone(),
         state.current_providers.clone(),
+        state.gemini_tokens.clone(),
     );
 
     // 提取完整的路径和查询参数
@@ -689,9 +820,39 @@ pub async fn handle_gemini(
 
     log::info!("[Gemini] 请求端点: {endpoint}");
 
+    // 识别客户端会话与项目目录（用于按会话 / 项目归集成本）
+    let client = state.sessions.identify(&headers, &body).await;
+    ensure_not_paused(&state, &client)?;
+
+    // 影子流量镜像（后台执行，不影响本次响应）
+    state
+        .shadow
+        .mirror(
+            &state,
+            &AppType::Gemini,
+            endpoint,
+            &provider.id,
+            &body,
+            &headers,
+        )
+        .await;
+
     let response = forwarder
         .forward_with_retry(&AppType::Gemini, endpoint, body, headers)
-        .await?;
+        .await
+        .inspect_err(|e| {
+            log_failure(
+                &state,
+                &forwarder
+                    .failed_provider_id()
+                    .unwrap_or_else(|| provider.id.clone()),
+                "gemini",
+                &gemini_model,
+                e,
+                start_time,
+                &client,
+            );
+        })?;
 
     let status = response.status();
     log::info!("[Gemini] 上游响应状态: {status}");
@@ -719,6 +880,7 @@ pub async fn handle_gemini(
         let usage_collector = {
             let state = state.clone();
             let provider_id = provider.id.clone();
+            let client = client.clone();
             let fallback_model = gemini_model.clone();
             let status_code = status.as_u16();
             let start_time_clone = start_time;
@@ -732,6 +894,7 @@ pub async fn handle_gemini(
                     let latency_ms = start_time_clone.elapsed().as_millis() as u64;
                     let state = state.clone();
                     let provider_id = provider_id.clone();
+                    let client = client.clone();
                     tokio::spawn(async move {
                         log_usage(
                             &state,
@@ -743,6 +906,7 @@ pub async fn handle_gemini(
                             first_token_ms,
                             true,
                             status_code,
+                            &client,
                         )
                         .await;
                     });
@@ -781,6 +945,7 @@ pub async fn handle_gemini(
                 tokio::spawn({
                     let state = state.clone();
                     let provider_id = provider.id.clone();
+                    let client = client.clone();
                     async move {
                         log_usage(
                             &state,
@@ -792,6 +957,7 @@ pub async fn handle_gemini(
                             None,
                             false,
                             status.as_u16(),
+                            &client,
                         )
                         .await;
                     }
@@ -841,11 +1007,42 @@ pub async fn handle_responses(
         config.max_retries,
         state.status.clone(),
         state.current_providers.clone(),
+        state.gemini_tokens.clone(),
     );
 
+    // 识别客户端会话与项目目录（用于按会话 / 项目归集成本）
+    let client = state.sessions.identify(&headers, &body).await;
+    ensure_not_paused(&state, &client)?;
+
+    // 影子流量镜像（后台执行，不影响本次响应）
+    state
+        .shadow
+        .mirror(
+            &state,
+            &AppType::Codex,
+            "/v1/responses",
+            &provider.id,
+            &body,
+            &headers,
+        )
+        .await;
+
     let response = forwarder
         .forward_with_retry(&AppType::Codex, "/v1/responses", body, headers)
-        .await?;
+        .await
+        .inspect_err(|e| {
+            log_failure(
+                &state,
+                &forwarder
+                    .failed_provider_id()
+                    .unwrap_or_else(|| provider.id.clone()),
+                "codex",
+                &request_model,
+                e,
+                start_time,
+                &client,
+            );
+        })?;
 
     let status = response.status();
     log::info!("[Codex] 上游响应状态: {status}");
@@ -873,6 +1070,7 @@ pub async fn handle_responses(
         let usage_collector = {
             let state = state.clone();
             let provider_id = provider.id.clone();
+            let client = client.clone();
             let request_model = request_model.clone();
             let status_code = status.as_u16();
             let start_time_clone = start_time;
@@ -894,6 +1092,7 @@ pub async fn handle_responses(
 
                     let state = state.clone();
                     let provider_id = provider_id.clone();
+                    let client = client.clone();
                     tokio::spawn(async move {
                         log_usage(
                             &state,
@@ -905,6 +1104,7 @@ pub async fn handle_responses(
                             first_token_ms,
                             true,
                             status_code,
+                            &client,
                         )
                         .await;
                     });
@@ -952,6 +1152,7 @@ pub async fn handle_responses(
                 tokio::spawn({
                     let state = state.clone();
                     let provider_id = provider.id.clone();
+                    let client = client.clone();
                     let model = model.to_string();
                     async move {
                         log_usage(
@@ -964,6 +1165,7 @@ pub async fn handle_responses(
                             None,
                             false,
                             status.as_u16(),
+                            &client,
                         )
                         .await;
                     }
@@ -1024,11 +1226,42 @@ pub async fn handle_chat_completions(
         config.max_retries,
         state.status.clone(),
         state.current_providers.clone(),
+        state.gemini_tokens.clone(),
     );
 
+    // 识别客户端会话与项目目录（用于按会话 / 项目归集成本）
+    let client = state.sessions.identify(&headers, &body).await;
+    ensure_not_paused(&state, &client)?;
+
+    // 影子流量镜像（后台执行，不影响本次响应）
+    state
+        .shadow
+        .mirror(
+            &state,
+            &AppType::Codex,
+            "/v1/chat/completions",
+            &provider.id,
+            &body,
+            &headers,
+        )
+        .await;
+
     let response = forwarder
         .forward_with_retry(&AppType::Codex, "/v1/chat/completions", body, headers)
-        .await?;
+        .await
+        .inspect_err(|e| {
+            log_failure(
+                &state,
+                &forwarder
+                    .failed_provider_id()
+                    .unwrap_or_else(|| provider.id.clone()),
+                "codex",
+                &request_model,
+                e,
+                start_time,
+                &client,
+            );
+        })?;
 
     let status = response.status();
     log::info!("[Codex] 上游响应状态: {status}");
@@ -1057,6 +1290,7 @@ pub async fn handle_chat_completions(
         let usage_collector = {
             let state = state.clone();
             let provider_id = provider.id.clone();
+            let client = client.clone();
             let request_model = request_model.clone();
             let status_code = status.as_u16();
             let start_time_clone = start_time;
@@ -1071,6 +1305,7 @@ pub async fn handle_chat_completions(
 
                     let state = state.clone();
                     let provider_id = provider_id.clone();
+                    let client = client.clone();
                     tokio::spawn(async move {
                         log_usage(
                             &state,
@@ -1082,6 +1317,7 @@ pub async fn handle_chat_completions(
                             first_token_ms,
                             true,
                             status_code,
+                            &client,
                         )
                         .await;
                     });
@@ -1129,6 +1365,7 @@ pub async fn handle_chat_completions(
                 tokio::spawn({
                     let state = state.clone();
                     let provider_id = provider.id.clone();
+                    let client = client.clone();
                     let model = model.to_string();
                     async move {
                         log_usage(
@@ -1141,6 +1378,7 @@ pub async fn handle_chat_completions(
                             None,
                             false,
                             status.as_u16(),
+                            &client,
                         )
                         .await;
                     }
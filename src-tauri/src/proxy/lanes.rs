@@ -0,0 +1,696 @@
+//! 请求优先级通道
+//!
+//! 在 `RequestForwarder` 之前对请求分流：按路由前缀、请求头、User-Agent 或
+//! 客户端 API Key 归入不同通道，每个通道有独立的并发上限，
+//! 共享全局并发时按权重公平调度（start-time fair queuing）。
+//!
+//! 典型用法：把 Agent 批量发起的子请求放进低权重的 background 通道，
+//! 交互式请求走高权重通道，避免被大量并行请求堵在后面。
+
+use super::server::ProxyState;
+use super::types::{LaneMatcher, LaneStatus, PriorityLane, ProxyConfig};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderMap, Uri},
+    middleware::Next,
+    response::Response,
+};
+use futures::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::sync::oneshot;
+
+/// 未命中任何规则的请求进入的通道
+pub const DEFAULT_LANE: &str = "default";
+
+/// 优先级通道调度器
+pub struct LaneScheduler {
+    shared: Arc<Mutex<SchedulerState>>,
+}
+
+struct SchedulerState {
+    /// 全局并发上限（0 表示不限）
+    global_limit: usize,
+    in_flight: usize,
+    lanes: Vec<LaneState>,
+    /// 全局虚拟时间（最近一次放行请求的起始标签）
+    virtual_time: f64,
+}
+
+struct LaneState {
+    config: PriorityLane,
+    in_flight: usize,
+    waiters: VecDeque<Waiter>,
+    /// 下一个请求的虚拟起始标签，每放行一个请求前进 1/weight
+    vtime: f64,
+    dispatched: u64,
+    total_wait_ms: u64,
+    max_wait_ms: u64,
+}
+
+struct Waiter {
+    tx: oneshot::Sender<LanePermit>,
+    enqueued_at: Instant,
+}
+
+/// 并发许可，drop 时释放槽位并唤醒下一个排队请求
+pub struct LanePermit {
+    shared: Arc<Mutex<SchedulerState>>,
+    lane: String,
+    armed: bool,
+}
+
+impl LaneScheduler {
+    pub fn new(config: &ProxyConfig) -> Self {
+        let state = SchedulerState {
+            global_limit: config.max_concurrent_requests,
+            in_flight: 0,
+            lanes: build_lanes(&config.priority_lanes)
+                .into_iter()
+                .map(LaneState::new)
+                .collect(),
+            virtual_time: 0.0,
+        };
+        Self {
+            shared: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// 是否配置了任何并发限制（未配置时中间件直接放行）
+    pub fn is_active(&self) -> bool {
+        let state = lock_state(&self.shared);
+        state.global_limit > 0 || state.lanes.iter().any(|l| l.config.max_concurrency > 0)
+    }
+
+    /// 根据请求信息选择通道
+    pub fn classify(&self, uri: &Uri, headers: &HeaderMap) -> String {
+        let state = lock_state(&self.shared);
+        state
+            .lanes
+            .iter()
+            .find(|lane| {
+                lane.config
+                    .matchers
+                    .iter()
+                    .any(|m| matcher_matches(m, uri, headers))
+            })
+            .map(|lane| lane.config.name.clone())
+            .unwrap_or_else(|| DEFAULT_LANE.to_string())
+    }
+
+    /// 获取通道并发许可，槽位不足时排队等待
+    pub async fn acquire(&self, lane: &str) -> LanePermit {
+        let rx = {
+            let mut state = lock_state(&self.shared);
+            let idx = state.lane_index(lane);
+            state.lanes[idx].prune_cancelled();
+
+            if state.lanes[idx].waiters.is_empty() && state.can_start(idx) {
+                state.activate(idx);
+                state.start(idx, 0);
+                return LanePermit::new(&self.shared, &state.lanes[idx].config.name);
+            }
+
+            let (tx, rx) = oneshot::channel();
+            state.activate(idx);
+            state.lanes[idx].waiters.push_back(Waiter {
+                tx,
+                enqueued_at: Instant::now(),
+            });
+            log::debug!(
+                "[Lanes] 请求进入排队: lane={}, queued={}",
+                state.lanes[idx].config.name,
+                state.lanes[idx].waiters.len()
+            );
+            rx
+        };
+
+        match rx.await {
+            Ok(permit) => permit,
+            // 调度器已销毁（服务器停止），不再限制
+            Err(_) => LanePermit {
+                shared: self.shared.clone(),
+                lane: lane.to_string(),
+                armed: false,
+            },
+        }
+    }
+
+    /// 应用新的通道配置，保留同名通道的运行状态
+    ///
+    /// 被删除通道中的排队请求会转入 default 通道
+    pub fn reconfigure(&self, config: &ProxyConfig) {
+        let mut state = lock_state(&self.shared);
+        let mut old_lanes = std::mem::take(&mut state.lanes);
+        let mut lanes: Vec<LaneState> = build_lanes(&config.priority_lanes)
+            .into_iter()
+            .map(
+                |cfg| match old_lanes.iter().position(|l| l.config.name == cfg.name) {
+                    Some(pos) => {
+                        let mut lane = old_lanes.swap_remove(pos);
+                        lane.config = cfg;
+                        lane
+                    }
+                    None => LaneState::new(cfg),
+                },
+            )
+            .collect();
+
+        let default_idx = lanes
+            .iter()
+            .position(|l| l.config.name == DEFAULT_LANE)
+            .unwrap_or(lanes.len() - 1);
+        for removed in old_lanes {
+            lanes[default_idx].waiters.extend(removed.waiters);
+        }
+
+        state.lanes = lanes;
+        state.global_limit = config.max_concurrent_requests;
+        state.dispatch(&self.shared);
+    }
+
+    /// 各通道状态快照
+    pub fn snapshot(&self) -> Vec<LaneStatus> {
+        let mut state = lock_state(&self.shared);
+        for lane in &mut state.lanes {
+            lane.prune_cancelled();
+        }
+        state
+            .lanes
+            .iter()
+            .map(|lane| LaneStatus {
+                name: lane.config.name.clone(),
+                in_flight: lane.in_flight,
+                queued: lane.waiters.len(),
+                max_concurrency: lane.config.max_concurrency,
+                dispatched: lane.dispatched,
+                avg_wait_ms: lane.total_wait_ms.checked_div(lane.dispatched).unwrap_or(0),
+                max_wait_ms: lane.max_wait_ms,
+                oldest_wait_ms: lane
+                    .waiters
+                    .front()
+                    .map(|w| w.enqueued_at.elapsed().as_millis() as u64)
+                    .unwrap_or(0),
+            })
+            .collect()
+    }
+}
+
+impl SchedulerState {
+    fn lane_index(&self, name: &str) -> usize {
+        self.lanes
+            .iter()
+            .position(|l| l.config.name == name)
+            .or_else(|| {
+                self.lanes
+                    .iter()
+                    .position(|l| l.config.name == DEFAULT_LANE)
+            })
+            .unwrap_or(self.lanes.len() - 1)
+    }
+
+    fn has_global_capacity(&self) -> bool {
+        self.global_limit == 0 || self.in_flight < self.global_limit
+    }
+
+    fn can_start(&self, idx: usize) -> bool {
+        let lane = &self.lanes[idx];
+        self.has_global_capacity()
+            && (lane.config.max_concurrency == 0 || lane.in_flight < lane.config.max_concurrency)
+    }
+
+    /// 空闲通道重新进入竞争时，不允许使用积攒的虚拟时间抢占
+    fn activate(&mut self, idx: usize) {
+        let lane = &mut self.lanes[idx];
+        if lane.waiters.is_empty() && lane.vtime < self.virtual_time {
+            lane.vtime = self.virtual_time;
+        }
+    }
+
+    fn start(&mut self, idx: usize, wait_ms: u64) {
+        self.in_flight += 1;
+        let lane = &mut self.lanes[idx];
+        lane.in_flight += 1;
+        lane.dispatched += 1;
+        lane.total_wait_ms += wait_ms;
+        lane.max_wait_ms = lane.max_wait_ms.max(wait_ms);
+        self.virtual_time = self.virtual_time.max(lane.vtime);
+        lane.vtime += 1.0 / lane.config.weight.max(1) as f64;
+    }
+
+    fn finish(&mut self, lane: &str) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        // 通道可能已被重新配置删除，此时只释放全局槽位
+        if let Some(lane) = self.lanes.iter_mut().find(|l| l.config.name == lane) {
+            lane.in_flight = lane.in_flight.saturating_sub(1);
+        }
+    }
+
+    /// 选出虚拟时间最小且有空余槽位的排队通道
+    fn next_lane(&self) -> Option<usize> {
+        (0..self.lanes.len())
+            .filter(|&idx| !self.lanes[idx].waiters.is_empty() && self.can_start(idx))
+            .min_by(|&a, &b| self.lanes[a].vtime.total_cmp(&self.lanes[b].vtime))
+    }
+
+    /// 尽可能多地放行排队请求
+    fn dispatch(&mut self, shared: &Arc<Mutex<SchedulerState>>) {
+        for lane in &mut self.lanes {
+            lane.prune_cancelled();
+        }
+
+        while let Some(idx) = self.next_lane() {
+            let Some(waiter) = self.lanes[idx].waiters.pop_front() else {
+                break;
+            };
+            if waiter.tx.is_closed() {
+                continue;
+            }
+            let wait_ms = waiter.enqueued_at.elapsed().as_millis() as u64;
+            let before = (self.virtual_time, self.lanes[idx].stats());
+            self.start(idx, wait_ms);
+
+            let permit = LanePermit::new(shared, &self.lanes[idx].config.name);
+            if let Err(mut permit) = waiter.tx.send(permit) {
+                // 等待方刚刚取消（客户端断开），撤销本次放行的全部记账
+                permit.armed = false;
+                let name = permit.lane.clone();
+                self.finish(&name);
+                self.virtual_time = before.0;
+                self.lanes[idx].restore_stats(before.1);
+            }
+        }
+    }
+}
+
+impl LaneState {
+    fn new(config: PriorityLane) -> Self {
+        Self {
+            config,
+            in_flight: 0,
+            waiters: VecDeque::new(),
+            vtime: 0.0,
+            dispatched: 0,
+            total_wait_ms: 0,
+            max_wait_ms: 0,
+        }
+    }
+
+    /// 丢弃客户端已断开的排队请求
+    fn prune_cancelled(&mut self) {
+        self.waiters.retain(|w| !w.tx.is_closed());
+    }
+
+    /// 放行记账（vtime / dispatched / total_wait_ms / max_wait_ms）
+    fn stats(&self) -> (f64, u64, u64, u64) {
+        (
+            self.vtime,
+            self.dispatched,
+            self.total_wait_ms,
+            self.max_wait_ms,
+        )
+    }
+
+    fn restore_stats(&mut self, stats: (f64, u64, u64, u64)) {
+        (
+            self.vtime,
+            self.dispatched,
+            self.total_wait_ms,
+            self.max_wait_ms,
+        ) = stats;
+    }
+}
+
+impl LanePermit {
+    fn new(shared: &Arc<Mutex<SchedulerState>>, lane: &str) -> Self {
+        Self {
+            shared: shared.clone(),
+            lane: lane.to_string(),
+            armed: true,
+        }
+    }
+
+    pub fn lane(&self) -> &str {
+        &self.lane
+    }
+}
+
+impl Drop for LanePermit {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let mut state = lock_state(&self.shared);
+        state.finish(&self.lane);
+        state.dispatch(&self.shared);
+    }
+}
+
+fn lock_state(shared: &Mutex<SchedulerState>) -> std::sync::MutexGuard<'_, SchedulerState> {
+    shared.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 配置的通道加上兜底的 default 通道（未显式配置时追加在末尾）
+fn build_lanes(configured: &[PriorityLane]) -> Vec<PriorityLane> {
+    let mut lanes = Vec::with_capacity(configured.len() + 1);
+    for lane in configured {
+        if lanes.iter().any(|l: &PriorityLane| l.name == lane.name) {
+            log::warn!("[Lanes] 忽略重复的通道配置: {}", lane.name);
+            continue;
+        }
+        lanes.push(lane.clone());
+    }
+    if !lanes.iter().any(|l| l.name == DEFAULT_LANE) {
+        lanes.push(PriorityLane {
+            name: DEFAULT_LANE.to_string(),
+            weight: 1,
+            max_concurrency: 0,
+            matchers: Vec::new(),
+        });
+    }
+    lanes
+}
+
+fn matcher_matches(matcher: &LaneMatcher, uri: &Uri, headers: &HeaderMap) -> bool {
+    match matcher {
+        LaneMatcher::RoutePrefix { prefix } => uri.path().starts_with(prefix.as_str()),
+        LaneMatcher::Header { name, value } => match headers.get(name.as_str()) {
+            Some(actual) => match value {
+                Some(expected) => actual
+                    .to_str()
+                    .map(|v| v.eq_ignore_ascii_case(expected))
+                    .unwrap_or(false),
+                None => true,
+            },
+            None => false,
+        },
+        LaneMatcher::UserAgent { contains } => headers
+            .get("user-agent")
+            .and_then(|v| v.to_str().ok())
+            .map(|ua| ua.to_lowercase().contains(&contains.to_lowercase()))
+            .unwrap_or(false),
+        LaneMatcher::VirtualKey { key } => {
+            client_keys(uri, headers).any(|candidate| candidate == key.as_str())
+        }
+    }
+}
+
+/// 客户端携带的所有 API Key 候选
+fn client_keys<'a>(uri: &'a Uri, headers: &'a HeaderMap) -> impl Iterator<Item = &'a str> {
+    let from_headers = ["x-api-key", "x-goog-api-key"]
+        .into_iter()
+        .filter_map(|name| headers.get(name).and_then(|v| v.to_str().ok()));
+    let bearer = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let query = uri
+        .query()
+        .into_iter()
+        .flat_map(|q| q.split('&'))
+        .filter_map(|pair| pair.strip_prefix("key="));
+    from_headers.chain(bearer).chain(query)
+}
+
+/// 代理路由中间件：排队获取许可后再交给处理器
+///
+/// 许可会跟随响应体，流式响应传输完毕（或客户端断开）时才释放
+pub async fn lane_middleware(
+    State(state): State<ProxyState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let scheduler = state.lanes.clone();
+    if !scheduler.is_active() {
+        return next.run(request).await;
+    }
+
+    let lane = scheduler.classify(request.uri(), request.headers());
+    let permit = scheduler.acquire(&lane).await;
+    log::debug!("[Lanes] 请求放行: lane={}", permit.lane());
+
+    let response = next.run(request).await;
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(PermitStream {
+        inner: body.into_data_stream(),
+        _permit: permit,
+    });
+    Response::from_parts(parts, body)
+}
+
+/// 持有许可的响应体流
+struct PermitStream<S> {
+    inner: S,
+    _permit: LanePermit,
+}
+
+impl<S: Stream + Unpin> Stream for PermitStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use std::time::Duration;
+
+    fn lane(
+        name: &str,
+        weight: u32,
+        max_concurrency: usize,
+        matchers: Vec<LaneMatcher>,
+    ) -> PriorityLane {
+        PriorityLane {
+            name: name.to_string(),
+            weight,
+            max_concurrency,
+            matchers,
+        }
+    }
+
+    fn config(global: usize, lanes: Vec<PriorityLane>) -> ProxyConfig {
+        ProxyConfig {
+            max_concurrent_requests: global,
+            priority_lanes: lanes,
+            ..ProxyConfig::default()
+        }
+    }
+
+    fn in_flight(scheduler: &LaneScheduler, name: &str) -> usize {
+        scheduler
+            .snapshot()
+            .into_iter()
+            .find(|l| l.name == name)
+            .map(|l| l.in_flight)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_classify() {
+        let scheduler = LaneScheduler::new(&config(
+            0,
+            vec![
+                lane(
+                    "agents",
+                    1,
+                    2,
+                    vec![
+                        LaneMatcher::Header {
+                            name: "x-cc-switch-lane".to_string(),
+                            value: Some("background".to_string()),
+                        },
+                        LaneMatcher::VirtualKey {
+                            key: "sk-agent".to_string(),
+                        },
+                    ],
+                ),
+                lane(
+                    "codex",
+                    1,
+                    0,
+                    vec![LaneMatcher::RoutePrefix {
+                        prefix: "/codex/".to_string(),
+                    }],
+                ),
+                lane(
+                    "interactive",
+                    4,
+                    0,
+                    vec![LaneMatcher::UserAgent {
+                        contains: "claude-cli".to_string(),
+                    }],
+                ),
+            ],
+        ));
+
+        let uri: Uri = "/v1/messages".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("user-agent", HeaderValue::from_static("Claude-CLI/1.0"));
+        assert_eq!(scheduler.classify(&uri, &headers), "interactive");
+
+        headers.insert("x-cc-switch-lane", HeaderValue::from_static("Background"));
+        assert_eq!(scheduler.classify(&uri, &headers), "agents");
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer sk-agent"));
+        assert_eq!(scheduler.classify(&uri, &headers), "agents");
+
+        let gemini: Uri = "/v1beta/models/x:generateContent?alt=sse&key=sk-agent"
+            .parse()
+            .unwrap();
+        assert_eq!(scheduler.classify(&gemini, &HeaderMap::new()), "agents");
+
+        let codex: Uri = "/codex/v1/responses".parse().unwrap();
+        assert_eq!(scheduler.classify(&codex, &HeaderMap::new()), "codex");
+
+        assert_eq!(scheduler.classify(&uri, &HeaderMap::new()), DEFAULT_LANE);
+    }
+
+    #[tokio::test]
+    async fn test_lane_concurrency_cap() {
+        let scheduler = Arc::new(LaneScheduler::new(&config(
+            0,
+            vec![lane("background", 1, 1, Vec::new())],
+        )));
+        assert!(scheduler.is_active());
+
+        let first = scheduler.acquire("background").await;
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire("background").await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        assert_eq!(scheduler.snapshot()[0].queued, 1);
+
+        // 其他通道不受影响
+        let _other = scheduler.acquire(DEFAULT_LANE).await;
+
+        drop(first);
+        let second = waiting.await.unwrap();
+        assert_eq!(second.lane(), "background");
+        let status = &scheduler.snapshot()[0];
+        assert_eq!(status.in_flight, 1);
+        assert_eq!(status.queued, 0);
+        assert_eq!(status.dispatched, 2);
+        assert!(status.max_wait_ms >= 20);
+    }
+
+    #[tokio::test]
+    async fn test_weighted_fair_scheduling() {
+        let scheduler = Arc::new(LaneScheduler::new(&config(
+            1,
+            vec![
+                lane("interactive", 4, 0, Vec::new()),
+                lane("background", 1, 0, Vec::new()),
+            ],
+        )));
+        let holder = scheduler.acquire("background").await;
+
+        let (order_tx, mut order_rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut tasks = Vec::new();
+        // 大量后台请求先排队，交互请求后到
+        for lane_name in [
+            "background",
+            "background",
+            "background",
+            "interactive",
+            "interactive",
+        ] {
+            let scheduler = scheduler.clone();
+            let order_tx = order_tx.clone();
+            tasks.push(tokio::spawn(async move {
+                let permit = scheduler.acquire(lane_name).await;
+                order_tx.send(lane_name).unwrap();
+                drop(permit);
+            }));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        drop(order_tx);
+
+        drop(holder);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        let mut order = Vec::new();
+        while let Some(name) = order_rx.recv().await {
+            order.push(name);
+        }
+
+        // 交互通道权重更高，不会排在所有后台请求之后
+        assert_eq!(order.len(), 5);
+        let last_interactive = order.iter().rposition(|&n| n == "interactive").unwrap();
+        assert!(last_interactive < 4, "order: {order:?}");
+        assert_eq!(
+            scheduler
+                .snapshot()
+                .iter()
+                .map(|l| l.in_flight)
+                .sum::<usize>(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_releases_slot() {
+        let scheduler = Arc::new(LaneScheduler::new(&config(1, Vec::new())));
+        let holder = scheduler.acquire(DEFAULT_LANE).await;
+
+        let cancelled = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire(DEFAULT_LANE).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        cancelled.abort();
+        let _ = cancelled.await;
+
+        // 已断开的排队请求不计入排队数，放行时也不计入等待统计
+        let status = scheduler.snapshot().remove(0);
+        assert_eq!(status.queued, 0);
+        assert_eq!(status.oldest_wait_ms, 0);
+
+        drop(holder);
+        let status = scheduler.snapshot().remove(0);
+        assert_eq!(status.dispatched, 1);
+        assert_eq!(status.max_wait_ms, 0);
+        assert_eq!(in_flight(&scheduler, DEFAULT_LANE), 0);
+        let _next = tokio::time::timeout(Duration::from_secs(1), scheduler.acquire(DEFAULT_LANE))
+            .await
+            .expect("槽位应已归还");
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_moves_waiters_to_default() {
+        let scheduler = Arc::new(LaneScheduler::new(&config(
+            0,
+            vec![lane("background", 1, 1, Vec::new())],
+        )));
+        let holder = scheduler.acquire("background").await;
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.acquire("background").await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // 删除 background 通道：排队请求转入不限并发的 default 通道并立即放行
+        scheduler.reconfigure(&config(0, Vec::new()));
+        let permit = tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(permit.lane(), DEFAULT_LANE);
+        assert!(!scheduler.is_active());
+
+        drop(holder);
+        drop(permit);
+        assert_eq!(in_flight(&scheduler, DEFAULT_LANE), 0);
+    }
+}
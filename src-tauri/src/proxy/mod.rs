@@ -6,14 +6,17 @@ pub mod circuit_breaker;
 pub mod circuit_recovery;
 pub mod error;
 mod forwarder;
+mod gemini_oauth;
 mod handlers;
 mod health;
+pub(crate) mod lanes;
 pub mod provider_router;
 pub mod providers;
 pub mod response_handler;
 mod router;
 pub(crate) mod server;
 pub mod session;
+mod shadow;
 pub(crate) mod types;
 pub mod usage;
 
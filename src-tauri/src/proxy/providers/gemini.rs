@@ -14,27 +14,57 @@ use reqwest::RequestBuilder;
 /// Gemini 适配器
 pub struct GeminiAdapter;
 
+/// Gemini CLI 内置的 OAuth 客户端（installed app，公开发布于 gemini-cli 源码中）
+///
+/// oauth_creds.json 中通常不包含 client_id/client_secret，刷新时回退到此客户端
+const GEMINI_CLI_OAUTH_CLIENT_ID: &str =
+    "681255809395-oo8ft2oprdrnp9e3aqf6av3hmdib135j.apps.googleusercontent.com";
+const GEMINI_CLI_OAUTH_CLIENT_SECRET: &str = "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";
+
+/// access_token 过期前提前刷新的时间窗口（毫秒）
+const EXPIRY_SKEW_MS: i64 = 60_000;
+
 /// OAuth 凭证结构
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct OAuthCredentials {
     pub access_token: String,
     pub refresh_token: Option<String>,
     pub client_id: Option<String>,
     pub client_secret: Option<String>,
+    /// access_token 过期时间（Unix 毫秒，gemini-cli 的 expiry_date 字段）
+    pub expiry_date: Option<i64>,
+    /// 刷新 token 的端点（token_uri 字段）
+    pub token_uri: Option<String>,
 }
 
-#[allow(dead_code)]
 impl OAuthCredentials {
-    /// 检查是否需要刷新 token（有 refresh_token 但没有有效的 access_token）
+    /// 检查是否需要刷新 token
+    ///
+    /// 有 refresh_token，且 access_token 为空或即将过期（提前 60 秒）
     pub fn needs_refresh(&self) -> bool {
-        self.refresh_token.is_some() && self.access_token.is_empty()
+        if self.refresh_token.is_none() {
+            return false;
+        }
+        if self.access_token.is_empty() {
+            return true;
+        }
+        self.expiry_date
+            .is_some_and(|expiry| expiry - EXPIRY_SKEW_MS <= chrono::Utc::now().timestamp_millis())
     }
 
     /// 检查是否可以刷新 token
     pub fn can_refresh(&self) -> bool {
         self.refresh_token.is_some() && self.client_id.is_some() && self.client_secret.is_some()
     }
+
+    /// 缺少 client_id/client_secret 时补上 Gemini CLI 内置客户端
+    pub fn with_default_client(mut self) -> Self {
+        if self.client_id.is_none() && self.client_secret.is_none() {
+            self.client_id = Some(GEMINI_CLI_OAUTH_CLIENT_ID.to_string());
+            self.client_secret = Some(GEMINI_CLI_OAUTH_CLIENT_SECRET.to_string());
+        }
+        self
+    }
 }
 
 impl GeminiAdapter {
@@ -78,6 +108,8 @@ impl GeminiAdapter {
                 refresh_token: None,
                 client_id: None,
                 client_secret: None,
+                expiry_date: None,
+                token_uri: None,
             });
         }
 
@@ -101,6 +133,11 @@ impl GeminiAdapter {
                     .get("client_secret")
                     .and_then(|v| v.as_str())
                     .map(|s| s.to_string());
+                let expiry_date = json.get("expiry_date").and_then(|v| v.as_i64());
+                let token_uri = json
+                    .get("token_uri")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
 
                 // 如果有 access_token 或 refresh_token，返回凭证
                 if !access_token.is_empty() || refresh_token.is_some() {
@@ -109,6 +146,8 @@ impl GeminiAdapter {
                         refresh_token,
                         client_id,
                         client_secret,
+                        expiry_date,
+                        token_uri,
                     });
                 }
             }
@@ -117,8 +156,36 @@ impl GeminiAdapter {
         None
     }
 
+    /// 返回替换了 API Key（OAuth 凭证）的 Provider 副本
+    ///
+    /// 写入位置与 `extract_key_raw` 的读取优先级一致
+    pub fn with_replaced_key(&self, provider: &Provider, key: String) -> Provider {
+        let mut updated = provider.clone();
+        let config = &mut updated.settings_config;
+
+        if let Some(env) = config.get_mut("env").and_then(|v| v.as_object_mut()) {
+            for name in ["GOOGLE_GEMINI_API_KEY", "GEMINI_API_KEY"] {
+                if env.get(name).is_some_and(|v| v.is_string()) {
+                    env.insert(name.to_string(), serde_json::Value::String(key));
+                    return updated;
+                }
+            }
+        }
+
+        if let Some(obj) = config.as_object_mut() {
+            for name in ["apiKey", "api_key"] {
+                if obj.get(name).is_some_and(|v| v.is_string()) {
+                    obj.insert(name.to_string(), serde_json::Value::String(key));
+                    return updated;
+                }
+            }
+        }
+
+        updated
+    }
+
     /// 从 Provider 配置中提取原始 API Key
-    fn extract_key_raw(&self, provider: &Provider) -> Option<String> {
+    pub(crate) fn extract_key_raw(&self, provider: &Provider) -> Option<String> {
         if let Some(env) = provider.settings_config.get("env") {
             // 优先使用 GOOGLE_GEMINI_API_KEY
             if let Some(key) = env.get("GOOGLE_GEMINI_API_KEY").and_then(|v| v.as_str()) {
@@ -417,6 +484,52 @@ mod tests {
         assert_eq!(creds.refresh_token, Some("1//refresh".to_string()));
     }
 
+    #[test]
+    fn test_oauth_needs_refresh_by_expiry() {
+        let adapter = GeminiAdapter::new();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let expired = adapter
+            .parse_oauth_credentials(&format!(
+                "{{\"access_token\":\"ya29.old\",\"refresh_token\":\"1//r\",\"expiry_date\":{}}}",
+                now - 1000
+            ))
+            .unwrap();
+        assert!(expired.needs_refresh());
+
+        let valid = adapter
+            .parse_oauth_credentials(&format!(
+                "{{\"access_token\":\"ya29.new\",\"refresh_token\":\"1//r\",\"expiry_date\":{}}}",
+                now + 3_600_000
+            ))
+            .unwrap();
+        assert!(!valid.needs_refresh());
+
+        // 无 refresh_token 时无法刷新
+        let bare = adapter.parse_oauth_credentials("ya29.bare").unwrap();
+        assert!(!bare.needs_refresh());
+        assert!(!bare.with_default_client().can_refresh());
+        assert!(valid.with_default_client().can_refresh());
+    }
+
+    #[test]
+    fn test_with_replaced_key() {
+        let adapter = GeminiAdapter::new();
+        let provider = create_provider(json!({
+            "env": {
+                "GEMINI_API_KEY": "{\"access_token\":\"ya29.old\"}",
+                "GOOGLE_GEMINI_BASE_URL": "https://generativelanguage.googleapis.com"
+            }
+        }));
+
+        let updated = adapter.with_replaced_key(&provider, "ya29.new".to_string());
+        assert_eq!(updated.settings_config["env"]["GEMINI_API_KEY"], "ya29.new");
+        assert_eq!(
+            updated.settings_config["env"]["GOOGLE_GEMINI_BASE_URL"],
+            "https://generativelanguage.googleapis.com"
+        );
+    }
+
     #[test]
     fn test_parse_oauth_credentials_invalid() {
         let adapter = GeminiAdapter::new();
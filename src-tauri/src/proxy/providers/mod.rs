@@ -10,6 +10,7 @@
 //! - `gemini`: Gemini (Google) 适配器
 //! - `models`: API 数据模型
 //! - `transform`: 格式转换
+//! - `tool_repair`: 工具调用修复
 
 mod adapter;
 mod auth;
@@ -18,6 +19,7 @@ mod codex;
 mod gemini;
 pub mod models;
 pub mod streaming;
+pub mod tool_repair;
 pub mod transform;
 
 use crate::app_config::AppType;
@@ -29,7 +31,7 @@ pub use adapter::ProviderAdapter;
 pub use auth::{AuthInfo, AuthStrategy};
 pub use claude::ClaudeAdapter;
 pub use codex::CodexAdapter;
-pub use gemini::GeminiAdapter;
+pub use gemini::{GeminiAdapter, OAuthCredentials};
 
 /// 供应商类型枚举
 ///
@@ -2,10 +2,12 @@
 //!
 //! 实现 OpenAI SSE → Anthropic SSE 格式转换
 
+use super::tool_repair::ToolCallRepairer;
 use bytes::Bytes;
 use futures::stream::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::BTreeMap;
 
 /// OpenAI 流式响应数据结构
 #[derive(Debug, Deserialize)]
@@ -31,6 +33,10 @@ struct Delta {
     #[serde(default)]
     reasoning: Option<String>, // OpenRouter 的推理内容
     #[serde(default)]
+    reasoning_content: Option<String>, // DeepSeek 等的推理内容
+    #[serde(default)]
+    reasoning_details: Option<Vec<serde_json::Value>>, // OpenRouter 结构化推理（含 signature）
+    #[serde(default)]
     tool_calls: Option<Vec<DeltaToolCall>>,
 }
 
@@ -62,9 +68,21 @@ struct Usage {
     completion_tokens: u32,
 }
 
+/// 待修复的工具调用（按 index 累积的分片）
+#[derive(Debug, Default)]
+struct PendingToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
 /// 创建 Anthropic SSE 流
+///
+/// 传入 `repairer` 时，工具调用不再逐片转发，而是按 index 缓冲，
+/// 在 finish_reason 到达时修复后一次性输出
 pub fn create_anthropic_sse_stream(
     stream: impl Stream<Item = Result<Bytes, reqwest::Error>> + Send + 'static,
+    repairer: Option<ToolCallRepairer>,
 ) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send {
     async_stream::stream! {
         let mut buffer = String::new();
@@ -74,6 +92,7 @@ pub fn create_anthropic_sse_stream(
         let mut has_sent_message_start = false;
         let mut current_block_type: Option<String> = None;
         let mut tool_call_id = None;
+        let mut pending_tool_calls: BTreeMap<usize, PendingToolCall> = BTreeMap::new();
 
         log::info!("[Claude/OpenRouter] ====== 开始流式响应转换 ======");
 
@@ -97,6 +116,17 @@ pub fn create_anthropic_sse_stream(
                             if let Some(data) = l.strip_prefix("data: ") {
                                 if data.trim() == "[DONE]" {
                                     log::info!("[Claude/OpenRouter] <<< OpenAI SSE: [DONE]");
+                                    // 上游未发送 finish_reason 时，仍需输出已缓冲的工具调用
+                                    if let Some(repairer) = &repairer {
+                                        for sse_data in finish_pending_tool_calls(
+                                            std::mem::take(&mut pending_tool_calls),
+                                            repairer,
+                                            &mut content_index,
+                                            &mut current_block_type,
+                                        ) {
+                                            yield Ok(Bytes::from(sse_data));
+                                        }
+                                    }
                                     let event = json!({"type": "message_stop"});
                                     let sse_data = format!("event: message_stop\ndata: {}\n\n",
                                         serde_json::to_string(&event).unwrap_or_default());
@@ -145,8 +175,38 @@ pub fn create_anthropic_sse_stream(
                                         }
 
                                         // 处理 reasoning（thinking）
-                                        if let Some(reasoning) = &choice.delta.reasoning {
-                                            if current_block_type.is_none() {
+                                        let reasoning_details = choice.delta.reasoning_details.as_deref().unwrap_or_default();
+                                        let reasoning = choice
+                                            .delta
+                                            .reasoning
+                                            .clone()
+                                            .or_else(|| choice.delta.reasoning_content.clone())
+                                            .or_else(|| {
+                                                let text: String = reasoning_details
+                                                    .iter()
+                                                    .filter(|d| d.get("type").and_then(|t| t.as_str()) == Some("reasoning.text"))
+                                                    .filter_map(|d| d.get("text").and_then(|t| t.as_str()))
+                                                    .collect();
+                                                (!text.is_empty()).then_some(text)
+                                            })
+                                            .filter(|r| !r.is_empty());
+                                        let signature = reasoning_details
+                                            .iter()
+                                            .find_map(|d| d.get("signature").and_then(|s| s.as_str()));
+
+                                        if reasoning.is_some() || signature.is_some() {
+                                            if current_block_type.as_deref() != Some("thinking") {
+                                                if current_block_type.is_some() {
+                                                    let event = json!({
+                                                        "type": "content_block_stop",
+                                                        "index": content_index
+                                                    });
+                                                    let sse_data = format!("event: content_block_stop\ndata: {}\n\n",
+                                                        serde_json::to_string(&event).unwrap_or_default());
+                                                    yield Ok(Bytes::from(sse_data));
+                                                    content_index += 1;
+                                                }
+
                                                 let event = json!({
                                                     "type": "content_block_start",
                                                     "index": content_index,
@@ -161,17 +221,65 @@ pub fn create_anthropic_sse_stream(
                                                 current_block_type = Some("thinking".to_string());
                                             }
 
+                                            if let Some(reasoning) = &reasoning {
+                                                let event = json!({
+                                                    "type": "content_block_delta",
+                                                    "index": content_index,
+                                                    "delta": {
+                                                        "type": "thinking_delta",
+                                                        "thinking": reasoning
+                                                    }
+                                                });
+                                                let sse_data = format!("event: content_block_delta\ndata: {}\n\n",
+                                                    serde_json::to_string(&event).unwrap_or_default());
+                                                yield Ok(Bytes::from(sse_data));
+                                            }
+
+                                            if let Some(signature) = signature {
+                                                let event = json!({
+                                                    "type": "content_block_delta",
+                                                    "index": content_index,
+                                                    "delta": {
+                                                        "type": "signature_delta",
+                                                        "signature": signature
+                                                    }
+                                                });
+                                                let sse_data = format!("event: content_block_delta\ndata: {}\n\n",
+                                                    serde_json::to_string(&event).unwrap_or_default());
+                                                yield Ok(Bytes::from(sse_data));
+                                            }
+                                        }
+
+                                        // 处理加密推理（redacted_thinking，单独成块）
+                                        for detail in reasoning_details {
+                                            if detail.get("type").and_then(|t| t.as_str()) != Some("reasoning.encrypted") {
+                                                continue;
+                                            }
+                                            let Some(data) = detail.get("data").and_then(|d| d.as_str()) else {
+                                                continue;
+                                            };
+                                            if current_block_type.is_some() {
+                                                let event = json!({
+                                                    "type": "content_block_stop",
+                                                    "index": content_index
+                                                });
+                                                let sse_data = format!("event: content_block_stop\ndata: {}\n\n",
+                                                    serde_json::to_string(&event).unwrap_or_default());
+                                                yield Ok(Bytes::from(sse_data));
+                                                content_index += 1;
+                                            }
                                             let event = json!({
-                                                "type": "content_block_delta",
+                                                "type": "content_block_start",
                                                 "index": content_index,
-                                                "delta": {
-                                                    "type": "thinking_delta",
-                                                    "thinking": reasoning
+                                                "content_block": {
+                                                    "type": "redacted_thinking",
+                                                    "data": data
                                                 }
                                             });
-                                            let sse_data = format!("event: content_block_delta\ndata: {}\n\n",
+                                            let sse_data = format!("event: content_block_start\ndata: {}\n\n",
                                                 serde_json::to_string(&event).unwrap_or_default());
                                             yield Ok(Bytes::from(sse_data));
+                                            current_block_type = Some("redacted_thinking".to_string());
                                         }
 
                                         // 处理文本内容
@@ -220,6 +328,22 @@ pub fn create_anthropic_sse_stream(
                                         // 处理工具调用
                                         if let Some(tool_calls) = &choice.delta.tool_calls {
                                             for tool_call in tool_calls {
+                                                if repairer.is_some() {
+                                                    let pending = pending_tool_calls.entry(tool_call.index).or_default();
+                                                    if let Some(id) = &tool_call.id {
+                                                        pending.id = id.clone();
+                                                    }
+                                                    if let Some(function) = &tool_call.function {
+                                                        if let Some(name) = &function.name {
+                                                            pending.name.push_str(name);
+                                                        }
+                                                        if let Some(args) = &function.arguments {
+                                                            pending.arguments.push_str(args);
+                                                        }
+                                                    }
+                                                    continue;
+                                                }
+
                                                 if let Some(id) = &tool_call.id {
                                                     if current_block_type.is_some() {
                                                         let event = json!({
@@ -271,6 +395,29 @@ pub fn create_anthropic_sse_stream(
 
                                         // 处理 finish_reason
                                         if let Some(finish_reason) = &choice.finish_reason {
+                                            if let Some(repairer) = &repairer {
+                                                if !pending_tool_calls.is_empty() {
+                                                    if current_block_type.is_some() {
+                                                        let event = json!({
+                                                            "type": "content_block_stop",
+                                                            "index": content_index
+                                                        });
+                                                        let sse_data = format!("event: content_block_stop\ndata: {}\n\n",
+                                                            serde_json::to_string(&event).unwrap_or_default());
+                                                        yield Ok(Bytes::from(sse_data));
+                                                        content_index += 1;
+                                                        current_block_type = None;
+                                                    }
+                                                    for sse_data in flush_repaired_tool_calls(
+                                                        std::mem::take(&mut pending_tool_calls),
+                                                        repairer,
+                                                        &mut content_index,
+                                                    ) {
+                                                        yield Ok(Bytes::from(sse_data));
+                                                    }
+                                                }
+                                            }
+
                                             if current_block_type.is_some() {
                                                 let event = json!({
                                                     "type": "content_block_stop",
@@ -321,7 +468,126 @@ pub fn create_anthropic_sse_stream(
                 }
             }
         }
+
+        // 流在没有 finish_reason 和 [DONE] 的情况下结束时，补发缓冲的工具调用
+        if let Some(repairer) = &repairer {
+            if !pending_tool_calls.is_empty() {
+                for sse_data in finish_pending_tool_calls(
+                    std::mem::take(&mut pending_tool_calls),
+                    repairer,
+                    &mut content_index,
+                    &mut current_block_type,
+                ) {
+                    yield Ok(Bytes::from(sse_data));
+                }
+                let event = json!({"type": "message_stop"});
+                yield Ok(Bytes::from(format!("event: message_stop\ndata: {}\n\n",
+                    serde_json::to_string(&event).unwrap_or_default())));
+            }
+        }
+    }
+}
+
+/// 在缺少 finish_reason 时收尾缓冲的工具调用
+///
+/// 关闭当前内容块、输出修复后的工具调用，并补发 stop_reason 为 tool_use 的 message_delta
+fn finish_pending_tool_calls(
+    pending: BTreeMap<usize, PendingToolCall>,
+    repairer: &ToolCallRepairer,
+    content_index: &mut usize,
+    current_block_type: &mut Option<String>,
+) -> Vec<String> {
+    if pending.is_empty() {
+        return Vec::new();
+    }
+
+    let mut events = Vec::new();
+    if current_block_type.take().is_some() {
+        let event = json!({
+            "type": "content_block_stop",
+            "index": *content_index
+        });
+        events.push(format!(
+            "event: content_block_stop\ndata: {}\n\n",
+            serde_json::to_string(&event).unwrap_or_default()
+        ));
+        *content_index += 1;
+    }
+
+    events.extend(flush_repaired_tool_calls(pending, repairer, content_index));
+
+    let event = json!({
+        "type": "message_delta",
+        "delta": {
+            "stop_reason": map_stop_reason(Some("tool_calls")),
+            "stop_sequence": null
+        },
+        "usage": null
+    });
+    events.push(format!(
+        "event: message_delta\ndata: {}\n\n",
+        serde_json::to_string(&event).unwrap_or_default()
+    ));
+
+    events
+}
+
+/// 修复缓冲的工具调用并生成对应的 Anthropic SSE 事件
+///
+/// 每个工具调用输出 content_block_start / 一次完整的 input_json_delta / content_block_stop
+fn flush_repaired_tool_calls(
+    pending: BTreeMap<usize, PendingToolCall>,
+    repairer: &ToolCallRepairer,
+    content_index: &mut usize,
+) -> Vec<String> {
+    let mut events = Vec::new();
+
+    for (_, call) in pending {
+        let repaired = repairer.repair(&call.name, &call.arguments);
+        if !repaired.repairs.is_empty() {
+            log::info!(
+                "[Claude/OpenRouter] 工具调用 {} 已修复 ({} 处)",
+                call.id,
+                repaired.repairs.len()
+            );
+        }
+
+        let start = json!({
+            "type": "content_block_start",
+            "index": *content_index,
+            "content_block": {
+                "type": "tool_use",
+                "id": call.id,
+                "name": repaired.name
+            }
+        });
+        let delta = json!({
+            "type": "content_block_delta",
+            "index": *content_index,
+            "delta": {
+                "type": "input_json_delta",
+                "partial_json": repaired.input.to_string()
+            }
+        });
+        let stop = json!({
+            "type": "content_block_stop",
+            "index": *content_index
+        });
+
+        for (name, event) in [
+            ("content_block_start", start),
+            ("content_block_delta", delta),
+            ("content_block_stop", stop),
+        ] {
+            events.push(format!(
+                "event: {name}\ndata: {}\n\n",
+                serde_json::to_string(&event).unwrap_or_default()
+            ));
+        }
+        *content_index += 1;
     }
+
+    events
 }
 
 /// 映射停止原因
@@ -336,3 +602,141 @@ fn map_stop_reason(finish_reason: Option<&str>) -> Option<String> {
         .to_string()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    async fn collect_events(chunks: Vec<&str>, repairer: Option<ToolCallRepairer>) -> Vec<Value> {
+        let input: Vec<Result<Bytes, reqwest::Error>> = chunks
+            .into_iter()
+            .map(|c| Ok(Bytes::from(format!("data: {c}\n\n"))))
+            .collect();
+        let output: Vec<_> = create_anthropic_sse_stream(futures::stream::iter(input), repairer)
+            .collect()
+            .await;
+
+        output
+            .into_iter()
+            .flat_map(|b| {
+                let text = String::from_utf8(b.unwrap().to_vec()).unwrap();
+                text.lines()
+                    .filter_map(|l| l.strip_prefix("data: "))
+                    .map(|d| serde_json::from_str::<Value>(d).unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_tool_calls_are_repaired() {
+        let repairer = ToolCallRepairer::from_request(&serde_json::json!({
+            "tools": [
+                {"name": "Read", "input_schema": {"type": "object", "properties": {"file_path": {"type": "string"}}}},
+                {"name": "Bash", "input_schema": {"type": "object", "properties": {"command": {"type": "string"}}}}
+            ]
+        }));
+
+        let events = collect_events(
+            vec![
+                r#"{"id":"c1","model":"m","choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_a","function":{"name":"read","arguments":"{\"file_"}}]}}]}"#,
+                r#"{"id":"c1","model":"m","choices":[{"delta":{"tool_calls":[{"index":1,"id":"call_b","function":{"name":"Bash","arguments":"{\"command\":"}}]}}]}"#,
+                r#"{"id":"c1","model":"m","choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"path\":\"/a\"}"}}]}}]}"#,
+                r#"{"id":"c1","model":"m","choices":[{"delta":{"tool_calls":[{"index":1,"function":{"arguments":" \"ls\","}}]}}]}"#,
+                r#"{"id":"c1","model":"m","choices":[{"delta":{},"finish_reason":"tool_calls"}]}"#,
+                "[DONE]",
+            ],
+            repairer,
+        )
+        .await;
+
+        let starts: Vec<&Value> = events
+            .iter()
+            .filter(|e| e["type"] == "content_block_start")
+            .collect();
+        assert_eq!(starts.len(), 2);
+        assert_eq!(starts[0]["content_block"]["name"], "Read");
+        assert_eq!(starts[0]["content_block"]["id"], "call_a");
+        assert_eq!(starts[1]["content_block"]["name"], "Bash");
+        assert_eq!(starts[1]["index"], 1);
+
+        let deltas: Vec<&Value> = events
+            .iter()
+            .filter(|e| e["type"] == "content_block_delta")
+            .collect();
+        assert_eq!(deltas[0]["delta"]["partial_json"], r#"{"file_path":"/a"}"#);
+        assert_eq!(deltas[1]["delta"]["partial_json"], r#"{"command":"ls"}"#);
+
+        assert_eq!(events.last().unwrap()["type"], "message_stop");
+    }
+
+    #[tokio::test]
+    async fn test_pending_tool_calls_flushed_without_finish_reason() {
+        let repairer = ToolCallRepairer::from_request(&serde_json::json!({
+            "tools": [
+                {"name": "Bash", "input_schema": {"type": "object", "properties": {"command": {"type": "string"}}}}
+            ]
+        }));
+
+        let events = collect_events(
+            vec![
+                r#"{"id":"c3","model":"m","choices":[{"delta":{"content":"running"}}]}"#,
+                r#"{"id":"c3","model":"m","choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_c","function":{"name":"Bash","arguments":"{\"command\":\"ls\"}"}}]}}]}"#,
+                "[DONE]",
+            ],
+            repairer.clone(),
+        )
+        .await;
+
+        let start = events
+            .iter()
+            .find(|e| {
+                e["type"] == "content_block_start" && e["content_block"]["type"] == "tool_use"
+            })
+            .expect("tool_use block");
+        assert_eq!(start["content_block"]["id"], "call_c");
+        assert_eq!(start["index"], 1);
+        let delta = events
+            .iter()
+            .find(|e| e["type"] == "message_delta")
+            .unwrap();
+        assert_eq!(delta["delta"]["stop_reason"], "tool_use");
+        assert_eq!(events.last().unwrap()["type"], "message_stop");
+
+        // 没有 [DONE] 直接断流时同样补发
+        let events = collect_events(
+            vec![
+                r#"{"id":"c4","model":"m","choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_d","function":{"name":"Bash","arguments":"{}"}}]}}]}"#,
+            ],
+            repairer,
+        )
+        .await;
+        assert!(events
+            .iter()
+            .any(|e| e["type"] == "content_block_start" && e["content_block"]["id"] == "call_d"));
+        assert_eq!(events.last().unwrap()["type"], "message_stop");
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_content_becomes_thinking_block() {
+        let events = collect_events(
+            vec![
+                r#"{"id":"c2","model":"m","choices":[{"delta":{"reasoning_content":"hmm"}}]}"#,
+                r#"{"id":"c2","model":"m","choices":[{"delta":{"reasoning_details":[{"type":"reasoning.text","signature":"sig"}]}}]}"#,
+                r#"{"id":"c2","model":"m","choices":[{"delta":{"content":"ok"}}]}"#,
+                r#"{"id":"c2","model":"m","choices":[{"delta":{},"finish_reason":"stop"}]}"#,
+            ],
+            None,
+        )
+        .await;
+
+        assert_eq!(events[1]["content_block"]["type"], "thinking");
+        assert_eq!(events[2]["delta"]["thinking"], "hmm");
+        assert_eq!(events[3]["delta"]["type"], "signature_delta");
+        assert_eq!(events[3]["delta"]["signature"], "sig");
+        assert_eq!(events[4]["type"], "content_block_stop");
+        assert_eq!(events[5]["content_block"]["type"], "text");
+        assert_eq!(events[5]["index"], 1);
+    }
+}
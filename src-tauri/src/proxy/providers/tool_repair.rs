@@ -0,0 +1,630 @@
+//! 工具调用修复模块
+//!
+//! 非 Anthropic 模型经格式转换后经常产生畸形的工具调用：
+//! - arguments 不是合法 JSON（截断、尾随逗号、markdown 代码块包裹等）
+//! - 工具名不存在（大小写错误、拼写错误）
+//! - 参数类型与 schema 不符（数字写成字符串、对象被序列化成字符串）
+//!
+//! 本模块根据请求中的 `tools` schema 校验并修复这些问题，每次修复都会写入日志。
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// 单次工具调用的修复结果
+#[derive(Debug, Clone)]
+pub struct RepairedToolCall {
+    /// 修复后的工具名
+    pub name: String,
+    /// 修复后的参数
+    pub input: Value,
+    /// 修复记录（为空表示无需修复）
+    pub repairs: Vec<String>,
+}
+
+/// 工具调用修复器
+///
+/// 从 Anthropic 请求体的 `tools` 字段构建，用于修复上游返回的工具调用
+#[derive(Debug, Clone)]
+pub struct ToolCallRepairer {
+    /// 工具名 → input_schema
+    tools: HashMap<String, Value>,
+}
+
+impl ToolCallRepairer {
+    /// 从 Anthropic 请求体构建修复器
+    ///
+    /// 请求中没有工具定义时返回 `None`
+    pub fn from_request(body: &Value) -> Option<Self> {
+        let tools: HashMap<String, Value> = body
+            .get("tools")?
+            .as_array()?
+            .iter()
+            .filter_map(|t| {
+                let name = t.get("name")?.as_str()?;
+                let schema = t.get("input_schema").cloned().unwrap_or(json!({}));
+                Some((name.to_string(), schema))
+            })
+            .collect();
+
+        if tools.is_empty() {
+            None
+        } else {
+            Some(Self { tools })
+        }
+    }
+
+    /// 修复单个工具调用
+    pub fn repair(&self, name: &str, arguments: &str) -> RepairedToolCall {
+        let mut repairs = Vec::new();
+
+        // 1. 工具名
+        let resolved_name = self.resolve_name(name, &mut repairs);
+
+        // 2. 参数 JSON
+        let mut input = match serde_json::from_str::<Value>(arguments) {
+            Ok(v) => v,
+            Err(_) => match repair_json(arguments) {
+                Some(v) => {
+                    repairs.push(format!("修复非法 JSON 参数: {}", truncate(arguments, 200)));
+                    v
+                }
+                None => {
+                    repairs.push(format!(
+                        "无法修复 JSON 参数，使用空对象: {}",
+                        truncate(arguments, 200)
+                    ));
+                    json!({})
+                }
+            },
+        };
+
+        // 3. 按 schema 校验并修正
+        if let Some(schema) = self.tools.get(&resolved_name) {
+            coerce_to_schema(&mut input, schema, "", &mut repairs);
+        }
+
+        for repair in &repairs {
+            log::warn!("[ToolRepair] {resolved_name}: {repair}");
+        }
+
+        RepairedToolCall {
+            name: resolved_name,
+            input,
+            repairs,
+        }
+    }
+
+    /// 修复 OpenAI 响应中的所有工具调用（原地修改）
+    ///
+    /// 返回修复次数
+    pub fn repair_openai_response(&self, body: &mut Value) -> usize {
+        let mut count = 0;
+        let Some(choices) = body.get_mut("choices").and_then(|c| c.as_array_mut()) else {
+            return 0;
+        };
+
+        for choice in choices {
+            let Some(tool_calls) = choice
+                .get_mut("message")
+                .and_then(|m| m.get_mut("tool_calls"))
+                .and_then(|t| t.as_array_mut())
+            else {
+                continue;
+            };
+
+            for tc in tool_calls {
+                let Some(function) = tc.get_mut("function") else {
+                    continue;
+                };
+                let name = function
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                let arguments = match function.get("arguments") {
+                    Some(Value::String(s)) => s.clone(),
+                    // 部分上游直接返回对象
+                    Some(v @ Value::Object(_)) => v.to_string(),
+                    _ => String::new(),
+                };
+
+                let repaired = self.repair(&name, &arguments);
+                if !repaired.repairs.is_empty() {
+                    count += repaired.repairs.len();
+                    function["name"] = json!(repaired.name);
+                    function["arguments"] = json!(repaired.input.to_string());
+                }
+            }
+        }
+
+        count
+    }
+
+    /// 解析工具名：精确匹配 → 忽略大小写/分隔符匹配 → 编辑距离足够近
+    ///
+    /// 相差太远的名称保持原样，由客户端返回可恢复的“工具不存在”错误，
+    /// 避免臆造的工具被映射成 Bash / Write 等真实工具执行
+    fn resolve_name(&self, name: &str, repairs: &mut Vec<String>) -> String {
+        if self.tools.contains_key(name) {
+            return name.to_string();
+        }
+
+        let normalized = normalize_name(name);
+        if let Some(real) = self.tools.keys().find(|k| normalize_name(k) == normalized) {
+            repairs.push(format!("工具名 {name} → {real}"));
+            return real.clone();
+        }
+
+        let closest = self
+            .tools
+            .keys()
+            .map(|k| (levenshtein(&normalize_name(k), &normalized), k))
+            .min_by_key(|(distance, k)| (*distance, k.len()));
+        match closest {
+            Some((distance, real)) if is_close_name(distance, normalized.chars().count()) => {
+                repairs.push(format!("未知工具名 {name}，映射到最接近的 {real}"));
+                real.clone()
+            }
+            _ => {
+                log::warn!("[ToolRepair] 未知工具名 {name} 与已声明工具相差过大，保持原样");
+                name.to_string()
+            }
+        }
+    }
+}
+
+/// 编辑距离不超过 2，或不超过名称长度的 30% 时视为拼写错误
+fn is_close_name(distance: usize, len: usize) -> bool {
+    distance <= 2 || distance * 10 <= len * 3
+}
+
+/// 去除大小写和分隔符差异
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// 编辑距离
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        format!("{}...", s.chars().take(max).collect::<String>())
+    }
+}
+
+/// 尝试修复常见的非法 JSON
+///
+/// 处理：空字符串、markdown 代码块、前后多余文本、尾随逗号、
+/// 未闭合的字符串/括号、重复拼接的多个对象
+pub fn repair_json(raw: &str) -> Option<Value> {
+    let mut text = raw.trim();
+
+    if text.is_empty() {
+        return Some(json!({}));
+    }
+
+    // markdown 代码块
+    if let Some(stripped) = text.strip_prefix("```") {
+        let stripped = stripped.trim_start_matches(|c: char| c.is_alphanumeric());
+        text = stripped.trim_end().trim_end_matches("```").trim();
+    }
+
+    // 截掉第一个 { 或 [ 之前的内容
+    if let Some(start) = text.find(['{', '[']) {
+        text = &text[start..];
+    } else {
+        return None;
+    }
+
+    // 多个 JSON 值拼接（如参数被重复发送）：取第一个完整值
+    let mut stream = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+    if let Some(Ok(v)) = stream.next() {
+        return Some(v);
+    }
+
+    let balanced = balance_json(&remove_trailing_commas(text));
+    serde_json::from_str(&balanced).ok()
+}
+
+/// 移除 `}`/`]` 前的尾随逗号（忽略字符串内部）
+fn remove_trailing_commas(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            ',' => {
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']') | None) {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// 补全未闭合的字符串和括号，丢弃多余的闭合括号
+fn balance_json(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 8);
+    let mut stack = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in text.chars() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' => {
+                stack.push('}');
+                out.push(c);
+            }
+            '[' => {
+                stack.push(']');
+                out.push(c);
+            }
+            '}' | ']' => {
+                if stack.last() == Some(&c) {
+                    stack.pop();
+                    out.push(c);
+                }
+                if stack.is_empty() {
+                    break;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    if in_string {
+        if escaped {
+            out.pop();
+        }
+        out.push('"');
+    }
+
+    // 截断在 key 或冒号之后的情况
+    let trimmed = out.trim_end();
+    if trimmed.ends_with(':') {
+        out = format!("{trimmed} null");
+    } else if trimmed.ends_with(',') {
+        out = trimmed.trim_end_matches(',').to_string();
+    }
+
+    while let Some(closer) = stack.pop() {
+        out.push(closer);
+    }
+
+    out
+}
+
+/// 按 JSON schema 修正参数类型
+///
+/// 只做无损的、确定性的修正；缺失的必填字段只记录不修复
+fn coerce_to_schema(value: &mut Value, schema: &Value, path: &str, repairs: &mut Vec<String>) {
+    let expected = schema.get("type").and_then(|t| t.as_str());
+    let display_path = if path.is_empty() { "<root>" } else { path };
+
+    // 对象/数组被序列化成了字符串
+    if matches!(expected, Some("object") | Some("array")) {
+        if let Value::String(s) = value {
+            if let Ok(parsed) = serde_json::from_str::<Value>(s) {
+                if (expected == Some("object") && parsed.is_object())
+                    || (expected == Some("array") && parsed.is_array())
+                {
+                    repairs.push(format!(
+                        "{display_path}: 字符串解析为 {}",
+                        expected.unwrap()
+                    ));
+                    *value = parsed;
+                }
+            }
+        }
+    }
+
+    match expected {
+        Some("object") => {
+            if value.is_null() && path.is_empty() {
+                repairs.push("<root>: null 替换为空对象".to_string());
+                *value = Value::Object(Map::new());
+            }
+            let Some(obj) = value.as_object_mut() else {
+                return;
+            };
+
+            if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
+                for (key, prop_schema) in props {
+                    if let Some(v) = obj.get_mut(key) {
+                        let child = if path.is_empty() {
+                            key.clone()
+                        } else {
+                            format!("{path}.{key}")
+                        };
+                        coerce_to_schema(v, prop_schema, &child, repairs);
+                    }
+                }
+
+                if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                    let unknown: Vec<String> = obj
+                        .keys()
+                        .filter(|k| !props.contains_key(*k))
+                        .cloned()
+                        .collect();
+                    for key in unknown {
+                        repairs.push(format!("{display_path}: 移除未定义的参数 {key}"));
+                        obj.remove(&key);
+                    }
+                }
+            }
+
+            if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+                for key in required.iter().filter_map(|k| k.as_str()) {
+                    if !obj.contains_key(key) {
+                        log::warn!("[ToolRepair] {display_path}: 缺少必填参数 {key}");
+                    }
+                }
+            }
+        }
+        Some("array") => {
+            if let Some(items_schema) = schema.get("items") {
+                if let Some(arr) = value.as_array_mut() {
+                    for (i, item) in arr.iter_mut().enumerate() {
+                        coerce_to_schema(item, items_schema, &format!("{path}[{i}]"), repairs);
+                    }
+                } else if !value.is_null() {
+                    // 单个值包装为数组
+                    let mut item = value.take();
+                    coerce_to_schema(&mut item, items_schema, &format!("{path}[0]"), repairs);
+                    repairs.push(format!("{display_path}: 单个值包装为数组"));
+                    *value = json!([item]);
+                }
+            }
+        }
+        Some("integer") | Some("number") => {
+            if let Value::String(s) = value {
+                let parsed = if expected == Some("integer") {
+                    s.trim().parse::<i64>().ok().map(Value::from)
+                } else {
+                    s.trim()
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(serde_json::Number::from_f64)
+                        .map(Value::Number)
+                };
+                if let Some(n) = parsed {
+                    repairs.push(format!("{display_path}: 字符串 \"{s}\" 转为数字"));
+                    *value = n;
+                }
+            }
+        }
+        Some("boolean") => {
+            if let Value::String(s) = value {
+                let parsed = match s.trim().to_lowercase().as_str() {
+                    "true" => Some(true),
+                    "false" => Some(false),
+                    _ => None,
+                };
+                if let Some(b) = parsed {
+                    repairs.push(format!("{display_path}: 字符串 \"{s}\" 转为布尔值"));
+                    *value = Value::Bool(b);
+                }
+            }
+        }
+        Some("string") => {
+            let converted = match value {
+                Value::Number(n) => Some(n.to_string()),
+                Value::Bool(b) => Some(b.to_string()),
+                _ => None,
+            };
+            if let Some(s) = converted {
+                repairs.push(format!("{display_path}: {s} 转为字符串"));
+                *value = Value::String(s);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repairer() -> ToolCallRepairer {
+        ToolCallRepairer::from_request(&json!({
+            "tools": [
+                {
+                    "name": "Read",
+                    "input_schema": {
+                        "type": "object",
+                        "properties": {
+                            "file_path": {"type": "string"},
+                            "offset": {"type": "integer"},
+                            "limit": {"type": "integer"}
+                        },
+                        "required": ["file_path"],
+                        "additionalProperties": false
+                    }
+                },
+                {
+                    "name": "TodoWrite",
+                    "input_schema": {
+                        "type": "object",
+                        "properties": {
+                            "todos": {
+                                "type": "array",
+                                "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "content": {"type": "string"},
+                                        "done": {"type": "boolean"}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                {"name": "Bash", "input_schema": {"type": "object", "properties": {"command": {"type": "string"}}}}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_request_without_tools() {
+        assert!(ToolCallRepairer::from_request(&json!({"messages": []})).is_none());
+        assert!(ToolCallRepairer::from_request(&json!({"tools": []})).is_none());
+    }
+
+    #[test]
+    fn test_valid_call_is_untouched() {
+        let result = repairer().repair("Read", r#"{"file_path":"/tmp/a.rs"}"#);
+        assert_eq!(result.name, "Read");
+        assert_eq!(result.input, json!({"file_path": "/tmp/a.rs"}));
+        assert!(result.repairs.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_tool_name_maps_to_closest() {
+        let r = repairer();
+        assert_eq!(r.repair("read", "{}").name, "Read");
+        assert_eq!(r.repair("todo_write", "{}").name, "TodoWrite");
+        assert_eq!(r.repair("bash_", "{}").name, "Bash");
+        assert_eq!(r.repair("Reed", "{}").name, "Read");
+        assert_eq!(r.repair("TodoWrit", "{}").name, "TodoWrite");
+    }
+
+    #[test]
+    fn test_far_off_tool_name_is_kept() {
+        let r = repairer();
+        let result = r.repair("delete_repo", r#"{"repo":"x"}"#);
+        assert_eq!(result.name, "delete_repo");
+        // 不按其他工具的 schema 改写参数
+        assert_eq!(result.input, json!({"repo": "x"}));
+        assert!(result.repairs.is_empty());
+        assert_eq!(r.repair("bash_exec", "{}").name, "bash_exec");
+    }
+
+    #[test]
+    fn test_repair_truncated_json() {
+        let result = repairer().repair("Bash", r#"{"command": "ls -la"#);
+        assert_eq!(result.input, json!({"command": "ls -la"}));
+        assert_eq!(result.repairs.len(), 1);
+    }
+
+    #[test]
+    fn test_repair_json_variants() {
+        assert_eq!(repair_json(""), Some(json!({})));
+        assert_eq!(repair_json(r#"{"a": 1,}"#), Some(json!({"a": 1})));
+        assert_eq!(repair_json(r#"{"a": [1, 2,]"#), Some(json!({"a": [1, 2]})));
+        assert_eq!(
+            repair_json("```json\n{\"a\": \"b\"}\n```"),
+            Some(json!({"a": "b"}))
+        );
+        assert_eq!(repair_json(r#"{"a": 1}{"a": 1}"#), Some(json!({"a": 1})));
+        assert_eq!(repair_json(r#"{"a":"#), Some(json!({"a": null})));
+        assert_eq!(repair_json(r#"{"s": "x, y}"#), Some(json!({"s": "x, y}"})));
+        assert_eq!(repair_json("not json at all"), None);
+    }
+
+    #[test]
+    fn test_schema_coercion() {
+        let result = repairer().repair(
+            "Read",
+            r#"{"file_path": "/a", "offset": "10", "limit": 5, "extra": true}"#,
+        );
+        assert_eq!(
+            result.input,
+            json!({"file_path": "/a", "offset": 10, "limit": 5})
+        );
+        assert_eq!(result.repairs.len(), 2);
+    }
+
+    #[test]
+    fn test_schema_coercion_nested() {
+        let result = repairer().repair(
+            "TodoWrite",
+            r#"{"todos": "[{\"content\": 1, \"done\": \"false\"}]"}"#,
+        );
+        assert_eq!(
+            result.input,
+            json!({"todos": [{"content": "1", "done": false}]})
+        );
+    }
+
+    #[test]
+    fn test_repair_openai_response() {
+        let mut body = json!({
+            "choices": [{
+                "message": {
+                    "role": "assistant",
+                    "tool_calls": [
+                        {"id": "a", "type": "function", "function": {"name": "bash", "arguments": "{\"command\": \"pwd\""}},
+                        {"id": "b", "type": "function", "function": {"name": "Read", "arguments": "{\"file_path\": \"/x\"}"}}
+                    ]
+                }
+            }]
+        });
+
+        let count = repairer().repair_openai_response(&mut body);
+        assert_eq!(count, 2);
+        let calls = &body["choices"][0]["message"]["tool_calls"];
+        assert_eq!(calls[0]["function"]["name"], "Bash");
+        assert_eq!(calls[0]["function"]["arguments"], r#"{"command":"pwd"}"#);
+        assert_eq!(calls[1]["function"]["arguments"], "{\"file_path\": \"/x\"}");
+    }
+}
@@ -176,6 +176,8 @@ fn convert_message_to_openai(
     if let Some(blocks) = content.as_array() {
         let mut content_parts = Vec::new();
         let mut tool_calls = Vec::new();
+        let mut reasoning_text = String::new();
+        let mut reasoning_details = Vec::new();
 
         for block in blocks {
             let block_type = block.get("type").and_then(|t| t.as_str()).unwrap_or("");
@@ -187,18 +189,13 @@ fn convert_message_to_openai(
                     }
                 }
                 "image" => {
-                    if let Some(source) = block.get("source") {
-                        let media_type = source
-                            .get("media_type")
-                            .and_then(|m| m.as_str())
-                            .unwrap_or("image/png");
-                        let data = source.get("data").and_then(|d| d.as_str()).unwrap_or("");
-                        content_parts.push(json!({
-                            "type": "image_url",
-                            "image_url": {"url": format!("data:{};base64,{}", media_type, data)}
-                        }));
+                    if let Some(part) = convert_image_to_openai(block) {
+                        content_parts.push(part);
                     }
                 }
+                "document" => {
+                    content_parts.extend(convert_document_to_openai(block));
+                }
                 "tool_use" => {
                     let id = block.get("id").and_then(|i| i.as_str()).unwrap_or("");
                     let name = block.get("name").and_then(|n| n.as_str()).unwrap_or("");
@@ -214,31 +211,41 @@ fn convert_message_to_openai(
                 }
                 "tool_result" => {
                     // tool_result 变成单独的 tool role 消息
+                    // OpenAI 的 tool 消息只支持文本，图片/文档挂到随后的 user 消息中
                     let tool_use_id = block
                         .get("tool_use_id")
                         .and_then(|i| i.as_str())
                         .unwrap_or("");
-                    let content_val = block.get("content");
-                    let content_str = match content_val {
-                        Some(Value::String(s)) => s.clone(),
-                        Some(v) => serde_json::to_string(v).unwrap_or_default(),
-                        None => String::new(),
-                    };
+                    let (content_str, media_parts) =
+                        convert_tool_result_content(block.get("content"));
                     result.push(json!({
                         "role": "tool",
                         "tool_call_id": tool_use_id,
                         "content": content_str
                     }));
+                    content_parts.extend(media_parts);
                 }
                 "thinking" => {
-                    // 跳过 thinking blocks
+                    // thinking → reasoning（保留 signature 以便回传给上游）
+                    let thinking = block.get("thinking").and_then(|t| t.as_str()).unwrap_or("");
+                    reasoning_text.push_str(thinking);
+                    let mut detail = json!({"type": "reasoning.text", "text": thinking});
+                    if let Some(signature) = block.get("signature").and_then(|s| s.as_str()) {
+                        detail["signature"] = json!(signature);
+                    }
+                    reasoning_details.push(detail);
+                }
+                "redacted_thinking" => {
+                    // redacted_thinking 只能以加密形式透传
+                    let data = block.get("data").and_then(|d| d.as_str()).unwrap_or("");
+                    reasoning_details.push(json!({"type": "reasoning.encrypted", "data": data}));
                 }
                 _ => {}
             }
         }
 
         // 添加带内容和/或工具调用的消息
-        if !content_parts.is_empty() || !tool_calls.is_empty() {
+        if !content_parts.is_empty() || !tool_calls.is_empty() || !reasoning_details.is_empty() {
             let mut msg = json!({"role": role});
 
             // 内容处理
@@ -254,6 +261,12 @@ fn convert_message_to_openai(
                 msg["content"] = json!(content_parts);
             }
 
+            // 推理内容
+            if !reasoning_details.is_empty() {
+                msg["reasoning"] = json!(reasoning_text);
+                msg["reasoning_details"] = json!(reasoning_details);
+            }
+
             // 工具调用
             if !tool_calls.is_empty() {
                 msg["tool_calls"] = json!(tool_calls);
@@ -270,6 +283,224 @@ fn convert_message_to_openai(
     Ok(result)
 }
 
+/// Anthropic image 块 → OpenAI image_url 部分
+///
+/// 支持 base64 和 url 两种 source
+fn convert_image_to_openai(block: &Value) -> Option<Value> {
+    let source = block.get("source")?;
+    let url = match source.get("type").and_then(|t| t.as_str()) {
+        Some("url") => source.get("url").and_then(|u| u.as_str())?.to_string(),
+        _ => {
+            let media_type = source
+                .get("media_type")
+                .and_then(|m| m.as_str())
+                .unwrap_or("image/png");
+            let data = source.get("data").and_then(|d| d.as_str()).unwrap_or("");
+            format!("data:{media_type};base64,{data}")
+        }
+    };
+    Some(json!({"type": "image_url", "image_url": {"url": url}}))
+}
+
+/// Anthropic document 块 → OpenAI 内容部分
+///
+/// - base64/url PDF → `file` 部分
+/// - text source → `text` 部分
+/// - content source → 逐块展开
+fn convert_document_to_openai(block: &Value) -> Vec<Value> {
+    let Some(source) = block.get("source") else {
+        return Vec::new();
+    };
+    let title = block.get("title").and_then(|t| t.as_str());
+
+    match source.get("type").and_then(|t| t.as_str()) {
+        Some("text") => source
+            .get("data")
+            .and_then(|d| d.as_str())
+            .map(|text| vec![json!({"type": "text", "text": text})])
+            .unwrap_or_default(),
+        Some("content") => match source.get("content") {
+            Some(Value::String(text)) => vec![json!({"type": "text", "text": text})],
+            Some(Value::Array(blocks)) => blocks
+                .iter()
+                .filter_map(|b| match b.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => b
+                        .get("text")
+                        .and_then(|t| t.as_str())
+                        .map(|text| json!({"type": "text", "text": text})),
+                    Some("image") => convert_image_to_openai(b),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        },
+        Some("url") => source
+            .get("url")
+            .and_then(|u| u.as_str())
+            .map(|url| {
+                vec![json!({
+                    "type": "file",
+                    "file": {
+                        "filename": title.unwrap_or("document.pdf"),
+                        "file_data": url
+                    }
+                })]
+            })
+            .unwrap_or_default(),
+        _ => {
+            let media_type = source
+                .get("media_type")
+                .and_then(|m| m.as_str())
+                .unwrap_or("application/pdf");
+            let data = source.get("data").and_then(|d| d.as_str()).unwrap_or("");
+            vec![json!({
+                "type": "file",
+                "file": {
+                    "filename": title.unwrap_or("document.pdf"),
+                    "file_data": format!("data:{media_type};base64,{data}")
+                }
+            })]
+        }
+    }
+}
+
+/// 转换 tool_result 内容
+///
+/// 返回 (文本内容, 需要放入后续 user 消息的多媒体部分)
+fn convert_tool_result_content(content: Option<&Value>) -> (String, Vec<Value>) {
+    match content {
+        Some(Value::String(s)) => (s.clone(), Vec::new()),
+        Some(Value::Array(blocks)) => {
+            let mut texts = Vec::new();
+            let mut media = Vec::new();
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        if let Some(text) = block.get("text").and_then(|t| t.as_str()) {
+                            texts.push(text.to_string());
+                        }
+                    }
+                    Some("image") => media.extend(convert_image_to_openai(block)),
+                    Some("document") => media.extend(convert_document_to_openai(block)),
+                    _ => texts.push(serde_json::to_string(block).unwrap_or_default()),
+                }
+            }
+            (texts.join("\n"), media)
+        }
+        Some(v) => (serde_json::to_string(v).unwrap_or_default(), Vec::new()),
+        None => (String::new(), Vec::new()),
+    }
+}
+
+/// OpenAI image_url 部分 → Anthropic image 块
+///
+/// data URL 还原为 base64 source，其余作为 url source
+fn convert_image_url_to_anthropic(part: &Value) -> Option<Value> {
+    let url = part
+        .get("image_url")
+        .and_then(|i| i.get("url").or(Some(i)))
+        .and_then(|u| u.as_str())?;
+
+    let source = match parse_data_url(url) {
+        Some((media_type, data)) => {
+            json!({"type": "base64", "media_type": media_type, "data": data})
+        }
+        None => json!({"type": "url", "url": url}),
+    };
+    Some(json!({"type": "image", "source": source}))
+}
+
+/// OpenAI file 部分 → Anthropic document 块
+fn convert_file_to_anthropic(part: &Value) -> Option<Value> {
+    let file = part.get("file")?;
+    let file_data = file.get("file_data").and_then(|d| d.as_str())?;
+
+    let source = match parse_data_url(file_data) {
+        Some((media_type, data)) => {
+            json!({"type": "base64", "media_type": media_type, "data": data})
+        }
+        None => json!({"type": "url", "url": file_data}),
+    };
+    let mut block = json!({"type": "document", "source": source});
+    if let Some(filename) = file.get("filename").and_then(|f| f.as_str()) {
+        block["title"] = json!(filename);
+    }
+    Some(block)
+}
+
+/// 解析 `data:<media_type>;base64,<data>` 格式的 URL
+fn parse_data_url(url: &str) -> Option<(&str, &str)> {
+    let rest = url.strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    let media_type = meta.strip_suffix(";base64")?;
+    Some((media_type, data))
+}
+
+/// 从 OpenAI 消息中提取推理内容 → Anthropic thinking/redacted_thinking 块
+///
+/// 优先使用 `reasoning_details`（带 signature），其次 `reasoning_content`（DeepSeek 等），
+/// 最后 `reasoning`（OpenRouter）
+fn convert_reasoning_to_anthropic(message: &Value) -> Vec<Value> {
+    if let Some(details) = message.get("reasoning_details").and_then(|d| d.as_array()) {
+        let blocks: Vec<Value> = details
+            .iter()
+            .filter_map(|detail| match detail.get("type").and_then(|t| t.as_str()) {
+                Some("reasoning.text") => {
+                    let text = detail.get("text").and_then(|t| t.as_str()).unwrap_or("");
+                    let mut block = json!({"type": "thinking", "thinking": text});
+                    if let Some(signature) = detail.get("signature").and_then(|s| s.as_str()) {
+                        block["signature"] = json!(signature);
+                    }
+                    Some(block)
+                }
+                Some("reasoning.summary") => detail
+                    .get("summary")
+                    .and_then(|s| s.as_str())
+                    .map(|summary| json!({"type": "thinking", "thinking": summary})),
+                Some("reasoning.encrypted") => detail
+                    .get("data")
+                    .and_then(|d| d.as_str())
+                    .map(|data| json!({"type": "redacted_thinking", "data": data})),
+                _ => None,
+            })
+            .collect();
+        if !blocks.is_empty() {
+            return blocks;
+        }
+    }
+
+    message
+        .get("reasoning_content")
+        .and_then(|r| r.as_str())
+        .or_else(|| message.get("reasoning").and_then(|r| r.as_str()))
+        .filter(|r| !r.is_empty())
+        .map(|reasoning| vec![json!({"type": "thinking", "thinking": reasoning})])
+        .unwrap_or_default()
+}
+
+/// OpenAI 消息内容 → Anthropic 内容块（不含工具调用）
+fn convert_openai_content_to_anthropic(content: Option<&Value>) -> Vec<Value> {
+    match content {
+        Some(Value::String(text)) if !text.is_empty() => {
+            vec![json!({"type": "text", "text": text})]
+        }
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| match part.get("type").and_then(|t| t.as_str()) {
+                Some("text") => part
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .filter(|t| !t.is_empty())
+                    .map(|text| json!({"type": "text", "text": text})),
+                Some("image_url") => convert_image_url_to_anthropic(part),
+                Some("file") => convert_file_to_anthropic(part),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
 /// 清理 JSON schema（移除不支持的 format）
 fn clean_schema(mut schema: Value) -> Value {
     if let Some(obj) = schema.as_object_mut() {
@@ -307,13 +538,15 @@ pub fn openai_to_anthropic(body: Value) -> Result<Value, ProxyError> {
         .get("message")
         .ok_or_else(|| ProxyError::TransformError("No message in choice".to_string()))?;
 
-    let mut content = Vec::new();
+    // 推理内容（thinking 块必须位于最前）
+    let mut content = convert_reasoning_to_anthropic(message);
 
-    // 文本内容
-    if let Some(text) = message.get("content").and_then(|c| c.as_str()) {
-        if !text.is_empty() {
-            content.push(json!({"type": "text", "text": text}));
-        }
+    // 文本/多模态内容
+    content.extend(convert_openai_content_to_anthropic(message.get("content")));
+
+    // 生成的图片（OpenRouter images 字段）
+    if let Some(images) = message.get("images").and_then(|i| i.as_array()) {
+        content.extend(images.iter().filter_map(convert_image_url_to_anthropic));
     }
 
     // 工具调用
@@ -637,4 +870,157 @@ mod tests {
         // 应该使用普通模型
         assert_eq!(result["model"], "anthropic/claude-sonnet-4.5");
     }
+
+    fn load_fixture(content: &str) -> Value {
+        serde_json::from_str(content).expect("invalid fixture json")
+    }
+
+    #[test]
+    fn test_anthropic_to_openai_multimodal_fixture() {
+        let provider = create_openrouter_provider();
+        let input = load_fixture(include_str!(
+            "../../../tests/fixtures/transform/anthropic_multimodal_request.json"
+        ));
+
+        let result = anthropic_to_openai(input, &provider).unwrap();
+        let messages = result["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 5);
+
+        // 用户消息：文本 + base64 图片 + URL 图片 + PDF + 文本文档
+        let parts = messages[1]["content"].as_array().unwrap();
+        assert_eq!(parts.len(), 5);
+        assert_eq!(parts[1]["type"], "image_url");
+        assert!(parts[1]["image_url"]["url"]
+            .as_str()
+            .unwrap()
+            .starts_with("data:image/png;base64,iVBOR"));
+        assert_eq!(
+            parts[2]["image_url"]["url"],
+            "https://example.com/mockup.jpg"
+        );
+        assert_eq!(parts[3]["type"], "file");
+        assert_eq!(parts[3]["file"]["filename"], "spec.pdf");
+        assert!(parts[3]["file"]["file_data"]
+            .as_str()
+            .unwrap()
+            .starts_with("data:application/pdf;base64,JVBER"));
+        assert_eq!(parts[4]["text"], "Buttons must use the primary color.");
+
+        // 助手消息：thinking/redacted_thinking → reasoning
+        let assistant = &messages[2];
+        assert_eq!(
+            assistant["reasoning"],
+            "The user wants a visual diff. I should look at the rendered page first."
+        );
+        assert_eq!(assistant["reasoning_details"][0]["type"], "reasoning.text");
+        assert_eq!(
+            assistant["reasoning_details"][0]["signature"],
+            "EqQBCkYIARgCIkDm1w0Tu0pJeYdsKcJQ"
+        );
+        assert_eq!(
+            assistant["reasoning_details"][1]["type"],
+            "reasoning.encrypted"
+        );
+        assert_eq!(assistant["content"], "Let me capture the current page.");
+        assert_eq!(assistant["tool_calls"][0]["function"]["name"], "Screenshot");
+
+        // tool_result：文本进入 tool 消息，图片挂到随后的 user 消息
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["content"], "Captured 1280x720 screenshot.");
+        assert_eq!(messages[4]["role"], "user");
+        assert!(messages[4]["content"][0]["image_url"]["url"]
+            .as_str()
+            .unwrap()
+            .starts_with("data:image/jpeg;base64,"));
+    }
+
+    #[test]
+    fn test_assistant_message_round_trip_fixture() {
+        let provider = create_openrouter_provider();
+        let input = load_fixture(include_str!(
+            "../../../tests/fixtures/transform/anthropic_multimodal_request.json"
+        ));
+        let original = input["messages"][1]["content"].clone();
+
+        let openai_request = anthropic_to_openai(input, &provider).unwrap();
+        let openai_response = json!({
+            "id": "gen-round-trip",
+            "model": "anthropic/claude-sonnet-4.5",
+            "choices": [{
+                "index": 0,
+                "message": openai_request["messages"][2].clone(),
+                "finish_reason": "tool_calls"
+            }]
+        });
+
+        let result = openai_to_anthropic(openai_response).unwrap();
+        assert_eq!(result["content"], original);
+    }
+
+    #[test]
+    fn test_user_content_round_trip_fixture() {
+        let provider = create_openrouter_provider();
+        let input = load_fixture(include_str!(
+            "../../../tests/fixtures/transform/anthropic_multimodal_request.json"
+        ));
+        let original = input["messages"][0]["content"].as_array().unwrap().clone();
+
+        let openai_request = anthropic_to_openai(input, &provider).unwrap();
+        let blocks =
+            convert_openai_content_to_anthropic(Some(&openai_request["messages"][1]["content"]));
+
+        // 图片与 PDF 完整还原
+        assert_eq!(blocks[0], original[0]);
+        assert_eq!(blocks[1], original[1]);
+        assert_eq!(blocks[2], original[2]);
+        assert_eq!(blocks[3], original[3]);
+        // 文本文档降级为文本块
+        assert_eq!(blocks[4]["type"], "text");
+        assert_eq!(blocks[4]["text"], "Buttons must use the primary color.");
+    }
+
+    #[test]
+    fn test_openai_to_anthropic_reasoning_details_fixture() {
+        let input = load_fixture(include_str!(
+            "../../../tests/fixtures/transform/openrouter_reasoning_response.json"
+        ));
+
+        let result = openai_to_anthropic(input).unwrap();
+        let content = result["content"].as_array().unwrap();
+        assert_eq!(content.len(), 4);
+        assert_eq!(content[0]["type"], "thinking");
+        assert_eq!(content[0]["signature"], "ErcBCkgIBhABGAIiQLm5c2iD");
+        assert_eq!(content[1]["type"], "redacted_thinking");
+        assert_eq!(content[1]["data"], "EmwKAhgBEgyXq8L0cnRlZGFjdGVk");
+        assert_eq!(content[2]["type"], "text");
+        assert_eq!(content[3]["type"], "tool_use");
+        assert_eq!(content[3]["input"]["new_string"], "var(--primary)");
+        assert_eq!(result["stop_reason"], "tool_use");
+    }
+
+    #[test]
+    fn test_openai_to_anthropic_reasoning_content_fixture() {
+        let input = load_fixture(include_str!(
+            "../../../tests/fixtures/transform/deepseek_reasoning_content_response.json"
+        ));
+
+        let result = openai_to_anthropic(input).unwrap();
+        assert_eq!(result["content"][0]["type"], "thinking");
+        assert_eq!(
+            result["content"][0]["thinking"],
+            "9.11 vs 9.8: compare the tenths digit, 1 < 8."
+        );
+        assert!(result["content"][0].get("signature").is_none());
+        assert_eq!(result["content"][1]["text"], "9.8 is larger.");
+    }
+
+    #[test]
+    fn test_parse_data_url() {
+        assert_eq!(
+            parse_data_url("data:image/webp;base64,UklGR"),
+            Some(("image/webp", "UklGR"))
+        );
+        assert_eq!(parse_data_url("https://example.com/a.png"), None);
+        assert_eq!(parse_data_url("data:text/plain,hello"), None);
+    }
 }
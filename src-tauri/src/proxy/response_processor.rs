@@ -337,6 +337,8 @@ async fn log_usage_internal(
         first_token_ms,
         status_code,
         None,
+        None,
+        None,
         None, // provider_type
         is_streaming,
     ) {
@@ -2,9 +2,13 @@
 //!
 //! 基于Axum的HTTP服务器，处理代理请求
 
-use super::{handlers, types::*, ProxyError};
 use super::circuit_recovery::CircuitRecoveryChecker;
+use super::gemini_oauth::GeminiTokenManager;
+use super::lanes::{self, LaneScheduler};
 use super::provider_router::ProviderRouter;
+use super::session::ClientSessionResolver;
+use super::shadow::ShadowMirror;
+use super::{handlers, types::*, ProxyError};
 use crate::database::Database;
 use axum::{
     routing::{get, post},
@@ -29,6 +33,14 @@ pub struct ProxyState {
     pub current_providers: Arc<RwLock<std::collections::HashMap<String, (String, String)>>>,
     /// 当前活跃连接数（原子计数器，无需锁）
     pub active_connections: Arc<AtomicUsize>,
+    /// Gemini OAuth token 管理器（跨请求共享，保证刷新单飞）
+    pub gemini_tokens: Arc<GeminiTokenManager>,
+    /// 优先级通道调度器
+    pub lanes: Arc<LaneScheduler>,
+    /// 影子流量镜像器
+    pub shadow: Arc<ShadowMirror>,
+    /// 客户端会话识别器（会话 ID / 项目目录）
+    pub sessions: Arc<ClientSessionResolver>,
 }
 
 /// 代理HTTP服务器
@@ -52,6 +64,12 @@ impl ProxyServer {
             start_time: Arc::new(RwLock::new(None)),
             current_providers: Arc::new(RwLock::new(std::collections::HashMap::new())),
             active_connections: Arc::new(AtomicUsize::new(0)),
+            gemini_tokens: Arc::new(GeminiTokenManager::with_token_endpoint(
+                config.gemini_token_endpoint.clone(),
+            )),
+            lanes: Arc::new(LaneScheduler::new(&config)),
+            shadow: Arc::new(ShadowMirror::new()),
+            sessions: Arc::new(ClientSessionResolver::new()),
         };
 
         Self {
@@ -162,6 +180,10 @@ impl ProxyServer {
         // 读取当前活跃连接数
         status.active_connections = self.state.active_connections.load(Ordering::Relaxed);
 
+        // 优先级通道排队情况
+        status.lanes = self.state.lanes.snapshot();
+        status.queued_requests = status.lanes.iter().map(|l| l.queued).sum();
+
         status
     }
 
@@ -172,9 +194,6 @@ impl ProxyServer {
             .allow_headers(Any);
 
         Router::new()
-            // 健康检查
-            .route("/health", get(handlers::health_check))
-            .route("/status", get(handlers::get_status))
             // Claude API (支持带前缀和不带前缀两种格式)
             .route("/v1/messages", post(handlers::handle_messages))
             .route("/claude/v1/messages", post(handlers::handle_messages))
@@ -193,6 +212,14 @@ impl ProxyServer {
             // Gemini API (支持带前缀和不带前缀)
             .route("/v1beta/*path", post(handlers::handle_gemini))
             .route("/gemini/v1beta/*path", post(handlers::handle_gemini))
+            // 代理路由经过优先级通道排队
+            .route_layer(axum::middleware::from_fn_with_state(
+                self.state.clone(),
+                lanes::lane_middleware,
+            ))
+            // 健康检查
+            .route("/health", get(handlers::health_check))
+            .route("/status", get(handlers::get_status))
             .layer(cors)
             .with_state(self.state.clone())
     }
@@ -200,5 +227,9 @@ impl ProxyServer {
     /// 在不重启服务的情况下更新运行时配置
     pub async fn apply_runtime_config(&self, config: &ProxyConfig) {
         *self.state.config.write().await = config.clone();
+        self.state.lanes.reconfigure(config);
+        self.state
+            .gemini_tokens
+            .set_token_endpoint(config.gemini_token_endpoint.clone());
     }
 }
@@ -2,9 +2,17 @@
 //!
 //! 为每个代理请求创建会话上下文，在整个请求生命周期中跟踪状态和元数据。
 
-use std::time::Instant;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// 携带客户端会话 ID 的请求头（按优先级排列）
+const SESSION_ID_HEADERS: &[&str] = &["x-claude-code-session-id", "x-session-id", "session_id"];
+
 /// 客户端请求格式
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(dead_code)]
@@ -176,6 +184,200 @@ impl ProxySession {
     }
 }
 
+/// 客户端会话上下文
+///
+/// 记录发起请求的 CLI 会话（如 Claude Code 会话 ID）及其工作目录，用于按会话 / 项目归集成本
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientSession {
+    /// 客户端会话 ID
+    pub session_id: Option<String>,
+    /// 项目目录（客户端工作目录）
+    pub project_path: Option<String>,
+    /// 请求指纹（模型 + 最后一条消息的 SHA-256 前 16 位）
+    pub request_hash: Option<String>,
+}
+
+impl ClientSession {
+    /// 从请求头和请求体中提取会话 ID、工作目录与请求指纹
+    ///
+    /// - 会话 ID：优先读取会话请求头，其次解析 Claude Code 的 `metadata.user_id`（`..._session_<uuid>`）
+    /// - 工作目录：解析系统提示中的 `Working directory:` 行或 Codex 的 `<cwd>` 环境上下文
+    /// - 请求指纹：同一会话内指纹反复出现通常意味着代理循环
+    pub fn from_request(headers: &axum::http::HeaderMap, body: &serde_json::Value) -> Self {
+        let session_id = SESSION_ID_HEADERS
+            .iter()
+            .filter_map(|name| headers.get(*name))
+            .filter_map(|value| value.to_str().ok())
+            .map(str::trim)
+            .find(|value| is_valid_session_id(value))
+            .map(str::to_string)
+            .or_else(|| {
+                body.pointer("/metadata/user_id")
+                    .and_then(|v| v.as_str())
+                    .and_then(|user_id| user_id.rsplit_once("_session_"))
+                    .map(|(_, id)| id.trim())
+                    .filter(|id| is_valid_session_id(id))
+                    .map(str::to_string)
+            });
+
+        let project_path = ["system", "instructions", "input"]
+            .iter()
+            .filter_map(|key| body.get(*key))
+            .find_map(find_working_directory);
+
+        Self {
+            session_id,
+            project_path,
+            request_hash: request_fingerprint(body),
+        }
+    }
+}
+
+/// 计算请求指纹：模型 + `messages` / `contents` / `input` 的最后一项
+fn request_fingerprint(body: &serde_json::Value) -> Option<String> {
+    let last =
+        ["messages", "contents", "input"]
+            .iter()
+            .find_map(|key| match body.get(*key)? {
+                serde_json::Value::Array(items) => items.last(),
+                value => Some(value),
+            })?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(body.get("model").and_then(|m| m.as_str()).unwrap_or(""));
+    hasher.update([0]);
+    hasher.update(last.to_string());
+    Some(format!("{:x}", hasher.finalize())[..16].to_string())
+}
+
+/// 会话 ID 仅允许字母、数字、`-` 与 `_`（同时用作会话文件名，避免路径穿越）
+fn is_valid_session_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// 在 JSON 值的文本内容中查找工作目录
+fn find_working_directory(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => parse_working_directory(text),
+        serde_json::Value::Array(items) => items.iter().find_map(find_working_directory),
+        serde_json::Value::Object(map) => map.values().find_map(find_working_directory),
+        _ => None,
+    }
+}
+
+/// 解析文本中的工作目录（`Working directory: /path` 或 `<cwd>/path</cwd>`）
+fn parse_working_directory(text: &str) -> Option<String> {
+    let from_line = text.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Working directory:")
+            .map(|path| path.trim().to_string())
+    });
+    let path = from_line.or_else(|| {
+        let start = text.find("<cwd>")? + "<cwd>".len();
+        let end = text[start..].find("</cwd>")? + start;
+        Some(text[start..end].trim().to_string())
+    })?;
+    (!path.is_empty()).then_some(path)
+}
+
+/// 未找到会话记录时的缓存时长（会话文件可能稍后才写入）
+const NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// 客户端会话识别器
+///
+/// 请求中缺少工作目录时，按会话 ID 在 `~/.claude/projects/*/<session>.jsonl` 中查找会话记录，
+/// 读取其中的 `cwd` 作为项目目录（与 `commands/claude.rs::list_projects` 的解析方式一致）
+pub struct ClientSessionResolver {
+    /// 会话 ID -> (项目目录, 缓存时间)；未找到的会话在 `NEGATIVE_CACHE_TTL` 内不再扫描
+    projects: Mutex<HashMap<String, (Option<String>, Instant)>>,
+}
+
+impl ClientSessionResolver {
+    pub fn new() -> Self {
+        Self {
+            projects: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn projects(&self) -> MutexGuard<'_, HashMap<String, (Option<String>, Instant)>> {
+        self.projects.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 识别请求所属的客户端会话与项目目录
+    ///
+    /// 目录扫描在阻塞线程池中进行，且不持有缓存锁
+    pub async fn identify(
+        &self,
+        headers: &axum::http::HeaderMap,
+        body: &serde_json::Value,
+    ) -> ClientSession {
+        let mut client = ClientSession::from_request(headers, body);
+        let Some(session_id) = client.session_id.clone() else {
+            return client;
+        };
+
+        if let Some(path) = &client.project_path {
+            self.projects()
+                .insert(session_id, (Some(path.clone()), Instant::now()));
+            return client;
+        }
+
+        let cached = self.projects().get(&session_id).cloned();
+        match cached {
+            Some((Some(path), _)) => client.project_path = Some(path),
+            Some((None, cached_at)) if cached_at.elapsed() < NEGATIVE_CACHE_TTL => {}
+            _ => {
+                let projects_dir = crate::config::get_claude_config_dir().join("projects");
+                let id = session_id.clone();
+                let found =
+                    tokio::task::spawn_blocking(move || find_session_project(&projects_dir, &id))
+                        .await
+                        .ok()
+                        .flatten();
+                self.projects()
+                    .insert(session_id, (found.clone(), Instant::now()));
+                client.project_path = found;
+            }
+        }
+        client
+    }
+}
+
+impl Default for ClientSessionResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 在 Claude Code 项目目录中查找会话文件，并读取其记录的工作目录
+fn find_session_project(projects_dir: &Path, session_id: &str) -> Option<String> {
+    let file_name = format!("{session_id}.jsonl");
+    std::fs::read_dir(projects_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path().join(&file_name))
+        .filter(|path| path.is_file())
+        .find_map(|path| {
+            let file = std::fs::File::open(path).ok()?;
+            // 部分会话文件首行的 cwd 为空，检查前几行
+            BufReader::new(file)
+                .lines()
+                .take(10)
+                .map_while(Result::ok)
+                .filter_map(|line| serde_json::from_str::<serde_json::Value>(&line).ok())
+                .find_map(|json| {
+                    json.get("cwd")
+                        .and_then(|v| v.as_str())
+                        .filter(|cwd| !cwd.is_empty())
+                        .map(str::to_string)
+                })
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -295,4 +497,95 @@ mod tests {
         assert_eq!(ClientFormat::GeminiCli.as_str(), "gemini_cli");
         assert_eq!(ClientFormat::Unknown.as_str(), "unknown");
     }
+
+    #[test]
+    fn test_client_session_from_claude_code_request() {
+        let body = json!({
+            "model": "claude-sonnet-4",
+            "metadata": {
+                "user_id": "user_abc_account_123_session_0f3c2a9e-1b2c-4d5e-8f90-123456789abc"
+            },
+            "system": [
+                {"type": "text", "text": "You are Claude Code."},
+                {"type": "text", "text": "<env>\nWorking directory: /home/dev/acme\nIs directory a git repo: Yes\n</env>"}
+            ],
+            "messages": []
+        });
+
+        let client = ClientSession::from_request(&axum::http::HeaderMap::new(), &body);
+        assert_eq!(
+            client.session_id.as_deref(),
+            Some("0f3c2a9e-1b2c-4d5e-8f90-123456789abc")
+        );
+        assert_eq!(client.project_path.as_deref(), Some("/home/dev/acme"));
+    }
+
+    #[test]
+    fn test_client_session_header_and_codex_cwd() {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("session_id", "codex-session-1".parse().unwrap());
+        let body = json!({
+            "input": [{
+                "role": "user",
+                "content": [{"type": "input_text", "text": "<environment_context>\n  <cwd>/srv/project</cwd>\n</environment_context>"}]
+            }]
+        });
+
+        let client = ClientSession::from_request(&headers, &body);
+        assert_eq!(client.session_id.as_deref(), Some("codex-session-1"));
+        assert_eq!(client.project_path.as_deref(), Some("/srv/project"));
+
+        // 非法会话 ID 被忽略
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("x-session-id", "../../etc".parse().unwrap());
+        let client = ClientSession::from_request(&headers, &json!({}));
+        assert_eq!(client, ClientSession::default());
+    }
+
+    #[test]
+    fn test_find_session_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let project_dir = dir.path().join("-home-dev-acme");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        std::fs::write(
+            project_dir.join("abc-123.jsonl"),
+            "{\"type\":\"summary\",\"cwd\":null}\n{\"type\":\"user\",\"cwd\":\"/home/dev/acme\"}\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_session_project(dir.path(), "abc-123").as_deref(),
+            Some("/home/dev/acme")
+        );
+        assert_eq!(find_session_project(dir.path(), "missing"), None);
+    }
+
+    #[test]
+    fn test_request_fingerprint() {
+        let request = |model: &str, last: &str| {
+            json!({
+                "model": model,
+                "messages": [
+                    {"role": "user", "content": "hi"},
+                    {"role": "user", "content": last}
+                ]
+            })
+        };
+
+        let a = request_fingerprint(&request("claude-sonnet-4", "run tests")).unwrap();
+        assert_eq!(a.len(), 16);
+        assert_eq!(
+            request_fingerprint(&request("claude-sonnet-4", "run tests")),
+            Some(a.clone())
+        );
+        assert_ne!(
+            request_fingerprint(&request("claude-sonnet-4", "fix build")),
+            Some(a.clone())
+        );
+        assert_ne!(
+            request_fingerprint(&request("claude-opus-4", "run tests")),
+            Some(a)
+        );
+        assert_eq!(request_fingerprint(&json!({"messages": []})), None);
+    }
 }
@@ -0,0 +1,419 @@
+//! 影子流量镜像
+//!
+//! 按应用配置的采样比例，把真实请求异步复制一份发给候选供应商，
+//! 响应读完即丢弃，只把延迟、错误、Token 与成本记入 `proxy_shadow_logs`。
+//!
+//! 影子请求不经过 ProviderRouter：失败既不影响客户端响应，
+//! 也不会计入任何供应商在主路径上的熔断器。
+
+use super::{
+    forwarder::RequestForwarder,
+    providers::get_adapter,
+    server::ProxyState,
+    usage::{
+        calculator::CostCalculator,
+        logger::{RequestLog, UsageLogger},
+        parser::TokenUsage,
+    },
+    ProxyError,
+};
+use crate::app_config::AppType;
+use crate::provider::Provider;
+use futures::StreamExt;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// 影子流量镜像器（在代理服务器生命周期内共享）
+#[derive(Default)]
+pub struct ShadowMirror {
+    /// 每个应用的请求计数，用于按比例均匀采样
+    counters: Mutex<HashMap<String, u64>>,
+}
+
+impl ShadowMirror {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按配置镜像请求（立即返回，影子请求在后台执行）
+    ///
+    /// 影子供应商与本次主供应商相同时跳过
+    pub async fn mirror(
+        &self,
+        state: &ProxyState,
+        app_type: &AppType,
+        endpoint: &str,
+        primary_provider_id: &str,
+        body: &Value,
+        headers: &axum::http::HeaderMap,
+    ) {
+        let app_type_str = app_type.as_str();
+        let config = match state.db.get_shadow_config(app_type_str).await {
+            Ok(Some(config)) if config.enabled => config,
+            Ok(_) => return,
+            Err(e) => {
+                log::warn!("[Shadow] 读取影子流量配置失败: {e}");
+                return;
+            }
+        };
+
+        if config.provider_id == primary_provider_id {
+            return;
+        }
+        if !self.should_sample(app_type_str, config.sample_percent) {
+            return;
+        }
+
+        let provider = match state
+            .db
+            .get_provider_by_id(&config.provider_id, app_type_str)
+        {
+            Ok(Some(provider)) => provider,
+            Ok(None) => {
+                log::warn!("[Shadow] 影子供应商不存在: {}", config.provider_id);
+                return;
+            }
+            Err(e) => {
+                log::warn!("[Shadow] 读取影子供应商失败: {e}");
+                return;
+            }
+        };
+
+        log::debug!(
+            "[Shadow] 镜像 {} 请求到影子供应商: {}",
+            app_type_str,
+            provider.name
+        );
+
+        tokio::spawn(run_shadow_request(
+            state.clone(),
+            app_type.clone(),
+            provider,
+            endpoint.to_string(),
+            body.clone(),
+            headers.clone(),
+        ));
+    }
+
+    /// 确定性均匀采样：每 100 个请求恰好放行 `percent` 个
+    fn should_sample(&self, app_type: &str, percent: u8) -> bool {
+        let percent = u64::from(percent.min(100));
+        if percent == 0 {
+            return false;
+        }
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let n = counters.entry(app_type.to_string()).or_insert(0);
+        let current = *n;
+        *n += 1;
+        (current + 1) * percent / 100 > current * percent / 100
+    }
+}
+
+/// 执行影子请求并记录结果（所有错误只记日志）
+async fn run_shadow_request(
+    state: ProxyState,
+    app_type: AppType,
+    provider: Provider,
+    endpoint: String,
+    body: Value,
+    headers: axum::http::HeaderMap,
+) {
+    let config = state.config.read().await.clone();
+    let forwarder = RequestForwarder::new(
+        state.db.clone(),
+        config.request_timeout,
+        0,
+        state.status.clone(),
+        state.current_providers.clone(),
+        state.gemini_tokens.clone(),
+    );
+
+    let adapter = get_adapter(&app_type);
+    let needs_transform = adapter.needs_transform(&provider);
+    let request_model = request_model(&body, &endpoint);
+    let start = Instant::now();
+
+    let outcome = match forwarder
+        .forward_shadow(&app_type, &provider, &endpoint, &body, &headers)
+        .await
+    {
+        Ok(response) => read_shadow_response(response, start).await,
+        Err(e) => Err(e),
+    };
+
+    let latency_ms = start.elapsed().as_millis() as u64;
+    let (status_code, usage, first_token_ms, is_streaming, error_message) = match outcome {
+        Ok(result) => {
+            let usage = parse_usage(&app_type, &endpoint, needs_transform, &result);
+            (
+                result.status,
+                usage,
+                result.first_token_ms,
+                result.is_sse,
+                None,
+            )
+        }
+        Err(e) => {
+            log::info!("[Shadow] 影子供应商 {} 请求失败: {e}", provider.name);
+            (
+                error_status_code(&e),
+                None,
+                None,
+                false,
+                Some(e.to_string()),
+            )
+        }
+    };
+
+    let model = usage
+        .as_ref()
+        .and_then(|u| u.model.clone())
+        .unwrap_or(request_model);
+    let usage = usage.unwrap_or_default();
+
+    let multiplier = provider
+        .meta
+        .as_ref()
+        .and_then(|m| m.cost_multiplier.as_deref())
+        .and_then(|cm| Decimal::from_str(cm).ok())
+        .unwrap_or(Decimal::ONE);
+
+    let logger = UsageLogger::new(&state.db);
+    let pricing = match logger.get_effective_pricing(&model, &usage, chrono::Utc::now().timestamp())
+    {
+        Ok(pricing) => pricing,
+        Err(e) => {
+            log::warn!("[Shadow] 读取模型定价失败: {e}");
+            None
+        }
+    };
+    let cost = CostCalculator::try_calculate(&usage, pricing.as_ref(), multiplier);
+
+    let log = RequestLog {
+        request_id: uuid::Uuid::new_v4().to_string(),
+        provider_id: provider.id.clone(),
+        app_type: app_type.as_str().to_string(),
+        model,
+        usage,
+        cost,
+        latency_ms,
+        first_token_ms,
+        status_code,
+        error_message,
+        session_id: None,
+        project_path: None,
+        request_hash: None,
+        provider_type: Some(
+            super::providers::ProviderType::from_app_type_and_config(&app_type, &provider)
+                .as_str()
+                .to_string(),
+        ),
+        is_streaming,
+        cost_multiplier: multiplier.to_string(),
+    };
+
+    if let Err(e) = logger.log_shadow_request(&log) {
+        log::warn!("[Shadow] 记录影子请求失败: {e}");
+    }
+}
+
+/// 读取完毕的影子响应
+struct ShadowResponse {
+    status: u16,
+    is_sse: bool,
+    first_token_ms: Option<u64>,
+    /// SSE 事件（流式）
+    events: Vec<Value>,
+    /// 响应 JSON（非流式）
+    body: Option<Value>,
+}
+
+async fn read_shadow_response(
+    response: reqwest::Response,
+    start: Instant,
+) -> Result<ShadowResponse, ProxyError> {
+    let status = response.status().as_u16();
+    let is_sse = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.contains("text/event-stream"))
+        .unwrap_or(false);
+
+    if !is_sse {
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| ProxyError::ForwardFailed(format!("读取影子响应失败: {e}")))?;
+        return Ok(ShadowResponse {
+            status,
+            is_sse,
+            first_token_ms: None,
+            events: Vec::new(),
+            body: serde_json::from_slice(&bytes).ok(),
+        });
+    }
+
+    let mut first_token_ms = None;
+    let mut events = Vec::new();
+    let mut buffer = String::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let bytes =
+            chunk.map_err(|e| ProxyError::ForwardFailed(format!("读取影子响应流失败: {e}")))?;
+        if first_token_ms.is_none() {
+            first_token_ms = Some(start.elapsed().as_millis() as u64);
+        }
+        buffer.push_str(&String::from_utf8_lossy(&bytes));
+        while let Some(pos) = buffer.find("\n\n") {
+            let event_text = buffer[..pos].to_string();
+            buffer = buffer[pos + 2..].to_string();
+            events.extend(parse_sse_data(&event_text));
+        }
+    }
+    events.extend(parse_sse_data(&buffer));
+
+    Ok(ShadowResponse {
+        status,
+        is_sse,
+        first_token_ms,
+        events,
+        body: None,
+    })
+}
+
+fn parse_sse_data(event_text: &str) -> Vec<Value> {
+    event_text
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter(|data| data.trim() != "[DONE]")
+        .filter_map(|data| serde_json::from_str(data).ok())
+        .collect()
+}
+
+/// 按应用和端点选择与主路径一致的 usage 解析方式
+fn parse_usage(
+    app_type: &AppType,
+    endpoint: &str,
+    needs_transform: bool,
+    response: &ShadowResponse,
+) -> Option<TokenUsage> {
+    let openai_format = match app_type {
+        AppType::Claude => needs_transform,
+        AppType::Codex => endpoint.contains("/chat/completions"),
+        AppType::Gemini => false,
+    };
+
+    match (app_type, response.is_sse) {
+        (AppType::Gemini, true) => TokenUsage::from_gemini_stream_chunks(&response.events),
+        (AppType::Gemini, false) => response
+            .body
+            .as_ref()
+            .and_then(TokenUsage::from_gemini_response),
+        (_, true) if openai_format => TokenUsage::from_openai_stream_events(&response.events),
+        (_, false) if openai_format => response
+            .body
+            .as_ref()
+            .and_then(TokenUsage::from_openai_response),
+        (AppType::Codex, true) => TokenUsage::from_codex_stream_events(&response.events),
+        (AppType::Codex, false) => response
+            .body
+            .as_ref()
+            .and_then(TokenUsage::from_codex_response),
+        (_, true) => TokenUsage::from_claude_stream_events(&response.events),
+        (_, false) => response
+            .body
+            .as_ref()
+            .and_then(TokenUsage::from_claude_response),
+    }
+}
+
+/// 请求中的模型名（Gemini 从路径中提取）
+fn request_model(body: &Value, endpoint: &str) -> String {
+    body.get("model")
+        .and_then(|m| m.as_str())
+        .map(str::to_string)
+        .or_else(|| {
+            let path = endpoint.split('?').next().unwrap_or(endpoint);
+            let mut segments = path.split('/').skip_while(|s| *s != "models");
+            segments
+                .nth(1)
+                .map(|s| s.split(':').next().unwrap_or(s).to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn error_status_code(error: &ProxyError) -> u16 {
+    match error {
+        ProxyError::UpstreamError { status, .. } => *status,
+        ProxyError::Timeout(_) => 504,
+        _ => 502,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_should_sample_is_exact_per_app() {
+        let mirror = ShadowMirror::new();
+        let sampled = (0..100)
+            .filter(|_| mirror.should_sample("claude", 10))
+            .count();
+        assert_eq!(sampled, 10);
+
+        let sampled = (0..200)
+            .filter(|_| mirror.should_sample("codex", 25))
+            .count();
+        assert_eq!(sampled, 50);
+
+        assert!((0..50).all(|_| mirror.should_sample("gemini", 100)));
+        assert!(!(0..50).any(|_| mirror.should_sample("gemini-off", 0)));
+    }
+
+    #[test]
+    fn test_request_model() {
+        assert_eq!(
+            request_model(&json!({"model": "claude-sonnet-4-5"}), "/v1/messages"),
+            "claude-sonnet-4-5"
+        );
+        assert_eq!(
+            request_model(
+                &json!({}),
+                "/v1beta/models/gemini-2.5-pro:streamGenerateContent?alt=sse"
+            ),
+            "gemini-2.5-pro"
+        );
+        assert_eq!(request_model(&json!({}), "/v1/responses"), "unknown");
+    }
+
+    #[test]
+    fn test_parse_usage_openai_format_for_transformed_claude() {
+        let response = ShadowResponse {
+            status: 200,
+            is_sse: false,
+            first_token_ms: None,
+            events: Vec::new(),
+            body: Some(json!({
+                "model": "anthropic/claude-sonnet-4.5",
+                "usage": {"prompt_tokens": 120, "completion_tokens": 30}
+            })),
+        };
+
+        let usage = parse_usage(&AppType::Claude, "/v1/messages", true, &response).unwrap();
+        assert_eq!(usage.input_tokens, 120);
+        assert_eq!(usage.output_tokens, 30);
+    }
+
+    #[test]
+    fn test_parse_sse_data_skips_done() {
+        let events = parse_sse_data("event: message\ndata: {\"a\":1}\ndata: [DONE]");
+        assert_eq!(events, vec![json!({"a": 1})]);
+    }
+}
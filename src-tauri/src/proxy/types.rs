@@ -18,6 +18,15 @@ pub struct ProxyConfig {
     /// 是否正在接管 Live 配置
     #[serde(default)]
     pub live_takeover_active: bool,
+    /// 全局并发上限（0 表示不限）
+    #[serde(default)]
+    pub max_concurrent_requests: usize,
+    /// 优先级通道（按顺序匹配，未命中的请求进入 default 通道）
+    #[serde(default)]
+    pub priority_lanes: Vec<PriorityLane>,
+    /// Gemini OAuth token 端点（为空时使用凭证中的 token_uri 或 Google 默认端点）
+    #[serde(default)]
+    pub gemini_token_endpoint: Option<String>,
 }
 
 impl Default for ProxyConfig {
@@ -30,10 +39,51 @@ impl Default for ProxyConfig {
             request_timeout: 300,
             enable_logging: true,
             live_takeover_active: false,
+            max_concurrent_requests: 0,
+            priority_lanes: Vec::new(),
+            gemini_token_endpoint: None,
         }
     }
 }
 
+/// 优先级通道配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriorityLane {
+    /// 通道名称（唯一）
+    pub name: String,
+    /// 调度权重，共享全局并发时按权重分配槽位
+    #[serde(default = "default_lane_weight")]
+    pub weight: u32,
+    /// 通道并发上限（0 表示不限）
+    #[serde(default)]
+    pub max_concurrency: usize,
+    /// 匹配规则，任意一条命中即归入该通道
+    #[serde(default)]
+    pub matchers: Vec<LaneMatcher>,
+}
+
+fn default_lane_weight() -> u32 {
+    1
+}
+
+/// 通道匹配规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LaneMatcher {
+    /// 请求路径前缀，如 "/codex/"
+    RoutePrefix { prefix: String },
+    /// 请求头存在（可选匹配值，忽略大小写）
+    Header {
+        name: String,
+        #[serde(default)]
+        value: Option<String>,
+    },
+    /// User-Agent 包含指定子串（忽略大小写）
+    UserAgent { contains: String },
+    /// 客户端发给代理的 API Key（x-api-key / Bearer / x-goog-api-key / ?key=）
+    VirtualKey { key: String },
+}
+
 /// 代理服务器状态
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ProxyStatus {
@@ -68,6 +118,32 @@ pub struct ProxyStatus {
     /// 当前活跃的代理目标列表
     #[serde(default)]
     pub active_targets: Vec<ActiveTarget>,
+    /// 排队中的请求总数
+    #[serde(default)]
+    pub queued_requests: usize,
+    /// 各优先级通道的排队与并发情况
+    #[serde(default)]
+    pub lanes: Vec<LaneStatus>,
+}
+
+/// 优先级通道运行状态
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LaneStatus {
+    pub name: String,
+    /// 正在处理的请求数
+    pub in_flight: usize,
+    /// 排队中的请求数
+    pub queued: usize,
+    /// 通道并发上限（0 表示不限）
+    pub max_concurrency: usize,
+    /// 已放行的请求数
+    pub dispatched: u64,
+    /// 平均排队时间（毫秒）
+    pub avg_wait_ms: u64,
+    /// 最长排队时间（毫秒）
+    pub max_wait_ms: u64,
+    /// 当前队首请求已等待的时间（毫秒）
+    pub oldest_wait_ms: u64,
 }
 
 /// 活跃的代理目标信息
@@ -108,6 +184,21 @@ pub struct ProviderHealth {
     pub updated_at: String,
 }
 
+/// 影子流量配置（每个应用一条）
+///
+/// 按比例把请求异步镜像到候选供应商，响应被丢弃，仅记录统计用于对比
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShadowConfig {
+    /// 应用类型 (claude/codex/gemini)
+    pub app_type: String,
+    /// 影子供应商 ID
+    pub provider_id: String,
+    /// 采样比例 (0-100)
+    pub sample_percent: u8,
+    /// 是否启用
+    pub enabled: bool,
+}
+
 /// Live 配置备份记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LiveBackup {
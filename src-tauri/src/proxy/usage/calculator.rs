@@ -25,6 +25,64 @@ pub struct ModelPricing {
     pub cache_creation_cost_per_million: Decimal,
 }
 
+/// 定价规则：在满足条件时覆盖模型的基础定价
+///
+/// 条件之间为“且”关系，未设置的条件视为始终满足：
+/// - 阶梯：提示词 Token（input + cache_read + cache_creation）达到 `min_prompt_tokens`，
+///   整个请求按该规则计价（Claude 长上下文、Gemini 2.5 Pro >200k）
+/// - 生效日期：`[effective_from, effective_to)`，Unix 秒，用于按历史价格计费
+/// - 时段：UTC 一天内的分钟 `[start, end)`，支持跨零点（如错峰优惠）
+#[derive(Debug, Clone)]
+pub struct PricingRule {
+    pub min_prompt_tokens: u64,
+    pub effective_from: Option<i64>,
+    pub effective_to: Option<i64>,
+    pub time_window: Option<(u32, u32)>,
+    pub pricing: ModelPricing,
+}
+
+impl PricingRule {
+    /// 规则是否适用于指定用量和时间（Unix 秒）
+    pub fn matches(&self, usage: &TokenUsage, at: i64) -> bool {
+        if prompt_tokens(usage) < self.min_prompt_tokens {
+            return false;
+        }
+        if self.effective_from.is_some_and(|from| at < from) {
+            return false;
+        }
+        if self.effective_to.is_some_and(|to| at >= to) {
+            return false;
+        }
+        if let Some((start, end)) = self.time_window {
+            let minute = (at.rem_euclid(86_400) / 60) as u32;
+            let in_window = if start <= end {
+                minute >= start && minute < end
+            } else {
+                minute >= start || minute < end
+            };
+            if !in_window {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// 同时命中多条规则时的优先级：阶梯越高越优先，其次是带时段的，最后是生效日期较新的
+    fn specificity(&self) -> (u64, bool, i64) {
+        (
+            self.min_prompt_tokens,
+            self.time_window.is_some(),
+            self.effective_from.unwrap_or(i64::MIN),
+        )
+    }
+}
+
+fn prompt_tokens(usage: &TokenUsage) -> u64 {
+    u64::from(usage.input_tokens)
+        + u64::from(usage.cache_read_tokens)
+        + u64::from(usage.cache_creation_tokens)
+}
+
 /// 成本计算器
 pub struct CostCalculator;
 
@@ -75,6 +133,21 @@ impl CostCalculator {
     ) -> Option<CostBreakdown> {
         pricing.map(|p| Self::calculate(usage, p, cost_multiplier))
     }
+
+    /// 选出指定用量和时间下生效的定价（无规则命中时使用基础定价）
+    pub fn select_pricing<'a>(
+        base: &'a ModelPricing,
+        rules: &'a [PricingRule],
+        usage: &TokenUsage,
+        at: i64,
+    ) -> &'a ModelPricing {
+        rules
+            .iter()
+            .filter(|rule| rule.matches(usage, at))
+            .max_by_key(|rule| rule.specificity())
+            .map(|rule| &rule.pricing)
+            .unwrap_or(base)
+    }
 }
 
 impl ModelPricing {
@@ -183,4 +256,81 @@ mod tests {
         assert!(cost.total_cost > Decimal::ZERO);
         assert!(cost.total_cost.to_string().len() > 2); // 确保保留了小数位
     }
+
+    fn rule(min_prompt_tokens: u64, input: &str) -> PricingRule {
+        PricingRule {
+            min_prompt_tokens,
+            effective_from: None,
+            effective_to: None,
+            time_window: None,
+            pricing: ModelPricing::from_strings(input, "15", "0", "0").unwrap(),
+        }
+    }
+
+    fn usage_with_input(input_tokens: u32) -> TokenUsage {
+        TokenUsage {
+            input_tokens,
+            output_tokens: 100,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            model: None,
+        }
+    }
+
+    #[test]
+    fn test_select_pricing_token_tier() {
+        let base = ModelPricing::from_strings("3", "15", "0.3", "3.75").unwrap();
+        let rules = vec![rule(200_000, "6")];
+
+        let small = usage_with_input(150_000);
+        let picked = CostCalculator::select_pricing(&base, &rules, &small, 0);
+        assert_eq!(picked.input_cost_per_million, Decimal::from(3));
+
+        // 缓存 Token 也计入提示词长度
+        let mut large = usage_with_input(150_000);
+        large.cache_read_tokens = 60_000;
+        let picked = CostCalculator::select_pricing(&base, &rules, &large, 0);
+        assert_eq!(picked.input_cost_per_million, Decimal::from(6));
+    }
+
+    #[test]
+    fn test_select_pricing_effective_dates() {
+        let base = ModelPricing::from_strings("3", "15", "0", "0").unwrap();
+        let mut old_price = rule(0, "5");
+        old_price.effective_to = Some(1_700_000_000);
+        let rules = vec![old_price];
+        let usage = usage_with_input(1000);
+
+        let before = CostCalculator::select_pricing(&base, &rules, &usage, 1_699_999_999);
+        assert_eq!(before.input_cost_per_million, Decimal::from(5));
+        let after = CostCalculator::select_pricing(&base, &rules, &usage, 1_700_000_000);
+        assert_eq!(after.input_cost_per_million, Decimal::from(3));
+    }
+
+    #[test]
+    fn test_select_pricing_time_window_wraps_midnight() {
+        let base = ModelPricing::from_strings("2", "8", "0", "0").unwrap();
+        let mut off_peak = rule(0, "1");
+        // UTC 16:30 - 00:30
+        off_peak.time_window = Some((16 * 60 + 30, 30));
+        let mut long_context = rule(200_000, "4");
+        long_context.time_window = None;
+        let rules = vec![off_peak, long_context];
+        let usage = usage_with_input(1000);
+
+        let day = 1_700_006_400; // 2023-11-15 00:00:00 UTC
+        let at = |h: i64, m: i64| day + h * 3600 + m * 60;
+        let pick = |t: i64, u: &TokenUsage| {
+            CostCalculator::select_pricing(&base, &rules, u, t).input_cost_per_million
+        };
+
+        assert_eq!(pick(at(17, 0), &usage), Decimal::from(1));
+        assert_eq!(pick(at(0, 15), &usage), Decimal::from(1));
+        assert_eq!(pick(at(0, 30), &usage), Decimal::from(2));
+        assert_eq!(pick(at(12, 0), &usage), Decimal::from(2));
+
+        // 阶梯规则优先于时段规则
+        let large = usage_with_input(250_000);
+        assert_eq!(pick(at(17, 0), &large), Decimal::from(4));
+    }
 }
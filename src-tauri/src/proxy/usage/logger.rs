@@ -4,7 +4,9 @@ use super::calculator::{CostBreakdown, CostCalculator, ModelPricing};
 use super::parser::TokenUsage;
 use crate::database::Database;
 use crate::error::AppError;
-use crate::services::usage_stats::find_model_pricing_row;
+use crate::provider::ProviderMeta;
+use crate::services::currency::resolve_billing;
+use crate::services::usage_stats::find_effective_pricing;
 use rust_decimal::Decimal;
 use std::time::SystemTime;
 
@@ -22,6 +24,10 @@ pub struct RequestLog {
     pub status_code: u16,
     pub error_message: Option<String>,
     pub session_id: Option<String>,
+    /// 项目目录（客户端工作目录）
+    pub project_path: Option<String>,
+    /// 请求指纹（模型 + 最后一条消息，用于检测重复请求）
+    pub request_hash: Option<String>,
     /// 供应商类型 (claude, claude_auth, codex, gemini, gemini_cli, openrouter)
     pub provider_type: Option<String>,
     /// 是否为流式请求
@@ -42,6 +48,15 @@ impl<'a> UsageLogger<'a> {
 
     /// 记录成功的请求
     pub fn log_request(&self, log: &RequestLog) -> Result<(), AppError> {
+        self.insert_log("proxy_request_logs", log)
+    }
+
+    /// 记录影子请求（单独存表，不计入主统计）
+    pub fn log_shadow_request(&self, log: &RequestLog) -> Result<(), AppError> {
+        self.insert_log("proxy_shadow_logs", log)
+    }
+
+    fn insert_log(&self, table: &str, log: &RequestLog) -> Result<(), AppError> {
         let conn = crate::database::lock_conn!(self.db.conn);
 
         let (input_cost, output_cost, cache_read_cost, cache_creation_cost, total_cost) =
@@ -68,14 +83,36 @@ impl<'a> UsageLogger<'a> {
             .unwrap()
             .as_secs() as i64;
 
+        // 按供应商结算币种同时记录结算成本
+        let meta: ProviderMeta = conn
+            .query_row(
+                "SELECT meta FROM providers WHERE id = ?1 AND app_type = ?2",
+                rusqlite::params![log.provider_id, log.app_type],
+                |row| row.get::<_, String>(0),
+            )
+            .ok()
+            .and_then(|meta| serde_json::from_str(&meta).ok())
+            .unwrap_or_default();
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let billing = resolve_billing(&conn, &meta, &today)?;
+        let total_usd = log
+            .cost
+            .as_ref()
+            .map(|c| c.total_cost)
+            .unwrap_or(Decimal::ZERO);
+        let billing_cost = billing.convert(total_usd).map(|c| c.to_string());
+
         conn.execute(
-            "INSERT INTO proxy_request_logs (
+            &format!(
+                "INSERT INTO {table} (
                 request_id, provider_id, app_type, model,
                 input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
                 input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
                 latency_ms, first_token_ms, status_code, error_message, session_id,
-                provider_type, is_streaming, cost_multiplier, created_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
+                provider_type, is_streaming, cost_multiplier, created_at,
+                billing_currency, billing_rate, billing_cost, project_path, request_hash
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27)"
+            ),
             rusqlite::params![
                 log.request_id,
                 log.provider_id,
@@ -99,6 +136,11 @@ impl<'a> UsageLogger<'a> {
                 log.is_streaming as i64,
                 log.cost_multiplier,
                 created_at,
+                billing.currency,
+                billing.rate.map(|r| r.to_string()),
+                billing_cost,
+                log.project_path,
+                log.request_hash,
             ],
         )
         .map_err(|e| AppError::Database(format!("记录请求日志失败: {e}")))?;
@@ -107,7 +149,7 @@ impl<'a> UsageLogger<'a> {
     }
 
     /// 记录失败的请求
-    #[allow(dead_code, clippy::too_many_arguments)]
+    #[allow(clippy::too_many_arguments)]
     pub fn log_error(
         &self,
         request_id: String,
@@ -117,6 +159,9 @@ impl<'a> UsageLogger<'a> {
         status_code: u16,
         error_message: String,
         latency_ms: u64,
+        session_id: Option<String>,
+        project_path: Option<String>,
+        request_hash: Option<String>,
     ) -> Result<(), AppError> {
         let log = RequestLog {
             request_id,
@@ -129,7 +174,9 @@ impl<'a> UsageLogger<'a> {
             first_token_ms: None,
             status_code,
             error_message: Some(error_message),
-            session_id: None,
+            session_id,
+            project_path,
+            request_hash,
             provider_type: None,
             is_streaming: false,
             cost_multiplier: "1.0".to_string(),
@@ -138,18 +185,15 @@ impl<'a> UsageLogger<'a> {
         self.log_request(&log)
     }
 
-    /// 获取模型定价
-    pub fn get_model_pricing(&self, model_id: &str) -> Result<Option<ModelPricing>, AppError> {
+    /// 获取指定用量在指定时刻（Unix 秒）生效的模型定价（应用阶梯 / 日期 / 时段规则）
+    pub fn get_effective_pricing(
+        &self,
+        model_id: &str,
+        usage: &TokenUsage,
+        at: i64,
+    ) -> Result<Option<ModelPricing>, AppError> {
         let conn = crate::database::lock_conn!(self.db.conn);
-        let row = find_model_pricing_row(&conn, model_id)?;
-        match row {
-            Some((input, output, cache_read, cache_creation)) => {
-                ModelPricing::from_strings(&input, &output, &cache_read, &cache_creation)
-                    .map(Some)
-                    .map_err(|e| AppError::Database(format!("解析定价数据失败: {e}")))
-            }
-            None => Ok(None),
-        }
+        find_effective_pricing(&conn, model_id, usage, at)
     }
 
     /// 计算并记录请求
@@ -166,10 +210,12 @@ impl<'a> UsageLogger<'a> {
         first_token_ms: Option<u64>,
         status_code: u16,
         session_id: Option<String>,
+        project_path: Option<String>,
+        request_hash: Option<String>,
         provider_type: Option<String>,
         is_streaming: bool,
     ) -> Result<(), AppError> {
-        let pricing = self.get_model_pricing(&model)?;
+        let pricing = self.get_effective_pricing(&model, &usage, chrono::Utc::now().timestamp())?;
 
         if pricing.is_none() {
             log::warn!("模型 {model} 的定价信息未找到，成本将记录为 0");
@@ -189,6 +235,8 @@ impl<'a> UsageLogger<'a> {
             status_code,
             error_message: None,
             session_id,
+            project_path,
+            request_hash,
             provider_type,
             is_streaming,
             cost_multiplier: cost_multiplier.to_string(),
@@ -238,6 +286,8 @@ mod tests {
             None,
             200,
             None,
+            None,
+            None,
             Some("claude".to_string()),
             false,
         )?;
@@ -268,6 +318,9 @@ mod tests {
             500,
             "Internal Server Error".to_string(),
             50,
+            None,
+            None,
+            None,
         )?;
 
         // 验证错误记录已插入
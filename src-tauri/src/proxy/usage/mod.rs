@@ -8,7 +8,7 @@ pub mod parser;
 
 // 仅导出内部使用的类型,避免未使用警告
 #[allow(unused_imports)]
-pub use calculator::{CostBreakdown, CostCalculator, ModelPricing};
+pub use calculator::{CostBreakdown, CostCalculator, ModelPricing, PricingRule};
 #[allow(unused_imports)]
 pub use logger::{RequestLog, UsageLogger};
 #[allow(unused_imports)]
@@ -0,0 +1,520 @@
+//! 多币种成本
+//!
+//! 请求成本始终以 USD 计算并存入 `*_cost_usd` 列；供应商可在 meta 中声明结算币种
+//! （`billingCurrency`）和固定汇率（`billingRate`，1 USD 折合多少结算币种），
+//! 记录请求时同时写入结算币种成本，便于与中转站账单对账。
+//!
+//! 未声明固定汇率时按 `exchange_rates` 表中请求当天生效的汇率换算。
+//! 统计查询可指定报表币种：结算币种与报表币种一致的请求直接使用结算成本，
+//! 其余请求按当天汇率由 USD 换算。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::provider::ProviderMeta;
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// 成本计算的基准币种
+pub const BASE_CURRENCY: &str = "USD";
+
+/// 默认报表币种的设置键
+const REPORTING_CURRENCY_SETTING: &str = "reporting_currency";
+
+/// 汇率（1 USD 折合 `rate` 单位的 `currency`），自 `effective_date` 起生效
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeRate {
+    pub currency: String,
+    /// 生效日期，格式 YYYY-MM-DD（UTC）
+    pub effective_date: String,
+    pub rate: String,
+}
+
+/// 请求的结算信息
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Billing {
+    pub currency: String,
+    /// 1 USD 折合的结算币种金额；没有可用汇率时为 None
+    pub rate: Option<Decimal>,
+}
+
+impl Billing {
+    /// 将 USD 成本换算为结算币种
+    pub fn convert(&self, cost_usd: Decimal) -> Option<Decimal> {
+        self.rate.map(|rate| (cost_usd * rate).round_dp(6))
+    }
+}
+
+/// 校验并规范化币种代码（三位字母，大写）
+pub(crate) fn normalize_currency(code: &str) -> Result<String, AppError> {
+    let code = code.trim().to_ascii_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(AppError::InvalidInput(format!(
+            "币种代码无效（应为 ISO 4217 三位字母）: {code}"
+        )));
+    }
+    Ok(code)
+}
+
+/// 查询指定日期生效的汇率；早于最早一条记录时使用最早的汇率
+pub(crate) fn lookup_rate(
+    conn: &Connection,
+    currency: &str,
+    date: &str,
+) -> Result<Option<Decimal>, AppError> {
+    if currency == BASE_CURRENCY {
+        return Ok(Some(Decimal::ONE));
+    }
+
+    let rate: Option<String> = conn
+        .query_row(
+            "SELECT rate FROM exchange_rates
+             WHERE currency = ?1
+             ORDER BY CASE WHEN effective_date <= ?2 THEN 0 ELSE 1 END,
+                      CASE WHEN effective_date <= ?2 THEN effective_date END DESC,
+                      effective_date ASC
+             LIMIT 1",
+            params![currency, date],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| AppError::Database(format!("查询汇率失败: {e}")))?;
+
+    Ok(rate.and_then(|r| Decimal::from_str(&r).ok()))
+}
+
+/// 根据供应商 meta 确定结算币种和汇率
+pub(crate) fn resolve_billing(
+    conn: &Connection,
+    meta: &ProviderMeta,
+    date: &str,
+) -> Result<Billing, AppError> {
+    let currency = meta
+        .billing_currency
+        .as_deref()
+        .and_then(|c| normalize_currency(c).ok())
+        .unwrap_or_else(|| BASE_CURRENCY.to_string());
+
+    let fixed_rate = meta
+        .billing_rate
+        .as_deref()
+        .and_then(|r| Decimal::from_str(r.trim()).ok())
+        .filter(|r| *r > Decimal::ZERO);
+    let rate = match fixed_rate {
+        Some(rate) => Some(rate),
+        None => lookup_rate(conn, &currency, date)?,
+    };
+
+    Ok(Billing { currency, rate })
+}
+
+/// 生成按报表币种计算单条日志成本的 SQL 表达式（日志表别名须为 `l`）
+///
+/// `currency` 必须已经过 [`normalize_currency`] 校验
+pub(crate) fn cost_sql(currency: &str) -> String {
+    if currency == BASE_CURRENCY {
+        return "CAST(l.total_cost_usd AS REAL)".to_string();
+    }
+
+    format!(
+        "(CASE WHEN l.billing_currency = '{currency}' AND l.billing_cost IS NOT NULL
+            THEN CAST(l.billing_cost AS REAL)
+            ELSE CAST(l.total_cost_usd AS REAL) * COALESCE(
+                (SELECT CAST(r.rate AS REAL) FROM exchange_rates r
+                 WHERE r.currency = '{currency}'
+                   AND r.effective_date <= date(l.created_at, 'unixepoch')
+                 ORDER BY r.effective_date DESC LIMIT 1),
+                (SELECT CAST(r.rate AS REAL) FROM exchange_rates r
+                 WHERE r.currency = '{currency}'
+                 ORDER BY r.effective_date ASC LIMIT 1))
+         END)"
+    )
+}
+
+/// UPDATE 成本时同步结算成本的 SQL 片段（`?5` 为新的 USD 总成本）
+pub(crate) const BILLING_COST_UPDATE_SQL: &str =
+    "billing_cost = CASE WHEN billing_rate IS NULL THEN billing_cost
+                         ELSE printf('%.6f', CAST(?5 AS REAL) * CAST(billing_rate AS REAL)) END";
+
+impl Database {
+    /// 获取汇率列表（可按币种过滤），按币种和日期排序
+    pub fn get_exchange_rates(
+        &self,
+        currency: Option<&str>,
+    ) -> Result<Vec<ExchangeRate>, AppError> {
+        let currency = currency.map(normalize_currency).transpose()?;
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn.prepare(
+            "SELECT currency, effective_date, rate FROM exchange_rates
+             WHERE ?1 IS NULL OR currency = ?1
+             ORDER BY currency, effective_date",
+        )?;
+        let rows = stmt.query_map([currency], |row| {
+            Ok(ExchangeRate {
+                currency: row.get(0)?,
+                effective_date: row.get(1)?,
+                rate: row.get(2)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 新增或更新汇率
+    pub fn set_exchange_rate(&self, rate: &ExchangeRate) -> Result<(), AppError> {
+        let currency = normalize_currency(&rate.currency)?;
+        if currency == BASE_CURRENCY {
+            return Err(AppError::InvalidInput(format!(
+                "{BASE_CURRENCY} 为基准币种，无需设置汇率"
+            )));
+        }
+        chrono::NaiveDate::parse_from_str(&rate.effective_date, "%Y-%m-%d").map_err(|_| {
+            AppError::InvalidInput(format!(
+                "生效日期格式无效（应为 YYYY-MM-DD）: {}",
+                rate.effective_date
+            ))
+        })?;
+        let value = Decimal::from_str(rate.rate.trim())
+            .ok()
+            .filter(|r| *r > Decimal::ZERO)
+            .ok_or_else(|| AppError::InvalidInput(format!("汇率无效: {}", rate.rate)))?;
+
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "INSERT OR REPLACE INTO exchange_rates (currency, effective_date, rate)
+             VALUES (?1, ?2, ?3)",
+            params![currency, rate.effective_date, value.normalize().to_string()],
+        )
+        .map_err(|e| AppError::Database(format!("保存汇率失败: {e}")))?;
+        Ok(())
+    }
+
+    /// 删除汇率
+    ///
+    /// 默认报表币种或供应商结算币种的最后一条汇率不可删除，否则相关统计将无法换算
+    pub fn delete_exchange_rate(
+        &self,
+        currency: &str,
+        effective_date: &str,
+    ) -> Result<(), AppError> {
+        let currency = normalize_currency(currency)?;
+        let conn = lock_conn!(self.conn);
+        let remaining: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM exchange_rates WHERE currency = ?1 AND effective_date != ?2",
+                params![currency, effective_date],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if remaining == 0 {
+            ensure_currency_unused(&conn, &currency)?;
+        }
+        conn.execute(
+            "DELETE FROM exchange_rates WHERE currency = ?1 AND effective_date = ?2",
+            params![currency, effective_date],
+        )
+        .map_err(|e| AppError::Database(format!("删除汇率失败: {e}")))?;
+        Ok(())
+    }
+
+    /// 获取默认报表币种（未设置时为 USD）
+    pub fn get_reporting_currency(&self) -> Result<String, AppError> {
+        Ok(self
+            .get_setting(REPORTING_CURRENCY_SETTING)?
+            .and_then(|c| normalize_currency(&c).ok())
+            .unwrap_or_else(|| BASE_CURRENCY.to_string()))
+    }
+
+    /// 设置默认报表币种
+    pub fn set_reporting_currency(&self, currency: &str) -> Result<(), AppError> {
+        let currency = normalize_currency(currency)?;
+        let conn = lock_conn!(self.conn);
+        ensure_convertible(&conn, &currency)?;
+        drop(conn);
+        self.set_setting(REPORTING_CURRENCY_SETTING, &currency)
+    }
+}
+
+/// 确定统计查询使用的报表币种：显式指定优先，否则使用默认报表币种
+pub(crate) fn resolve_report_currency(
+    conn: &Connection,
+    currency: Option<&str>,
+) -> Result<String, AppError> {
+    let currency = match currency {
+        Some(code) => normalize_currency(code)?,
+        None => conn
+            .query_row(
+                "SELECT value FROM settings WHERE key = ?1",
+                [REPORTING_CURRENCY_SETTING],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .and_then(|c| normalize_currency(&c).ok())
+            .unwrap_or_else(|| BASE_CURRENCY.to_string()),
+    };
+    ensure_convertible(conn, &currency)?;
+    Ok(currency)
+}
+
+/// 检查币种是否被默认报表币种或供应商结算币种使用
+fn ensure_currency_unused(conn: &Connection, currency: &str) -> Result<(), AppError> {
+    let reporting = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            [REPORTING_CURRENCY_SETTING],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))?
+        .and_then(|c| normalize_currency(&c).ok());
+    if reporting.as_deref() == Some(currency) {
+        return Err(AppError::InvalidInput(format!(
+            "{currency} 是默认报表币种，不能删除其最后一条汇率"
+        )));
+    }
+
+    let provider: Option<String> = conn
+        .query_row(
+            "SELECT name FROM providers
+             WHERE json_valid(meta)
+               AND upper(trim(json_extract(meta, '$.billingCurrency'))) = ?1
+             LIMIT 1",
+            [currency],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))?;
+    if let Some(name) = provider {
+        return Err(AppError::InvalidInput(format!(
+            "供应商 {name} 以 {currency} 结算，不能删除其最后一条汇率"
+        )));
+    }
+    Ok(())
+}
+
+/// 非基准币种必须至少有一条汇率，否则无法由 USD 换算
+fn ensure_convertible(conn: &Connection, currency: &str) -> Result<(), AppError> {
+    if currency == BASE_CURRENCY {
+        return Ok(());
+    }
+    let has_rate = conn
+        .query_row(
+            "SELECT 1 FROM exchange_rates WHERE currency = ?1 LIMIT 1",
+            [currency],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))?
+        .is_some();
+    if !has_rate {
+        return Err(AppError::InvalidInput(format!(
+            "缺少 {currency} 的汇率，请先添加汇率"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_rate_by_date() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        for (date, rate) in [("2025-01-01", "7.2"), ("2025-06-01", "7.1")] {
+            db.set_exchange_rate(&ExchangeRate {
+                currency: "cny".to_string(),
+                effective_date: date.to_string(),
+                rate: rate.to_string(),
+            })?;
+        }
+
+        let conn = lock_conn!(db.conn);
+        let rate = |date: &str| lookup_rate(&conn, "CNY", date).map(|r| r.map(|d| d.to_string()));
+        assert_eq!(rate("2024-12-31")?.as_deref(), Some("7.2"));
+        assert_eq!(rate("2025-03-15")?.as_deref(), Some("7.2"));
+        assert_eq!(rate("2025-06-01")?.as_deref(), Some("7.1"));
+        assert_eq!(lookup_rate(&conn, "EUR", "2025-06-01")?, None);
+        assert_eq!(lookup_rate(&conn, "USD", "2025-06-01")?, Some(Decimal::ONE));
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_billing_prefers_fixed_rate() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        db.set_exchange_rate(&ExchangeRate {
+            currency: "CNY".to_string(),
+            effective_date: "2025-01-01".to_string(),
+            rate: "7.2".to_string(),
+        })?;
+        let conn = lock_conn!(db.conn);
+
+        let mut meta = ProviderMeta {
+            billing_currency: Some("cny".to_string()),
+            ..Default::default()
+        };
+        let billing = resolve_billing(&conn, &meta, "2025-02-01")?;
+        assert_eq!(billing.currency, "CNY");
+        assert_eq!(
+            billing.convert(Decimal::from(2)),
+            Some(Decimal::from_str("14.4").unwrap())
+        );
+
+        meta.billing_rate = Some("1".to_string());
+        let billing = resolve_billing(&conn, &meta, "2025-02-01")?;
+        assert_eq!(billing.rate, Some(Decimal::ONE));
+
+        let billing = resolve_billing(&conn, &ProviderMeta::default(), "2025-02-01")?;
+        assert_eq!(billing.currency, "USD");
+        assert_eq!(billing.rate, Some(Decimal::ONE));
+        Ok(())
+    }
+
+    #[test]
+    fn test_exchange_rate_validation() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let rate = |currency: &str, date: &str, value: &str| ExchangeRate {
+            currency: currency.to_string(),
+            effective_date: date.to_string(),
+            rate: value.to_string(),
+        };
+        assert!(db
+            .set_exchange_rate(&rate("USD", "2025-01-01", "1"))
+            .is_err());
+        assert!(db
+            .set_exchange_rate(&rate("RMB1", "2025-01-01", "7"))
+            .is_err());
+        assert!(db
+            .set_exchange_rate(&rate("CNY", "2025/01/01", "7"))
+            .is_err());
+        assert!(db
+            .set_exchange_rate(&rate("CNY", "2025-01-01", "-7"))
+            .is_err());
+        assert!(db.set_reporting_currency("EUR").is_err());
+        assert_eq!(db.get_reporting_currency()?, "USD");
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_rate_in_use_cannot_be_deleted() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        for (currency, date) in [
+            ("CNY", "2025-01-01"),
+            ("CNY", "2025-06-01"),
+            ("EUR", "2025-01-01"),
+        ] {
+            db.set_exchange_rate(&ExchangeRate {
+                currency: currency.to_string(),
+                effective_date: date.to_string(),
+                rate: "7".to_string(),
+            })?;
+        }
+        db.set_reporting_currency("CNY")?;
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO providers (id, app_type, name, settings_config, meta)
+                 VALUES ('relay', 'claude', 'Relay', '{}', ?1)",
+                [r#"{"billingCurrency":"eur"}"#],
+            )?;
+        }
+
+        // 仍有其他汇率时可以删除
+        db.delete_exchange_rate("CNY", "2025-06-01")?;
+        assert!(db.delete_exchange_rate("CNY", "2025-01-01").is_err());
+        assert!(db.delete_exchange_rate("EUR", "2025-01-01").is_err());
+        assert_eq!(db.get_exchange_rates(None)?.len(), 2);
+
+        db.set_reporting_currency("USD")?;
+        db.delete_exchange_rate("CNY", "2025-01-01")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_usage_reported_in_billing_currency() -> Result<(), AppError> {
+        use crate::proxy::usage::{logger::UsageLogger, parser::TokenUsage};
+
+        let db = Database::memory()?;
+        db.set_exchange_rate(&ExchangeRate {
+            currency: "CNY".to_string(),
+            effective_date: "2020-01-01".to_string(),
+            rate: "7".to_string(),
+        })?;
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO model_pricing (model_id, display_name, input_cost_per_million, output_cost_per_million)
+                 VALUES ('test-model', 'Test Model', '1', '0')",
+                [],
+            )?;
+            // 中转站按 1 USD = 1 CNY 结算
+            conn.execute(
+                "INSERT INTO providers (id, app_type, name, settings_config, meta)
+                 VALUES ('relay', 'claude', 'Relay', '{}', ?1)",
+                [r#"{"billingCurrency":"CNY","billingRate":"1"}"#],
+            )?;
+        }
+
+        let logger = UsageLogger::new(&db);
+        for (request_id, provider_id) in [("r1", "relay"), ("r2", "official")] {
+            logger.log_with_calculation(
+                request_id.to_string(),
+                provider_id.to_string(),
+                "claude".to_string(),
+                "test-model".to_string(),
+                TokenUsage {
+                    input_tokens: 1_000_000,
+                    ..Default::default()
+                },
+                Decimal::ONE,
+                100,
+                None,
+                200,
+                None,
+                None,
+                None,
+                None,
+                false,
+            )?;
+        }
+
+        {
+            let conn = lock_conn!(db.conn);
+            let (currency, cost): (String, Option<String>) = conn.query_row(
+                "SELECT billing_currency, billing_cost FROM proxy_request_logs WHERE request_id = 'r1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            assert_eq!(currency, "CNY");
+            assert_eq!(cost.as_deref(), Some("1"));
+        }
+
+        // USD：两条各 1 USD
+        let summary = db.get_usage_summary(None, None, Some("usd"))?;
+        assert_eq!(summary.currency, "USD");
+        assert_eq!(summary.total_cost, "2.000000");
+
+        // CNY：中转站按账单 1 CNY，官方按汇率 7 CNY
+        let summary = db.get_usage_summary(None, None, Some("CNY"))?;
+        assert_eq!(summary.currency, "CNY");
+        assert_eq!(summary.total_cost, "8.000000");
+
+        let trends = db.get_daily_trends(1, Some("CNY"))?;
+        let total: f64 = trends
+            .iter()
+            .map(|d| d.total_cost.parse::<f64>().unwrap())
+            .sum();
+        assert!((total - 8.0).abs() < 1e-9);
+
+        let stats = db.get_provider_stats(Some("CNY"))?;
+        let relay = stats.iter().find(|s| s.provider_id == "relay").unwrap();
+        assert_eq!(relay.total_cost, "1.000000");
+
+        assert!(db.get_usage_summary(None, None, Some("EUR")).is_err());
+        db.set_reporting_currency("CNY")?;
+        assert_eq!(db.get_usage_summary(None, None, None)?.currency, "CNY");
+        Ok(())
+    }
+}
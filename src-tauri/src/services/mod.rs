@@ -1,13 +1,21 @@
 pub mod config;
+pub mod currency;
 pub mod env_checker;
 pub mod env_manager;
 pub mod mcp;
+pub mod pricing_import;
 pub mod prompt;
 pub mod provider;
 pub mod proxy;
+pub mod session_usage_import;
 pub mod skill;
 pub mod speedtest;
 pub mod stream_check;
+pub mod usage_analytics;
+pub mod usage_anomaly;
+pub mod usage_export;
+pub mod usage_forecast;
+pub mod usage_retention;
 pub mod usage_stats;
 
 pub use config::ConfigService;
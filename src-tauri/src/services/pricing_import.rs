@@ -0,0 +1,844 @@
+//! 模型定价目录导入
+//!
+//! 支持 LiteLLM 的 `model_prices.json`、CSV 以及本应用导出的 JSON 三种格式，
+//! 来源可以是本地文件或配置的 URL。导入分两步：先与 `model_pricing` 比对生成差异预览，
+//! 用户确认后再写入；导入只新增或更新，不会删除已有定价。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::services::usage_stats::normalize_model_id;
+use rusqlite::params;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// 定价目录 URL 的设置键
+const CATALOG_URL_SETTING: &str = "pricing_catalog_url";
+
+/// LiteLLM 中可计入对话定价的模式
+const LITELLM_CHAT_MODES: [&str; 3] = ["chat", "responses", "completion"];
+
+/// 定价目录格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PricingFormat {
+    /// LiteLLM `model_prices_and_context_window.json`（按 Token 计价）
+    Litellm,
+    /// CSV（表头必须包含 model_id / input / output 列，按百万 Token 计价）
+    Csv,
+    /// 本应用导出的 JSON
+    Native,
+}
+
+/// 定价目录来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PricingSource {
+    File {
+        path: String,
+    },
+    Url {
+        url: String,
+    },
+    /// 使用设置中保存的目录 URL
+    Configured,
+}
+
+/// 单个模型的定价（单位：USD / 百万 Token）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingEntry {
+    pub model_id: String,
+    #[serde(default)]
+    pub display_name: String,
+    pub input_cost_per_million: String,
+    pub output_cost_per_million: String,
+    #[serde(default = "zero")]
+    pub cache_read_cost_per_million: String,
+    #[serde(default = "zero")]
+    pub cache_creation_cost_per_million: String,
+}
+
+fn zero() -> String {
+    "0".to_string()
+}
+
+/// 模型 ID 别名：请求中的 `alias` 按 `model_id` 的定价计费
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelAlias {
+    pub alias: String,
+    pub model_id: String,
+}
+
+/// 本应用的定价目录格式（也用于导出）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingCatalog {
+    #[serde(default)]
+    pub models: Vec<PricingEntry>,
+    #[serde(default)]
+    pub aliases: Vec<ModelAlias>,
+}
+
+/// 已有定价的变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingChange {
+    pub before: PricingEntry,
+    pub after: PricingEntry,
+}
+
+/// 导入预览（差异）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingImportPreview {
+    pub format: PricingFormat,
+    /// 新增的模型
+    pub added: Vec<PricingEntry>,
+    /// 价格发生变化的模型
+    pub changed: Vec<PricingChange>,
+    /// 价格相同的模型数
+    pub unchanged: u32,
+    /// 新增或变更的别名
+    pub aliases: Vec<ModelAlias>,
+    /// 无法识别或不含对话价格而跳过的条目数
+    pub skipped: u32,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingImportResult {
+    pub upserted: u32,
+    pub aliases: u32,
+}
+
+pub struct PricingImportService;
+
+impl PricingImportService {
+    /// 读取并解析定价目录，返回与当前定价的差异
+    pub async fn preview(
+        db: &Database,
+        source: &PricingSource,
+        format: Option<PricingFormat>,
+    ) -> Result<PricingImportPreview, AppError> {
+        let (content, hint) = Self::fetch(db, source).await?;
+        let format = match format {
+            Some(format) => format,
+            None => detect_format(&content, &hint)?,
+        };
+        let (catalog, skipped) = parse_catalog(&content, format)?;
+        db.diff_pricing_catalog(format, catalog, skipped)
+    }
+
+    async fn fetch(db: &Database, source: &PricingSource) -> Result<(String, String), AppError> {
+        let url = match source {
+            PricingSource::File { path } => {
+                let content = std::fs::read_to_string(path).map_err(|e| AppError::io(path, e))?;
+                return Ok((content, path.clone()));
+            }
+            PricingSource::Url { url } => url.clone(),
+            PricingSource::Configured => db
+                .get_pricing_catalog_url()?
+                .ok_or_else(|| AppError::InvalidInput("尚未配置定价目录 URL".to_string()))?,
+        };
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| AppError::Message(format!("创建 HTTP 客户端失败: {e}")))?;
+        let response = client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| AppError::Message(format!("下载定价目录失败: {e}")))?;
+        if !response.status().is_success() {
+            return Err(AppError::Message(format!(
+                "下载定价目录失败: HTTP {}",
+                response.status()
+            )));
+        }
+        let content = response
+            .text()
+            .await
+            .map_err(|e| AppError::Message(format!("读取定价目录失败: {e}")))?;
+        Ok((content, url))
+    }
+}
+
+/// 根据文件名和内容推断格式
+fn detect_format(content: &str, hint: &str) -> Result<PricingFormat, AppError> {
+    let path = hint.split(['?', '#']).next().unwrap_or(hint).to_lowercase();
+    if path.ends_with(".csv") {
+        return Ok(PricingFormat::Csv);
+    }
+
+    match serde_json::from_str::<Value>(content) {
+        Ok(Value::Array(_)) => Ok(PricingFormat::Native),
+        Ok(Value::Object(map)) if map.contains_key("models") => Ok(PricingFormat::Native),
+        Ok(Value::Object(_)) => Ok(PricingFormat::Litellm),
+        Ok(_) => Err(AppError::InvalidInput("无法识别的定价目录格式".to_string())),
+        // 非 JSON 内容按 CSV 处理
+        Err(_) => Ok(PricingFormat::Csv),
+    }
+}
+
+/// 解析定价目录，返回目录和跳过的条目数
+fn parse_catalog(content: &str, format: PricingFormat) -> Result<(PricingCatalog, u32), AppError> {
+    match format {
+        PricingFormat::Litellm => parse_litellm(content),
+        PricingFormat::Csv => parse_csv(content),
+        PricingFormat::Native => {
+            let value: Value = serde_json::from_str(content)
+                .map_err(|e| AppError::InvalidInput(format!("定价目录 JSON 解析失败: {e}")))?;
+            let catalog = if value.is_array() {
+                serde_json::from_value(value).map(|models| PricingCatalog {
+                    models,
+                    aliases: Vec::new(),
+                })
+            } else {
+                serde_json::from_value(value)
+            }
+            .map_err(|e| AppError::InvalidInput(format!("定价目录格式无效: {e}")))?;
+            validate_catalog(catalog)
+        }
+    }
+}
+
+/// 解析 LiteLLM 价格表
+///
+/// 条目以 `provider/model` 或裸模型名为键，价格单位为 USD / Token。
+/// 同一模型标准化后重名时优先采用不带前缀的条目（官方价格），
+/// 带前缀的条目（bedrock/、vertex_ai/ 等渠道价）只用于补缺。
+fn parse_litellm(content: &str) -> Result<(PricingCatalog, u32), AppError> {
+    let root: serde_json::Map<String, Value> = serde_json::from_str(content)
+        .map_err(|e| AppError::InvalidInput(format!("LiteLLM 价格表解析失败: {e}")))?;
+
+    let mut skipped = 0;
+    let mut selected: BTreeMap<String, (bool, PricingEntry)> = BTreeMap::new();
+    for (key, spec) in &root {
+        if key == "sample_spec" {
+            continue;
+        }
+        let mode = spec.get("mode").and_then(|m| m.as_str());
+        if mode.is_some_and(|m| !LITELLM_CHAT_MODES.contains(&m)) {
+            skipped += 1;
+            continue;
+        }
+        let per_token = |field: &str| spec.get(field).and_then(decimal_from_json);
+        let (Some(input), Some(output)) = (
+            per_token("input_cost_per_token"),
+            per_token("output_cost_per_token"),
+        ) else {
+            skipped += 1;
+            continue;
+        };
+
+        let last_segment = key.rsplit('/').next().unwrap_or(key);
+        let model_id = normalize_model_id(last_segment);
+        let canonical = !key.contains('/');
+        let entry = PricingEntry {
+            model_id: model_id.clone(),
+            display_name: last_segment.to_string(),
+            input_cost_per_million: per_million(input),
+            output_cost_per_million: per_million(output),
+            cache_read_cost_per_million: per_million(
+                per_token("cache_read_input_token_cost").unwrap_or_default(),
+            ),
+            cache_creation_cost_per_million: per_million(
+                per_token("cache_creation_input_token_cost").unwrap_or_default(),
+            ),
+        };
+
+        match selected.get(&model_id) {
+            Some((true, _)) => {}
+            Some((false, _)) if !canonical => {}
+            _ => {
+                selected.insert(model_id, (canonical, entry));
+            }
+        }
+    }
+
+    let catalog = PricingCatalog {
+        models: selected.into_values().map(|(_, entry)| entry).collect(),
+        aliases: Vec::new(),
+    };
+    Ok((catalog, skipped))
+}
+
+/// 解析 CSV 价格表
+///
+/// 表头示例：`model_id,display_name,input,output,cache_read,cache_creation,aliases`，
+/// 其中 `aliases` 用 `|` 分隔多个别名
+fn parse_csv(content: &str) -> Result<(PricingCatalog, u32), AppError> {
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let header = lines
+        .next()
+        .ok_or_else(|| AppError::InvalidInput("CSV 内容为空".to_string()))?;
+    let columns: Vec<String> = split_csv_line(header)
+        .into_iter()
+        .map(|c| c.to_lowercase())
+        .collect();
+    let column = |names: &[&str]| columns.iter().position(|c| names.contains(&c.as_str()));
+
+    let model_col = column(&["model_id", "model"]);
+    let input_col = column(&["input", "input_cost_per_million"]);
+    let output_col = column(&["output", "output_cost_per_million"]);
+    let (Some(model_col), Some(input_col), Some(output_col)) = (model_col, input_col, output_col)
+    else {
+        return Err(AppError::InvalidInput(
+            "CSV 表头必须包含 model_id、input、output 列".to_string(),
+        ));
+    };
+    let display_col = column(&["display_name", "name"]);
+    let cache_read_col = column(&["cache_read", "cache_read_cost_per_million"]);
+    let cache_creation_col = column(&["cache_creation", "cache_creation_cost_per_million"]);
+    let aliases_col = column(&["aliases", "alias"]);
+
+    let mut catalog = PricingCatalog::default();
+    let mut skipped = 0;
+    for line in lines {
+        let fields = split_csv_line(line);
+        let field = |idx: Option<usize>| {
+            idx.and_then(|i| fields.get(i))
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        let Some(model_id) = field(Some(model_col)) else {
+            skipped += 1;
+            continue;
+        };
+
+        for alias in field(aliases_col).iter().flat_map(|a| a.split('|')) {
+            let alias = alias.trim();
+            if !alias.is_empty() {
+                catalog.aliases.push(ModelAlias {
+                    alias: alias.to_string(),
+                    model_id: model_id.clone(),
+                });
+            }
+        }
+        catalog.models.push(PricingEntry {
+            display_name: field(display_col).unwrap_or_else(|| model_id.clone()),
+            model_id,
+            input_cost_per_million: field(Some(input_col)).unwrap_or_else(zero),
+            output_cost_per_million: field(Some(output_col)).unwrap_or_else(zero),
+            cache_read_cost_per_million: field(cache_read_col).unwrap_or_else(zero),
+            cache_creation_cost_per_million: field(cache_creation_col).unwrap_or_else(zero),
+        });
+    }
+
+    let (catalog, invalid) = validate_catalog(catalog)?;
+    Ok((catalog, skipped + invalid))
+}
+
+/// 拆分一行 CSV（支持双引号包裹和 `""` 转义）
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+/// 校验价格可解析，规范化数值表示；无效条目计入跳过数
+fn validate_catalog(catalog: PricingCatalog) -> Result<(PricingCatalog, u32), AppError> {
+    let mut skipped = 0;
+    let mut models = Vec::with_capacity(catalog.models.len());
+    for mut entry in catalog.models {
+        entry.model_id = entry.model_id.trim().to_string();
+        let prices = [
+            &mut entry.input_cost_per_million,
+            &mut entry.output_cost_per_million,
+            &mut entry.cache_read_cost_per_million,
+            &mut entry.cache_creation_cost_per_million,
+        ];
+        let mut valid = !entry.model_id.is_empty();
+        for price in prices {
+            match Decimal::from_str(price.trim()) {
+                Ok(value) if !value.is_sign_negative() => *price = value.normalize().to_string(),
+                _ => valid = false,
+            }
+        }
+        if !valid {
+            log::warn!("跳过无效的定价条目: {}", entry.model_id);
+            skipped += 1;
+            continue;
+        }
+        if entry.display_name.is_empty() {
+            entry.display_name = entry.model_id.clone();
+        }
+        models.push(entry);
+    }
+
+    // 只包含别名的目录（为已有模型补充别名）同样有效
+    if models.is_empty() && catalog.aliases.is_empty() {
+        return Err(AppError::InvalidInput(
+            "定价目录中没有可导入的模型或别名".to_string(),
+        ));
+    }
+    Ok((
+        PricingCatalog {
+            models,
+            aliases: catalog.aliases,
+        },
+        skipped,
+    ))
+}
+
+fn decimal_from_json(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Number(n) => Decimal::from_str(&n.to_string())
+            .or_else(|_| Decimal::from_scientific(&n.to_string()))
+            .ok(),
+        Value::String(s) => Decimal::from_str(s)
+            .or_else(|_| Decimal::from_scientific(s))
+            .ok(),
+        _ => None,
+    }
+}
+
+fn per_million(per_token: Decimal) -> String {
+    (per_token * Decimal::from(1_000_000))
+        .round_dp(6)
+        .normalize()
+        .to_string()
+}
+
+/// 两个价格字符串数值是否相等（"3" 与 "3.00" 视为相同）
+fn same_price(a: &str, b: &str) -> bool {
+    match (Decimal::from_str(a), Decimal::from_str(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn same_prices(a: &PricingEntry, b: &PricingEntry) -> bool {
+    same_price(&a.input_cost_per_million, &b.input_cost_per_million)
+        && same_price(&a.output_cost_per_million, &b.output_cost_per_million)
+        && same_price(
+            &a.cache_read_cost_per_million,
+            &b.cache_read_cost_per_million,
+        )
+        && same_price(
+            &a.cache_creation_cost_per_million,
+            &b.cache_creation_cost_per_million,
+        )
+}
+
+impl Database {
+    /// 将定价目录与当前 `model_pricing` 比对
+    fn diff_pricing_catalog(
+        &self,
+        format: PricingFormat,
+        catalog: PricingCatalog,
+        skipped: u32,
+    ) -> Result<PricingImportPreview, AppError> {
+        let existing: HashMap<String, PricingEntry> = self
+            .export_pricing_catalog()?
+            .models
+            .into_iter()
+            .map(|entry| (entry.model_id.clone(), entry))
+            .collect();
+        let existing_aliases: HashMap<String, String> = self
+            .get_model_aliases()?
+            .into_iter()
+            .map(|a| (a.alias, a.model_id))
+            .collect();
+
+        let mut preview = PricingImportPreview {
+            format,
+            added: Vec::new(),
+            changed: Vec::new(),
+            unchanged: 0,
+            aliases: Vec::new(),
+            skipped,
+        };
+        for entry in catalog.models {
+            match existing.get(&entry.model_id) {
+                None => preview.added.push(entry),
+                Some(before) if same_prices(before, &entry) => preview.unchanged += 1,
+                Some(before) => preview.changed.push(PricingChange {
+                    before: before.clone(),
+                    after: entry,
+                }),
+            }
+        }
+        preview.aliases = catalog
+            .aliases
+            .into_iter()
+            .filter(|a| existing_aliases.get(&a.alias) != Some(&a.model_id))
+            .collect();
+
+        Ok(preview)
+    }
+
+    /// 写入用户确认后的定价和别名
+    ///
+    /// 已有模型只更新价格，保留原显示名称
+    pub fn apply_pricing_import(
+        &self,
+        entries: &[PricingEntry],
+        aliases: &[ModelAlias],
+    ) -> Result<PricingImportResult, AppError> {
+        let (catalog, _) = validate_catalog(PricingCatalog {
+            models: entries.to_vec(),
+            aliases: aliases.to_vec(),
+        })?;
+
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        for entry in &catalog.models {
+            tx.execute(
+                "INSERT INTO model_pricing (
+                    model_id, display_name, input_cost_per_million, output_cost_per_million,
+                    cache_read_cost_per_million, cache_creation_cost_per_million
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                ON CONFLICT(model_id) DO UPDATE SET
+                    input_cost_per_million = excluded.input_cost_per_million,
+                    output_cost_per_million = excluded.output_cost_per_million,
+                    cache_read_cost_per_million = excluded.cache_read_cost_per_million,
+                    cache_creation_cost_per_million = excluded.cache_creation_cost_per_million",
+                params![
+                    entry.model_id,
+                    entry.display_name,
+                    entry.input_cost_per_million,
+                    entry.output_cost_per_million,
+                    entry.cache_read_cost_per_million,
+                    entry.cache_creation_cost_per_million,
+                ],
+            )
+            .map_err(|e| AppError::Database(format!("写入模型定价失败: {e}")))?;
+        }
+        for alias in &catalog.aliases {
+            insert_alias(&tx, alias)?;
+        }
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        log::info!(
+            "定价目录导入完成: {} 个模型，{} 个别名",
+            catalog.models.len(),
+            catalog.aliases.len()
+        );
+        Ok(PricingImportResult {
+            upserted: catalog.models.len() as u32,
+            aliases: catalog.aliases.len() as u32,
+        })
+    }
+
+    /// 导出当前定价和别名（本应用 JSON 格式）
+    pub fn export_pricing_catalog(&self) -> Result<PricingCatalog, AppError> {
+        let models = {
+            let conn = lock_conn!(self.conn);
+            let mut stmt = conn.prepare(
+                "SELECT model_id, display_name, input_cost_per_million, output_cost_per_million,
+                        cache_read_cost_per_million, cache_creation_cost_per_million
+                 FROM model_pricing
+                 ORDER BY model_id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(PricingEntry {
+                    model_id: row.get(0)?,
+                    display_name: row.get(1)?,
+                    input_cost_per_million: row.get(2)?,
+                    output_cost_per_million: row.get(3)?,
+                    cache_read_cost_per_million: row.get(4)?,
+                    cache_creation_cost_per_million: row.get(5)?,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        Ok(PricingCatalog {
+            models,
+            aliases: self.get_model_aliases()?,
+        })
+    }
+
+    /// 获取全部模型别名
+    pub fn get_model_aliases(&self) -> Result<Vec<ModelAlias>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt =
+            conn.prepare("SELECT alias, model_id FROM model_pricing_aliases ORDER BY alias")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ModelAlias {
+                alias: row.get(0)?,
+                model_id: row.get(1)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 新增或更新模型别名
+    pub fn set_model_alias(&self, alias: &ModelAlias) -> Result<(), AppError> {
+        if alias.alias.trim().is_empty() || alias.model_id.trim().is_empty() {
+            return Err(AppError::InvalidInput("别名和模型 ID 不能为空".to_string()));
+        }
+        let conn = lock_conn!(self.conn);
+        insert_alias(&conn, alias)
+    }
+
+    /// 删除模型别名
+    pub fn delete_model_alias(&self, alias: &str) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "DELETE FROM model_pricing_aliases WHERE alias = ?1",
+            [alias],
+        )
+        .map_err(|e| AppError::Database(format!("删除模型别名失败: {e}")))?;
+        Ok(())
+    }
+
+    /// 获取配置的定价目录 URL
+    pub fn get_pricing_catalog_url(&self) -> Result<Option<String>, AppError> {
+        Ok(self
+            .get_setting(CATALOG_URL_SETTING)?
+            .filter(|url| !url.trim().is_empty()))
+    }
+
+    /// 设置定价目录 URL（为空时清除）
+    pub fn set_pricing_catalog_url(&self, url: Option<&str>) -> Result<(), AppError> {
+        let url = url.map(str::trim).unwrap_or_default();
+        if !url.is_empty() {
+            url::Url::parse(url)
+                .map_err(|e| AppError::InvalidInput(format!("定价目录 URL 无效: {e}")))?;
+        }
+        self.set_setting(CATALOG_URL_SETTING, url)
+    }
+}
+
+fn insert_alias(conn: &rusqlite::Connection, alias: &ModelAlias) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO model_pricing_aliases (alias, model_id) VALUES (?1, ?2)",
+        params![alias.alias.trim(), alias.model_id.trim()],
+    )
+    .map_err(|e| AppError::Database(format!("保存模型别名失败: {e}")))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::usage_stats::find_model_pricing_id;
+
+    const LITELLM_SAMPLE: &str = r#"{
+        "sample_spec": {"input_cost_per_token": 0, "output_cost_per_token": 0},
+        "claude-sonnet-4-5-20250929": {
+            "input_cost_per_token": 3e-06,
+            "output_cost_per_token": 1.5e-05,
+            "cache_read_input_token_cost": 3e-07,
+            "cache_creation_input_token_cost": 3.75e-06,
+            "litellm_provider": "anthropic",
+            "mode": "chat"
+        },
+        "bedrock/claude-sonnet-4-5-20250929": {
+            "input_cost_per_token": 3.3e-06,
+            "output_cost_per_token": 1.65e-05,
+            "mode": "chat"
+        },
+        "openrouter/openai/gpt-4.1-nano": {
+            "input_cost_per_token": 1e-07,
+            "output_cost_per_token": 4e-07,
+            "mode": "chat"
+        },
+        "text-embedding-3-small": {
+            "input_cost_per_token": 2e-08,
+            "output_cost_per_token": 0,
+            "mode": "embedding"
+        },
+        "dall-e-3": {"mode": "image_generation"}
+    }"#;
+
+    #[test]
+    fn test_parse_litellm_prefers_canonical_entries() -> Result<(), AppError> {
+        assert_eq!(
+            detect_format(LITELLM_SAMPLE, "prices.json")?,
+            PricingFormat::Litellm
+        );
+        let (catalog, skipped) = parse_catalog(LITELLM_SAMPLE, PricingFormat::Litellm)?;
+        assert_eq!(skipped, 2);
+        assert_eq!(catalog.models.len(), 2);
+
+        let sonnet = &catalog.models[0];
+        assert_eq!(sonnet.model_id, "claude-sonnet-4-5-20250929");
+        assert_eq!(sonnet.input_cost_per_million, "3");
+        assert_eq!(sonnet.output_cost_per_million, "15");
+        assert_eq!(sonnet.cache_read_cost_per_million, "0.3");
+        assert_eq!(sonnet.cache_creation_cost_per_million, "3.75");
+
+        let nano = &catalog.models[1];
+        assert_eq!(nano.model_id, "gpt-4-1-nano");
+        assert_eq!(nano.input_cost_per_million, "0.1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_csv_with_quotes_and_aliases() -> Result<(), AppError> {
+        let csv = "model_id,display_name,input,output,cache_read,aliases\n\
+                   # comment\n\
+                   my-model,\"My Model, v2\",1.50,6,0.15,my-model-latest|vendor/my-model\n\
+                   broken,Broken,abc,1,0,\n";
+        assert_eq!(
+            detect_format(csv, "https://example.com/p.csv?x=1")?,
+            PricingFormat::Csv
+        );
+        let (catalog, skipped) = parse_catalog(csv, PricingFormat::Csv)?;
+        assert_eq!(skipped, 1);
+        assert_eq!(catalog.models.len(), 1);
+        assert_eq!(catalog.models[0].display_name, "My Model, v2");
+        assert_eq!(catalog.models[0].input_cost_per_million, "1.5");
+        assert_eq!(catalog.models[0].cache_creation_cost_per_million, "0");
+        assert_eq!(catalog.aliases.len(), 2);
+        assert_eq!(catalog.aliases[1].alias, "vendor/my-model");
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_and_apply_pricing_import() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let exported = db.export_pricing_catalog()?;
+        let mut sonnet = exported
+            .models
+            .iter()
+            .find(|m| m.model_id == "claude-sonnet-4-5")
+            .cloned()
+            .expect("seeded sonnet pricing");
+        let unchanged = sonnet.clone();
+        sonnet.output_cost_per_million = "16".to_string();
+        sonnet.display_name = "ignored".to_string();
+
+        let content = serde_json::to_string(&PricingCatalog {
+            models: vec![
+                unchanged,
+                sonnet.clone(),
+                PricingEntry {
+                    model_id: "new-model".to_string(),
+                    display_name: String::new(),
+                    input_cost_per_million: "1".to_string(),
+                    output_cost_per_million: "2".to_string(),
+                    cache_read_cost_per_million: zero(),
+                    cache_creation_cost_per_million: zero(),
+                },
+            ],
+            aliases: vec![ModelAlias {
+                alias: "house-model".to_string(),
+                model_id: "new-model".to_string(),
+            }],
+        })
+        .unwrap();
+        assert_eq!(detect_format(&content, "")?, PricingFormat::Native);
+
+        let (catalog, skipped) = parse_catalog(&content, PricingFormat::Native)?;
+        let preview = db.diff_pricing_catalog(PricingFormat::Native, catalog, skipped)?;
+        assert_eq!(preview.unchanged, 1);
+        assert_eq!(preview.changed.len(), 1);
+        assert_eq!(preview.changed[0].after.output_cost_per_million, "16");
+        assert_eq!(preview.added.len(), 1);
+        assert_eq!(preview.added[0].display_name, "new-model");
+        assert_eq!(preview.aliases.len(), 1);
+
+        let mut entries = preview.added.clone();
+        entries.push(preview.changed[0].after.clone());
+        let result = db.apply_pricing_import(&entries, &preview.aliases)?;
+        assert_eq!(result.upserted, 2);
+
+        let after = db.export_pricing_catalog()?;
+        let sonnet_after = after
+            .models
+            .iter()
+            .find(|m| m.model_id == "claude-sonnet-4-5")
+            .unwrap();
+        assert_eq!(sonnet_after.output_cost_per_million, "16");
+        assert_ne!(sonnet_after.display_name, "ignored");
+
+        let conn = lock_conn!(db.conn);
+        assert_eq!(
+            find_model_pricing_id(&conn, "house-model")?.as_deref(),
+            Some("new-model")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_alias_only_catalog() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let content = r#"{"models": [], "aliases": [{"alias": "house-sonnet", "modelId": "claude-sonnet-4-5"}]}"#;
+        let (catalog, skipped) = parse_catalog(content, PricingFormat::Native)?;
+        let preview = db.diff_pricing_catalog(PricingFormat::Native, catalog, skipped)?;
+        assert!(preview.added.is_empty());
+        assert_eq!(preview.aliases.len(), 1);
+
+        let result = db.apply_pricing_import(&[], &preview.aliases)?;
+        assert_eq!((result.upserted, result.aliases), (0, 1));
+        let conn = lock_conn!(db.conn);
+        assert_eq!(
+            find_model_pricing_id(&conn, "house-sonnet")?.as_deref(),
+            Some("claude-sonnet-4-5")
+        );
+        drop(conn);
+
+        let empty = r#"{"models": [], "aliases": []}"#;
+        assert!(parse_catalog(empty, PricingFormat::Native).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_alias_resolves_prefixed_and_dated_ids() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        db.apply_pricing_import(
+            &[PricingEntry {
+                model_id: "house-sonnet".to_string(),
+                display_name: "House Sonnet".to_string(),
+                input_cost_per_million: "2".to_string(),
+                output_cost_per_million: "10".to_string(),
+                cache_read_cost_per_million: zero(),
+                cache_creation_cost_per_million: zero(),
+            }],
+            &[],
+        )?;
+        db.set_model_alias(&ModelAlias {
+            alias: "claude-sonnet-4-5".to_string(),
+            model_id: "house-sonnet".to_string(),
+        })?;
+
+        {
+            let conn = lock_conn!(db.conn);
+            for model in ["claude-sonnet-4-5-20250929", "anthropic/claude-sonnet-4.5"] {
+                assert_eq!(
+                    find_model_pricing_id(&conn, model)?.as_deref(),
+                    Some("house-sonnet"),
+                    "{model}"
+                );
+            }
+        }
+
+        db.delete_model_alias("claude-sonnet-4-5")?;
+        let conn = lock_conn!(db.conn);
+        assert_eq!(
+            find_model_pricing_id(&conn, "anthropic/claude-sonnet-4.5")?.as_deref(),
+            Some("claude-sonnet-4-5")
+        );
+        Ok(())
+    }
+}
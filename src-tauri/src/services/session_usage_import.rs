@@ -0,0 +1,831 @@
+//! 会话日志用量导入
+//!
+//! 未经代理的请求（如官方账号直连）不会出现在请求日志中。本模块扫描本地 CLI 会话日志，
+//! 按 `model_pricing` 计价后写入 `proxy_request_logs`，并以 `source = 'session_log'` 与代理流量区分：
+//! - Claude Code：`~/.claude/projects/*/<session>.jsonl`，按 assistant 消息的 `message.usage` 记录
+//! - Codex：`~/.codex/sessions/**/*.jsonl`，按 `token_count` 事件的 `last_token_usage` 记录
+//! - Gemini CLI：`~/.gemini/tmp/*/chats/*.json`，按 gemini 消息的 `tokens` 记录
+//!
+//! JSONL 日志按文件偏移增量读取（偏移记录在 `session_log_offsets` 表），
+//! 记录 ID 由消息 ID 派生并作为 `request_id` 主键，重复扫描时自动去重。
+//! 已经过代理的轮次（同一会话在相近时间内已有代理日志）会被跳过，避免重复计费。
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::provider::ProviderMeta;
+use crate::proxy::usage::{CostCalculator, TokenUsage};
+use crate::services::currency::resolve_billing;
+use crate::services::usage_stats::find_effective_pricing;
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// 代理流量的日志来源
+pub const SOURCE_PROXY: &str = "proxy";
+/// 会话日志导入的日志来源
+pub const SOURCE_SESSION_LOG: &str = "session_log";
+
+/// 导入记录使用的供应商 ID（不对应任何已配置的供应商）
+const SESSION_LOG_PROVIDER_ID: &str = "session_log";
+
+/// 会话日志与代理日志的时间对齐窗口（秒）：日志时间戳为响应写入时刻，代理日志为请求开始时刻
+const PROXY_OVERLAP_WINDOW_SECS: i64 = 600;
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionUsageImportResult {
+    /// 扫描的日志文件数
+    pub files_scanned: u64,
+    /// 有新内容的日志文件数
+    pub files_updated: u64,
+    /// 新导入的记录数
+    pub imported: u64,
+    /// 已存在而跳过的记录数
+    pub duplicates: u64,
+    /// 已由代理记录而跳过的记录数
+    pub proxied: u64,
+    /// 未找到模型定价（成本记为 0）的记录数
+    pub unpriced: u64,
+}
+
+/// 会话日志根目录
+#[derive(Debug, Clone)]
+pub struct SessionLogRoots {
+    /// Claude Code 配置目录（`~/.claude`）
+    pub claude: PathBuf,
+    /// Codex 配置目录（`~/.codex`）
+    pub codex: PathBuf,
+    /// Gemini CLI 配置目录（`~/.gemini`）
+    pub gemini: PathBuf,
+}
+
+impl SessionLogRoots {
+    /// 使用当前生效的各应用配置目录（支持自定义目录覆盖）
+    pub fn from_config() -> Self {
+        Self {
+            claude: crate::config::get_claude_config_dir(),
+            codex: crate::codex_config::get_codex_config_dir(),
+            gemini: crate::gemini_config::get_gemini_dir(),
+        }
+    }
+}
+
+/// 会话日志格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogKind {
+    Claude,
+    Codex,
+    Gemini,
+}
+
+impl LogKind {
+    fn app_type(&self) -> &'static str {
+        match self {
+            Self::Claude => "claude",
+            Self::Codex => "codex",
+            Self::Gemini => "gemini",
+        }
+    }
+}
+
+/// 单条用量记录
+#[derive(Debug, Clone)]
+struct UsageRecord {
+    /// 去重 ID（作为 request_id）
+    id: String,
+    model: String,
+    usage: TokenUsage,
+    session_id: Option<String>,
+    project_path: Option<String>,
+    /// Unix 秒
+    created_at: i64,
+}
+
+/// 增量解析上下文（Codex 的会话信息只出现在文件开头，需随偏移一并保存）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ParseContext {
+    session_id: Option<String>,
+    project_path: Option<String>,
+    model: Option<String>,
+}
+
+/// 已记录的文件读取进度
+struct FileProgress {
+    offset: u64,
+    modified_at: i64,
+    context: ParseContext,
+}
+
+impl Database {
+    /// 从默认目录导入 Claude Code / Codex / Gemini CLI 会话日志中的用量
+    pub fn import_session_usage(&self) -> Result<SessionUsageImportResult, AppError> {
+        self.import_session_usage_from(&SessionLogRoots::from_config())
+    }
+
+    /// 从指定目录导入会话日志中的用量
+    pub fn import_session_usage_from(
+        &self,
+        roots: &SessionLogRoots,
+    ) -> Result<SessionUsageImportResult, AppError> {
+        let mut files = Vec::new();
+        for dir in list_dirs(&roots.claude.join("projects")) {
+            files.extend(list_files(&dir, "jsonl").map(|f| (LogKind::Claude, f)));
+        }
+        let mut codex_files = Vec::new();
+        collect_files_recursive(&roots.codex.join("sessions"), "jsonl", &mut codex_files);
+        files.extend(codex_files.into_iter().map(|f| (LogKind::Codex, f)));
+        for dir in list_dirs(&roots.gemini.join("tmp")) {
+            files.extend(list_files(&dir.join("chats"), "json").map(|f| (LogKind::Gemini, f)));
+        }
+
+        let mut result = SessionUsageImportResult::default();
+        self.remove_proxied_session_records()?;
+        for (kind, path) in files {
+            result.files_scanned += 1;
+            match self.import_session_file(kind, &path, &mut result) {
+                Ok(true) => result.files_updated += 1,
+                Ok(false) => {}
+                Err(e) => log::warn!("导入会话日志 {} 失败: {e}", path.display()),
+            }
+        }
+
+        log::info!(
+            "会话日志用量导入完成: 扫描 {} 个文件, 新增 {} 条, 重复 {} 条, 已由代理记录 {} 条, 未定价 {} 条",
+            result.files_scanned,
+            result.imported,
+            result.duplicates,
+            result.proxied,
+            result.unpriced
+        );
+        Ok(result)
+    }
+
+    /// 删除此前导入、但已被代理日志覆盖的会话记录（代理日志可能晚于会话日志写入）
+    fn remove_proxied_session_records(&self) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT request_id, app_type, session_id, created_at FROM proxy_request_logs
+             WHERE source = '{SOURCE_SESSION_LOG}' AND session_id IS NOT NULL"
+        ))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(stmt);
+
+        let mut removed = 0;
+        for (request_id, app_type, session_id, created_at) in rows {
+            if is_covered_by_proxy(&conn, &app_type, Some(&session_id), created_at)? {
+                removed += conn.execute(
+                    "DELETE FROM proxy_request_logs WHERE request_id = ?1",
+                    [&request_id],
+                )?;
+            }
+        }
+        if removed > 0 {
+            log::info!("已移除 {removed} 条与代理日志重复的会话日志用量");
+        }
+        Ok(())
+    }
+
+    /// 导入单个日志文件，返回是否读取了新内容
+    fn import_session_file(
+        &self,
+        kind: LogKind,
+        path: &Path,
+        result: &mut SessionUsageImportResult,
+    ) -> Result<bool, AppError> {
+        let key = path.to_string_lossy().to_string();
+        let metadata = std::fs::metadata(path).map_err(|e| AppError::io(path, e))?;
+        let size = metadata.len();
+        let modified_at = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        let progress = {
+            let conn = lock_conn!(self.conn);
+            get_file_progress(&conn, &key)?
+        };
+
+        let (records, offset, context) = match kind {
+            LogKind::Gemini => {
+                // Gemini 会话为整体重写的 JSON 文件，大小和修改时间不变时跳过
+                if progress
+                    .as_ref()
+                    .is_some_and(|p| p.offset == size && p.modified_at == modified_at)
+                {
+                    return Ok(false);
+                }
+                let content = std::fs::read_to_string(path).map_err(|e| AppError::io(path, e))?;
+                let records = serde_json::from_str::<Value>(&content)
+                    .map(|json| parse_gemini_session(&json))
+                    .unwrap_or_default();
+                (records, size, ParseContext::default())
+            }
+            LogKind::Claude | LogKind::Codex => {
+                // 文件被截断或重写时从头读取（已导入记录由主键去重）
+                let (start, mut context) = match progress {
+                    Some(p) if p.offset <= size => (p.offset, p.context),
+                    _ => (0, ParseContext::default()),
+                };
+                if start == size {
+                    return Ok(false);
+                }
+
+                let mut file = std::fs::File::open(path).map_err(|e| AppError::io(path, e))?;
+                file.seek(SeekFrom::Start(start))
+                    .map_err(|e| AppError::io(path, e))?;
+                let mut buf = Vec::new();
+                file.read_to_end(&mut buf)
+                    .map_err(|e| AppError::io(path, e))?;
+
+                // 只处理完整的行，末尾未写完的行留待下次读取
+                let Some(end) = buf.iter().rposition(|b| *b == b'\n') else {
+                    return Ok(false);
+                };
+                let fallback_session = path.file_stem().map(|s| s.to_string_lossy().to_string());
+                let records = String::from_utf8_lossy(&buf[..end])
+                    .lines()
+                    .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+                    .filter_map(|json| match kind {
+                        LogKind::Claude => parse_claude_entry(&json),
+                        _ => parse_codex_entry(&json, &mut context, fallback_session.as_deref()),
+                    })
+                    .collect();
+                (records, start + end as u64 + 1, context)
+            }
+        };
+
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn.transaction()?;
+        for record in &records {
+            if is_covered_by_proxy(
+                &tx,
+                kind.app_type(),
+                record.session_id.as_deref(),
+                record.created_at,
+            )? {
+                result.proxied += 1;
+                continue;
+            }
+            let (inserted, priced) = insert_record(&tx, kind.app_type(), record)?;
+            if inserted {
+                result.imported += 1;
+                if !priced {
+                    result.unpriced += 1;
+                }
+            } else {
+                result.duplicates += 1;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO session_log_offsets
+                (path, app_type, offset, modified_at, context, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                key,
+                kind.app_type(),
+                offset as i64,
+                modified_at,
+                serde_json::to_string(&context)
+                    .map_err(|e| AppError::JsonSerialize { source: e })?,
+                chrono::Utc::now().timestamp(),
+            ],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+}
+
+fn get_file_progress(conn: &Connection, path: &str) -> Result<Option<FileProgress>, AppError> {
+    Ok(conn
+        .query_row(
+            "SELECT offset, modified_at, context FROM session_log_offsets WHERE path = ?1",
+            [path],
+            |row| {
+                let context: Option<String> = row.get(2)?;
+                Ok(FileProgress {
+                    offset: row.get::<_, i64>(0)? as u64,
+                    modified_at: row.get(1)?,
+                    context: context
+                        .and_then(|c| serde_json::from_str(&c).ok())
+                        .unwrap_or_default(),
+                })
+            },
+        )
+        .optional()?)
+}
+
+/// 该轮次是否已有代理日志（无会话 ID 时无法对应，视为未覆盖）
+fn is_covered_by_proxy(
+    conn: &Connection,
+    app_type: &str,
+    session_id: Option<&str>,
+    created_at: i64,
+) -> Result<bool, AppError> {
+    let Some(session_id) = session_id else {
+        return Ok(false);
+    };
+    Ok(conn.query_row(
+        "SELECT EXISTS (
+            SELECT 1 FROM proxy_request_logs
+            WHERE source = 'proxy' AND app_type = ?1 AND session_id = ?2
+              AND created_at BETWEEN ?3 - ?4 AND ?3 + ?4
+        )",
+        params![app_type, session_id, created_at, PROXY_OVERLAP_WINDOW_SECS],
+        |row| row.get(0),
+    )?)
+}
+
+/// 计价并写入一条记录，返回 (是否新插入, 是否找到定价)
+///
+/// 已被日志保留策略聚合删除的记录视为重复，不再写入
+fn insert_record(
+    conn: &Connection,
+    app_type: &str,
+    record: &UsageRecord,
+) -> Result<(bool, bool), AppError> {
+    let rolled_up: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM rolled_up_session_records WHERE request_id = ?1)",
+        [&record.id],
+        |row| row.get(0),
+    )?;
+    if rolled_up {
+        return Ok((false, true));
+    }
+
+    let pricing = find_effective_pricing(conn, &record.model, &record.usage, record.created_at)?;
+    let cost = CostCalculator::try_calculate(&record.usage, pricing.as_ref(), Decimal::ONE);
+    let (input_cost, output_cost, cache_read_cost, cache_creation_cost, total_cost) = cost
+        .as_ref()
+        .map(|c| {
+            (
+                c.input_cost,
+                c.output_cost,
+                c.cache_read_cost,
+                c.cache_creation_cost,
+                c.total_cost,
+            )
+        })
+        .unwrap_or_default();
+
+    let date = chrono::DateTime::from_timestamp(record.created_at, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d")
+        .to_string();
+    let billing = resolve_billing(conn, &ProviderMeta::default(), &date)?;
+    let billing_cost = billing.convert(total_cost).map(|c| c.to_string());
+
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO proxy_request_logs (
+            request_id, provider_id, app_type, model,
+            input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
+            input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
+            latency_ms, status_code, session_id, project_path, is_streaming, cost_multiplier, created_at,
+            billing_currency, billing_rate, billing_cost, source
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, 0, 200, ?14, ?15, 0, '1', ?16, ?17, ?18, ?19, ?20)",
+        params![
+            record.id,
+            SESSION_LOG_PROVIDER_ID,
+            app_type,
+            record.model,
+            record.usage.input_tokens,
+            record.usage.output_tokens,
+            record.usage.cache_read_tokens,
+            record.usage.cache_creation_tokens,
+            input_cost.to_string(),
+            output_cost.to_string(),
+            cache_read_cost.to_string(),
+            cache_creation_cost.to_string(),
+            total_cost.to_string(),
+            record.session_id,
+            record.project_path,
+            record.created_at,
+            billing.currency,
+            billing.rate.map(|r| r.to_string()),
+            billing_cost,
+            SOURCE_SESSION_LOG,
+        ],
+    )? > 0;
+
+    Ok((inserted, pricing.is_some()))
+}
+
+/// 解析 RFC 3339 时间戳为 Unix 秒（缺失时使用当前时间）
+fn parse_timestamp(value: Option<&Value>) -> i64 {
+    value
+        .and_then(|v| v.as_str())
+        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .map(|t| t.timestamp())
+        .unwrap_or_else(|| chrono::Utc::now().timestamp())
+}
+
+fn u32_field(value: &Value, key: &str) -> u32 {
+    value.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// 解析 Claude Code 日志行（同一消息的多个内容块会重复写入相同 usage，由消息 ID 去重）
+fn parse_claude_entry(json: &Value) -> Option<UsageRecord> {
+    if json.get("type").and_then(|v| v.as_str()) != Some("assistant") {
+        return None;
+    }
+    let message = json.get("message")?;
+    let usage = message.get("usage")?;
+    let message_id = message.get("id").and_then(|v| v.as_str())?;
+    let model = str_field(message, "model")?;
+    // 本地生成的占位消息（如中断提示）不产生费用
+    if model == "<synthetic>" {
+        return None;
+    }
+
+    Some(UsageRecord {
+        id: format!("claude:{message_id}"),
+        model,
+        usage: TokenUsage {
+            input_tokens: u32_field(usage, "input_tokens"),
+            output_tokens: u32_field(usage, "output_tokens"),
+            cache_read_tokens: u32_field(usage, "cache_read_input_tokens"),
+            cache_creation_tokens: u32_field(usage, "cache_creation_input_tokens"),
+            model: None,
+        },
+        session_id: str_field(json, "sessionId"),
+        project_path: str_field(json, "cwd"),
+        created_at: parse_timestamp(json.get("timestamp")),
+    })
+}
+
+/// 解析 Codex 日志行
+///
+/// `session_meta` / `turn_context` 更新上下文，`token_count` 事件产生记录；
+/// Codex 会重复发送相同的累计用量，因此以会话 ID + 累计 Token 数作为去重 ID
+fn parse_codex_entry(
+    json: &Value,
+    context: &mut ParseContext,
+    fallback_session: Option<&str>,
+) -> Option<UsageRecord> {
+    let payload = json.get("payload")?;
+    match json.get("type").and_then(|v| v.as_str())? {
+        "session_meta" => {
+            context.session_id = str_field(payload, "id").or(context.session_id.take());
+            context.project_path = str_field(payload, "cwd").or(context.project_path.take());
+            None
+        }
+        "turn_context" => {
+            context.model = str_field(payload, "model").or(context.model.take());
+            context.project_path = str_field(payload, "cwd").or(context.project_path.take());
+            None
+        }
+        "event_msg" if payload.get("type").and_then(|v| v.as_str()) == Some("token_count") => {
+            let info = payload.get("info")?;
+            let last = info.get("last_token_usage")?;
+            let total_tokens = info
+                .get("total_token_usage")
+                .and_then(|t| t.get("total_tokens"))
+                .and_then(|v| v.as_u64())?;
+            let session_id = context
+                .session_id
+                .clone()
+                .or_else(|| fallback_session.map(str::to_string))?;
+
+            // Codex 的 input_tokens 包含缓存命中部分，需拆分为实际输入与缓存读取
+            let cached = u32_field(last, "cached_input_tokens");
+            Some(UsageRecord {
+                id: format!("codex:{session_id}:{total_tokens}"),
+                model: context
+                    .model
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_string()),
+                usage: TokenUsage {
+                    input_tokens: u32_field(last, "input_tokens").saturating_sub(cached),
+                    output_tokens: u32_field(last, "output_tokens"),
+                    cache_read_tokens: cached,
+                    cache_creation_tokens: 0,
+                    model: None,
+                },
+                session_id: Some(session_id),
+                project_path: context.project_path.clone(),
+                created_at: parse_timestamp(json.get("timestamp")),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// 解析 Gemini CLI 会话文件
+fn parse_gemini_session(json: &Value) -> Vec<UsageRecord> {
+    let session_id = str_field(json, "sessionId");
+    let Some(messages) = json.get("messages").and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+
+    messages
+        .iter()
+        .filter(|m| m.get("type").and_then(|v| v.as_str()) == Some("gemini"))
+        .filter_map(|message| {
+            let tokens = message.get("tokens")?;
+            let message_id = message.get("id").and_then(|v| v.as_str())?;
+            // 与 Codex 相同，input 包含缓存命中部分；思考 Token 按输出计费
+            let cached = u32_field(tokens, "cached");
+            Some(UsageRecord {
+                id: format!(
+                    "gemini:{}:{message_id}",
+                    session_id.as_deref().unwrap_or("unknown")
+                ),
+                model: str_field(message, "model").unwrap_or_else(|| "unknown".to_string()),
+                usage: TokenUsage {
+                    input_tokens: u32_field(tokens, "input").saturating_sub(cached),
+                    output_tokens: u32_field(tokens, "output") + u32_field(tokens, "thoughts"),
+                    cache_read_tokens: cached,
+                    cache_creation_tokens: 0,
+                    model: None,
+                },
+                session_id: session_id.clone(),
+                project_path: None,
+                created_at: parse_timestamp(message.get("timestamp")),
+            })
+        })
+        .collect()
+}
+
+fn list_dirs(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn list_files<'a>(dir: &Path, extension: &'a str) -> impl Iterator<Item = PathBuf> + 'a {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(move |p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some(extension))
+}
+
+fn collect_files_recursive(dir: &Path, extension: &str, out: &mut Vec<PathBuf>) {
+    for sub in list_dirs(dir) {
+        collect_files_recursive(&sub, extension, out);
+    }
+    out.extend(list_files(dir, extension));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+
+    fn claude_line(message_id: &str, output_tokens: u32) -> String {
+        json!({
+            "type": "assistant",
+            "sessionId": "s-1",
+            "cwd": "/work/acme",
+            "timestamp": "2025-10-01T10:00:00.000Z",
+            "message": {
+                "id": message_id,
+                "model": "claude-sonnet-4-20250514",
+                "usage": {
+                    "input_tokens": 1000,
+                    "output_tokens": output_tokens,
+                    "cache_read_input_tokens": 2000,
+                    "cache_creation_input_tokens": 0
+                }
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_codex_entries() {
+        let mut context = ParseContext::default();
+        let lines = [
+            json!({"type": "session_meta", "payload": {"id": "c-1", "cwd": "/work/beta"}}),
+            json!({"type": "turn_context", "payload": {"model": "gpt-5-codex", "cwd": "/work/beta"}}),
+            json!({
+                "type": "event_msg",
+                "timestamp": "2025-10-01T10:00:00.000Z",
+                "payload": {"type": "token_count", "info": {
+                    "total_token_usage": {"total_tokens": 1500},
+                    "last_token_usage": {"input_tokens": 1200, "cached_input_tokens": 200, "output_tokens": 300}
+                }}
+            }),
+            json!({"type": "event_msg", "payload": {"type": "token_count", "info": null}}),
+        ];
+        let records: Vec<_> = lines
+            .iter()
+            .filter_map(|l| parse_codex_entry(l, &mut context, None))
+            .collect();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "codex:c-1:1500");
+        assert_eq!(records[0].model, "gpt-5-codex");
+        assert_eq!(records[0].usage.input_tokens, 1000);
+        assert_eq!(records[0].usage.cache_read_tokens, 200);
+        assert_eq!(records[0].project_path.as_deref(), Some("/work/beta"));
+    }
+
+    #[test]
+    fn test_parse_gemini_session() {
+        let json = json!({
+            "sessionId": "g-1",
+            "messages": [
+                {"id": "m1", "type": "user", "content": "hi"},
+                {"id": "m2", "type": "gemini", "model": "gemini-2.5-pro",
+                 "timestamp": "2025-10-01T10:00:00.000Z",
+                 "tokens": {"input": 500, "output": 100, "cached": 100, "thoughts": 50}}
+            ]
+        });
+        let records = parse_gemini_session(&json);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "gemini:g-1:m2");
+        assert_eq!(records[0].usage.input_tokens, 400);
+        assert_eq!(records[0].usage.output_tokens, 150);
+    }
+
+    #[test]
+    fn test_import_session_usage_incremental() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT OR REPLACE INTO model_pricing (model_id, display_name, input_cost_per_million, output_cost_per_million, cache_read_cost_per_million)
+                 VALUES ('claude-sonnet-4-20250514', 'Sonnet 4', '3', '15', '0.3')",
+                [],
+            )?;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let roots = SessionLogRoots {
+            claude: dir.path().join("claude"),
+            codex: dir.path().join("codex"),
+            gemini: dir.path().join("gemini"),
+        };
+        let project_dir = roots.claude.join("projects/-work-acme");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        let session_file = project_dir.join("s-1.jsonl");
+
+        // 同一消息重复出现两次，最后一行尚未写完
+        let partial = claude_line("msg_3", 10);
+        std::fs::write(
+            &session_file,
+            format!(
+                "{}\n{}\n{}\n{}",
+                claude_line("msg_1", 500),
+                claude_line("msg_1", 500),
+                claude_line("msg_2", 100),
+                &partial[..20]
+            ),
+        )
+        .unwrap();
+
+        let result = db.import_session_usage_from(&roots)?;
+        assert_eq!(result.files_scanned, 1);
+        assert_eq!(result.imported, 2);
+        assert_eq!(result.duplicates, 1);
+        assert_eq!(result.unpriced, 0);
+
+        // 补全最后一行后只读取新增内容
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&session_file)
+            .unwrap();
+        writeln!(file, "{}", &partial[20..]).unwrap();
+        let result = db.import_session_usage_from(&roots)?;
+        assert_eq!((result.imported, result.duplicates), (1, 0));
+
+        // 无新内容时不再读取
+        let result = db.import_session_usage_from(&roots)?;
+        assert_eq!((result.files_updated, result.imported), (0, 0));
+
+        let logs = db.get_request_logs(
+            &crate::services::usage_stats::LogFilters {
+                source: Some(SOURCE_SESSION_LOG.to_string()),
+                ..Default::default()
+            },
+            0,
+            10,
+        )?;
+        assert_eq!(logs.total, 3);
+        let first = logs
+            .data
+            .iter()
+            .find(|l| l.request_id == "claude:msg_1")
+            .unwrap();
+        // 1000 * 3 + 500 * 15 + 2000 * 0.3 = 11100 / 1M
+        assert_eq!(first.total_cost_usd, "0.0111");
+        assert_eq!(first.session_id.as_deref(), Some("s-1"));
+        assert_eq!(first.project_path.as_deref(), Some("/work/acme"));
+
+        // 导入的用量计入汇总，但不计入代理供应商统计
+        assert_eq!(db.get_usage_summary(None, None, None)?.total_requests, 3);
+        assert!(db.get_provider_stats(None)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_skips_turns_logged_by_proxy() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let dir = tempfile::tempdir().unwrap();
+        let roots = SessionLogRoots {
+            claude: dir.path().join("claude"),
+            codex: dir.path().join("codex"),
+            gemini: dir.path().join("gemini"),
+        };
+        let project_dir = roots.claude.join("projects/-work-acme");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        let session_file = project_dir.join("s-1.jsonl");
+        std::fs::write(&session_file, format!("{}\n", claude_line("msg_1", 500))).unwrap();
+
+        // 代理日志晚于首次导入写入：已导入的重复记录在下次导入时被移除
+        assert_eq!(db.import_session_usage_from(&roots)?.imported, 1);
+        let turn_at = chrono::DateTime::parse_from_rfc3339("2025-10-01T09:58:30Z")
+            .unwrap()
+            .timestamp();
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model, session_id, status_code, latency_ms, created_at)
+                 VALUES ('req-1', 'p1', 'claude', 'claude-sonnet-4-20250514', 's-1', 200, 90000, ?1)",
+                [turn_at],
+            )?;
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&session_file)
+            .unwrap();
+        writeln!(file, "{}", claude_line("msg_2", 100)).unwrap();
+        let result = db.import_session_usage_from(&roots)?;
+        assert_eq!((result.imported, result.proxied), (0, 1));
+
+        let logs = db.get_request_logs(
+            &crate::services::usage_stats::LogFilters {
+                source: Some(SOURCE_SESSION_LOG.to_string()),
+                ..Default::default()
+            },
+            0,
+            10,
+        )?;
+        assert_eq!(logs.total, 0);
+        assert_eq!(db.get_usage_summary(None, None, None)?.total_requests, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_skips_rolled_up_records() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let dir = tempfile::tempdir().unwrap();
+        let roots = SessionLogRoots {
+            claude: dir.path().join("claude"),
+            codex: dir.path().join("codex"),
+            gemini: dir.path().join("gemini"),
+        };
+        let project_dir = roots.claude.join("projects/-work-acme");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        std::fs::write(
+            project_dir.join("s-1.jsonl"),
+            format!("{}\n", claude_line("msg_1", 500)),
+        )
+        .unwrap();
+        assert_eq!(db.import_session_usage_from(&roots)?.imported, 1);
+
+        // 原始日志被聚合删除后，同一消息出现在新文件中也不再重复导入
+        db.rollup_request_logs(chrono::Utc::now().timestamp())?;
+        std::fs::write(
+            project_dir.join("s-1-copy.jsonl"),
+            format!("{}\n", claude_line("msg_1", 500)),
+        )
+        .unwrap();
+        let result = db.import_session_usage_from(&roots)?;
+        assert_eq!((result.imported, result.duplicates), (0, 1));
+        assert_eq!(db.get_usage_summary(None, None, None)?.total_requests, 1);
+        Ok(())
+    }
+}
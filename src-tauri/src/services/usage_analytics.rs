@@ -0,0 +1,492 @@
+//! 延迟与可靠性分析
+//!
+//! - 按供应商 / 模型统计 `latency_ms`、`first_token_ms` 与输出速度（tokens/s）的 p50/p90/p99
+//! - 按状态码和错误类别统计失败请求，并生成星期 × 小时的请求 / 错误热力图
+//!
+//! 仅统计代理流量（会话日志导入的记录没有延迟与状态信息）
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::services::session_usage_import::SOURCE_PROXY;
+use crate::services::usage_stats::{log_filter_clause, LogFilters};
+use chrono::{Datelike, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// 延迟统计的分组维度
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsGroupBy {
+    Provider,
+    Model,
+}
+
+/// 分位数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Percentiles {
+    /// 参与计算的样本数
+    pub samples: u64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl Percentiles {
+    /// 按最近秩（nearest-rank）法计算分位数，无样本时返回 None
+    pub fn from_samples(mut samples: Vec<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by(|a, b| a.total_cmp(b));
+        let rank = |p: f64| {
+            let index = ((p / 100.0) * samples.len() as f64).ceil() as usize;
+            samples[index.clamp(1, samples.len()) - 1]
+        };
+        Some(Self {
+            samples: samples.len() as u64,
+            p50: rank(50.0),
+            p90: rank(90.0),
+            p99: rank(99.0),
+        })
+    }
+}
+
+/// 单个供应商或模型的延迟统计（仅统计成功请求）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    /// 供应商 ID 或模型名称
+    pub key: String,
+    /// 供应商名称（按模型分组时为空）
+    pub name: Option<String>,
+    /// 应用类型（按模型分组时为空）
+    pub app_type: Option<String>,
+    pub request_count: u64,
+    pub latency_ms: Option<Percentiles>,
+    /// 首 Token 延迟（仅流式请求）
+    pub first_token_ms: Option<Percentiles>,
+    /// 输出速度：流式请求按首 Token 之后的时长计算，非流式按总延迟计算
+    pub output_tokens_per_second: Option<Percentiles>,
+}
+
+/// 错误类别
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCategory {
+    /// 429
+    RateLimit,
+    /// 401 / 403
+    Auth,
+    /// 其他 4xx
+    ClientError,
+    /// 上游过载（503 / 529）
+    Overloaded,
+    /// 超时（408 / 504 或错误信息含超时）
+    Timeout,
+    /// 网络或转发失败（502）
+    Network,
+    /// 其他 5xx
+    ServerError,
+    /// 无法归类
+    Other,
+}
+
+impl FailureCategory {
+    /// 根据状态码和错误信息归类
+    pub fn classify(status_code: u16, error_message: Option<&str>) -> Self {
+        let message = error_message.unwrap_or_default().to_lowercase();
+        if status_code == 408
+            || status_code == 504
+            || message.contains("timeout")
+            || message.contains("超时")
+        {
+            return Self::Timeout;
+        }
+        match status_code {
+            429 => Self::RateLimit,
+            401 | 403 => Self::Auth,
+            503 | 529 => Self::Overloaded,
+            502 => Self::Network,
+            400..=499 => Self::ClientError,
+            500..=599 => Self::ServerError,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// 按状态码统计的错误数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusCodeCount {
+    pub status_code: u16,
+    pub count: u64,
+}
+
+/// 按类别统计的错误数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureCategoryCount {
+    pub category: FailureCategory,
+    pub count: u64,
+}
+
+/// 热力图单元（星期 × 小时）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapCell {
+    /// 0 = 周一 … 6 = 周日
+    pub weekday: u32,
+    /// 0 - 23
+    pub hour: u32,
+    pub request_count: u64,
+    pub error_count: u64,
+}
+
+/// 错误分布
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorBreakdown {
+    pub total_requests: u64,
+    pub error_count: u64,
+    /// 错误率（百分比）
+    pub error_rate: f32,
+    /// 按错误数降序
+    pub by_status: Vec<StatusCodeCount>,
+    /// 按错误数降序
+    pub by_category: Vec<FailureCategoryCount>,
+    /// 完整的 7 × 24 网格（按星期、小时排序）
+    pub heatmap: Vec<HeatmapCell>,
+}
+
+/// 用于分析的单条请求
+struct RequestSample {
+    key: String,
+    name: Option<String>,
+    app_type: String,
+    latency_ms: i64,
+    first_token_ms: Option<i64>,
+    output_tokens: i64,
+    is_streaming: bool,
+}
+
+impl RequestSample {
+    /// 输出速度（tokens/s），无输出或耗时未知时返回 None
+    fn output_tokens_per_second(&self) -> Option<f64> {
+        if self.output_tokens <= 0 {
+            return None;
+        }
+        let generation_ms = match self.first_token_ms {
+            Some(first) if self.is_streaming => self.latency_ms - first,
+            _ => self.latency_ms,
+        };
+        (generation_ms > 0).then(|| self.output_tokens as f64 * 1000.0 / generation_ms as f64)
+    }
+}
+
+fn is_success(status_code: u16) -> bool {
+    (200..300).contains(&status_code)
+}
+
+/// 生成代理流量在指定时间范围内的 WHERE 子句（日志表别名为 `l`）
+fn proxy_window_clause(
+    start_date: Option<i64>,
+    end_date: Option<i64>,
+) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+    log_filter_clause(&LogFilters {
+        start_date,
+        end_date,
+        source: Some(SOURCE_PROXY.to_string()),
+        ..Default::default()
+    })
+}
+
+impl Database {
+    /// 获取延迟分位数统计（按供应商或模型分组，按请求数降序）
+    pub fn get_latency_stats(
+        &self,
+        group_by: StatsGroupBy,
+        start_date: Option<i64>,
+        end_date: Option<i64>,
+    ) -> Result<Vec<LatencyStats>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let (where_clause, params) = proxy_window_clause(start_date, end_date);
+        let sql = format!(
+            "SELECT l.provider_id, p.name, l.app_type, l.model,
+                    l.latency_ms, l.first_token_ms, l.output_tokens, l.is_streaming
+             FROM proxy_request_logs l
+             LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
+             {where_clause} AND l.status_code >= 200 AND l.status_code < 300"
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let rows = stmt.query_map(params_refs.as_slice(), |row| {
+            let provider_id: String = row.get(0)?;
+            let model: String = row.get(3)?;
+            Ok(RequestSample {
+                key: match group_by {
+                    StatsGroupBy::Provider => provider_id,
+                    StatsGroupBy::Model => model,
+                },
+                name: row.get(1)?,
+                app_type: row.get(2)?,
+                latency_ms: row.get(4)?,
+                first_token_ms: row.get(5)?,
+                output_tokens: row.get(6)?,
+                is_streaming: row.get::<_, i64>(7)? != 0,
+            })
+        })?;
+
+        let mut groups: HashMap<(String, Option<String>), Vec<RequestSample>> = HashMap::new();
+        for row in rows {
+            let sample = row?;
+            let app_type = match group_by {
+                StatsGroupBy::Provider => Some(sample.app_type.clone()),
+                StatsGroupBy::Model => None,
+            };
+            groups
+                .entry((sample.key.clone(), app_type))
+                .or_default()
+                .push(sample);
+        }
+
+        let mut stats: Vec<LatencyStats> = groups
+            .into_iter()
+            .map(|((key, app_type), samples)| LatencyStats {
+                name: match group_by {
+                    StatsGroupBy::Provider => samples.iter().find_map(|s| s.name.clone()),
+                    StatsGroupBy::Model => None,
+                },
+                request_count: samples.len() as u64,
+                latency_ms: Percentiles::from_samples(
+                    samples.iter().map(|s| s.latency_ms as f64).collect(),
+                ),
+                first_token_ms: Percentiles::from_samples(
+                    samples
+                        .iter()
+                        .filter(|s| s.is_streaming)
+                        .filter_map(|s| s.first_token_ms)
+                        .map(|v| v as f64)
+                        .collect(),
+                ),
+                output_tokens_per_second: Percentiles::from_samples(
+                    samples
+                        .iter()
+                        .filter_map(RequestSample::output_tokens_per_second)
+                        .collect(),
+                ),
+                key,
+                app_type,
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.request_count
+                .cmp(&a.request_count)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+
+        Ok(stats)
+    }
+
+    /// 获取错误分布与热力图
+    ///
+    /// `utc_offset_minutes` 为热力图使用的时区偏移（如 UTC+8 传 480），默认按 UTC 统计
+    pub fn get_error_breakdown(
+        &self,
+        start_date: Option<i64>,
+        end_date: Option<i64>,
+        utc_offset_minutes: Option<i32>,
+    ) -> Result<ErrorBreakdown, AppError> {
+        let conn = lock_conn!(self.conn);
+        let (where_clause, params) = proxy_window_clause(start_date, end_date);
+        let sql = format!(
+            "SELECT l.created_at, l.status_code, l.error_message
+             FROM proxy_request_logs l
+             {where_clause}"
+        );
+        let offset = i64::from(utc_offset_minutes.unwrap_or(0)) * 60;
+
+        let mut heatmap = vec![(0u64, 0u64); 7 * 24];
+        let mut by_status: BTreeMap<u16, u64> = BTreeMap::new();
+        let mut by_category: BTreeMap<FailureCategory, u64> = BTreeMap::new();
+        let mut total_requests = 0u64;
+        let mut error_count = 0u64;
+
+        let mut stmt = conn.prepare(&sql)?;
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let mut rows = stmt.query(params_refs.as_slice())?;
+        while let Some(row) = rows.next()? {
+            let created_at: i64 = row.get(0)?;
+            let status_code = row.get::<_, i64>(1)? as u16;
+            let error_message: Option<String> = row.get(2)?;
+
+            let time = chrono::DateTime::from_timestamp(created_at + offset, 0).unwrap_or_default();
+            let cell = &mut heatmap
+                [time.weekday().num_days_from_monday() as usize * 24 + time.hour() as usize];
+            cell.0 += 1;
+            total_requests += 1;
+
+            if !is_success(status_code) {
+                cell.1 += 1;
+                error_count += 1;
+                *by_status.entry(status_code).or_default() += 1;
+                *by_category
+                    .entry(FailureCategory::classify(
+                        status_code,
+                        error_message.as_deref(),
+                    ))
+                    .or_default() += 1;
+            }
+        }
+
+        let mut by_status: Vec<StatusCodeCount> = by_status
+            .into_iter()
+            .map(|(status_code, count)| StatusCodeCount { status_code, count })
+            .collect();
+        by_status.sort_by_key(|c| std::cmp::Reverse(c.count));
+        let mut by_category: Vec<FailureCategoryCount> = by_category
+            .into_iter()
+            .map(|(category, count)| FailureCategoryCount { category, count })
+            .collect();
+        by_category.sort_by_key(|c| std::cmp::Reverse(c.count));
+
+        Ok(ErrorBreakdown {
+            total_requests,
+            error_count,
+            error_rate: if total_requests > 0 {
+                (error_count as f32 / total_requests as f32) * 100.0
+            } else {
+                0.0
+            },
+            by_status,
+            by_category,
+            heatmap: heatmap
+                .into_iter()
+                .enumerate()
+                .map(|(i, (request_count, error_count))| HeatmapCell {
+                    weekday: (i / 24) as u32,
+                    hour: (i % 24) as u32,
+                    request_count,
+                    error_count,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+
+    #[test]
+    fn test_percentiles_nearest_rank() {
+        let p = Percentiles::from_samples((1..=100).map(f64::from).collect()).unwrap();
+        assert_eq!((p.samples, p.p50, p.p90, p.p99), (100, 50.0, 90.0, 99.0));
+        let p = Percentiles::from_samples(vec![7.0]).unwrap();
+        assert_eq!((p.p50, p.p99), (7.0, 7.0));
+        assert!(Percentiles::from_samples(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_failure_category_classify() {
+        assert_eq!(
+            FailureCategory::classify(429, None),
+            FailureCategory::RateLimit
+        );
+        assert_eq!(FailureCategory::classify(403, None), FailureCategory::Auth);
+        assert_eq!(
+            FailureCategory::classify(529, None),
+            FailureCategory::Overloaded
+        );
+        assert_eq!(
+            FailureCategory::classify(502, Some("请求转发失败: connection timeout")),
+            FailureCategory::Timeout
+        );
+        assert_eq!(
+            FailureCategory::classify(502, None),
+            FailureCategory::Network
+        );
+        assert_eq!(
+            FailureCategory::classify(422, None),
+            FailureCategory::ClientError
+        );
+    }
+
+    #[test]
+    fn test_latency_stats_and_error_breakdown() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        // 2025-10-13 为周一，10:00 UTC
+        let monday_10 = 1_760_349_600;
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO providers (id, app_type, name, settings_config) VALUES ('p1', 'claude', 'Relay', '{}')",
+                [],
+            )?;
+            for i in 0..10i64 {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model, output_tokens,
+                        latency_ms, first_token_ms, is_streaming, status_code, created_at
+                    ) VALUES (?1, 'p1', 'claude', 'claude-sonnet-4', 100, ?2, ?3, 1, 200, ?4)",
+                    params![format!("ok{i}"), 1000 + i * 100, 200 + i * 10, monday_10],
+                )?;
+            }
+            for (id, status, message) in [
+                ("e1", 429, "rate limited"),
+                ("e2", 429, "rate limited"),
+                ("e3", 504, "超时: upstream"),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model,
+                        latency_ms, status_code, error_message, created_at
+                    ) VALUES (?1, 'p1', 'claude', 'claude-sonnet-4', 50000, ?2, ?3, ?4)",
+                    params![id, status, message, monday_10 + 3600],
+                )?;
+            }
+            // 会话日志导入的记录不参与分析
+            conn.execute(
+                "INSERT INTO proxy_request_logs (
+                    request_id, provider_id, app_type, model, latency_ms, status_code, created_at, source
+                ) VALUES ('imported', 'session_log', 'claude', 'claude-sonnet-4', 0, 200, ?1, 'session_log')",
+                [monday_10],
+            )?;
+        }
+
+        let stats = db.get_latency_stats(StatsGroupBy::Provider, None, None)?;
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].key, "p1");
+        assert_eq!(stats[0].name.as_deref(), Some("Relay"));
+        assert_eq!(stats[0].request_count, 10);
+        let latency = stats[0].latency_ms.as_ref().unwrap();
+        assert_eq!(
+            (latency.p50, latency.p90, latency.p99),
+            (1400.0, 1800.0, 1900.0)
+        );
+        assert_eq!(stats[0].first_token_ms.as_ref().unwrap().p50, 240.0);
+        // 最快一次：100 tokens / (1000 - 200) ms
+        let tps = stats[0].output_tokens_per_second.as_ref().unwrap();
+        assert_eq!(tps.p99, 125.0);
+
+        let by_model = db.get_latency_stats(StatsGroupBy::Model, Some(monday_10 + 1), None)?;
+        assert!(by_model.is_empty());
+
+        let breakdown = db.get_error_breakdown(None, None, Some(480))?;
+        assert_eq!((breakdown.total_requests, breakdown.error_count), (13, 3));
+        assert_eq!(breakdown.by_status[0].status_code, 429);
+        assert_eq!(breakdown.by_status[0].count, 2);
+        assert_eq!(
+            breakdown.by_category[0].category,
+            FailureCategory::RateLimit
+        );
+        assert_eq!(breakdown.heatmap.len(), 7 * 24);
+        // UTC+8：周一 18:00 / 19:00
+        let cell = |hour: u32| breakdown.heatmap[hour as usize].clone();
+        assert_eq!((cell(18).request_count, cell(18).error_count), (10, 0));
+        assert_eq!((cell(19).request_count, cell(19).error_count), (3, 3));
+        Ok(())
+    }
+}
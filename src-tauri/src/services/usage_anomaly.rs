@@ -0,0 +1,727 @@
+//! 用量异常检测与告警
+//!
+//! 后台定期扫描 `proxy_request_logs`，按规则检测三类异常：
+//! - 花费速率：供应商最近窗口内的花费超过历史基线速率的 N 倍
+//! - 请求突发：单个会话在短时间内的请求数超过上限
+//! - 请求循环：同一会话反复发送指纹相同的请求（模型 + 最后一条消息）
+//!
+//! 告警写入 `usage_alerts` 并通过 `usage-anomaly` 事件通知前端（应用内提示与系统通知）；
+//! 开启自动暂停后，对应的供应商或会话会写入 `usage_pauses`：暂停的供应商在故障转移中被跳过，
+//! 暂停的会话在恢复前请求被拒绝
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::services::session_usage_import::SOURCE_PROXY;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::Emitter;
+
+/// 告警规则的存储键
+const ANOMALY_RULES_SETTING: &str = "usage_anomaly_rules";
+
+/// 后台检测间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 花费速率规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SpendRateRule {
+    pub enabled: bool,
+    /// 检测窗口（分钟）
+    pub window_minutes: u32,
+    /// 基线统计时长（小时，紧接检测窗口之前）
+    pub baseline_hours: u32,
+    /// 超过基线速率的倍数
+    pub multiplier: f64,
+    /// 窗口内最低触发花费（USD），避免基线接近 0 时误报
+    pub min_spend_usd: f64,
+}
+
+impl Default for SpendRateRule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_minutes: 60,
+            baseline_hours: 24,
+            multiplier: 3.0,
+            min_spend_usd: 2.0,
+        }
+    }
+}
+
+/// 会话请求突发规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SessionBurstRule {
+    pub enabled: bool,
+    pub window_minutes: u32,
+    /// 窗口内单个会话允许的最大请求数
+    pub max_requests: u32,
+}
+
+impl Default for SessionBurstRule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_minutes: 5,
+            max_requests: 60,
+        }
+    }
+}
+
+/// 重复请求（循环）规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RequestLoopRule {
+    pub enabled: bool,
+    pub window_minutes: u32,
+    /// 窗口内同一指纹出现的次数达到该值即视为循环
+    pub max_repeats: u32,
+}
+
+impl Default for RequestLoopRule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_minutes: 10,
+            max_repeats: 6,
+        }
+    }
+}
+
+/// 用量异常告警规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnomalyRules {
+    pub enabled: bool,
+    pub spend_rate: SpendRateRule,
+    pub session_burst: SessionBurstRule,
+    pub request_loop: RequestLoopRule,
+    /// 触发告警时自动暂停对应的供应商（花费速率）或会话（突发 / 循环）
+    pub auto_pause: bool,
+    /// 同一对象同类告警的冷却时间（分钟）
+    pub cooldown_minutes: u32,
+}
+
+impl Default for AnomalyRules {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            spend_rate: SpendRateRule::default(),
+            session_burst: SessionBurstRule::default(),
+            request_loop: RequestLoopRule::default(),
+            auto_pause: false,
+            cooldown_minutes: 30,
+        }
+    }
+}
+
+/// 异常类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    SpendRate,
+    SessionBurst,
+    RequestLoop,
+}
+
+impl AnomalyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnomalyKind::SpendRate => "spend_rate",
+            AnomalyKind::SessionBurst => "session_burst",
+            AnomalyKind::RequestLoop => "request_loop",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "session_burst" => AnomalyKind::SessionBurst,
+            "request_loop" => AnomalyKind::RequestLoop,
+            _ => AnomalyKind::SpendRate,
+        }
+    }
+}
+
+/// 用量异常告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageAlert {
+    pub id: i64,
+    pub kind: AnomalyKind,
+    pub app_type: Option<String>,
+    pub provider_id: Option<String>,
+    pub session_id: Option<String>,
+    pub message: String,
+    /// 观测值（花费 USD 或请求数）
+    pub observed: f64,
+    /// 触发阈值
+    pub threshold: f64,
+    /// 是否已自动暂停
+    pub paused: bool,
+    pub acknowledged: bool,
+    pub created_at: i64,
+}
+
+/// 暂停对象类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PauseKind {
+    Provider,
+    Session,
+}
+
+impl PauseKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PauseKind::Provider => "provider",
+            PauseKind::Session => "session",
+        }
+    }
+}
+
+/// 因用量异常暂停的供应商或会话
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct UsagePause {
+    pub kind: PauseKind,
+    /// 供应商为 `app_type:provider_id`，会话为会话 ID
+    pub target: String,
+    pub alert_id: Option<i64>,
+    pub reason: String,
+    pub created_at: i64,
+}
+
+/// 检测到的异常（尚未写入告警历史）
+#[derive(Debug, Clone)]
+struct Anomaly {
+    kind: AnomalyKind,
+    app_type: String,
+    provider_id: String,
+    session_id: Option<String>,
+    message: String,
+    observed: f64,
+    threshold: f64,
+}
+
+impl Anomaly {
+    /// 自动暂停的对象：花费速率暂停供应商，突发 / 循环优先暂停会话
+    fn pause_target(&self) -> (PauseKind, String) {
+        match (&self.kind, &self.session_id) {
+            (AnomalyKind::SpendRate, _) | (_, None) => (
+                PauseKind::Provider,
+                format!("{}:{}", self.app_type, self.provider_id),
+            ),
+            (_, Some(session_id)) => (PauseKind::Session, session_id.clone()),
+        }
+    }
+}
+
+impl Database {
+    /// 获取告警规则
+    pub fn get_anomaly_rules(&self) -> Result<AnomalyRules, AppError> {
+        match self.get_setting(ANOMALY_RULES_SETTING)? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Message(format!("解析告警规则失败: {e}"))),
+            None => Ok(AnomalyRules::default()),
+        }
+    }
+
+    /// 保存告警规则
+    pub fn set_anomaly_rules(&self, rules: &AnomalyRules) -> Result<(), AppError> {
+        if rules.spend_rate.window_minutes == 0
+            || rules.spend_rate.baseline_hours == 0
+            || rules.session_burst.window_minutes == 0
+            || rules.request_loop.window_minutes == 0
+        {
+            return Err(AppError::InvalidInput("检测窗口必须大于 0".to_string()));
+        }
+        if rules.spend_rate.multiplier < 1.0 {
+            return Err(AppError::InvalidInput("花费倍数不能小于 1".to_string()));
+        }
+        if rules.request_loop.max_repeats < 2 {
+            return Err(AppError::InvalidInput("重复次数阈值不能小于 2".to_string()));
+        }
+
+        let json = serde_json::to_string(rules)
+            .map_err(|e| AppError::Message(format!("序列化告警规则失败: {e}")))?;
+        self.set_setting(ANOMALY_RULES_SETTING, &json)
+    }
+
+    /// 执行一次异常检测，返回新产生的告警（冷却期内的重复告警被忽略）
+    pub fn run_anomaly_check(&self, now: i64) -> Result<Vec<UsageAlert>, AppError> {
+        let rules = self.get_anomaly_rules()?;
+        if !rules.enabled {
+            return Ok(Vec::new());
+        }
+
+        let conn = lock_conn!(self.conn);
+        let mut anomalies = Vec::new();
+        if rules.spend_rate.enabled {
+            anomalies.extend(detect_spend_rate(&conn, &rules.spend_rate, now)?);
+        }
+        if rules.session_burst.enabled {
+            anomalies.extend(detect_session_burst(&conn, &rules.session_burst, now)?);
+        }
+        if rules.request_loop.enabled {
+            anomalies.extend(detect_request_loop(&conn, &rules.request_loop, now)?);
+        }
+
+        let cooldown_start = now - i64::from(rules.cooldown_minutes) * 60;
+        let mut alerts = Vec::new();
+        for anomaly in anomalies {
+            let recent: bool = conn.query_row(
+                "SELECT EXISTS(
+                    SELECT 1 FROM usage_alerts
+                    WHERE kind = ?1 AND app_type IS ?2 AND provider_id IS ?3
+                      AND session_id IS ?4 AND created_at >= ?5)",
+                params![
+                    anomaly.kind.as_str(),
+                    anomaly.app_type,
+                    anomaly.provider_id,
+                    anomaly.session_id,
+                    cooldown_start
+                ],
+                |row| row.get(0),
+            )?;
+            if recent {
+                continue;
+            }
+
+            conn.execute(
+                "INSERT INTO usage_alerts (
+                    kind, app_type, provider_id, session_id, message,
+                    observed, threshold, paused, created_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    anomaly.kind.as_str(),
+                    anomaly.app_type,
+                    anomaly.provider_id,
+                    anomaly.session_id,
+                    anomaly.message,
+                    anomaly.observed,
+                    anomaly.threshold,
+                    rules.auto_pause,
+                    now
+                ],
+            )?;
+            let id = conn.last_insert_rowid();
+
+            if rules.auto_pause {
+                let (kind, target) = anomaly.pause_target();
+                conn.execute(
+                    "INSERT OR REPLACE INTO usage_pauses (kind, target, alert_id, reason, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![kind.as_str(), target, id, anomaly.message, now],
+                )?;
+            }
+
+            alerts.push(UsageAlert {
+                id,
+                kind: anomaly.kind,
+                app_type: Some(anomaly.app_type),
+                provider_id: Some(anomaly.provider_id),
+                session_id: anomaly.session_id,
+                message: anomaly.message,
+                observed: anomaly.observed,
+                threshold: anomaly.threshold,
+                paused: rules.auto_pause,
+                acknowledged: false,
+                created_at: now,
+            });
+        }
+
+        Ok(alerts)
+    }
+
+    /// 获取告警历史（按时间倒序）
+    pub fn get_usage_alerts(&self, limit: Option<u32>) -> Result<Vec<UsageAlert>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, app_type, provider_id, session_id, message,
+                    observed, threshold, paused, acknowledged, created_at
+             FROM usage_alerts
+             ORDER BY created_at DESC, id DESC
+             LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit.map(i64::from).unwrap_or(-1)], |row| {
+            Ok(UsageAlert {
+                id: row.get(0)?,
+                kind: AnomalyKind::parse(&row.get::<_, String>(1)?),
+                app_type: row.get(2)?,
+                provider_id: row.get(3)?,
+                session_id: row.get(4)?,
+                message: row.get(5)?,
+                observed: row.get(6)?,
+                threshold: row.get(7)?,
+                paused: row.get(8)?,
+                acknowledged: row.get(9)?,
+                created_at: row.get(10)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 标记告警为已读
+    pub fn acknowledge_usage_alert(&self, id: i64) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "UPDATE usage_alerts SET acknowledged = 1 WHERE id = ?1",
+            [id],
+        )?;
+        Ok(())
+    }
+
+    /// 获取当前暂停的供应商与会话
+    pub fn get_usage_pauses(&self) -> Result<Vec<UsagePause>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let mut stmt = conn.prepare(
+            "SELECT kind, target, alert_id, reason, created_at
+             FROM usage_pauses ORDER BY created_at DESC",
+        )?;
+        let rows = stmt.query_map([], pause_from_row)?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 解除暂停
+    pub fn resume_usage_pause(&self, kind: PauseKind, target: &str) -> Result<bool, AppError> {
+        let conn = lock_conn!(self.conn);
+        let removed = conn.execute(
+            "DELETE FROM usage_pauses WHERE kind = ?1 AND target = ?2",
+            params![kind.as_str(), target],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// 查找请求命中的暂停记录（供应商或会话）
+    pub fn find_usage_pause(
+        &self,
+        app_type: &str,
+        provider_id: &str,
+        session_id: Option<&str>,
+    ) -> Result<Option<UsagePause>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let pause = conn
+            .query_row(
+                "SELECT kind, target, alert_id, reason, created_at FROM usage_pauses
+                 WHERE (kind = 'provider' AND target = ?1) OR (kind = 'session' AND target = ?2)
+                 LIMIT 1",
+                params![format!("{app_type}:{provider_id}"), session_id],
+                pause_from_row,
+            )
+            .optional()?;
+        Ok(pause)
+    }
+
+    /// 查找会话的暂停记录
+    pub fn find_session_pause(&self, session_id: &str) -> Result<Option<UsagePause>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let pause = conn
+            .query_row(
+                "SELECT kind, target, alert_id, reason, created_at FROM usage_pauses
+                 WHERE kind = 'session' AND target = ?1",
+                [session_id],
+                pause_from_row,
+            )
+            .optional()?;
+        Ok(pause)
+    }
+}
+
+/// 读取 `kind, target, alert_id, reason, created_at` 行
+fn pause_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<UsagePause> {
+    Ok(UsagePause {
+        kind: if row.get::<_, String>(0)? == "session" {
+            PauseKind::Session
+        } else {
+            PauseKind::Provider
+        },
+        target: row.get(1)?,
+        alert_id: row.get(2)?,
+        reason: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+/// 检测花费速率异常（按供应商）
+fn detect_spend_rate(
+    conn: &Connection,
+    rule: &SpendRateRule,
+    now: i64,
+) -> Result<Vec<Anomaly>, AppError> {
+    let window = i64::from(rule.window_minutes) * 60;
+    let baseline = i64::from(rule.baseline_hours) * 3600;
+    let window_start = now - window;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT l.app_type, l.provider_id, COALESCE(MAX(p.name), l.provider_id),
+                COALESCE(SUM(CASE WHEN l.created_at >= ?2 THEN CAST(l.total_cost_usd AS REAL) END), 0),
+                COALESCE(SUM(CASE WHEN l.created_at < ?2 THEN CAST(l.total_cost_usd AS REAL) END), 0)
+         FROM proxy_request_logs l
+         LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
+         WHERE l.created_at >= ?1 AND l.created_at <= ?3 AND l.source = '{SOURCE_PROXY}'
+         GROUP BY l.app_type, l.provider_id"
+    ))?;
+    let rows = stmt.query_map(params![window_start - baseline, window_start, now], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, f64>(3)?,
+            row.get::<_, f64>(4)?,
+        ))
+    })?;
+
+    let mut anomalies = Vec::new();
+    for row in rows {
+        let (app_type, provider_id, name, recent, history) = row?;
+        let baseline_rate = history * window as f64 / baseline as f64;
+        let threshold = (baseline_rate * rule.multiplier).max(rule.min_spend_usd);
+        if recent >= threshold {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::SpendRate,
+                message: format!(
+                    "供应商 {name} 最近 {} 分钟花费 ${recent:.2}，超过基线 ${baseline_rate:.2} 的 {} 倍",
+                    rule.window_minutes, rule.multiplier
+                ),
+                app_type,
+                provider_id,
+                session_id: None,
+                observed: recent,
+                threshold,
+            });
+        }
+    }
+    Ok(anomalies)
+}
+
+/// 检测单个会话的请求突发
+fn detect_session_burst(
+    conn: &Connection,
+    rule: &SessionBurstRule,
+    now: i64,
+) -> Result<Vec<Anomaly>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT MAX(app_type), MAX(provider_id), session_id, COUNT(*)
+         FROM proxy_request_logs
+         WHERE created_at >= ?1 AND session_id IS NOT NULL AND source = '{SOURCE_PROXY}'
+         GROUP BY session_id
+         HAVING COUNT(*) > ?2"
+    ))?;
+    let rows = stmt.query_map(
+        params![now - i64::from(rule.window_minutes) * 60, rule.max_requests],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        },
+    )?;
+
+    let mut anomalies = Vec::new();
+    for row in rows {
+        let (app_type, provider_id, session_id, count) = row?;
+        anomalies.push(Anomaly {
+            kind: AnomalyKind::SessionBurst,
+            message: format!(
+                "会话 {session_id} 最近 {} 分钟发送了 {count} 个请求（上限 {}）",
+                rule.window_minutes, rule.max_requests
+            ),
+            app_type,
+            provider_id,
+            session_id: Some(session_id),
+            observed: count as f64,
+            threshold: f64::from(rule.max_requests),
+        });
+    }
+    Ok(anomalies)
+}
+
+/// 检测重复请求（同一会话内相同指纹反复出现）
+fn detect_request_loop(
+    conn: &Connection,
+    rule: &RequestLoopRule,
+    now: i64,
+) -> Result<Vec<Anomaly>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT MAX(app_type), MAX(provider_id), session_id, MAX(model), COUNT(*)
+         FROM proxy_request_logs
+         WHERE created_at >= ?1 AND request_hash IS NOT NULL AND source = '{SOURCE_PROXY}'
+         GROUP BY session_id, request_hash
+         HAVING COUNT(*) >= ?2"
+    ))?;
+    let rows = stmt.query_map(
+        params![now - i64::from(rule.window_minutes) * 60, rule.max_repeats],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        },
+    )?;
+
+    let mut anomalies = Vec::new();
+    for row in rows {
+        let (app_type, provider_id, session_id, model, count) = row?;
+        let origin = session_id
+            .as_deref()
+            .map(|id| format!("会话 {id}"))
+            .unwrap_or_else(|| format!("供应商 {provider_id}"));
+        anomalies.push(Anomaly {
+            kind: AnomalyKind::RequestLoop,
+            message: format!(
+                "{origin} 最近 {} 分钟重复发送了 {count} 次相同的 {model} 请求，疑似循环",
+                rule.window_minutes
+            ),
+            app_type,
+            provider_id,
+            session_id,
+            observed: count as f64,
+            threshold: f64::from(rule.max_repeats),
+        });
+    }
+    Ok(anomalies)
+}
+
+/// 启动后台异常检测（每分钟一次），新告警通过 `usage-anomaly` 事件推送给前端
+pub fn spawn_usage_anomaly_watcher(app: tauri::AppHandle, db: Arc<Database>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let db = db.clone();
+            let result =
+                tokio::task::spawn_blocking(move || db.run_anomaly_check(Utc::now().timestamp()))
+                    .await;
+            match result {
+                Ok(Ok(alerts)) => {
+                    for alert in alerts {
+                        log::warn!("用量异常: {}", alert.message);
+                        if let Err(e) = app.emit("usage-anomaly", &alert) {
+                            log::error!("发射用量异常事件失败: {e}");
+                        }
+                    }
+                }
+                Ok(Err(e)) => log::warn!("用量异常检测失败: {e}"),
+                Err(e) => log::warn!("用量异常检测任务异常: {e}"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert_log(
+        db: &Database,
+        session_id: Option<&str>,
+        request_hash: &str,
+        cost: &str,
+        created_at: i64,
+    ) -> Result<(), AppError> {
+        let conn = lock_conn!(db.conn);
+        conn.execute(
+            "INSERT INTO proxy_request_logs (
+                request_id, provider_id, app_type, model, total_cost_usd,
+                latency_ms, status_code, session_id, request_hash, created_at
+            ) VALUES (?1, 'p1', 'claude', 'claude-sonnet-4-5', ?2, 100, 200, ?3, ?4, ?5)",
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                cost,
+                session_id,
+                request_hash,
+                created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_spend_rate_anomaly_and_auto_pause() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let now = 1_700_000_000;
+        db.set_anomaly_rules(&AnomalyRules {
+            auto_pause: true,
+            ..Default::default()
+        })?;
+
+        // 基线：过去 24 小时每小时 0.1 USD
+        for hour in 2..26 {
+            insert_log(&db, None, &format!("h{hour}"), "0.1", now - hour * 3600)?;
+        }
+        assert!(db.run_anomaly_check(now)?.is_empty());
+
+        // 最近一小时花费 5 USD，远超基线
+        insert_log(&db, None, "burst", "5", now - 60)?;
+        let alerts = db.run_anomaly_check(now)?;
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, AnomalyKind::SpendRate);
+        assert!(alerts[0].paused);
+        assert!(db.find_usage_pause("claude", "p1", None)?.is_some());
+
+        // 冷却期内不重复告警
+        assert!(db.run_anomaly_check(now + 60)?.is_empty());
+        assert_eq!(db.get_usage_alerts(None)?.len(), 1);
+
+        assert!(db.resume_usage_pause(PauseKind::Provider, "claude:p1")?);
+        assert!(db.find_usage_pause("claude", "p1", None)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_burst_and_request_loop() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let now = 1_700_000_000;
+        db.set_anomaly_rules(&AnomalyRules {
+            auto_pause: true,
+            spend_rate: SpendRateRule {
+                enabled: false,
+                ..Default::default()
+            },
+            session_burst: SessionBurstRule {
+                max_requests: 5,
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+
+        for i in 0..6 {
+            insert_log(&db, Some("s1"), "same", "0.01", now - i * 10)?;
+            insert_log(&db, Some("s2"), &format!("h{i}"), "0.01", now - i * 10)?;
+        }
+
+        let alerts = db.run_anomaly_check(now)?;
+        let kinds: Vec<_> = alerts
+            .iter()
+            .map(|a| (a.kind, a.session_id.as_deref()))
+            .collect();
+        assert!(kinds.contains(&(AnomalyKind::RequestLoop, Some("s1"))));
+        assert!(kinds.contains(&(AnomalyKind::SessionBurst, Some("s1"))));
+        assert!(kinds.contains(&(AnomalyKind::SessionBurst, Some("s2"))));
+        assert!(!kinds.contains(&(AnomalyKind::RequestLoop, Some("s2"))));
+
+        // 暂停的是会话而不是供应商
+        assert!(db.find_usage_pause("claude", "p1", None)?.is_none());
+        assert!(db.find_session_pause("s1")?.is_some());
+        assert!(db.find_session_pause("s3")?.is_none());
+        let pause = db.find_usage_pause("claude", "p1", Some("s1"))?.unwrap();
+        assert_eq!(pause.kind, PauseKind::Session);
+
+        let id = alerts[0].id;
+        db.acknowledge_usage_alert(id)?;
+        assert!(db
+            .get_usage_alerts(None)?
+            .iter()
+            .any(|a| a.id == id && a.acknowledged));
+        Ok(())
+    }
+}
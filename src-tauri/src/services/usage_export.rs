@@ -0,0 +1,997 @@
+//! 使用数据导出与定期报表
+//!
+//! - 按 `LogFilters` 将 `proxy_request_logs` 导出为 CSV、JSON Lines 或 Parquet（供数仓加载）
+//! - 按配置每日 / 每周在指定目录生成汇总报表（按供应商、模型、应用、会话分组）
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::services::currency::{cost_sql, resolve_report_currency};
+use crate::services::usage_stats::{log_filter_clause, LogFilters, UsageSummary};
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDate, Utc};
+use parquet::basic::Compression;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 每批读取的日志条数（Parquet 每批写一个 row group）
+const EXPORT_BATCH_SIZE: i64 = 10_000;
+
+/// 报表配置的设置键
+const REPORT_CONFIG_SETTING: &str = "usage_report_config";
+
+/// 报表中会话维度最多列出的条数（按成本排序）
+const REPORT_SESSION_LIMIT: i64 = 50;
+
+/// 报表调度检查间隔
+const REPORT_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    Parquet,
+}
+
+/// 导出结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub path: String,
+    pub rows: u64,
+}
+
+/// 导出的单条请求日志（字段名即导出列名）
+#[derive(Debug, Clone, Serialize)]
+struct ExportRow {
+    request_id: String,
+    provider_id: String,
+    provider_name: Option<String>,
+    app_type: String,
+    model: String,
+    input_tokens: i64,
+    output_tokens: i64,
+    cache_read_tokens: i64,
+    cache_creation_tokens: i64,
+    input_cost_usd: f64,
+    output_cost_usd: f64,
+    cache_read_cost_usd: f64,
+    cache_creation_cost_usd: f64,
+    total_cost_usd: f64,
+    billing_currency: String,
+    billing_cost: Option<f64>,
+    cost_multiplier: f64,
+    latency_ms: i64,
+    first_token_ms: Option<i64>,
+    duration_ms: Option<i64>,
+    status_code: i64,
+    error_message: Option<String>,
+    session_id: Option<String>,
+    provider_type: Option<String>,
+    is_streaming: bool,
+    /// Unix 秒
+    created_at: i64,
+    project_path: Option<String>,
+    source: String,
+}
+
+/// Parquet schema（列顺序与 [`ExportRow::csv_fields`] 一致）
+const PARQUET_SCHEMA: &str = "
+message request_log {
+    REQUIRED BYTE_ARRAY request_id (UTF8);
+    REQUIRED BYTE_ARRAY provider_id (UTF8);
+    OPTIONAL BYTE_ARRAY provider_name (UTF8);
+    REQUIRED BYTE_ARRAY app_type (UTF8);
+    REQUIRED BYTE_ARRAY model (UTF8);
+    REQUIRED INT64 input_tokens;
+    REQUIRED INT64 output_tokens;
+    REQUIRED INT64 cache_read_tokens;
+    REQUIRED INT64 cache_creation_tokens;
+    REQUIRED DOUBLE input_cost_usd;
+    REQUIRED DOUBLE output_cost_usd;
+    REQUIRED DOUBLE cache_read_cost_usd;
+    REQUIRED DOUBLE cache_creation_cost_usd;
+    REQUIRED DOUBLE total_cost_usd;
+    REQUIRED BYTE_ARRAY billing_currency (UTF8);
+    OPTIONAL DOUBLE billing_cost;
+    REQUIRED DOUBLE cost_multiplier;
+    REQUIRED INT64 latency_ms;
+    OPTIONAL INT64 first_token_ms;
+    OPTIONAL INT64 duration_ms;
+    REQUIRED INT64 status_code;
+    OPTIONAL BYTE_ARRAY error_message (UTF8);
+    OPTIONAL BYTE_ARRAY session_id (UTF8);
+    OPTIONAL BYTE_ARRAY provider_type (UTF8);
+    REQUIRED BOOLEAN is_streaming;
+    REQUIRED INT64 created_at;
+    OPTIONAL BYTE_ARRAY project_path (UTF8);
+    REQUIRED BYTE_ARRAY source (UTF8);
+}";
+
+const CSV_HEADER: &str = "request_id,provider_id,provider_name,app_type,model,\
+input_tokens,output_tokens,cache_read_tokens,cache_creation_tokens,\
+input_cost_usd,output_cost_usd,cache_read_cost_usd,cache_creation_cost_usd,total_cost_usd,\
+billing_currency,billing_cost,cost_multiplier,latency_ms,first_token_ms,duration_ms,\
+status_code,error_message,session_id,provider_type,is_streaming,created_at,project_path,source";
+
+impl ExportRow {
+    fn csv_fields(&self) -> Vec<String> {
+        fn opt<T: ToString>(v: &Option<T>) -> String {
+            v.as_ref().map(ToString::to_string).unwrap_or_default()
+        }
+        vec![
+            self.request_id.clone(),
+            self.provider_id.clone(),
+            opt(&self.provider_name),
+            self.app_type.clone(),
+            self.model.clone(),
+            self.input_tokens.to_string(),
+            self.output_tokens.to_string(),
+            self.cache_read_tokens.to_string(),
+            self.cache_creation_tokens.to_string(),
+            self.input_cost_usd.to_string(),
+            self.output_cost_usd.to_string(),
+            self.cache_read_cost_usd.to_string(),
+            self.cache_creation_cost_usd.to_string(),
+            self.total_cost_usd.to_string(),
+            self.billing_currency.clone(),
+            opt(&self.billing_cost),
+            self.cost_multiplier.to_string(),
+            self.latency_ms.to_string(),
+            opt(&self.first_token_ms),
+            opt(&self.duration_ms),
+            self.status_code.to_string(),
+            opt(&self.error_message),
+            opt(&self.session_id),
+            opt(&self.provider_type),
+            self.is_streaming.to_string(),
+            self.created_at.to_string(),
+            opt(&self.project_path),
+            self.source.clone(),
+        ]
+    }
+}
+
+/// CSV 字段转义（含逗号、引号或换行时用双引号包裹）
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_csv_line<W: Write>(out: &mut W, fields: &[String]) -> Result<(), AppError> {
+    let line = fields
+        .iter()
+        .map(|f| csv_escape(f))
+        .collect::<Vec<_>>()
+        .join(",");
+    writeln!(out, "{line}").map_err(|e| AppError::IoContext {
+        context: "写入 CSV 失败".to_string(),
+        source: e,
+    })
+}
+
+fn parquet_error(e: parquet::errors::ParquetError) -> AppError {
+    AppError::Message(format!("写入 Parquet 失败: {e}"))
+}
+
+/// 按列写入一批日志（一个 row group）
+fn write_parquet_batch<W: Write + Send>(
+    writer: &mut SerializedFileWriter<W>,
+    rows: &[ExportRow],
+) -> Result<(), AppError> {
+    fn strings(values: impl Iterator<Item = String>) -> Vec<ByteArray> {
+        values.map(|v| ByteArray::from(v.into_bytes())).collect()
+    }
+    fn opt_strings(values: impl Iterator<Item = Option<String>>) -> (Vec<ByteArray>, Vec<i16>) {
+        let mut data = Vec::new();
+        let mut levels = Vec::new();
+        for value in values {
+            levels.push(value.is_some() as i16);
+            data.extend(value.map(|v| ByteArray::from(v.into_bytes())));
+        }
+        (data, levels)
+    }
+    fn opt_values<T>(values: impl Iterator<Item = Option<T>>) -> (Vec<T>, Vec<i16>) {
+        let mut data = Vec::new();
+        let mut levels = Vec::new();
+        for value in values {
+            levels.push(value.is_some() as i16);
+            data.extend(value);
+        }
+        (data, levels)
+    }
+
+    let mut row_group = writer.next_row_group().map_err(parquet_error)?;
+    let mut index = 0;
+    while let Some(mut column) = row_group.next_column().map_err(parquet_error)? {
+        let r = rows.iter();
+        let written = match index {
+            0 => column.typed::<ByteArrayType>().write_batch(
+                &strings(r.map(|x| x.request_id.clone())),
+                None,
+                None,
+            ),
+            1 => column.typed::<ByteArrayType>().write_batch(
+                &strings(r.map(|x| x.provider_id.clone())),
+                None,
+                None,
+            ),
+            2 => {
+                let (data, levels) = opt_strings(r.map(|x| x.provider_name.clone()));
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&data, Some(&levels), None)
+            }
+            3 => column.typed::<ByteArrayType>().write_batch(
+                &strings(r.map(|x| x.app_type.clone())),
+                None,
+                None,
+            ),
+            4 => column.typed::<ByteArrayType>().write_batch(
+                &strings(r.map(|x| x.model.clone())),
+                None,
+                None,
+            ),
+            5..=8 => {
+                let data: Vec<i64> = r
+                    .map(|x| match index {
+                        5 => x.input_tokens,
+                        6 => x.output_tokens,
+                        7 => x.cache_read_tokens,
+                        _ => x.cache_creation_tokens,
+                    })
+                    .collect();
+                column.typed::<Int64Type>().write_batch(&data, None, None)
+            }
+            9..=13 => {
+                let data: Vec<f64> = r
+                    .map(|x| match index {
+                        9 => x.input_cost_usd,
+                        10 => x.output_cost_usd,
+                        11 => x.cache_read_cost_usd,
+                        12 => x.cache_creation_cost_usd,
+                        _ => x.total_cost_usd,
+                    })
+                    .collect();
+                column.typed::<DoubleType>().write_batch(&data, None, None)
+            }
+            14 => column.typed::<ByteArrayType>().write_batch(
+                &strings(r.map(|x| x.billing_currency.clone())),
+                None,
+                None,
+            ),
+            15 => {
+                let (data, levels) = opt_values(r.map(|x| x.billing_cost));
+                column
+                    .typed::<DoubleType>()
+                    .write_batch(&data, Some(&levels), None)
+            }
+            16 => column.typed::<DoubleType>().write_batch(
+                &r.map(|x| x.cost_multiplier).collect::<Vec<_>>(),
+                None,
+                None,
+            ),
+            17 => column.typed::<Int64Type>().write_batch(
+                &r.map(|x| x.latency_ms).collect::<Vec<_>>(),
+                None,
+                None,
+            ),
+            18 | 19 => {
+                let (data, levels) = opt_values(r.map(|x| {
+                    if index == 18 {
+                        x.first_token_ms
+                    } else {
+                        x.duration_ms
+                    }
+                }));
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&data, Some(&levels), None)
+            }
+            20 => column.typed::<Int64Type>().write_batch(
+                &r.map(|x| x.status_code).collect::<Vec<_>>(),
+                None,
+                None,
+            ),
+            21..=23 => {
+                let (data, levels) = opt_strings(r.map(|x| match index {
+                    21 => x.error_message.clone(),
+                    22 => x.session_id.clone(),
+                    _ => x.provider_type.clone(),
+                }));
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&data, Some(&levels), None)
+            }
+            24 => column.typed::<BoolType>().write_batch(
+                &r.map(|x| x.is_streaming).collect::<Vec<_>>(),
+                None,
+                None,
+            ),
+            25 => column.typed::<Int64Type>().write_batch(
+                &r.map(|x| x.created_at).collect::<Vec<_>>(),
+                None,
+                None,
+            ),
+            26 => {
+                let (data, levels) = opt_strings(r.map(|x| x.project_path.clone()));
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&data, Some(&levels), None)
+            }
+            _ => column.typed::<ByteArrayType>().write_batch(
+                &strings(r.map(|x| x.source.clone())),
+                None,
+                None,
+            ),
+        };
+        written.map_err(parquet_error)?;
+        column.close().map_err(parquet_error)?;
+        index += 1;
+    }
+    row_group.close().map_err(parquet_error)?;
+    Ok(())
+}
+
+/// 报表频率
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFrequency {
+    Daily,
+    Weekly,
+}
+
+impl ReportFrequency {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    /// 截至 `today`（UTC）最近一个完整周期的 [起始日, 结束日)
+    fn last_complete_period(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            Self::Daily => (today - ChronoDuration::days(1), today),
+            Self::Weekly => {
+                let this_monday =
+                    today - ChronoDuration::days(today.weekday().num_days_from_monday() as i64);
+                (this_monday - ChronoDuration::days(7), this_monday)
+            }
+        }
+    }
+}
+
+/// 定期报表配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReportConfig {
+    #[serde(default)]
+    pub daily: bool,
+    #[serde(default)]
+    pub weekly: bool,
+    /// 报表输出目录
+    #[serde(default)]
+    pub output_dir: Option<String>,
+    /// 报表币种（为空时使用默认报表币种）
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// 最近生成的日报周期起始日（YYYY-MM-DD）
+    #[serde(default)]
+    pub last_daily_period: Option<String>,
+    /// 最近生成的周报周期起始日（YYYY-MM-DD）
+    #[serde(default)]
+    pub last_weekly_period: Option<String>,
+}
+
+/// 报表中的一行分组统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportBreakdown {
+    pub key: String,
+    /// 可读名称（供应商名称等）
+    pub name: Option<String>,
+    pub request_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub total_cost: String,
+}
+
+/// 一个周期的汇总报表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub frequency: ReportFrequency,
+    /// 周期起始日（含，UTC）
+    pub period_start: String,
+    /// 周期结束日（不含，UTC）
+    pub period_end: String,
+    pub generated_at: i64,
+    pub currency: String,
+    pub summary: UsageSummary,
+    pub by_provider: Vec<ReportBreakdown>,
+    pub by_model: Vec<ReportBreakdown>,
+    pub by_app_type: Vec<ReportBreakdown>,
+    /// 成本最高的会话（最多 50 个）
+    pub by_session: Vec<ReportBreakdown>,
+}
+
+fn day_start(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .expect("midnight is valid")
+        .and_utc()
+        .timestamp()
+}
+
+impl Database {
+    /// 按过滤条件导出请求日志到文件
+    pub fn export_request_logs(
+        &self,
+        filters: &LogFilters,
+        format: ExportFormat,
+        path: &Path,
+    ) -> Result<ExportResult, AppError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e))?;
+        }
+        let file = File::create(path).map_err(|e| AppError::io(path, e))?;
+
+        let mut rows = 0u64;
+        match format {
+            ExportFormat::Csv | ExportFormat::Jsonl => {
+                let mut out = BufWriter::new(file);
+                if format == ExportFormat::Csv {
+                    writeln!(out, "{CSV_HEADER}").map_err(|e| AppError::io(path, e))?;
+                }
+                self.for_each_export_batch(filters, |batch| {
+                    for row in batch {
+                        if format == ExportFormat::Csv {
+                            write_csv_line(&mut out, &row.csv_fields())?;
+                        } else {
+                            let line = serde_json::to_string(row)
+                                .map_err(|e| AppError::JsonSerialize { source: e })?;
+                            writeln!(out, "{line}").map_err(|e| AppError::io(path, e))?;
+                        }
+                    }
+                    rows += batch.len() as u64;
+                    Ok(())
+                })?;
+                out.flush().map_err(|e| AppError::io(path, e))?;
+            }
+            ExportFormat::Parquet => {
+                let schema = Arc::new(parse_message_type(PARQUET_SCHEMA).map_err(parquet_error)?);
+                let props = Arc::new(
+                    WriterProperties::builder()
+                        .set_compression(Compression::SNAPPY)
+                        .build(),
+                );
+                let mut writer =
+                    SerializedFileWriter::new(file, schema, props).map_err(parquet_error)?;
+                self.for_each_export_batch(filters, |batch| {
+                    write_parquet_batch(&mut writer, batch)?;
+                    rows += batch.len() as u64;
+                    Ok(())
+                })?;
+                writer.close().map_err(parquet_error)?;
+            }
+        }
+
+        log::info!("已导出 {rows} 条请求日志到 {}", path.display());
+        Ok(ExportResult {
+            path: path.display().to_string(),
+            rows,
+        })
+    }
+
+    /// 分批读取符合条件的日志（按时间升序），批次之间释放数据库锁
+    fn for_each_export_batch(
+        &self,
+        filters: &LogFilters,
+        mut handle: impl FnMut(&[ExportRow]) -> Result<(), AppError>,
+    ) -> Result<(), AppError> {
+        let mut offset = 0i64;
+        loop {
+            let batch = {
+                let conn = lock_conn!(self.conn);
+                let (where_clause, mut params) = log_filter_clause(filters);
+                params.push(Box::new(EXPORT_BATCH_SIZE));
+                params.push(Box::new(offset));
+
+                let sql = format!(
+                    "SELECT l.request_id, l.provider_id, p.name, l.app_type, l.model,
+                            l.input_tokens, l.output_tokens, l.cache_read_tokens, l.cache_creation_tokens,
+                            l.input_cost_usd, l.output_cost_usd, l.cache_read_cost_usd,
+                            l.cache_creation_cost_usd, l.total_cost_usd,
+                            l.billing_currency, l.billing_cost, l.cost_multiplier,
+                            l.latency_ms, l.first_token_ms, l.duration_ms, l.status_code,
+                            l.error_message, l.session_id, l.provider_type, l.is_streaming,
+                            l.created_at, l.project_path, l.source
+                     FROM proxy_request_logs l
+                     LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
+                     {where_clause}
+                     ORDER BY l.created_at ASC, l.request_id ASC
+                     LIMIT ? OFFSET ?"
+                );
+                let mut stmt = conn.prepare(&sql)?;
+                let params_refs: Vec<&dyn rusqlite::ToSql> =
+                    params.iter().map(|p| p.as_ref()).collect();
+                let number = |s: String| s.parse::<f64>().unwrap_or(0.0);
+                let rows = stmt.query_map(params_refs.as_slice(), |row| {
+                    Ok(ExportRow {
+                        request_id: row.get(0)?,
+                        provider_id: row.get(1)?,
+                        provider_name: row.get(2)?,
+                        app_type: row.get(3)?,
+                        model: row.get(4)?,
+                        input_tokens: row.get(5)?,
+                        output_tokens: row.get(6)?,
+                        cache_read_tokens: row.get(7)?,
+                        cache_creation_tokens: row.get(8)?,
+                        input_cost_usd: number(row.get(9)?),
+                        output_cost_usd: number(row.get(10)?),
+                        cache_read_cost_usd: number(row.get(11)?),
+                        cache_creation_cost_usd: number(row.get(12)?),
+                        total_cost_usd: number(row.get(13)?),
+                        billing_currency: row.get(14)?,
+                        billing_cost: row.get::<_, Option<String>>(15)?.map(number),
+                        cost_multiplier: number(row.get(16)?),
+                        latency_ms: row.get(17)?,
+                        first_token_ms: row.get(18)?,
+                        duration_ms: row.get(19)?,
+                        status_code: row.get(20)?,
+                        error_message: row.get(21)?,
+                        session_id: row.get(22)?,
+                        provider_type: row.get(23)?,
+                        is_streaming: row.get(24)?,
+                        created_at: row.get(25)?,
+                        project_path: row.get(26)?,
+                        source: row.get(27)?,
+                    })
+                })?;
+                rows.collect::<Result<Vec<_>, _>>()?
+            };
+
+            if batch.is_empty() {
+                return Ok(());
+            }
+            handle(&batch)?;
+            if (batch.len() as i64) < EXPORT_BATCH_SIZE {
+                return Ok(());
+            }
+            offset += EXPORT_BATCH_SIZE;
+        }
+    }
+
+    /// 获取定期报表配置
+    pub fn get_usage_report_config(&self) -> Result<UsageReportConfig, AppError> {
+        match self.get_setting(REPORT_CONFIG_SETTING)? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Message(format!("解析报表配置失败: {e}"))),
+            None => Ok(UsageReportConfig::default()),
+        }
+    }
+
+    /// 保存定期报表配置（保留已生成周期的记录）
+    pub fn set_usage_report_config(&self, config: &UsageReportConfig) -> Result<(), AppError> {
+        if (config.daily || config.weekly)
+            && config
+                .output_dir
+                .as_deref()
+                .is_none_or(|dir| dir.trim().is_empty())
+        {
+            return Err(AppError::InvalidInput(
+                "启用定期报表时必须设置输出目录".to_string(),
+            ));
+        }
+        if let Some(currency) = config.currency.as_deref() {
+            let conn = lock_conn!(self.conn);
+            resolve_report_currency(&conn, Some(currency))?;
+        }
+
+        let current = self.get_usage_report_config()?;
+        let config = UsageReportConfig {
+            last_daily_period: current.last_daily_period,
+            last_weekly_period: current.last_weekly_period,
+            ..config.clone()
+        };
+        let json = serde_json::to_string(&config)
+            .map_err(|e| AppError::Message(format!("序列化报表配置失败: {e}")))?;
+        self.set_setting(REPORT_CONFIG_SETTING, &json)
+    }
+
+    /// 生成指定周期的汇总报表
+    pub fn build_usage_report(
+        &self,
+        frequency: ReportFrequency,
+        period_start: NaiveDate,
+        period_end: NaiveDate,
+        currency: Option<&str>,
+    ) -> Result<UsageReport, AppError> {
+        let start = day_start(period_start);
+        // 汇总接口的结束时间为闭区间
+        let end = day_start(period_end) - 1;
+
+        let currency = {
+            let conn = lock_conn!(self.conn);
+            resolve_report_currency(&conn, currency)?
+        };
+        let summary = self.get_usage_summary(Some(start), Some(end), Some(&currency))?;
+
+        let conn = lock_conn!(self.conn);
+        let breakdown = |key_expr: &str, name_expr: &str, limit: Option<i64>| {
+            query_breakdown(&conn, key_expr, name_expr, &currency, start, end, limit)
+        };
+        let by_provider = breakdown("l.provider_id", "MAX(p.name)", None)?;
+        let by_model = breakdown("l.model", "NULL", None)?;
+        let by_app_type = breakdown("l.app_type", "NULL", None)?;
+        let by_session = breakdown(
+            "COALESCE(l.session_id, '')",
+            "NULL",
+            Some(REPORT_SESSION_LIMIT),
+        )?;
+
+        Ok(UsageReport {
+            frequency,
+            period_start: period_start.format("%Y-%m-%d").to_string(),
+            period_end: period_end.format("%Y-%m-%d").to_string(),
+            generated_at: Utc::now().timestamp(),
+            currency,
+            summary,
+            by_provider,
+            by_model,
+            by_app_type,
+            by_session,
+        })
+    }
+
+    /// 立即生成最近一个完整周期的报表，返回写入的文件路径
+    pub fn generate_usage_report(
+        &self,
+        frequency: ReportFrequency,
+    ) -> Result<Vec<PathBuf>, AppError> {
+        let config = self.get_usage_report_config()?;
+        let output_dir = config
+            .output_dir
+            .as_deref()
+            .filter(|dir| !dir.trim().is_empty())
+            .ok_or_else(|| AppError::InvalidInput("尚未配置报表输出目录".to_string()))?;
+
+        let (start, end) = frequency.last_complete_period(Utc::now().date_naive());
+        let report = self.build_usage_report(frequency, start, end, config.currency.as_deref())?;
+        let paths = write_report_files(Path::new(output_dir), &report)?;
+
+        let mut config = self.get_usage_report_config()?;
+        match frequency {
+            ReportFrequency::Daily => config.last_daily_period = Some(report.period_start.clone()),
+            ReportFrequency::Weekly => {
+                config.last_weekly_period = Some(report.period_start.clone())
+            }
+        }
+        let json = serde_json::to_string(&config)
+            .map_err(|e| AppError::Message(format!("序列化报表配置失败: {e}")))?;
+        self.set_setting(REPORT_CONFIG_SETTING, &json)?;
+
+        Ok(paths)
+    }
+
+    /// 生成所有到期的报表（每个频率只补最近一个完整周期）
+    pub fn run_due_usage_reports(&self) -> Result<(), AppError> {
+        let config = self.get_usage_report_config()?;
+        let today = Utc::now().date_naive();
+
+        for (enabled, frequency, last) in [
+            (
+                config.daily,
+                ReportFrequency::Daily,
+                &config.last_daily_period,
+            ),
+            (
+                config.weekly,
+                ReportFrequency::Weekly,
+                &config.last_weekly_period,
+            ),
+        ] {
+            if !enabled {
+                continue;
+            }
+            let (start, _) = frequency.last_complete_period(today);
+            let due = last.as_deref() != Some(start.format("%Y-%m-%d").to_string().as_str());
+            if due {
+                let paths = self.generate_usage_report(frequency)?;
+                log::info!(
+                    "已生成{}使用报表: {}",
+                    frequency.as_str(),
+                    paths
+                        .iter()
+                        .map(|p| p.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 按指定维度聚合周期内的请求
+fn query_breakdown(
+    conn: &rusqlite::Connection,
+    key_expr: &str,
+    name_expr: &str,
+    currency: &str,
+    start: i64,
+    end: i64,
+    limit: Option<i64>,
+) -> Result<Vec<ReportBreakdown>, AppError> {
+    let cost_expr = cost_sql(currency);
+    let sql = format!(
+        "SELECT {key_expr} as key, {name_expr} as name,
+                COUNT(*),
+                COALESCE(SUM(l.input_tokens), 0),
+                COALESCE(SUM(l.output_tokens), 0),
+                COALESCE(SUM(l.cache_read_tokens), 0),
+                COALESCE(SUM(l.cache_creation_tokens), 0),
+                COALESCE(SUM({cost_expr}), 0) as total_cost
+         FROM proxy_request_logs l
+         LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
+         WHERE l.created_at >= ?1 AND l.created_at <= ?2
+         GROUP BY key
+         ORDER BY total_cost DESC
+         LIMIT ?3"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(rusqlite::params![start, end, limit.unwrap_or(-1)], |row| {
+        Ok(ReportBreakdown {
+            key: row.get(0)?,
+            name: row.get(1)?,
+            request_count: row.get::<_, i64>(2)? as u64,
+            input_tokens: row.get::<_, i64>(3)? as u64,
+            output_tokens: row.get::<_, i64>(4)? as u64,
+            cache_read_tokens: row.get::<_, i64>(5)? as u64,
+            cache_creation_tokens: row.get::<_, i64>(6)? as u64,
+            total_cost: format!("{:.6}", row.get::<_, f64>(7)?),
+        })
+    })?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// 写出报表：JSON（完整）+ CSV（各维度明细）
+fn write_report_files(dir: &Path, report: &UsageReport) -> Result<Vec<PathBuf>, AppError> {
+    std::fs::create_dir_all(dir).map_err(|e| AppError::io(dir, e))?;
+    let stem = format!(
+        "usage-{}-{}",
+        report.frequency.as_str(),
+        report.period_start
+    );
+
+    let json_path = dir.join(format!("{stem}.json"));
+    let json =
+        serde_json::to_string_pretty(report).map_err(|e| AppError::JsonSerialize { source: e })?;
+    std::fs::write(&json_path, json).map_err(|e| AppError::io(&json_path, e))?;
+
+    let csv_path = dir.join(format!("{stem}.csv"));
+    let mut out = BufWriter::new(File::create(&csv_path).map_err(|e| AppError::io(&csv_path, e))?);
+    write_csv_line(
+        &mut out,
+        &[
+            "dimension",
+            "key",
+            "name",
+            "request_count",
+            "input_tokens",
+            "output_tokens",
+            "cache_read_tokens",
+            "cache_creation_tokens",
+            "total_cost",
+            "currency",
+        ]
+        .map(String::from),
+    )?;
+    for (dimension, rows) in [
+        ("provider", &report.by_provider),
+        ("model", &report.by_model),
+        ("app_type", &report.by_app_type),
+        ("session", &report.by_session),
+    ] {
+        for row in rows {
+            write_csv_line(
+                &mut out,
+                &[
+                    dimension.to_string(),
+                    row.key.clone(),
+                    row.name.clone().unwrap_or_default(),
+                    row.request_count.to_string(),
+                    row.input_tokens.to_string(),
+                    row.output_tokens.to_string(),
+                    row.cache_read_tokens.to_string(),
+                    row.cache_creation_tokens.to_string(),
+                    row.total_cost.clone(),
+                    report.currency.clone(),
+                ],
+            )?;
+        }
+    }
+    out.flush().map_err(|e| AppError::io(&csv_path, e))?;
+
+    Ok(vec![json_path, csv_path])
+}
+
+/// 启动定期报表调度（每小时检查一次是否有到期报表）
+pub fn spawn_usage_report_scheduler(db: Arc<Database>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(REPORT_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let db = db.clone();
+            let result = tokio::task::spawn_blocking(move || db.run_due_usage_reports()).await;
+            match result {
+                Ok(Err(e)) => log::warn!("生成定期使用报表失败: {e}"),
+                Err(e) => log::warn!("定期使用报表任务异常: {e}"),
+                Ok(Ok(())) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use rusqlite::params;
+
+    fn seed_logs(db: &Database, base: i64) -> Result<(), AppError> {
+        let conn = lock_conn!(db.conn);
+        conn.execute(
+            "INSERT INTO providers (id, app_type, name, settings_config) VALUES ('p1', 'claude', 'Provider, One', '{}')",
+            [],
+        )?;
+        for (i, (model, session, error)) in [
+            ("claude-sonnet-4-5", Some("s1"), None),
+            ("claude-haiku-4-5", Some("s1"), Some("boom \"quoted\"")),
+            ("gpt-5", None, None),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            conn.execute(
+                "INSERT INTO proxy_request_logs (
+                    request_id, provider_id, app_type, model, input_tokens, output_tokens,
+                    total_cost_usd, latency_ms, status_code, error_message, session_id, created_at
+                ) VALUES (?1, 'p1', 'claude', ?2, 100, 10, ?3, 50, 200, ?4, ?5, ?6)",
+                params![
+                    format!("r{i}"),
+                    model,
+                    format!("{}", (i + 1) as f64 * 0.5),
+                    error,
+                    session,
+                    base + i as i64
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_request_logs_all_formats() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        seed_logs(&db, 1_000)?;
+        let dir = tempfile::tempdir().unwrap();
+        let filters = LogFilters {
+            model: Some("claude".to_string()),
+            ..Default::default()
+        };
+
+        let csv = dir.path().join("logs.csv");
+        assert_eq!(
+            db.export_request_logs(&filters, ExportFormat::Csv, &csv)?
+                .rows,
+            2
+        );
+        let content = std::fs::read_to_string(&csv).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("request_id,provider_id,provider_name"));
+        assert!(lines[1].contains("\"Provider, One\""));
+        assert!(lines[2].contains("\"boom \"\"quoted\"\"\""));
+
+        let jsonl = dir.path().join("logs.jsonl");
+        db.export_request_logs(&LogFilters::default(), ExportFormat::Jsonl, &jsonl)?;
+        let rows: Vec<serde_json::Value> = std::fs::read_to_string(&jsonl)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2]["model"], "gpt-5");
+        assert_eq!(rows[2]["total_cost_usd"], 1.5);
+
+        let parquet = dir.path().join("nested/logs.parquet");
+        db.export_request_logs(&LogFilters::default(), ExportFormat::Parquet, &parquet)?;
+        let reader = SerializedFileReader::new(File::open(&parquet).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 3);
+        assert_eq!(
+            reader
+                .metadata()
+                .file_metadata()
+                .schema_descr()
+                .num_columns(),
+            CSV_HEADER.split(',').count()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_complete_period() {
+        // 2025-10-15 为周三
+        let today = NaiveDate::from_ymd_opt(2025, 10, 15).unwrap();
+        let (start, end) = ReportFrequency::Daily.last_complete_period(today);
+        assert_eq!(
+            (start.to_string(), end.to_string()),
+            ("2025-10-14".into(), "2025-10-15".into())
+        );
+        let (start, end) = ReportFrequency::Weekly.last_complete_period(today);
+        assert_eq!(
+            (start.to_string(), end.to_string()),
+            ("2025-10-06".into(), "2025-10-13".into())
+        );
+    }
+
+    #[test]
+    fn test_usage_report_breakdowns_and_scheduling() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let (start, end) = ReportFrequency::Daily.last_complete_period(Utc::now().date_naive());
+        seed_logs(&db, day_start(start) + 3600)?;
+
+        let report = db.build_usage_report(ReportFrequency::Daily, start, end, None)?;
+        assert_eq!(report.summary.total_requests, 3);
+        assert_eq!(report.by_provider.len(), 1);
+        assert_eq!(report.by_provider[0].name.as_deref(), Some("Provider, One"));
+        assert_eq!(report.by_provider[0].total_cost, "3.000000");
+        assert_eq!(report.by_model.len(), 3);
+        assert_eq!(report.by_model[0].key, "gpt-5");
+        assert_eq!(report.by_session.len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        assert!(db
+            .set_usage_report_config(&UsageReportConfig {
+                daily: true,
+                ..Default::default()
+            })
+            .is_err());
+        db.set_usage_report_config(&UsageReportConfig {
+            daily: true,
+            output_dir: Some(dir.path().display().to_string()),
+            ..Default::default()
+        })?;
+
+        db.run_due_usage_reports()?;
+        let json_path = dir.path().join(format!("usage-daily-{start}.json"));
+        let written: UsageReport =
+            serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(written.summary.total_requests, 3);
+        assert!(dir.path().join(format!("usage-daily-{start}.csv")).exists());
+        assert_eq!(
+            db.get_usage_report_config()?.last_daily_period,
+            Some(start.to_string())
+        );
+
+        // 已生成的周期不会重复生成
+        std::fs::remove_file(&json_path).unwrap();
+        db.run_due_usage_reports()?;
+        assert!(!json_path.exists());
+        Ok(())
+    }
+}
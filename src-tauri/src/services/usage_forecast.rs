@@ -0,0 +1,582 @@
+//! 预算预测
+//!
+//! 基于最近 28 天的每日花费（含已聚合的历史数据）拟合线性趋势 + 星期季节项，
+//! 预测整体与各供应商的月末花费，并给出 90% 置信区间；
+//! 同时估算供应商月度限额（`limitMonthlyUsd`）与用量脚本返回的预付余额何时耗尽
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::provider::UsageData;
+use crate::services::currency::{cost_sql, lookup_rate, resolve_report_currency, BASE_CURRENCY};
+use crate::services::session_usage_import::SOURCE_PROXY;
+use crate::services::usage_retention::usage_rows_sql;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use rusqlite::{params, OptionalExtension};
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// 拟合使用的历史天数（不含今天）
+const HISTORY_DAYS: i64 = 28;
+
+/// 少于该天数时不估计星期季节项
+const MIN_SEASONAL_DAYS: usize = 14;
+
+/// 趋势与季节项交替估计的轮数
+const SEASONAL_ITERATIONS: usize = 5;
+
+/// 90% 置信区间的 z 值
+const Z_90: f64 = 1.645;
+
+/// 余额耗尽估算的最长预测天数
+const BALANCE_HORIZON_DAYS: i64 = 365;
+
+/// 预付余额预测
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceForecast {
+    /// 用量脚本返回的剩余额度
+    pub remaining: f64,
+    pub unit: Option<String>,
+    /// 预计还能使用的天数（单位无法换算或预测期内不会耗尽时为空）
+    pub days_remaining: Option<i64>,
+    /// 预计耗尽日期（YYYY-MM-DD）
+    pub exhausted_on: Option<String>,
+}
+
+/// 花费预测（整体或单个供应商）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendForecast {
+    /// `total` 或 `app_type:provider_id`
+    pub key: String,
+    pub app_type: Option<String>,
+    pub provider_id: Option<String>,
+    pub provider_name: Option<String>,
+    /// 本月至今的实际花费
+    pub month_to_date: String,
+    /// 预测月末花费
+    pub projected_month_end: String,
+    /// 置信区间下限（不低于本月至今花费）
+    pub projected_low: String,
+    /// 置信区间上限
+    pub projected_high: String,
+    /// 历史日均花费
+    pub daily_average: String,
+    /// 趋势斜率（每天花费的变化量）
+    pub trend_per_day: String,
+    /// 月度限额（已换算为报表币种）
+    pub monthly_limit: Option<String>,
+    /// 预计本月达到限额的日期（本月内不会达到时为空）
+    pub limit_reached_on: Option<String>,
+    pub balance: Option<BalanceForecast>,
+}
+
+/// 预算预测结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetForecast {
+    pub currency: String,
+    pub today: String,
+    pub month_end: String,
+    /// 实际参与拟合的历史天数
+    pub history_days: u32,
+    pub total: SpendForecast,
+    /// 各供应商预测（按预测月末花费降序）
+    pub providers: Vec<SpendForecast>,
+}
+
+/// 线性趋势 + 星期季节项（加性）模型
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TrendModel {
+    origin: NaiveDate,
+    intercept: f64,
+    slope: f64,
+    /// 周一到周日的季节偏移（和为 0）
+    seasonal: [f64; 7],
+    residual_std: f64,
+    mean: f64,
+}
+
+impl TrendModel {
+    /// 以 `origin` 为第 0 天拟合连续的每日序列
+    pub(crate) fn fit(origin: NaiveDate, values: &[f64]) -> Self {
+        let n = values.len();
+        let mut model = Self {
+            origin,
+            intercept: 0.0,
+            slope: 0.0,
+            seasonal: [0.0; 7],
+            residual_std: 0.0,
+            mean: 0.0,
+        };
+        if n == 0 {
+            return model;
+        }
+
+        model.mean = values.iter().sum::<f64>() / n as f64;
+        model.fit_trend(values);
+
+        // 交替估计季节项与趋势（季节项会使单次最小二乘的斜率产生偏差）
+        if n >= MIN_SEASONAL_DAYS {
+            for _ in 0..SEASONAL_ITERATIONS {
+                let mut sums = [0.0; 7];
+                let mut counts = [0usize; 7];
+                for (t, y) in values.iter().enumerate() {
+                    let w = model.weekday(t as i64);
+                    sums[w] += y - (model.intercept + model.slope * t as f64);
+                    counts[w] += 1;
+                }
+                for w in 0..7 {
+                    if counts[w] > 0 {
+                        model.seasonal[w] = sums[w] / counts[w] as f64;
+                    }
+                }
+                let center = model.seasonal.iter().sum::<f64>() / 7.0;
+                model.seasonal.iter_mut().for_each(|s| *s -= center);
+
+                let deseasonalized: Vec<f64> = values
+                    .iter()
+                    .enumerate()
+                    .map(|(t, y)| y - model.seasonal[model.weekday(t as i64)])
+                    .collect();
+                model.fit_trend(&deseasonalized);
+            }
+        }
+
+        let sse: f64 = values
+            .iter()
+            .enumerate()
+            .map(|(t, y)| (y - model.fitted(t as i64)).powi(2))
+            .sum();
+        model.residual_std = (sse / n.saturating_sub(2).max(1) as f64).sqrt();
+        model
+    }
+
+    /// 最小二乘拟合线性趋势
+    fn fit_trend(&mut self, values: &[f64]) {
+        let n = values.len() as f64;
+        let mean_t = (n - 1.0) / 2.0;
+        let mean_y = values.iter().sum::<f64>() / n;
+        let (mut cov, mut var) = (0.0, 0.0);
+        for (t, y) in values.iter().enumerate() {
+            cov += (t as f64 - mean_t) * (y - mean_y);
+            var += (t as f64 - mean_t).powi(2);
+        }
+        self.slope = if var > 0.0 { cov / var } else { 0.0 };
+        self.intercept = mean_y - self.slope * mean_t;
+    }
+
+    fn weekday(&self, t: i64) -> usize {
+        (self.origin + Duration::days(t))
+            .weekday()
+            .num_days_from_monday() as usize
+    }
+
+    fn fitted(&self, t: i64) -> f64 {
+        self.intercept + self.slope * t as f64 + self.seasonal[self.weekday(t)]
+    }
+
+    /// 预测指定日期的花费（不小于 0）
+    pub(crate) fn predict(&self, date: NaiveDate) -> f64 {
+        self.fitted((date - self.origin).num_days()).max(0.0)
+    }
+
+    /// 从今天起逐日累加预测花费，返回累计达到 `budget` 的日期（`horizon` 内未达到时为空）
+    ///
+    /// 今天只计入预测值中尚未发生的部分
+    pub(crate) fn exhaustion_date(
+        &self,
+        today: NaiveDate,
+        today_actual: f64,
+        budget: f64,
+        horizon: NaiveDate,
+    ) -> Option<NaiveDate> {
+        let mut spent = (self.predict(today) - today_actual).max(0.0);
+        if budget <= spent {
+            return Some(today);
+        }
+        let mut day = today;
+        while day < horizon {
+            day += Duration::days(1);
+            spent += self.predict(day);
+            if spent >= budget {
+                return Some(day);
+            }
+        }
+        None
+    }
+}
+
+/// 单个序列的每日花费
+#[derive(Debug, Default)]
+struct DailySeries {
+    days: BTreeMap<NaiveDate, f64>,
+}
+
+impl DailySeries {
+    fn add(&mut self, day: NaiveDate, cost: f64) {
+        *self.days.entry(day).or_default() += cost;
+    }
+
+    fn sum_between(&self, start: NaiveDate, end: NaiveDate) -> f64 {
+        self.days.range(start..=end).map(|(_, v)| v).sum()
+    }
+}
+
+/// 预测上下文（整体与各供应商共享）
+struct ForecastContext {
+    today: NaiveDate,
+    month_start: NaiveDate,
+    month_end: NaiveDate,
+    history_start: NaiveDate,
+}
+
+impl ForecastContext {
+    fn forecast(&self, key: String, series: &DailySeries) -> (SpendForecast, TrendModel, f64) {
+        // 从首个有数据的日期开始拟合，避免把尚未使用的日子当作 0 拉低趋势
+        let start = series
+            .days
+            .keys()
+            .next()
+            .copied()
+            .unwrap_or(self.today)
+            .max(self.history_start);
+        let values: Vec<f64> = (0..(self.today - start).num_days())
+            .map(|i| {
+                series
+                    .days
+                    .get(&(start + Duration::days(i)))
+                    .copied()
+                    .unwrap_or(0.0)
+            })
+            .collect();
+        let model = TrendModel::fit(start, &values);
+
+        let month_to_date = series.sum_between(self.month_start, self.today);
+        let today_actual = series.days.get(&self.today).copied().unwrap_or(0.0);
+        let mut projected = month_to_date + (model.predict(self.today) - today_actual).max(0.0);
+        let mut day = self.today;
+        while day < self.month_end {
+            day += Duration::days(1);
+            projected += model.predict(day);
+        }
+        let horizon = (self.month_end - self.today).num_days() + 1;
+        let margin = Z_90 * model.residual_std * (horizon as f64).sqrt();
+
+        let forecast = SpendForecast {
+            key,
+            app_type: None,
+            provider_id: None,
+            provider_name: None,
+            month_to_date: format!("{month_to_date:.6}"),
+            projected_month_end: format!("{projected:.6}"),
+            projected_low: format!("{:.6}", (projected - margin).max(month_to_date)),
+            projected_high: format!("{:.6}", projected + margin),
+            daily_average: format!("{:.6}", model.mean),
+            trend_per_day: format!("{:.6}", model.slope),
+            monthly_limit: None,
+            limit_reached_on: None,
+            balance: None,
+        };
+        (forecast, model, today_actual)
+    }
+}
+
+impl Database {
+    /// 预测本月月末花费及限额 / 余额耗尽时间
+    ///
+    /// `balances` 以 `app_type:provider_id` 为键，值为用量脚本返回的余额信息
+    pub fn get_budget_forecast(
+        &self,
+        currency: Option<&str>,
+        balances: &HashMap<String, UsageData>,
+    ) -> Result<BudgetForecast, AppError> {
+        let conn = lock_conn!(self.conn);
+        let currency = resolve_report_currency(&conn, currency)?;
+        let cost_expr = cost_sql(&currency);
+
+        let today = Utc::now().date_naive();
+        let month_start = today.with_day(1).expect("day 1 is valid");
+        let next_month = if today.month() == 12 {
+            NaiveDate::from_ymd_opt(today.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(today.year(), today.month() + 1, 1)
+        }
+        .expect("first day of month is valid");
+        let ctx = ForecastContext {
+            today,
+            month_start,
+            month_end: next_month - Duration::days(1),
+            history_start: today - Duration::days(HISTORY_DAYS),
+        };
+        let query_start = ctx.history_start.min(month_start);
+
+        let sql = format!(
+            "SELECT date(l.created_at, 'unixepoch') as day, l.app_type, l.provider_id, l.source,
+                    COALESCE(SUM({cost_expr}), 0)
+             FROM {} l
+             WHERE l.created_at >= ?1
+             GROUP BY day, l.app_type, l.provider_id, l.source",
+            usage_rows_sql(false)
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(
+            [query_start
+                .and_hms_opt(0, 0, 0)
+                .expect("midnight is valid")
+                .and_utc()
+                .timestamp()],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, f64>(4)?,
+                ))
+            },
+        )?;
+
+        let mut total = DailySeries::default();
+        let mut providers: BTreeMap<(String, String), DailySeries> = BTreeMap::new();
+        for row in rows {
+            let (day, app_type, provider_id, source, cost) = row?;
+            let Ok(day) = NaiveDate::parse_from_str(&day, "%Y-%m-%d") else {
+                continue;
+            };
+            total.add(day, cost);
+            // 会话日志导入的用量不归属任何供应商
+            if source == SOURCE_PROXY {
+                providers
+                    .entry((app_type, provider_id))
+                    .or_default()
+                    .add(day, cost);
+            }
+        }
+        drop(stmt);
+
+        let (total_forecast, _, _) = ctx.forecast("total".to_string(), &total);
+        let today_str = today.format("%Y-%m-%d").to_string();
+        let rate = lookup_rate(&conn, &currency, &today_str)?.and_then(|r| r.to_f64());
+
+        let mut provider_forecasts = Vec::new();
+        for ((app_type, provider_id), series) in &providers {
+            let key = format!("{app_type}:{provider_id}");
+            let (mut forecast, model, today_actual) = ctx.forecast(key.clone(), series);
+            forecast.app_type = Some(app_type.clone());
+            forecast.provider_id = Some(provider_id.clone());
+
+            let (name, meta): (Option<String>, Option<String>) = conn
+                .query_row(
+                    "SELECT name, meta FROM providers WHERE id = ?1 AND app_type = ?2",
+                    params![provider_id, app_type],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?
+                .unwrap_or((None, None));
+            forecast.provider_name = name;
+
+            // 月度限额按 USD 配置，换算为报表币种后比较
+            let limit = meta
+                .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok())
+                .and_then(|m| m.get("limitMonthlyUsd")?.as_str()?.parse::<f64>().ok())
+                .zip(rate)
+                .map(|(limit, rate)| limit * rate);
+            if let Some(limit) = limit {
+                let month_to_date: f64 = forecast.month_to_date.parse().unwrap_or(0.0);
+                forecast.monthly_limit = Some(format!("{limit:.2}"));
+                forecast.limit_reached_on = model
+                    .exhaustion_date(today, today_actual, limit - month_to_date, ctx.month_end)
+                    .map(|d| d.format("%Y-%m-%d").to_string());
+            }
+
+            provider_forecasts.push((forecast, model, today_actual));
+        }
+
+        // 余额：单位换算为报表币种后按预测花费逐日扣减
+        for (key, data) in balances {
+            let Some(remaining) = data.remaining else {
+                continue;
+            };
+            let entry = provider_forecasts
+                .iter_mut()
+                .find(|(f, _, _)| &f.key == key);
+            let balance_in_currency = balance_currency(data.unit.as_deref())
+                .map(|unit| lookup_rate(&conn, &unit, &today_str))
+                .transpose()?
+                .flatten()
+                .and_then(|r| r.to_f64())
+                .zip(rate)
+                .and_then(|(unit_rate, rate)| {
+                    (unit_rate > 0.0).then(|| remaining / unit_rate * rate)
+                });
+
+            let mut balance = BalanceForecast {
+                remaining,
+                unit: data.unit.clone(),
+                days_remaining: None,
+                exhausted_on: None,
+            };
+            if let (Some((_, model, today_actual)), Some(amount)) = (&entry, balance_in_currency) {
+                let horizon = today + Duration::days(BALANCE_HORIZON_DAYS);
+                if let Some(date) = model.exhaustion_date(today, *today_actual, amount, horizon) {
+                    balance.days_remaining = Some((date - today).num_days());
+                    balance.exhausted_on = Some(date.format("%Y-%m-%d").to_string());
+                }
+            }
+            if let Some((forecast, _, _)) = entry {
+                forecast.balance = Some(balance);
+            }
+        }
+
+        let mut provider_forecasts: Vec<SpendForecast> =
+            provider_forecasts.into_iter().map(|(f, _, _)| f).collect();
+        provider_forecasts.sort_by(|a, b| {
+            let a: f64 = a.projected_month_end.parse().unwrap_or(0.0);
+            let b: f64 = b.projected_month_end.parse().unwrap_or(0.0);
+            b.total_cmp(&a)
+        });
+
+        let history_days = total
+            .days
+            .keys()
+            .next()
+            .map(|first| (today - (*first).max(ctx.history_start)).num_days().max(0))
+            .unwrap_or(0) as u32;
+
+        Ok(BudgetForecast {
+            currency,
+            today: today_str,
+            month_end: ctx.month_end.format("%Y-%m-%d").to_string(),
+            history_days,
+            total: total_forecast,
+            providers: provider_forecasts,
+        })
+    }
+}
+
+/// 将用量脚本的余额单位识别为币种代码（无法识别时为空，如「次」「tokens」）
+fn balance_currency(unit: Option<&str>) -> Option<String> {
+    let unit = unit?.trim();
+    match unit {
+        "$" | "美元" => Some(BASE_CURRENCY.to_string()),
+        "¥" | "￥" | "元" | "RMB" | "rmb" => Some("CNY".to_string()),
+        code if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Some(code.to_ascii_uppercase())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_trend_model_fits_slope_and_weekday_seasonality() {
+        // 2024-01-01 为周一；工作日 10 + 0.5t，周末额外 -6
+        let origin = date("2024-01-01");
+        let values: Vec<f64> = (0..28)
+            .map(|t| {
+                let weekend = t % 7 >= 5;
+                10.0 + 0.5 * t as f64 + if weekend { -6.0 } else { 0.0 }
+            })
+            .collect();
+        let model = TrendModel::fit(origin, &values);
+
+        assert!((model.slope - 0.5).abs() < 0.02);
+        let monday = model.predict(date("2024-01-29"));
+        let saturday = model.predict(date("2024-02-03"));
+        assert!((monday - 24.0).abs() < 0.5);
+        assert!((saturday - 20.5).abs() < 0.5);
+        assert!(model.residual_std < 1.0);
+    }
+
+    #[test]
+    fn test_exhaustion_date() {
+        let model = TrendModel::fit(date("2024-01-01"), &[2.0; 10]);
+        let today = date("2024-01-11");
+        // 今天已花 1，今天还剩 1；之后每天 2
+        assert_eq!(
+            model.exhaustion_date(today, 1.0, 5.0, date("2024-12-31")),
+            Some(date("2024-01-13"))
+        );
+        assert_eq!(
+            model.exhaustion_date(today, 0.0, 1.0, date("2024-12-31")),
+            Some(today)
+        );
+        assert_eq!(
+            model.exhaustion_date(today, 0.0, 100.0, date("2024-01-20")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_balance_currency() {
+        assert_eq!(balance_currency(Some("$")).as_deref(), Some("USD"));
+        assert_eq!(balance_currency(Some("usd")).as_deref(), Some("USD"));
+        assert_eq!(balance_currency(Some("￥")).as_deref(), Some("CNY"));
+        assert_eq!(balance_currency(Some("次")), None);
+        assert_eq!(balance_currency(None), None);
+    }
+
+    #[test]
+    fn test_budget_forecast_with_limit_and_balance() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let now = Utc::now().timestamp();
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO providers (id, app_type, name, settings_config, meta)
+                 VALUES ('p1', 'claude', 'Relay', '{}', ?1)",
+                [r#"{"limitMonthlyUsd":"1000"}"#],
+            )?;
+            for day in 0..20 {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model, total_cost_usd,
+                        latency_ms, status_code, created_at
+                    ) VALUES (?1, 'p1', 'claude', 'm', '3', 100, 200, ?2)",
+                    params![format!("r{day}"), now - day * 86_400],
+                )?;
+            }
+        }
+
+        let balances = HashMap::from([(
+            "claude:p1".to_string(),
+            UsageData {
+                plan_name: None,
+                extra: None,
+                is_valid: Some(true),
+                invalid_message: None,
+                total: None,
+                used: None,
+                remaining: Some(12.0),
+                unit: Some("USD".to_string()),
+            },
+        )]);
+        let forecast = db.get_budget_forecast(Some("USD"), &balances)?;
+
+        assert_eq!(forecast.providers.len(), 1);
+        let provider = &forecast.providers[0];
+        assert_eq!(provider.provider_name.as_deref(), Some("Relay"));
+        assert_eq!(provider.monthly_limit.as_deref(), Some("1000.00"));
+        assert_eq!(provider.limit_reached_on, None);
+        assert_eq!(provider.trend_per_day, "0.000000");
+
+        let projected: f64 = provider.projected_month_end.parse().unwrap();
+        let month_to_date: f64 = provider.month_to_date.parse().unwrap();
+        assert!(projected >= month_to_date);
+
+        // 每天 3 USD，今天已花 3，余额 12 → 4 天后耗尽
+        let balance = provider.balance.as_ref().unwrap();
+        assert_eq!(balance.days_remaining, Some(4));
+        assert_eq!(forecast.total.month_to_date, provider.month_to_date);
+        Ok(())
+    }
+}
@@ -0,0 +1,475 @@
+//! 请求日志保留与数据库维护
+//!
+//! - 超出保留期的 `proxy_request_logs` / `proxy_shadow_logs` 按天聚合进 `usage_daily_rollups` 后删除
+//! - 汇总、每日趋势、供应商 / 模型统计通过 [`usage_rows_sql`] 同时读取原始日志与聚合数据
+//! - 被删除的会话日志记录 ID 写入 `rolled_up_session_records`，避免重新导入时重复计入
+//! - 清理后执行增量 VACUUM 回收空间；切换为 `auto_vacuum = INCREMENTAL` 需要一次完整 VACUUM，
+//!   仅在手动执行维护时进行，后台任务不会在启动时长时间占用数据库
+//!
+//! 保留清理默认关闭，保存配置启用后才会删除原始日志
+//!
+//! 聚合数据不保留会话、项目、延迟分位数与错误明细，相关统计仅覆盖保留期内的原始日志
+
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use crate::services::session_usage_import::SOURCE_SESSION_LOG;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 日志保留配置的存储键
+const RETENTION_CONFIG_SETTING: &str = "log_retention_config";
+
+/// 后台维护间隔
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(6 * 3600);
+
+/// 启动后首次后台维护的延迟
+const MAINTENANCE_STARTUP_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// 默认保留原始日志的天数
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+/// 最短保留天数（逐小时趋势与周报依赖最近的原始日志）
+const MIN_RETENTION_DAYS: u32 = 7;
+
+/// 日志保留配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogRetentionConfig {
+    /// 是否自动清理超出保留期的原始日志（默认关闭，需用户保存配置启用）
+    #[serde(default)]
+    pub enabled: bool,
+    /// 原始日志保留天数
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+    /// 最近一次维护时间（Unix 秒）
+    #[serde(default)]
+    pub last_run_at: Option<i64>,
+}
+
+fn default_retention_days() -> u32 {
+    DEFAULT_RETENTION_DAYS
+}
+
+impl Default for LogRetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: DEFAULT_RETENTION_DAYS,
+            last_run_at: None,
+        }
+    }
+}
+
+/// 单次维护结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogMaintenanceResult {
+    /// 写入或合并的聚合行数
+    pub rolled_up_rows: u64,
+    /// 删除的原始日志行数（含影子日志）
+    pub deleted_rows: u64,
+    /// VACUUM 回收的字节数
+    pub freed_bytes: u64,
+    pub run_at: i64,
+}
+
+/// 数据库占用信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseSizeInfo {
+    /// 数据库总大小（page_count × page_size）
+    pub total_bytes: u64,
+    /// 空闲页占用的字节数（可被 VACUUM 回收）
+    pub free_bytes: u64,
+    pub request_log_rows: u64,
+    pub shadow_log_rows: u64,
+    pub rollup_rows: u64,
+    /// 最早一条原始请求日志的时间
+    pub oldest_request_log_at: Option<i64>,
+}
+
+impl Database {
+    /// 获取日志保留配置
+    pub fn get_log_retention_config(&self) -> Result<LogRetentionConfig, AppError> {
+        match self.get_setting(RETENTION_CONFIG_SETTING)? {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| AppError::Message(format!("解析日志保留配置失败: {e}"))),
+            None => Ok(LogRetentionConfig::default()),
+        }
+    }
+
+    /// 保存日志保留配置（保留最近一次维护时间）
+    pub fn set_log_retention_config(&self, config: &LogRetentionConfig) -> Result<(), AppError> {
+        if config.retention_days < MIN_RETENTION_DAYS {
+            return Err(AppError::InvalidInput(format!(
+                "日志保留天数不能少于 {MIN_RETENTION_DAYS} 天"
+            )));
+        }
+
+        let config = LogRetentionConfig {
+            last_run_at: self.get_log_retention_config()?.last_run_at,
+            ..config.clone()
+        };
+        let json = serde_json::to_string(&config)
+            .map_err(|e| AppError::Message(format!("序列化日志保留配置失败: {e}")))?;
+        self.set_setting(RETENTION_CONFIG_SETTING, &json)
+    }
+
+    /// 执行一次日志维护：按配置聚合并清理过期日志，然后回收空间（必要时切换增量 VACUUM）
+    pub fn run_log_maintenance(&self) -> Result<LogMaintenanceResult, AppError> {
+        self.run_log_maintenance_with(true)
+    }
+
+    /// 后台维护：与手动维护相同，但不执行耗时的完整 VACUUM
+    fn run_scheduled_log_maintenance(&self) -> Result<LogMaintenanceResult, AppError> {
+        self.run_log_maintenance_with(false)
+    }
+
+    fn run_log_maintenance_with(
+        &self,
+        allow_full_vacuum: bool,
+    ) -> Result<LogMaintenanceResult, AppError> {
+        let mut config = self.get_log_retention_config()?;
+        let now = Utc::now().timestamp();
+
+        let mut result = if config.enabled {
+            // 截止到保留期起始日的 UTC 零点，保证每天的数据整体聚合
+            let today = now - now.rem_euclid(86_400);
+            let cutoff = today - i64::from(config.retention_days.max(MIN_RETENTION_DAYS)) * 86_400;
+            self.rollup_request_logs(cutoff)?
+        } else {
+            LogMaintenanceResult::default()
+        };
+        result.freed_bytes = self.vacuum_incremental(allow_full_vacuum)?;
+        result.run_at = now;
+
+        config.last_run_at = Some(now);
+        let json = serde_json::to_string(&config)
+            .map_err(|e| AppError::Message(format!("序列化日志保留配置失败: {e}")))?;
+        self.set_setting(RETENTION_CONFIG_SETTING, &json)?;
+
+        Ok(result)
+    }
+
+    /// 将 `created_at < cutoff` 的原始日志按天聚合后删除
+    pub fn rollup_request_logs(&self, cutoff: i64) -> Result<LogMaintenanceResult, AppError> {
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // 记录将被删除的会话日志 ID，导入时据此去重
+        tx.execute(
+            &format!(
+                "INSERT OR IGNORE INTO rolled_up_session_records (request_id)
+                 SELECT request_id FROM proxy_request_logs
+                 WHERE created_at < ?1 AND source = '{SOURCE_SESSION_LOG}'"
+            ),
+            [cutoff],
+        )?;
+
+        let mut result = LogMaintenanceResult::default();
+        for (table, is_shadow) in [("proxy_request_logs", 0), ("proxy_shadow_logs", 1)] {
+            result.rolled_up_rows += rollup_table(&tx, table, is_shadow, cutoff)? as u64;
+            result.deleted_rows += tx.execute(
+                &format!("DELETE FROM {table} WHERE created_at < ?1"),
+                [cutoff],
+            )? as u64;
+        }
+
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        if result.deleted_rows > 0 {
+            log::info!(
+                "已聚合并清理 {} 条过期请求日志（聚合行 {}）",
+                result.deleted_rows,
+                result.rolled_up_rows
+            );
+        }
+        Ok(result)
+    }
+
+    /// 增量 VACUUM，返回回收的字节数
+    ///
+    /// 旧数据库默认 `auto_vacuum = NONE`，需切换为 INCREMENTAL 并执行一次完整 VACUUM；
+    /// `allow_full_vacuum` 为 false 时跳过切换，等待手动维护
+    fn vacuum_incremental(&self, allow_full_vacuum: bool) -> Result<u64, AppError> {
+        let conn = lock_conn!(self.conn);
+        let before = database_bytes(&conn)?;
+
+        let auto_vacuum: i64 = conn.query_row("PRAGMA auto_vacuum", [], |row| row.get(0))?;
+        if auto_vacuum != 2 && !allow_full_vacuum {
+            log::debug!("数据库尚未启用增量 VACUUM，跳过空间回收");
+            return Ok(0);
+        }
+        if auto_vacuum != 2 {
+            log::info!("切换数据库为增量 VACUUM 模式");
+            conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")?;
+        } else {
+            conn.execute_batch("PRAGMA incremental_vacuum;")?;
+        }
+
+        let after = database_bytes(&conn)?;
+        Ok(before.saturating_sub(after))
+    }
+
+    /// 获取数据库占用信息
+    pub fn get_database_size(&self) -> Result<DatabaseSizeInfo, AppError> {
+        let conn = lock_conn!(self.conn);
+        let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        let freelist_count: i64 = conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+        let count = |table: &str| -> Result<u64, AppError> {
+            let n: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })?;
+            Ok(n as u64)
+        };
+
+        Ok(DatabaseSizeInfo {
+            total_bytes: database_bytes(&conn)?,
+            free_bytes: (freelist_count * page_size) as u64,
+            request_log_rows: count("proxy_request_logs")?,
+            shadow_log_rows: count("proxy_shadow_logs")?,
+            rollup_rows: count("usage_daily_rollups")?,
+            oldest_request_log_at: conn.query_row(
+                "SELECT MIN(created_at) FROM proxy_request_logs",
+                [],
+                |row| row.get(0),
+            )?,
+        })
+    }
+}
+
+/// 原始日志与聚合数据的统一视图（别名由调用方指定）
+///
+/// 每行提供 `request_count` / `success_count` / `latency_ms_sum`，原始日志按单条计；
+/// 成本列与原始日志一致，可直接配合 `cost_sql` 使用
+pub(crate) fn usage_rows_sql(is_shadow: bool) -> String {
+    let (table, shadow) = if is_shadow {
+        ("proxy_shadow_logs", 1)
+    } else {
+        ("proxy_request_logs", 0)
+    };
+    format!(
+        "(SELECT provider_id, app_type, model, source, billing_currency, created_at,
+                1 AS request_count,
+                CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END AS success_count,
+                input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
+                total_cost_usd, billing_cost, latency_ms AS latency_ms_sum
+          FROM {table}
+          UNION ALL
+          SELECT provider_id, app_type, model, source, billing_currency, created_at,
+                request_count, success_count,
+                input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
+                total_cost_usd, billing_cost, latency_ms_sum
+          FROM usage_daily_rollups WHERE is_shadow = {shadow})"
+    )
+}
+
+/// 将指定日志表中过期的记录合并到按天聚合表，返回写入的聚合行数
+fn rollup_table(
+    conn: &Connection,
+    table: &str,
+    is_shadow: i64,
+    cutoff: i64,
+) -> Result<usize, AppError> {
+    let sql = format!(
+        "INSERT INTO usage_daily_rollups (
+            created_at, is_shadow, provider_id, app_type, model, source, billing_currency,
+            request_count, success_count, input_tokens, output_tokens,
+            cache_read_tokens, cache_creation_tokens, total_cost_usd, billing_cost, latency_ms_sum
+         )
+         SELECT (created_at / 86400) * 86400 AS day, ?2, provider_id, app_type, model, source,
+                billing_currency,
+                COUNT(*),
+                SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END),
+                SUM(input_tokens), SUM(output_tokens),
+                SUM(cache_read_tokens), SUM(cache_creation_tokens),
+                printf('%.6f', SUM(CAST(total_cost_usd AS REAL))),
+                CASE WHEN COUNT(billing_cost) > 0
+                     THEN printf('%.6f', SUM(CAST(billing_cost AS REAL))) END,
+                SUM(latency_ms)
+         FROM {table}
+         WHERE created_at < ?1
+         GROUP BY day, provider_id, app_type, model, source, billing_currency
+         ON CONFLICT (created_at, is_shadow, provider_id, app_type, model, source, billing_currency)
+         DO UPDATE SET
+            request_count = request_count + excluded.request_count,
+            success_count = success_count + excluded.success_count,
+            input_tokens = input_tokens + excluded.input_tokens,
+            output_tokens = output_tokens + excluded.output_tokens,
+            cache_read_tokens = cache_read_tokens + excluded.cache_read_tokens,
+            cache_creation_tokens = cache_creation_tokens + excluded.cache_creation_tokens,
+            total_cost_usd = printf('%.6f',
+                CAST(total_cost_usd AS REAL) + CAST(excluded.total_cost_usd AS REAL)),
+            billing_cost = CASE WHEN billing_cost IS NULL AND excluded.billing_cost IS NULL THEN NULL
+                ELSE printf('%.6f', COALESCE(CAST(billing_cost AS REAL), 0)
+                    + COALESCE(CAST(excluded.billing_cost AS REAL), 0)) END,
+            latency_ms_sum = latency_ms_sum + excluded.latency_ms_sum"
+    );
+    Ok(conn.execute(&sql, params![cutoff, is_shadow])?)
+}
+
+/// 数据库总字节数
+fn database_bytes(conn: &Connection) -> Result<u64, AppError> {
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    Ok((page_size * page_count) as u64)
+}
+
+/// 启动后台日志维护任务（启动 10 分钟后执行一次，之后每 6 小时执行一次）
+pub fn spawn_log_maintenance_scheduler(db: Arc<Database>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + MAINTENANCE_STARTUP_DELAY,
+            MAINTENANCE_INTERVAL,
+        );
+        loop {
+            interval.tick().await;
+            let db = db.clone();
+            let result =
+                tokio::task::spawn_blocking(move || db.run_scheduled_log_maintenance()).await;
+            match result {
+                Ok(Err(e)) => log::warn!("日志维护失败: {e}"),
+                Err(e) => log::warn!("日志维护任务异常: {e}"),
+                Ok(Ok(_)) => {}
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert_log(
+        conn: &Connection,
+        table: &str,
+        id: &str,
+        status: i64,
+        cost: &str,
+        created_at: i64,
+    ) -> Result<(), AppError> {
+        conn.execute(
+            &format!(
+                "INSERT INTO {table} (
+                    request_id, provider_id, app_type, model, input_tokens, output_tokens,
+                    total_cost_usd, latency_ms, status_code, created_at
+                ) VALUES (?1, 'p1', 'claude', 'claude-sonnet-4-5', 100, 10, ?2, 200, ?3, ?4)"
+            ),
+            params![id, cost, status, created_at],
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollup_preserves_stats() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let day = 20_000 * 86_400;
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO providers (id, app_type, name, settings_config) VALUES ('p1', 'claude', 'P1', '{}')",
+                [],
+            )?;
+            insert_log(&conn, "proxy_request_logs", "old1", 200, "0.5", day + 10)?;
+            insert_log(&conn, "proxy_request_logs", "old2", 500, "0.25", day + 20)?;
+            insert_log(
+                &conn,
+                "proxy_request_logs",
+                "new1",
+                200,
+                "1",
+                day + 2 * 86_400,
+            )?;
+            insert_log(&conn, "proxy_shadow_logs", "shadow1", 200, "2", day + 30)?;
+        }
+
+        let before = db.get_usage_summary(None, None, Some("USD"))?;
+        let result = db.rollup_request_logs(day + 86_400)?;
+        assert_eq!(result.deleted_rows, 3);
+        assert_eq!(result.rolled_up_rows, 2);
+
+        let after = db.get_usage_summary(None, None, Some("USD"))?;
+        assert_eq!(after.total_requests, before.total_requests);
+        assert_eq!(after.total_cost, before.total_cost);
+        assert_eq!(after.total_input_tokens, 300);
+        assert_eq!(after.success_rate, before.success_rate);
+
+        let stats = db.get_provider_stats(Some("USD"))?;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].request_count, 3);
+        assert_eq!(stats[0].avg_latency_ms, 200);
+        assert!(stats[1].is_shadow);
+        assert_eq!(stats[1].total_cost, "2.000000");
+
+        // 同一天补入的旧日志（如会话日志导入）合并到已有聚合行
+        {
+            let conn = lock_conn!(db.conn);
+            insert_log(&conn, "proxy_request_logs", "late", 200, "0.25", day + 40)?;
+        }
+        let result = db.rollup_request_logs(day + 86_400)?;
+        assert_eq!(result.deleted_rows, 1);
+        let conn = lock_conn!(db.conn);
+        let (count, success, cost): (i64, i64, String) = conn.query_row(
+            "SELECT request_count, success_count, total_cost_usd FROM usage_daily_rollups WHERE is_shadow = 0",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        assert_eq!((count, success, cost.as_str()), (3, 2, "1.000000"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_log_maintenance_and_config() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let config = db.get_log_retention_config()?;
+        assert_eq!(config.retention_days, 30);
+        assert!(!config.enabled);
+        assert!(db
+            .set_log_retention_config(&LogRetentionConfig {
+                retention_days: 1,
+                ..Default::default()
+            })
+            .is_err());
+
+        let now = Utc::now().timestamp();
+        {
+            let conn = lock_conn!(db.conn);
+            insert_log(
+                &conn,
+                "proxy_request_logs",
+                "old",
+                200,
+                "1",
+                now - 40 * 86_400,
+            )?;
+            insert_log(&conn, "proxy_request_logs", "recent", 200, "1", now)?;
+        }
+
+        // 未启用时不删除任何原始日志
+        assert_eq!(db.run_log_maintenance()?.deleted_rows, 0);
+        assert_eq!(db.get_database_size()?.request_log_rows, 2);
+
+        db.set_log_retention_config(&LogRetentionConfig {
+            enabled: true,
+            ..Default::default()
+        })?;
+        let result = db.run_log_maintenance()?;
+        assert_eq!(result.deleted_rows, 1);
+        assert_eq!(
+            db.get_log_retention_config()?.last_run_at,
+            Some(result.run_at)
+        );
+
+        let size = db.get_database_size()?;
+        assert_eq!(size.request_log_rows, 1);
+        assert_eq!(size.rollup_rows, 1);
+        assert_eq!(size.oldest_request_log_at, Some(now));
+        assert!(size.total_bytes > 0);
+        Ok(())
+    }
+}
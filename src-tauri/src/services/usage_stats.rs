@@ -4,6 +4,10 @@
 
 use crate::database::{lock_conn, Database};
 use crate::error::AppError;
+use crate::proxy::usage::{CostCalculator, ModelPricing, PricingRule, TokenUsage};
+use crate::services::currency::{cost_sql, resolve_report_currency, BILLING_COST_UPDATE_SQL};
+use crate::services::session_usage_import::SOURCE_PROXY;
+use crate::services::usage_retention::usage_rows_sql;
 use chrono::{Duration, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
@@ -17,6 +21,8 @@ use std::str::FromStr;
 pub struct UsageSummary {
     pub total_requests: u64,
     pub total_cost: String,
+    /// total_cost 的币种
+    pub currency: String,
     pub total_input_tokens: u64,
     pub total_output_tokens: u64,
     pub total_cache_creation_tokens: u64,
@@ -31,6 +37,8 @@ pub struct DailyStats {
     pub date: String,
     pub request_count: u64,
     pub total_cost: String,
+    /// total_cost 的币种
+    pub currency: String,
     pub total_tokens: u64,
     pub total_input_tokens: u64,
     pub total_output_tokens: u64,
@@ -47,8 +55,13 @@ pub struct ProviderStats {
     pub request_count: u64,
     pub total_tokens: u64,
     pub total_cost: String,
+    /// total_cost 的币种
+    pub currency: String,
     pub success_rate: f32,
     pub avg_latency_ms: u64,
+    /// 是否为影子流量统计（来自 proxy_shadow_logs）
+    #[serde(default)]
+    pub is_shadow: bool,
 }
 
 /// 模型统计
@@ -72,6 +85,12 @@ pub struct LogFilters {
     pub status_code: Option<u16>,
     pub start_date: Option<i64>,
     pub end_date: Option<i64>,
+    /// 客户端会话 ID（精确匹配）
+    pub session_id: Option<String>,
+    /// 项目目录（精确匹配）
+    pub project_path: Option<String>,
+    /// 日志来源（proxy / session_log）
+    pub source: Option<String>,
 }
 
 /// 分页请求日志响应
@@ -109,17 +128,64 @@ pub struct RequestLogDetail {
     pub duration_ms: Option<u64>,
     pub status_code: u16,
     pub error_message: Option<String>,
+    /// 客户端会话 ID
+    pub session_id: Option<String>,
+    /// 项目目录（客户端工作目录）
+    pub project_path: Option<String>,
+    /// 日志来源（proxy: 代理流量，session_log: 会话日志导入）
+    pub source: String,
     pub created_at: i64,
 }
 
+/// 项目统计（按客户端工作目录聚合，未识别项目的请求归入 `project_path = None`）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    pub project_path: Option<String>,
+    pub session_count: u64,
+    pub request_count: u64,
+    pub total_input_tokens: u64,
+    pub total_output_tokens: u64,
+    pub total_cache_creation_tokens: u64,
+    pub total_cache_read_tokens: u64,
+    pub total_cost: String,
+    /// total_cost 的币种
+    pub currency: String,
+    pub first_request_at: i64,
+    pub last_request_at: i64,
+}
+
+/// 会话统计（按客户端会话 ID 聚合）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStats {
+    pub session_id: String,
+    pub project_path: Option<String>,
+    pub request_count: u64,
+    pub total_input_tokens: u64,
+    pub total_output_tokens: u64,
+    pub total_cache_creation_tokens: u64,
+    pub total_cache_read_tokens: u64,
+    pub total_cost: String,
+    /// total_cost 的币种
+    pub currency: String,
+    /// 会话中使用过的模型
+    pub models: Vec<String>,
+    pub first_request_at: i64,
+    pub last_request_at: i64,
+}
+
 impl Database {
-    /// 获取使用量汇总
+    /// 获取使用量汇总（成本按 `currency` 报表币种返回，未指定时使用默认报表币种）
     pub fn get_usage_summary(
         &self,
         start_date: Option<i64>,
         end_date: Option<i64>,
+        currency: Option<&str>,
     ) -> Result<UsageSummary, AppError> {
         let conn = lock_conn!(self.conn);
+        let currency = resolve_report_currency(&conn, currency)?;
+        let cost_expr = cost_sql(&currency);
 
         let (where_clause, params_vec) = if start_date.is_some() || end_date.is_some() {
             let mut conditions = Vec::new();
@@ -139,16 +205,17 @@ impl Database {
             (String::new(), Vec::new())
         };
 
+        let rows_sql = usage_rows_sql(false);
         let sql = format!(
             "SELECT 
-                COUNT(*) as total_requests,
-                COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost,
+                COALESCE(SUM(request_count), 0) as total_requests,
+                COALESCE(SUM({cost_expr}), 0) as total_cost,
                 COALESCE(SUM(input_tokens), 0) as total_input_tokens,
                 COALESCE(SUM(output_tokens), 0) as total_output_tokens,
                 COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
                 COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens,
-                COALESCE(SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END), 0) as success_count
-             FROM proxy_request_logs
+                COALESCE(SUM(success_count), 0) as success_count
+             FROM {rows_sql} l
              {where_clause}"
         );
 
@@ -170,6 +237,7 @@ impl Database {
             Ok(UsageSummary {
                 total_requests: total_requests as u64,
                 total_cost: format!("{total_cost:.6}"),
+                currency: currency.clone(),
                 total_input_tokens: total_input_tokens as u64,
                 total_output_tokens: total_output_tokens as u64,
                 total_cache_creation_tokens: total_cache_creation_tokens as u64,
@@ -181,176 +249,177 @@ impl Database {
         Ok(result)
     }
 
-    /// 获取每日趋势
-    pub fn get_daily_trends(&self, days: u32) -> Result<Vec<DailyStats>, AppError> {
+    /// 获取最近 `hours` 小时的逐小时趋势（含当前小时，按 UTC 整点分桶）
+    pub fn get_hourly_trends(
+        &self,
+        hours: u32,
+        currency: Option<&str>,
+    ) -> Result<Vec<DailyStats>, AppError> {
         let conn = lock_conn!(self.conn);
+        let currency = resolve_report_currency(&conn, currency)?;
+        let cost_expr = cost_sql(&currency);
 
-        if days <= 1 {
-            let sql = "SELECT 
-                    strftime('%Y-%m-%dT%H:00:00Z', datetime(created_at, 'unixepoch')) as bucket,
-                    COUNT(*) as request_count,
-                    COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost,
-                    COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
-                    COALESCE(SUM(input_tokens), 0) as total_input_tokens,
-                    COALESCE(SUM(output_tokens), 0) as total_output_tokens,
-                    COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
-                    COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens
-                 FROM proxy_request_logs
-                 WHERE created_at >= strftime('%s', 'now', '-1 day')
-                 GROUP BY bucket
-                 ORDER BY bucket ASC";
-
-            let mut stmt = conn.prepare(sql)?;
-            let rows = stmt.query_map([], |row| {
-                Ok(DailyStats {
-                    date: row.get(0)?,
-                    request_count: row.get::<_, i64>(1)? as u64,
-                    total_cost: format!("{:.6}", row.get::<_, f64>(2)?),
-                    total_tokens: row.get::<_, i64>(3)? as u64,
-                    total_input_tokens: row.get::<_, i64>(4)? as u64,
-                    total_output_tokens: row.get::<_, i64>(5)? as u64,
-                    total_cache_creation_tokens: row.get::<_, i64>(6)? as u64,
-                    total_cache_read_tokens: row.get::<_, i64>(7)? as u64,
-                })
-            })?;
+        let hours = hours.max(1) as i64;
+        let now = Utc::now().timestamp();
+        let first_bucket = now - now.rem_euclid(3600) - (hours - 1) * 3600;
 
-            let mut buckets: HashMap<String, DailyStats> = HashMap::new();
-            for row in rows {
-                let stat = row?;
-                buckets.insert(stat.date.clone(), stat);
-            }
+        let sql = format!(
+            "SELECT 
+                strftime('%Y-%m-%dT%H:00:00Z', datetime(created_at, 'unixepoch')) as bucket,
+                COUNT(*) as request_count,
+                COALESCE(SUM({cost_expr}), 0) as total_cost,
+                COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
+                COALESCE(SUM(input_tokens), 0) as total_input_tokens,
+                COALESCE(SUM(output_tokens), 0) as total_output_tokens,
+                COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
+                COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens
+             FROM proxy_request_logs l
+             WHERE created_at >= ?
+             GROUP BY bucket
+             ORDER BY bucket ASC"
+        );
 
-            let mut stats = Vec::new();
-            let today = Utc::now().date_naive();
-            for hour in 0..24 {
-                let bucket = today
-                    .and_hms_opt(hour, 0, 0)
-                    .unwrap()
-                    .format("%Y-%m-%dT%H:00:00Z")
-                    .to_string();
-
-                if let Some(stat) = buckets.remove(&bucket) {
-                    stats.push(stat);
-                } else {
-                    stats.push(DailyStats {
-                        date: bucket,
-                        request_count: 0,
-                        total_cost: "0.000000".to_string(),
-                        total_tokens: 0,
-                        total_input_tokens: 0,
-                        total_output_tokens: 0,
-                        total_cache_creation_tokens: 0,
-                        total_cache_read_tokens: 0,
-                    });
-                }
-            }
-            Ok(stats)
-        } else {
-            let sql = "SELECT 
-                    date(created_at, 'unixepoch') as bucket,
-                    COUNT(*) as request_count,
-                    COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost,
-                    COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
-                    COALESCE(SUM(input_tokens), 0) as total_input_tokens,
-                    COALESCE(SUM(output_tokens), 0) as total_output_tokens,
-                    COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
-                    COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens
-                 FROM proxy_request_logs
-                 WHERE created_at >= strftime('%s', 'now', ?)
-                 GROUP BY bucket
-                 ORDER BY bucket ASC";
-
-            let mut stmt = conn.prepare(sql)?;
-            let rows = stmt.query_map([format!("-{days} days")], |row| {
-                Ok(DailyStats {
-                    date: row.get(0)?,
-                    request_count: row.get::<_, i64>(1)? as u64,
-                    total_cost: format!("{:.6}", row.get::<_, f64>(2)?),
-                    total_tokens: row.get::<_, i64>(3)? as u64,
-                    total_input_tokens: row.get::<_, i64>(4)? as u64,
-                    total_output_tokens: row.get::<_, i64>(5)? as u64,
-                    total_cache_creation_tokens: row.get::<_, i64>(6)? as u64,
-                    total_cache_read_tokens: row.get::<_, i64>(7)? as u64,
-                })
-            })?;
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([first_bucket], |row| {
+            Ok(DailyStats {
+                date: row.get(0)?,
+                request_count: row.get::<_, i64>(1)? as u64,
+                total_cost: format!("{:.6}", row.get::<_, f64>(2)?),
+                currency: currency.clone(),
+                total_tokens: row.get::<_, i64>(3)? as u64,
+                total_input_tokens: row.get::<_, i64>(4)? as u64,
+                total_output_tokens: row.get::<_, i64>(5)? as u64,
+                total_cache_creation_tokens: row.get::<_, i64>(6)? as u64,
+                total_cache_read_tokens: row.get::<_, i64>(7)? as u64,
+            })
+        })?;
 
-            let mut map = HashMap::new();
-            for row in rows {
-                let stat = row?;
-                map.insert(stat.date.clone(), stat);
-            }
+        let mut buckets: HashMap<String, DailyStats> = HashMap::new();
+        for row in rows {
+            let stat = row?;
+            buckets.insert(stat.date.clone(), stat);
+        }
 
-            let mut stats = Vec::new();
-            let start_day =
-                Utc::now().date_naive() - Duration::days((days.saturating_sub(1)) as i64);
-
-            for i in 0..days {
-                let day = start_day + Duration::days(i as i64);
-                let key = day.format("%Y-%m-%d").to_string();
-                if let Some(stat) = map.remove(&key) {
-                    stats.push(stat);
-                } else {
-                    stats.push(DailyStats {
-                        date: key,
-                        request_count: 0,
-                        total_cost: "0.000000".to_string(),
-                        total_tokens: 0,
-                        total_input_tokens: 0,
-                        total_output_tokens: 0,
-                        total_cache_creation_tokens: 0,
-                        total_cache_read_tokens: 0,
-                    });
-                }
+        let mut stats = Vec::new();
+        for i in 0..hours {
+            let bucket = chrono::DateTime::from_timestamp(first_bucket + i * 3600, 0)
+                .unwrap_or_default()
+                .format("%Y-%m-%dT%H:00:00Z")
+                .to_string();
+
+            if let Some(stat) = buckets.remove(&bucket) {
+                stats.push(stat);
+            } else {
+                stats.push(DailyStats {
+                    date: bucket,
+                    request_count: 0,
+                    total_cost: "0.000000".to_string(),
+                    currency: currency.clone(),
+                    total_tokens: 0,
+                    total_input_tokens: 0,
+                    total_output_tokens: 0,
+                    total_cache_creation_tokens: 0,
+                    total_cache_read_tokens: 0,
+                });
             }
-            Ok(stats)
         }
+        Ok(stats)
     }
 
-    /// 获取 Provider 统计
-    pub fn get_provider_stats(&self) -> Result<Vec<ProviderStats>, AppError> {
-        let conn = lock_conn!(self.conn);
+    /// 获取每日趋势（成本按 `currency` 报表币种返回，未指定时使用默认报表币种）
+    ///
+    /// `days <= 1` 时返回最近 24 小时的逐小时趋势
+    pub fn get_daily_trends(
+        &self,
+        days: u32,
+        currency: Option<&str>,
+    ) -> Result<Vec<DailyStats>, AppError> {
+        if days <= 1 {
+            return self.get_hourly_trends(24, currency);
+        }
 
-        let sql = "SELECT 
-                l.provider_id,
-                p.name as provider_name,
-                COUNT(*) as request_count,
-                COALESCE(SUM(l.input_tokens + l.output_tokens), 0) as total_tokens,
-                COALESCE(SUM(CAST(l.total_cost_usd AS REAL)), 0) as total_cost,
-                COALESCE(SUM(CASE WHEN l.status_code >= 200 AND l.status_code < 300 THEN 1 ELSE 0 END), 0) as success_count,
-                COALESCE(AVG(l.latency_ms), 0) as avg_latency
-             FROM proxy_request_logs l
-             LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
-             GROUP BY l.provider_id, l.app_type
-             ORDER BY total_cost DESC";
+        let conn = lock_conn!(self.conn);
+        let currency = resolve_report_currency(&conn, currency)?;
+        let cost_expr = cost_sql(&currency);
 
-        let mut stmt = conn.prepare(sql)?;
-        let rows = stmt.query_map([], |row| {
-            let request_count: i64 = row.get(2)?;
-            let success_count: i64 = row.get(5)?;
-            let success_rate = if request_count > 0 {
-                (success_count as f32 / request_count as f32) * 100.0
-            } else {
-                0.0
-            };
+        let rows_sql = usage_rows_sql(false);
+        let sql = format!(
+            "SELECT 
+                date(created_at, 'unixepoch') as bucket,
+                COALESCE(SUM(request_count), 0) as request_count,
+                COALESCE(SUM({cost_expr}), 0) as total_cost,
+                COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
+                COALESCE(SUM(input_tokens), 0) as total_input_tokens,
+                COALESCE(SUM(output_tokens), 0) as total_output_tokens,
+                COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
+                COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens
+             FROM {rows_sql} l
+             WHERE created_at >= strftime('%s', 'now', ?)
+             GROUP BY bucket
+             ORDER BY bucket ASC"
+        );
 
-            Ok(ProviderStats {
-                provider_id: row.get(0)?,
-                provider_name: row
-                    .get::<_, Option<String>>(1)?
-                    .unwrap_or_else(|| "Unknown".to_string()),
-                request_count: request_count as u64,
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([format!("-{days} days")], |row| {
+            Ok(DailyStats {
+                date: row.get(0)?,
+                request_count: row.get::<_, i64>(1)? as u64,
+                total_cost: format!("{:.6}", row.get::<_, f64>(2)?),
+                currency: currency.clone(),
                 total_tokens: row.get::<_, i64>(3)? as u64,
-                total_cost: format!("{:.6}", row.get::<_, f64>(4)?),
-                success_rate,
-                avg_latency_ms: row.get::<_, f64>(6)? as u64,
+                total_input_tokens: row.get::<_, i64>(4)? as u64,
+                total_output_tokens: row.get::<_, i64>(5)? as u64,
+                total_cache_creation_tokens: row.get::<_, i64>(6)? as u64,
+                total_cache_read_tokens: row.get::<_, i64>(7)? as u64,
             })
         })?;
 
-        let mut stats = Vec::new();
+        let mut map = HashMap::new();
         for row in rows {
-            stats.push(row?);
+            let stat = row?;
+            map.insert(stat.date.clone(), stat);
         }
 
+        let mut stats = Vec::new();
+        let start_day = Utc::now().date_naive() - Duration::days((days.saturating_sub(1)) as i64);
+
+        for i in 0..days {
+            let day = start_day + Duration::days(i as i64);
+            let key = day.format("%Y-%m-%d").to_string();
+            if let Some(stat) = map.remove(&key) {
+                stats.push(stat);
+            } else {
+                stats.push(DailyStats {
+                    date: key,
+                    request_count: 0,
+                    total_cost: "0.000000".to_string(),
+                    currency: currency.clone(),
+                    total_tokens: 0,
+                    total_input_tokens: 0,
+                    total_output_tokens: 0,
+                    total_cache_creation_tokens: 0,
+                    total_cache_read_tokens: 0,
+                });
+            }
+        }
+        Ok(stats)
+    }
+
+    /// 获取 Provider 统计
+    ///
+    /// 先返回主路径统计，再追加影子流量统计（`is_shadow = true`），便于对比；
+    /// 仅统计代理流量，会话日志导入的用量不归属任何供应商
+    /// 成本按 `currency` 报表币种返回，未指定时使用默认报表币种
+    pub fn get_provider_stats(
+        &self,
+        currency: Option<&str>,
+    ) -> Result<Vec<ProviderStats>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let currency = resolve_report_currency(&conn, currency)?;
+
+        let mut stats = query_provider_stats(&conn, false, &currency)?;
+        stats.extend(query_provider_stats(&conn, true, &currency)?);
+
         Ok(stats)
     }
 
@@ -358,16 +427,19 @@ impl Database {
     pub fn get_model_stats(&self) -> Result<Vec<ModelStats>, AppError> {
         let conn = lock_conn!(self.conn);
 
-        let sql = "SELECT 
+        let sql = format!(
+            "SELECT 
                 model,
-                COUNT(*) as request_count,
+                SUM(request_count) as request_count,
                 COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
                 COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost
-             FROM proxy_request_logs
+             FROM {}
              GROUP BY model
-             ORDER BY total_cost DESC";
+             ORDER BY total_cost DESC",
+            usage_rows_sql(false)
+        );
 
-        let mut stmt = conn.prepare(sql)?;
+        let mut stmt = conn.prepare(&sql)?;
         let rows = stmt.query_map([], |row| {
             let request_count: i64 = row.get(1)?;
             let total_cost: f64 = row.get(3)?;
@@ -394,6 +466,130 @@ impl Database {
         Ok(stats)
     }
 
+    /// 获取项目统计（按客户端工作目录聚合，成本按 `currency` 报表币种返回）
+    pub fn get_project_stats(
+        &self,
+        start_date: Option<i64>,
+        end_date: Option<i64>,
+        currency: Option<&str>,
+    ) -> Result<Vec<ProjectStats>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let currency = resolve_report_currency(&conn, currency)?;
+        let cost_expr = cost_sql(&currency);
+
+        let (where_clause, params) = log_filter_clause(&LogFilters {
+            start_date,
+            end_date,
+            ..Default::default()
+        });
+        let sql = format!(
+            "SELECT
+                l.project_path,
+                COUNT(DISTINCT l.session_id) as session_count,
+                COUNT(*) as request_count,
+                COALESCE(SUM(l.input_tokens), 0),
+                COALESCE(SUM(l.output_tokens), 0),
+                COALESCE(SUM(l.cache_creation_tokens), 0),
+                COALESCE(SUM(l.cache_read_tokens), 0),
+                COALESCE(SUM({cost_expr}), 0) as total_cost,
+                MIN(l.created_at),
+                MAX(l.created_at)
+             FROM proxy_request_logs l
+             {where_clause}
+             GROUP BY l.project_path
+             ORDER BY total_cost DESC, request_count DESC"
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let rows = stmt.query_map(params_refs.as_slice(), |row| {
+            Ok(ProjectStats {
+                project_path: row.get(0)?,
+                session_count: row.get::<_, i64>(1)? as u64,
+                request_count: row.get::<_, i64>(2)? as u64,
+                total_input_tokens: row.get::<_, i64>(3)? as u64,
+                total_output_tokens: row.get::<_, i64>(4)? as u64,
+                total_cache_creation_tokens: row.get::<_, i64>(5)? as u64,
+                total_cache_read_tokens: row.get::<_, i64>(6)? as u64,
+                total_cost: format!("{:.6}", row.get::<_, f64>(7)?),
+                currency: currency.clone(),
+                first_request_at: row.get(8)?,
+                last_request_at: row.get(9)?,
+            })
+        })?;
+
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// 获取会话统计（可按项目目录过滤，成本按 `currency` 报表币种返回）
+    ///
+    /// 单个会话的请求明细可通过 [`LogFilters::session_id`] 调用 `get_request_logs` 获取
+    pub fn get_session_stats(
+        &self,
+        project_path: Option<&str>,
+        start_date: Option<i64>,
+        end_date: Option<i64>,
+        currency: Option<&str>,
+    ) -> Result<Vec<SessionStats>, AppError> {
+        let conn = lock_conn!(self.conn);
+        let currency = resolve_report_currency(&conn, currency)?;
+        let cost_expr = cost_sql(&currency);
+
+        let (where_clause, params) = log_filter_clause(&LogFilters {
+            start_date,
+            end_date,
+            project_path: project_path.map(str::to_string),
+            ..Default::default()
+        });
+        let where_clause = if where_clause.is_empty() {
+            "WHERE l.session_id IS NOT NULL".to_string()
+        } else {
+            format!("{where_clause} AND l.session_id IS NOT NULL")
+        };
+        let sql = format!(
+            "SELECT
+                l.session_id,
+                MAX(l.project_path),
+                COUNT(*) as request_count,
+                COALESCE(SUM(l.input_tokens), 0),
+                COALESCE(SUM(l.output_tokens), 0),
+                COALESCE(SUM(l.cache_creation_tokens), 0),
+                COALESCE(SUM(l.cache_read_tokens), 0),
+                COALESCE(SUM({cost_expr}), 0) as total_cost,
+                GROUP_CONCAT(DISTINCT l.model),
+                MIN(l.created_at),
+                MAX(l.created_at) as last_request_at
+             FROM proxy_request_logs l
+             {where_clause}
+             GROUP BY l.session_id
+             ORDER BY last_request_at DESC"
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let rows = stmt.query_map(params_refs.as_slice(), |row| {
+            let models: Option<String> = row.get(8)?;
+            Ok(SessionStats {
+                session_id: row.get(0)?,
+                project_path: row.get(1)?,
+                request_count: row.get::<_, i64>(2)? as u64,
+                total_input_tokens: row.get::<_, i64>(3)? as u64,
+                total_output_tokens: row.get::<_, i64>(4)? as u64,
+                total_cache_creation_tokens: row.get::<_, i64>(5)? as u64,
+                total_cache_read_tokens: row.get::<_, i64>(6)? as u64,
+                total_cost: format!("{:.6}", row.get::<_, f64>(7)?),
+                currency: currency.clone(),
+                models: models
+                    .map(|m| m.split(',').map(str::to_string).collect())
+                    .unwrap_or_default(),
+                first_request_at: row.get(9)?,
+                last_request_at: row.get(10)?,
+            })
+        })?;
+
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
     /// 获取请求日志列表（分页）
     pub fn get_request_logs(
         &self,
@@ -403,39 +599,7 @@ impl Database {
     ) -> Result<PaginatedLogs, AppError> {
         let conn = lock_conn!(self.conn);
 
-        let mut conditions = Vec::new();
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
-
-        if let Some(ref app_type) = filters.app_type {
-            conditions.push("l.app_type = ?");
-            params.push(Box::new(app_type.clone()));
-        }
-        if let Some(ref provider_name) = filters.provider_name {
-            conditions.push("p.name LIKE ?");
-            params.push(Box::new(format!("%{provider_name}%")));
-        }
-        if let Some(ref model) = filters.model {
-            conditions.push("l.model LIKE ?");
-            params.push(Box::new(format!("%{model}%")));
-        }
-        if let Some(status) = filters.status_code {
-            conditions.push("l.status_code = ?");
-            params.push(Box::new(status as i64));
-        }
-        if let Some(start) = filters.start_date {
-            conditions.push("l.created_at >= ?");
-            params.push(Box::new(start));
-        }
-        if let Some(end) = filters.end_date {
-            conditions.push("l.created_at <= ?");
-            params.push(Box::new(end));
-        }
-
-        let where_clause = if conditions.is_empty() {
-            String::new()
-        } else {
-            format!("WHERE {}", conditions.join(" AND "))
-        };
+        let (where_clause, mut params) = log_filter_clause(filters);
 
         // 获取总数
         let count_sql = format!(
@@ -458,7 +622,7 @@ impl Database {
                     l.input_tokens, l.output_tokens, l.cache_read_tokens, l.cache_creation_tokens,
                     l.input_cost_usd, l.output_cost_usd, l.cache_read_cost_usd, l.cache_creation_cost_usd, l.total_cost_usd,
                     l.is_streaming, l.latency_ms, l.first_token_ms, l.duration_ms,
-                    l.status_code, l.error_message, l.created_at
+                    l.status_code, l.error_message, l.created_at, l.session_id, l.project_path, l.source
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              {where_clause}
@@ -490,6 +654,9 @@ impl Database {
                 duration_ms: row.get::<_, Option<i64>>(17)?.map(|v| v as u64),
                 status_code: row.get::<_, i64>(18)? as u16,
                 error_message: row.get(19)?,
+                session_id: row.get(21)?,
+                project_path: row.get(22)?,
+                source: row.get(23)?,
                 created_at: row.get(20)?,
             })
         })?;
@@ -529,7 +696,7 @@ impl Database {
                     input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
                     input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
                     is_streaming, latency_ms, first_token_ms, duration_ms,
-                    status_code, error_message, created_at
+                    status_code, error_message, created_at, l.session_id, l.project_path, l.source
              FROM proxy_request_logs l
              LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
              WHERE l.request_id = ?",
@@ -556,6 +723,9 @@ impl Database {
                     duration_ms: row.get::<_, Option<i64>>(17)?.map(|v| v as u64),
                     status_code: row.get::<_, i64>(18)? as u16,
                     error_message: row.get(19)?,
+                    session_id: row.get(21)?,
+                    project_path: row.get(22)?,
+                    source: row.get(23)?,
                     created_at: row.get(20)?,
                 })
             },
@@ -611,25 +781,29 @@ impl Database {
             })
             .unwrap_or((None, None));
 
-        // 计算今日使用量
+        // 计算今日 / 本月使用量（包含已聚合的历史日志，避免保留策略删除原始日志后少算）
+        let rows_sql = usage_rows_sql(false);
         let daily_usage: f64 = conn
             .query_row(
-                "SELECT COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
-             FROM proxy_request_logs
-             WHERE provider_id = ? AND app_type = ?
-               AND date(created_at, 'unixepoch') = date('now')",
+                &format!(
+                    "SELECT COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
+                     FROM {rows_sql}
+                     WHERE provider_id = ? AND app_type = ?
+                       AND date(created_at, 'unixepoch') = date('now')"
+                ),
                 params![provider_id, app_type],
                 |row| row.get(0),
             )
             .unwrap_or(0.0);
 
-        // 计算本月使用量
         let monthly_usage: f64 = conn
             .query_row(
-                "SELECT COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
-             FROM proxy_request_logs
-             WHERE provider_id = ? AND app_type = ?
-               AND strftime('%Y-%m', created_at, 'unixepoch') = strftime('%Y-%m', 'now')",
+                &format!(
+                    "SELECT COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
+                     FROM {rows_sql}
+                     WHERE provider_id = ? AND app_type = ?
+                       AND strftime('%Y-%m', created_at, 'unixepoch') = strftime('%Y-%m', 'now')"
+                ),
                 params![provider_id, app_type],
                 |row| row.get(0),
             )
@@ -667,20 +841,15 @@ pub struct ProviderLimitStatus {
     pub monthly_exceeded: bool,
 }
 
-#[derive(Clone)]
-struct PricingInfo {
-    input: rust_decimal::Decimal,
-    output: rust_decimal::Decimal,
-    cache_read: rust_decimal::Decimal,
-    cache_creation: rust_decimal::Decimal,
-}
+/// 模型定价缓存：基础定价 + 规则（未找到定价时为 None）
+type PricingCache = HashMap<String, Option<(ModelPricing, Vec<PricingRule>)>>;
 
 impl Database {
     fn maybe_backfill_log_costs(
         conn: &Connection,
         log: &mut RequestLogDetail,
         provider_cache: &mut HashMap<(String, String), rust_decimal::Decimal>,
-        pricing_cache: &mut HashMap<String, PricingInfo>,
+        pricing_cache: &mut PricingCache,
     ) -> Result<(), AppError> {
         let total_cost = rust_decimal::Decimal::from_str(&log.total_cost_usd)
             .unwrap_or(rust_decimal::Decimal::ZERO);
@@ -694,10 +863,18 @@ impl Database {
             return Ok(());
         }
 
-        let pricing = match Self::get_model_pricing_cached(conn, pricing_cache, &log.model)? {
-            Some(info) => info,
-            None => return Ok(()),
+        let usage = TokenUsage {
+            input_tokens: log.input_tokens,
+            output_tokens: log.output_tokens,
+            cache_read_tokens: log.cache_read_tokens,
+            cache_creation_tokens: log.cache_creation_tokens,
+            model: None,
         };
+        let Some((base, rules)) = Self::get_model_pricing_cached(conn, pricing_cache, &log.model)?
+        else {
+            return Ok(());
+        };
+        let pricing = CostCalculator::select_pricing(&base, &rules, &usage, log.created_at);
         let multiplier = Self::get_cost_multiplier_cached(
             conn,
             provider_cache,
@@ -705,37 +882,25 @@ impl Database {
             &log.app_type,
         )?;
 
-        let million = rust_decimal::Decimal::from(1_000_000u64);
-        let input_cost = rust_decimal::Decimal::from(log.input_tokens as u64) * pricing.input
-            / million
-            * multiplier;
-        let output_cost = rust_decimal::Decimal::from(log.output_tokens as u64) * pricing.output
-            / million
-            * multiplier;
-        let cache_read_cost = rust_decimal::Decimal::from(log.cache_read_tokens as u64)
-            * pricing.cache_read
-            / million
-            * multiplier;
-        let cache_creation_cost = rust_decimal::Decimal::from(log.cache_creation_tokens as u64)
-            * pricing.cache_creation
-            / million
-            * multiplier;
-        let total_cost = input_cost + output_cost + cache_read_cost + cache_creation_cost;
-
-        log.input_cost_usd = format!("{input_cost:.6}");
-        log.output_cost_usd = format!("{output_cost:.6}");
-        log.cache_read_cost_usd = format!("{cache_read_cost:.6}");
-        log.cache_creation_cost_usd = format!("{cache_creation_cost:.6}");
-        log.total_cost_usd = format!("{total_cost:.6}");
+        let cost = CostCalculator::calculate(&usage, pricing, multiplier);
+
+        log.input_cost_usd = format!("{:.6}", cost.input_cost);
+        log.output_cost_usd = format!("{:.6}", cost.output_cost);
+        log.cache_read_cost_usd = format!("{:.6}", cost.cache_read_cost);
+        log.cache_creation_cost_usd = format!("{:.6}", cost.cache_creation_cost);
+        log.total_cost_usd = format!("{:.6}", cost.total_cost);
 
         conn.execute(
-            "UPDATE proxy_request_logs
-             SET input_cost_usd = ?1,
-                 output_cost_usd = ?2,
-                 cache_read_cost_usd = ?3,
-                 cache_creation_cost_usd = ?4,
-                 total_cost_usd = ?5
-             WHERE request_id = ?6",
+            &format!(
+                "UPDATE proxy_request_logs
+                 SET input_cost_usd = ?1,
+                     output_cost_usd = ?2,
+                     cache_read_cost_usd = ?3,
+                     cache_creation_cost_usd = ?4,
+                     total_cost_usd = ?5,
+                     {BILLING_COST_UPDATE_SQL}
+                 WHERE request_id = ?6"
+            ),
             params![
                 log.input_cost_usd,
                 log.output_cost_usd,
@@ -785,37 +950,306 @@ impl Database {
 
     fn get_model_pricing_cached(
         conn: &Connection,
-        cache: &mut HashMap<String, PricingInfo>,
+        cache: &mut PricingCache,
         model: &str,
-    ) -> Result<Option<PricingInfo>, AppError> {
-        if let Some(info) = cache.get(model) {
-            return Ok(Some(info.clone()));
+    ) -> Result<Option<(ModelPricing, Vec<PricingRule>)>, AppError> {
+        if let Some(entry) = cache.get(model) {
+            return Ok(entry.clone());
         }
 
-        let row = find_model_pricing_row(conn, model)?;
-        let Some((input, output, cache_read, cache_creation)) = row else {
-            return Ok(None);
+        let entry = match find_model_pricing_id(conn, model)? {
+            Some(pricing_id) => match query_pricing_row(conn, &pricing_id)? {
+                Some((input, output, cache_read, cache_creation)) => {
+                    let base =
+                        ModelPricing::from_strings(&input, &output, &cache_read, &cache_creation)
+                            .map_err(|e| AppError::Database(format!("解析定价数据失败: {e}")))?;
+                    Some((base, load_pricing_rules(conn, &pricing_id)?))
+                }
+                None => None,
+            },
+            None => None,
         };
 
-        let pricing = PricingInfo {
-            input: rust_decimal::Decimal::from_str(&input)
-                .map_err(|e| AppError::Database(format!("解析输入价格失败: {e}")))?,
-            output: rust_decimal::Decimal::from_str(&output)
-                .map_err(|e| AppError::Database(format!("解析输出价格失败: {e}")))?,
-            cache_read: rust_decimal::Decimal::from_str(&cache_read)
-                .map_err(|e| AppError::Database(format!("解析缓存读取价格失败: {e}")))?,
-            cache_creation: rust_decimal::Decimal::from_str(&cache_creation)
-                .map_err(|e| AppError::Database(format!("解析缓存写入价格失败: {e}")))?,
+        cache.insert(model.to_string(), entry.clone());
+        Ok(entry)
+    }
+}
+
+/// 成本重算结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecalculateCostsResult {
+    /// 扫描的请求数
+    pub scanned: u64,
+    /// 成本发生变化并已更新的请求数
+    pub updated: u64,
+    /// 未找到定价的请求数（成本保持不变）
+    pub unpriced: u64,
+}
+
+/// 模型定价规则（阶梯 / 生效日期 / 时段）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPricingRuleInfo {
+    /// 为空时新建
+    #[serde(default)]
+    pub id: Option<i64>,
+    /// 对应 model_pricing 中的 model_id
+    pub model_id: String,
+    /// 提示词 Token 达到该值时整个请求按此规则计价
+    #[serde(default)]
+    pub min_prompt_tokens: u64,
+    /// 生效起点（Unix 秒，含）
+    #[serde(default)]
+    pub effective_from: Option<i64>,
+    /// 生效终点（Unix 秒，不含）
+    #[serde(default)]
+    pub effective_to: Option<i64>,
+    /// UTC 时段起点，格式 HH:MM
+    #[serde(default)]
+    pub time_start: Option<String>,
+    /// UTC 时段终点，格式 HH:MM（早于起点表示跨零点）
+    #[serde(default)]
+    pub time_end: Option<String>,
+    pub input_cost_per_million: String,
+    pub output_cost_per_million: String,
+    pub cache_read_cost_per_million: String,
+    pub cache_creation_cost_per_million: String,
+}
+
+impl Database {
+    /// 获取定价规则（可按模型过滤）
+    pub fn get_pricing_rules(
+        &self,
+        model_id: Option<&str>,
+    ) -> Result<Vec<ModelPricingRuleInfo>, AppError> {
+        let conn = lock_conn!(self.conn);
+
+        let mut stmt = conn.prepare(
+            "SELECT id, model_id, min_prompt_tokens, effective_from, effective_to,
+                    time_start_minute, time_end_minute,
+                    input_cost_per_million, output_cost_per_million,
+                    cache_read_cost_per_million, cache_creation_cost_per_million
+             FROM model_pricing_rules
+             WHERE ?1 IS NULL OR model_id = ?1
+             ORDER BY model_id, min_prompt_tokens, effective_from",
+        )?;
+
+        let rows = stmt.query_map([model_id], |row| {
+            Ok(ModelPricingRuleInfo {
+                id: Some(row.get(0)?),
+                model_id: row.get(1)?,
+                min_prompt_tokens: row.get::<_, i64>(2)?.max(0) as u64,
+                effective_from: row.get(3)?,
+                effective_to: row.get(4)?,
+                time_start: row.get::<_, Option<i64>>(5)?.map(format_minute),
+                time_end: row.get::<_, Option<i64>>(6)?.map(format_minute),
+                input_cost_per_million: row.get(7)?,
+                output_cost_per_million: row.get(8)?,
+                cache_read_cost_per_million: row.get(9)?,
+                cache_creation_cost_per_million: row.get(10)?,
+            })
+        })?;
+
+        let mut rules = Vec::new();
+        for row in rows {
+            rules.push(row?);
+        }
+        Ok(rules)
+    }
+
+    /// 新增或更新定价规则，返回规则 ID
+    pub fn save_pricing_rule(&self, rule: &ModelPricingRuleInfo) -> Result<i64, AppError> {
+        ModelPricing::from_strings(
+            &rule.input_cost_per_million,
+            &rule.output_cost_per_million,
+            &rule.cache_read_cost_per_million,
+            &rule.cache_creation_cost_per_million,
+        )
+        .map_err(|e| AppError::InvalidInput(format!("价格格式无效: {e}")))?;
+
+        let window = match (&rule.time_start, &rule.time_end) {
+            (Some(start), Some(end)) => Some((parse_minute(start)?, parse_minute(end)?)),
+            (None, None) => None,
+            _ => {
+                return Err(AppError::InvalidInput(
+                    "时段的起点和终点必须同时设置".to_string(),
+                ))
+            }
         };
+        if let (Some(from), Some(to)) = (rule.effective_from, rule.effective_to) {
+            if from >= to {
+                return Err(AppError::InvalidInput(
+                    "生效起点必须早于生效终点".to_string(),
+                ));
+            }
+        }
 
-        cache.insert(model.to_string(), pricing.clone());
-        Ok(Some(pricing))
+        let conn = lock_conn!(self.conn);
+        if query_pricing_row(&conn, &rule.model_id)?.is_none() {
+            return Err(AppError::InvalidInput(format!(
+                "模型 {} 没有基础定价",
+                rule.model_id
+            )));
+        }
+
+        conn.execute(
+            "INSERT OR REPLACE INTO model_pricing_rules (
+                id, model_id, min_prompt_tokens, effective_from, effective_to,
+                time_start_minute, time_end_minute,
+                input_cost_per_million, output_cost_per_million,
+                cache_read_cost_per_million, cache_creation_cost_per_million
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                rule.id,
+                rule.model_id,
+                rule.min_prompt_tokens as i64,
+                rule.effective_from,
+                rule.effective_to,
+                window.map(|(s, _)| s),
+                window.map(|(_, e)| e),
+                rule.input_cost_per_million,
+                rule.output_cost_per_million,
+                rule.cache_read_cost_per_million,
+                rule.cache_creation_cost_per_million,
+            ],
+        )
+        .map_err(|e| AppError::Database(format!("保存定价规则失败: {e}")))?;
+
+        Ok(rule.id.unwrap_or_else(|| conn.last_insert_rowid()))
+    }
+
+    /// 删除定价规则
+    pub fn delete_pricing_rule(&self, id: i64) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute("DELETE FROM model_pricing_rules WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(format!("删除定价规则失败: {e}")))?;
+        Ok(())
+    }
+
+    /// 按当前定价重算指定时间范围内请求的成本
+    ///
+    /// 每条请求按其 `created_at` 时刻生效的价格和记录时的成本倍数计价，
+    /// 用于修改定价或补录历史价格之后
+    pub fn recalculate_costs(
+        &self,
+        start_date: Option<i64>,
+        end_date: Option<i64>,
+    ) -> Result<RecalculateCostsResult, AppError> {
+        let mut conn = lock_conn!(self.conn);
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        let logs = {
+            let mut stmt = tx.prepare(
+                "SELECT request_id, model, input_tokens, output_tokens,
+                        cache_read_tokens, cache_creation_tokens,
+                        cost_multiplier, total_cost_usd, created_at
+                 FROM proxy_request_logs
+                 WHERE (?1 IS NULL OR created_at >= ?1)
+                   AND (?2 IS NULL OR created_at <= ?2)",
+            )?;
+            let rows = stmt.query_map(params![start_date, end_date], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    TokenUsage {
+                        input_tokens: row.get(2)?,
+                        output_tokens: row.get(3)?,
+                        cache_read_tokens: row.get(4)?,
+                        cache_creation_tokens: row.get(5)?,
+                        model: None,
+                    },
+                    row.get::<_, String>(6)?,
+                    row.get::<_, String>(7)?,
+                    row.get::<_, i64>(8)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut result = RecalculateCostsResult {
+            scanned: logs.len() as u64,
+            updated: 0,
+            unpriced: 0,
+        };
+        let mut pricing_cache = PricingCache::new();
+
+        for (request_id, model, usage, multiplier, old_total, created_at) in logs {
+            let Some((base, rules)) =
+                Self::get_model_pricing_cached(&tx, &mut pricing_cache, &model)?
+            else {
+                result.unpriced += 1;
+                continue;
+            };
+            let pricing = CostCalculator::select_pricing(&base, &rules, &usage, created_at);
+            let multiplier =
+                rust_decimal::Decimal::from_str(&multiplier).unwrap_or(rust_decimal::Decimal::ONE);
+            let cost = CostCalculator::calculate(&usage, pricing, multiplier);
+
+            let unchanged = rust_decimal::Decimal::from_str(&old_total)
+                .map(|old| old == cost.total_cost.round_dp(6))
+                .unwrap_or(false);
+            if unchanged {
+                continue;
+            }
+
+            tx.execute(
+                &format!(
+                    "UPDATE proxy_request_logs
+                     SET input_cost_usd = ?1,
+                         output_cost_usd = ?2,
+                         cache_read_cost_usd = ?3,
+                         cache_creation_cost_usd = ?4,
+                         total_cost_usd = ?5,
+                         {BILLING_COST_UPDATE_SQL}
+                     WHERE request_id = ?6"
+                ),
+                params![
+                    format!("{:.6}", cost.input_cost),
+                    format!("{:.6}", cost.output_cost),
+                    format!("{:.6}", cost.cache_read_cost),
+                    format!("{:.6}", cost.cache_creation_cost),
+                    format!("{:.6}", cost.total_cost),
+                    request_id
+                ],
+            )
+            .map_err(|e| AppError::Database(format!("更新请求成本失败: {e}")))?;
+            result.updated += 1;
+        }
+
+        tx.commit().map_err(|e| AppError::Database(e.to_string()))?;
+
+        log::info!(
+            "成本重算完成: 扫描 {} 条，更新 {} 条，无定价 {} 条",
+            result.scanned,
+            result.updated,
+            result.unpriced
+        );
+        Ok(result)
+    }
+}
+
+/// 解析 HH:MM 为一天内的分钟数
+fn parse_minute(value: &str) -> Result<u32, AppError> {
+    let invalid = || AppError::InvalidInput(format!("时间格式无效（应为 HH:MM）: {value}"));
+    let (h, m) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let h: u32 = h.parse().map_err(|_| invalid())?;
+    let m: u32 = m.parse().map_err(|_| invalid())?;
+    if h > 24 || m > 59 || (h == 24 && m != 0) {
+        return Err(invalid());
     }
+    Ok(h * 60 + m)
+}
+
+fn format_minute(minute: i64) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
 }
 
 /// 标准化模型名称：去除供应商前缀并将点号替换为短横线
 /// 例如：anthropic/claude-haiku-4.5 → claude-haiku-4-5
-fn normalize_model_id(model_id: &str) -> String {
+pub(crate) fn normalize_model_id(model_id: &str) -> String {
     // 1. 去除供应商前缀（如 anthropic/、openai/）
     let stripped = if let Some(pos) = model_id.find('/') {
         &model_id[pos + 1..]
@@ -826,40 +1260,73 @@ fn normalize_model_id(model_id: &str) -> String {
     stripped.replace('.', "-")
 }
 
-pub(crate) fn find_model_pricing_row(
+fn query_pricing_row(
     conn: &Connection,
-    model_id: &str,
+    pricing_id: &str,
 ) -> Result<Option<(String, String, String, String)>, AppError> {
-    // 0. 标准化模型名称（去除前缀 + 点号转短横线）
-    // 例如：anthropic/claude-haiku-4.5 → claude-haiku-4-5
-    let normalized = normalize_model_id(model_id);
+    conn.query_row(
+        "SELECT input_cost_per_million, output_cost_per_million,
+                cache_read_cost_per_million, cache_creation_cost_per_million
+         FROM model_pricing
+         WHERE model_id = ?1",
+        [pricing_id],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        },
+    )
+    .optional()
+    .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))
+}
 
-    // 1. 精确匹配（先尝试原始名称，再尝试标准化后的名称）
-    for id in [model_id, normalized.as_str()] {
-        let exact = conn
+/// 将请求中的模型名解析为 model_pricing 中的 model_id
+pub(crate) fn find_model_pricing_id(
+    conn: &Connection,
+    model_id: &str,
+) -> Result<Option<String>, AppError> {
+    let exists = |id: &str| -> Result<bool, AppError> {
+        conn.query_row(
+            "SELECT 1 FROM model_pricing WHERE model_id = ?1",
+            [id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map(|row| row.is_some())
+        .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))
+    };
+    // 别名优先于同名定价行，且只在目标定价存在时生效
+    let resolve = |id: &str| -> Result<Option<String>, AppError> {
+        let alias_target: Option<String> = conn
             .query_row(
-                "SELECT input_cost_per_million, output_cost_per_million,
-                        cache_read_cost_per_million, cache_creation_cost_per_million
-                 FROM model_pricing
-                 WHERE model_id = ?1",
+                "SELECT model_id FROM model_pricing_aliases WHERE alias = ?1",
                 [id],
-                |row| {
-                    Ok((
-                        row.get::<_, String>(0)?,
-                        row.get::<_, String>(1)?,
-                        row.get::<_, String>(2)?,
-                        row.get::<_, String>(3)?,
-                    ))
-                },
+                |row| row.get(0),
             )
             .optional()
-            .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+            .map_err(|e| AppError::Database(format!("查询模型别名失败: {e}")))?;
+        if let Some(target) = alias_target {
+            if exists(&target)? {
+                return Ok(Some(target));
+            }
+        }
+        Ok(exists(id)?.then(|| id.to_string()))
+    };
+
+    // 0. 标准化模型名称（去除前缀 + 点号转短横线）
+    // 例如：anthropic/claude-haiku-4.5 → claude-haiku-4-5
+    let normalized = normalize_model_id(model_id);
 
-        if exact.is_some() {
-            if id != model_id {
-                log::info!("模型 {model_id} 标准化后精确匹配到: {id}");
+    // 1. 精确匹配（先尝试原始名称，再尝试标准化后的名称）
+    for id in [model_id, normalized.as_str()] {
+        if let Some(found) = resolve(id)? {
+            if found != model_id {
+                log::info!("模型 {model_id} 精确匹配到: {found}");
             }
-            return Ok(exact);
+            return Ok(Some(found));
         }
     }
 
@@ -869,35 +1336,181 @@ pub(crate) fn find_model_pricing_row(
     while let Some(pos) = current.rfind('-') {
         current = current[..pos].to_string();
 
-        let result = conn
-            .query_row(
-                "SELECT input_cost_per_million, output_cost_per_million,
-                        cache_read_cost_per_million, cache_creation_cost_per_million
-                 FROM model_pricing
-                 WHERE model_id = ?1",
-                [&current],
-                |row| {
-                    Ok((
-                        row.get::<_, String>(0)?,
-                        row.get::<_, String>(1)?,
-                        row.get::<_, String>(2)?,
-                        row.get::<_, String>(3)?,
-                    ))
-                },
-            )
-            .optional()
-            .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
-
-        if result.is_some() {
-            log::info!("模型 {model_id} 通过删除后缀匹配到: {current}");
-            return Ok(result);
+        if let Some(found) = resolve(&current)? {
+            log::info!("模型 {model_id} 通过删除后缀匹配到: {found}");
+            return Ok(Some(found));
         }
     }
 
-    log::warn!("模型 {model_id} 未找到定价信息，成本将记录为 0");
     Ok(None)
 }
 
+/// 读取模型的定价规则（阶梯 / 生效日期 / 时段）
+pub(crate) fn load_pricing_rules(
+    conn: &Connection,
+    pricing_id: &str,
+) -> Result<Vec<PricingRule>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT min_prompt_tokens, effective_from, effective_to,
+                time_start_minute, time_end_minute,
+                input_cost_per_million, output_cost_per_million,
+                cache_read_cost_per_million, cache_creation_cost_per_million
+         FROM model_pricing_rules
+         WHERE model_id = ?1",
+    )?;
+
+    let rows = stmt.query_map([pricing_id], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Option<i64>>(1)?,
+            row.get::<_, Option<i64>>(2)?,
+            row.get::<_, Option<i64>>(3)?,
+            row.get::<_, Option<i64>>(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(6)?,
+            row.get::<_, String>(7)?,
+            row.get::<_, String>(8)?,
+        ))
+    })?;
+
+    let mut rules = Vec::new();
+    for row in rows {
+        let (min_tokens, from, to, start, end, input, output, cache_read, cache_creation) = row?;
+        let pricing = ModelPricing::from_strings(&input, &output, &cache_read, &cache_creation)
+            .map_err(|e| AppError::Database(format!("解析定价规则失败: {e}")))?;
+        rules.push(PricingRule {
+            min_prompt_tokens: min_tokens.max(0) as u64,
+            effective_from: from,
+            effective_to: to,
+            time_window: start.zip(end).map(|(s, e)| (s as u32, e as u32)),
+            pricing,
+        });
+    }
+
+    Ok(rules)
+}
+
+/// 查询指定用量和时间下生效的模型定价
+pub(crate) fn find_effective_pricing(
+    conn: &Connection,
+    model_id: &str,
+    usage: &TokenUsage,
+    at: i64,
+) -> Result<Option<ModelPricing>, AppError> {
+    let mut cache = PricingCache::new();
+    Ok(
+        Database::get_model_pricing_cached(conn, &mut cache, model_id)?
+            .map(|(base, rules)| CostCalculator::select_pricing(&base, &rules, usage, at).clone()),
+    )
+}
+
+/// 根据日志过滤器生成 WHERE 子句（日志表别名为 `l`，供应商表别名为 `p`）
+pub(crate) fn log_filter_clause(filters: &LogFilters) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+    let mut conditions = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    if let Some(ref app_type) = filters.app_type {
+        conditions.push("l.app_type = ?");
+        params.push(Box::new(app_type.clone()));
+    }
+    if let Some(ref provider_name) = filters.provider_name {
+        conditions.push("p.name LIKE ?");
+        params.push(Box::new(format!("%{provider_name}%")));
+    }
+    if let Some(ref model) = filters.model {
+        conditions.push("l.model LIKE ?");
+        params.push(Box::new(format!("%{model}%")));
+    }
+    if let Some(status) = filters.status_code {
+        conditions.push("l.status_code = ?");
+        params.push(Box::new(status as i64));
+    }
+    if let Some(start) = filters.start_date {
+        conditions.push("l.created_at >= ?");
+        params.push(Box::new(start));
+    }
+    if let Some(end) = filters.end_date {
+        conditions.push("l.created_at <= ?");
+        params.push(Box::new(end));
+    }
+    if let Some(ref session_id) = filters.session_id {
+        conditions.push("l.session_id = ?");
+        params.push(Box::new(session_id.clone()));
+    }
+    if let Some(ref project_path) = filters.project_path {
+        conditions.push("l.project_path = ?");
+        params.push(Box::new(project_path.clone()));
+    }
+    if let Some(ref source) = filters.source {
+        conditions.push("l.source = ?");
+        params.push(Box::new(source.clone()));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    (where_clause, params)
+}
+
+/// 按 Provider 聚合主路径或影子流量（含已聚合的历史数据）
+fn query_provider_stats(
+    conn: &Connection,
+    is_shadow: bool,
+    currency: &str,
+) -> Result<Vec<ProviderStats>, AppError> {
+    let cost_expr = cost_sql(currency);
+    let rows_sql = usage_rows_sql(is_shadow);
+    let sql = format!(
+        "SELECT 
+            l.provider_id,
+            p.name as provider_name,
+            SUM(l.request_count) as request_count,
+            COALESCE(SUM(l.input_tokens + l.output_tokens), 0) as total_tokens,
+            COALESCE(SUM({cost_expr}), 0) as total_cost,
+            COALESCE(SUM(l.success_count), 0) as success_count,
+            COALESCE(SUM(l.latency_ms_sum) * 1.0 / SUM(l.request_count), 0) as avg_latency
+         FROM {rows_sql} l
+         LEFT JOIN providers p ON l.provider_id = p.id AND l.app_type = p.app_type
+         WHERE l.source = '{SOURCE_PROXY}'
+         GROUP BY l.provider_id, l.app_type
+         ORDER BY total_cost DESC"
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| {
+        let request_count: i64 = row.get(2)?;
+        let success_count: i64 = row.get(5)?;
+        let success_rate = if request_count > 0 {
+            (success_count as f32 / request_count as f32) * 100.0
+        } else {
+            0.0
+        };
+
+        Ok(ProviderStats {
+            provider_id: row.get(0)?,
+            provider_name: row
+                .get::<_, Option<String>>(1)?
+                .unwrap_or_else(|| "Unknown".to_string()),
+            request_count: request_count as u64,
+            total_tokens: row.get::<_, i64>(3)? as u64,
+            total_cost: format!("{:.6}", row.get::<_, f64>(4)?),
+            currency: currency.to_string(),
+            success_rate,
+            avg_latency_ms: row.get::<_, f64>(6)? as u64,
+            is_shadow,
+        })
+    })?;
+
+    let mut stats = Vec::new();
+    for row in rows {
+        stats.push(row?);
+    }
+
+    Ok(stats)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -927,7 +1540,7 @@ mod tests {
             )?;
         }
 
-        let summary = db.get_usage_summary(None, None)?;
+        let summary = db.get_usage_summary(None, None, None)?;
         assert_eq!(summary.total_requests, 2);
         assert_eq!(summary.success_rate, 100.0);
 
@@ -970,63 +1583,338 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_provider_stats_include_shadow() -> Result<(), AppError> {
+        let db = Database::memory()?;
+
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO proxy_request_logs (
+                    request_id, provider_id, app_type, model,
+                    input_tokens, output_tokens, total_cost_usd,
+                    latency_ms, status_code, created_at
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params!["req1", "p1", "claude", "claude-3", 100, 50, "0.01", 100, 200, 1000],
+            )?;
+            for (id, status) in [("shadow1", 200), ("shadow2", 502)] {
+                conn.execute(
+                    "INSERT INTO proxy_shadow_logs (
+                        request_id, provider_id, app_type, model,
+                        input_tokens, output_tokens, total_cost_usd,
+                        latency_ms, status_code, created_at
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    params![id, "p2", "claude", "claude-3", 100, 50, "0.02", 300, status, 1000],
+                )?;
+            }
+        }
+
+        let stats = db.get_provider_stats(None)?;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].provider_id, "p1");
+        assert!(!stats[0].is_shadow);
+        assert_eq!(stats[1].provider_id, "p2");
+        assert!(stats[1].is_shadow);
+        assert_eq!(stats[1].request_count, 2);
+        assert_eq!(stats[1].success_rate, 50.0);
+
+        // 影子流量不计入主汇总
+        let summary = db.get_usage_summary(None, None, None)?;
+        assert_eq!(summary.total_requests, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hourly_trends_rolling_window() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let now = Utc::now().timestamp();
+
+        {
+            let conn = lock_conn!(db.conn);
+            for (id, created_at) in [("req1", now - 2 * 3600), ("req2", now - 30 * 3600)] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model,
+                        input_tokens, output_tokens, total_cost_usd,
+                        latency_ms, status_code, created_at
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    params![id, "p1", "claude", "claude-3", 100, 50, "0.01", 100, 200, created_at],
+                )?;
+            }
+        }
+
+        let stats = db.get_hourly_trends(24, None)?;
+        assert_eq!(stats.len(), 24);
+        assert_eq!(
+            stats.last().unwrap().date,
+            Utc::now().format("%Y-%m-%dT%H:00:00Z").to_string()
+        );
+        assert_eq!(stats.iter().map(|s| s.request_count).sum::<u64>(), 1);
+        assert_eq!(stats[21].request_count, 1);
+
+        // days <= 1 时使用逐小时趋势
+        assert_eq!(db.get_daily_trends(1, None)?.len(), 24);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_and_session_stats() -> Result<(), AppError> {
+        let db = Database::memory()?;
+
+        {
+            let conn = lock_conn!(db.conn);
+            for (id, model, cost, session, project, created_at) in [
+                (
+                    "req1",
+                    "claude-3",
+                    "0.10",
+                    Some("s1"),
+                    Some("/work/acme"),
+                    1000,
+                ),
+                (
+                    "req2",
+                    "claude-4",
+                    "0.20",
+                    Some("s1"),
+                    Some("/work/acme"),
+                    2000,
+                ),
+                (
+                    "req3",
+                    "claude-3",
+                    "0.05",
+                    Some("s2"),
+                    Some("/work/acme"),
+                    3000,
+                ),
+                (
+                    "req4",
+                    "claude-3",
+                    "0.50",
+                    Some("s3"),
+                    Some("/work/beta"),
+                    4000,
+                ),
+                ("req5", "claude-3", "0.01", None, None, 5000),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model,
+                        input_tokens, output_tokens, total_cost_usd,
+                        latency_ms, status_code, session_id, project_path, created_at
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    params![
+                        id, "p1", "claude", model, 100, 50, cost, 100, 200, session, project,
+                        created_at
+                    ],
+                )?;
+            }
+        }
+
+        let projects = db.get_project_stats(None, None, None)?;
+        assert_eq!(projects.len(), 3);
+        assert_eq!(projects[0].project_path.as_deref(), Some("/work/beta"));
+        assert_eq!(projects[1].project_path.as_deref(), Some("/work/acme"));
+        assert_eq!(projects[1].session_count, 2);
+        assert_eq!(projects[1].request_count, 3);
+        assert_eq!(projects[1].total_input_tokens, 300);
+        assert_eq!(projects[1].total_cost, "0.350000");
+        assert_eq!(projects[1].currency, "USD");
+        assert_eq!(
+            (projects[1].first_request_at, projects[1].last_request_at),
+            (1000, 3000)
+        );
+        assert_eq!(projects[2].project_path, None);
+
+        let sessions = db.get_session_stats(Some("/work/acme"), None, None, None)?;
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].session_id, "s2");
+        assert_eq!(sessions[1].session_id, "s1");
+        assert_eq!(sessions[1].request_count, 2);
+        assert_eq!(sessions[1].total_cost, "0.300000");
+        let mut models = sessions[1].models.clone();
+        models.sort();
+        assert_eq!(models, vec!["claude-3", "claude-4"]);
+
+        // 未识别会话的请求不计入会话统计
+        assert_eq!(db.get_session_stats(None, None, None, None)?.len(), 3);
+        assert_eq!(db.get_session_stats(None, Some(2500), None, None)?.len(), 2);
+
+        // 按会话下钻到请求明细
+        let logs = db.get_request_logs(
+            &LogFilters {
+                session_id: Some("s1".to_string()),
+                ..Default::default()
+            },
+            0,
+            20,
+        )?;
+        assert_eq!(logs.total, 2);
+        assert_eq!(logs.data[0].request_id, "req2");
+        assert_eq!(logs.data[0].project_path.as_deref(), Some("/work/acme"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recalculate_costs_applies_tier_rules() -> Result<(), AppError> {
+        let db = Database::memory()?;
+
+        {
+            let conn = lock_conn!(db.conn);
+            conn.execute(
+                "INSERT INTO model_pricing (
+                    model_id, display_name, input_cost_per_million, output_cost_per_million,
+                    cache_read_cost_per_million, cache_creation_cost_per_million
+                ) VALUES ('tier-model', 'Tier Model', '3', '15', '0', '0')",
+                [],
+            )?;
+            for (id, model, input, created_at) in [
+                ("small", "tier-model", 1_000, 1000),
+                ("large", "tier-model", 250_000, 2000),
+                ("outside", "tier-model", 250_000, 5000),
+                ("unknown", "unknown-model-123", 1_000, 1500),
+            ] {
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model,
+                        input_tokens, output_tokens, total_cost_usd,
+                        latency_ms, status_code, created_at
+                    ) VALUES (?1, 'p1', 'claude', ?2, ?3, 0, '0', 100, 200, ?4)",
+                    params![id, model, input, created_at],
+                )?;
+            }
+        }
+
+        let rule = ModelPricingRuleInfo {
+            id: None,
+            model_id: "tier-model".to_string(),
+            min_prompt_tokens: 200_000,
+            effective_from: None,
+            effective_to: None,
+            time_start: None,
+            time_end: None,
+            input_cost_per_million: "6".to_string(),
+            output_cost_per_million: "22.5".to_string(),
+            cache_read_cost_per_million: "0".to_string(),
+            cache_creation_cost_per_million: "0".to_string(),
+        };
+        let rule_id = db.save_pricing_rule(&rule)?;
+        assert_eq!(db.get_pricing_rules(Some("tier-model"))?.len(), 1);
+
+        let result = db.recalculate_costs(Some(0), Some(3000))?;
+        assert_eq!(result.scanned, 3);
+        assert_eq!(result.updated, 2);
+        assert_eq!(result.unpriced, 1);
+
+        let cost_of = |id: &str| -> Result<f64, AppError> {
+            let conn = lock_conn!(db.conn);
+            let cost: String = conn.query_row(
+                "SELECT total_cost_usd FROM proxy_request_logs WHERE request_id = ?1",
+                [id],
+                |row| row.get(0),
+            )?;
+            Ok(cost.parse().unwrap())
+        };
+        assert!((cost_of("small")? - 0.003).abs() < 1e-9);
+        assert!((cost_of("large")? - 1.5).abs() < 1e-9);
+        assert_eq!(cost_of("outside")?, 0.0);
+
+        // 规则删除后再次重算恢复基础价格
+        db.delete_pricing_rule(rule_id)?;
+        let result = db.recalculate_costs(None, None)?;
+        assert_eq!(result.updated, 2);
+        assert!((cost_of("large")? - 0.75).abs() < 1e-9);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_pricing_rule_validation() -> Result<(), AppError> {
+        let db = Database::memory()?;
+        let mut rule = ModelPricingRuleInfo {
+            id: None,
+            model_id: "missing-model".to_string(),
+            min_prompt_tokens: 0,
+            effective_from: None,
+            effective_to: None,
+            time_start: Some("22:00".to_string()),
+            time_end: Some("06:00".to_string()),
+            input_cost_per_million: "1".to_string(),
+            output_cost_per_million: "2".to_string(),
+            cache_read_cost_per_million: "0".to_string(),
+            cache_creation_cost_per_million: "0".to_string(),
+        };
+        assert!(db.save_pricing_rule(&rule).is_err());
+
+        rule.time_end = Some("25:00".to_string());
+        assert!(db.save_pricing_rule(&rule).is_err());
+
+        assert_eq!(parse_minute("06:30")?, 390);
+        assert_eq!(format_minute(1320), "22:00");
+        Ok(())
+    }
+
     #[test]
     fn test_model_pricing_matching() -> Result<(), AppError> {
         let db = Database::memory()?;
         let conn = lock_conn!(db.conn);
 
         // 测试精确匹配
-        let result = find_model_pricing_row(&conn, "claude-sonnet-4-5")?;
+        let result = find_model_pricing_id(&conn, "claude-sonnet-4-5")?;
         assert!(result.is_some(), "应该能精确匹配 claude-sonnet-4-5");
 
         // 测试带供应商前缀的模型名称（anthropic/claude-haiku-4.5 → claude-haiku-4-5）
-        let result = find_model_pricing_row(&conn, "anthropic/claude-haiku-4.5")?;
+        let result = find_model_pricing_id(&conn, "anthropic/claude-haiku-4.5")?;
         assert!(
             result.is_some(),
             "应该能匹配带前缀的模型 anthropic/claude-haiku-4.5"
         );
 
         // 测试带供应商前缀 + 点号的模型名称
-        let result = find_model_pricing_row(&conn, "anthropic/claude-sonnet-4.5")?;
+        let result = find_model_pricing_id(&conn, "anthropic/claude-sonnet-4.5")?;
         assert!(
             result.is_some(),
             "应该能匹配带前缀的模型 anthropic/claude-sonnet-4.5"
         );
 
         // 测试逐步删除后缀匹配 - 日期后缀
-        let result = find_model_pricing_row(&conn, "claude-sonnet-4-5-20241022")?;
+        let result = find_model_pricing_id(&conn, "claude-sonnet-4-5-20241022")?;
         assert!(
             result.is_some(),
             "应该能通过删除后缀匹配 claude-sonnet-4-5-20241022"
         );
 
         // 测试逐步删除后缀匹配 - 多个后缀
-        let result = find_model_pricing_row(&conn, "claude-haiku-4-5-20240229-preview")?;
+        let result = find_model_pricing_id(&conn, "claude-haiku-4-5-20240229-preview")?;
         assert!(
             result.is_some(),
             "应该能通过删除后缀匹配 claude-haiku-4-5-20240229-preview"
         );
 
         // 测试 GPT 模型
-        let result = find_model_pricing_row(&conn, "gpt-5-2024-11-20")?;
+        let result = find_model_pricing_id(&conn, "gpt-5-2024-11-20")?;
         assert!(result.is_some(), "应该能通过删除后缀匹配 gpt-5-2024-11-20");
 
         // 测试 Gemini 模型
-        let result = find_model_pricing_row(&conn, "gemini-2.5-flash-exp")?;
+        let result = find_model_pricing_id(&conn, "gemini-2.5-flash-exp")?;
         assert!(
             result.is_some(),
             "应该能通过删除后缀匹配 gemini-2.5-flash-exp"
         );
 
         // 测试 claude-sonnet-4-5 命名格式
-        let result = find_model_pricing_row(&conn, "claude-sonnet-4-5-20250929")?;
+        let result = find_model_pricing_id(&conn, "claude-sonnet-4-5-20250929")?;
         assert!(
             result.is_some(),
             "应该能通过删除后缀匹配 claude-sonnet-4-5-20250929"
         );
 
         // 测试不存在的模型
-        let result = find_model_pricing_row(&conn, "unknown-model-123")?;
+        let result = find_model_pricing_id(&conn, "unknown-model-123")?;
         assert!(result.is_none(), "不应该匹配不存在的模型");
 
         Ok(())
@@ -18,6 +18,7 @@ import { useProvidersQuery } from "@/lib/query";
 import {
   providersApi,
   settingsApi,
+  usageApi,
   type AppId,
   type ProviderSwitchEvent,
 } from "@/lib/api";
@@ -110,6 +111,53 @@ function App() {
     };
   }, [activeApp, refetch]);
 
+  // 监听用量异常告警：应用内提示 + 系统通知
+  useEffect(() => {
+    let unsubscribe: (() => void) | undefined;
+
+    const notifyDesktop = async (title: string, body: string) => {
+      if (typeof Notification === "undefined") return;
+      try {
+        if (Notification.permission === "default") {
+          await Notification.requestPermission();
+        }
+        if (Notification.permission === "granted") {
+          new Notification(title, { body });
+        }
+      } catch (error) {
+        console.error("[App] Failed to show desktop notification", error);
+      }
+    };
+
+    const setupListener = async () => {
+      try {
+        unsubscribe = await usageApi.onAnomaly((alert) => {
+          const title = alert.paused
+            ? t("usage.anomalyPausedTitle")
+            : t("usage.anomalyTitle");
+          toast.warning(title, {
+            description: alert.message,
+            duration: 10000,
+            action: {
+              label: t("usage.anomalyAcknowledge"),
+              onClick: () => {
+                void usageApi.acknowledgeUsageAlert(alert.id);
+              },
+            },
+          });
+          void notifyDesktop(title, alert.message);
+        });
+      } catch (error) {
+        console.error("[App] Failed to subscribe usage anomaly event", error);
+      }
+    };
+
+    setupListener();
+    return () => {
+      unsubscribe?.();
+    };
+  }, [t]);
+
   // 应用启动时检测所有应用的环境变量冲突
   useEffect(() => {
     const checkEnvOnStartup = async () => {
@@ -55,6 +55,7 @@ export const CheckpointSettings: React.FC<CheckpointSettingsProps> = ({
     { value: "per_prompt", label: "After Each Prompt" },
     { value: "per_tool_use", label: "After Tool Use" },
     { value: "smart", label: "Smart (Recommended)" },
+    { value: "watch", label: "Watch Project Files" },
   ];
 
   useEffect(() => {
@@ -219,6 +220,7 @@ export const CheckpointSettings: React.FC<CheckpointSettingsProps> = ({
             {checkpointStrategy === "per_prompt" && "A checkpoint will be created after each user prompt"}
             {checkpointStrategy === "per_tool_use" && "A checkpoint will be created after each tool use"}
             {checkpointStrategy === "smart" && "Checkpoints will be created after destructive operations"}
+            {checkpointStrategy === "watch" && "Checkpoints will be created shortly after project files change"}
           </p>
         </div>
 
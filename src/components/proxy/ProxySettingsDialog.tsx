@@ -84,6 +84,15 @@ const createProxyConfigSchema = (t: TFunction) => {
       ),
     request_timeout: requestTimeoutSchema,
     enable_logging: z.boolean(),
+    gemini_token_endpoint: z
+      .string()
+      .trim()
+      .refine((value) => !value || /^https?:\/\/\S+$/.test(value), {
+        message: t("proxy.settings.validation.tokenEndpointInvalid", {
+          defaultValue: "请输入有效的 http(s) 地址",
+        }),
+      })
+      .nullish(),
   });
 };
 
@@ -112,6 +121,7 @@ export function ProxySettingsDialog({
       max_retries: 3,
       request_timeout: 300,
       enable_logging: true,
+      gemini_token_endpoint: "",
     },
   });
 
@@ -120,15 +130,18 @@ export function ProxySettingsDialog({
     if (config) {
       form.reset({
         ...config,
+        gemini_token_endpoint: config.gemini_token_endpoint ?? "",
       });
     }
   }, [config, form]);
 
   const onSubmit = async (data: ProxyConfigForm) => {
     try {
-      // 添加 enabled 字段（从当前配置中获取，保持不变）
+      // 添加 enabled 字段（从当前配置中获取，保持不变），并保留表单未覆盖的字段
       const configToSave: ProxyConfig = {
+        ...config,
         ...data,
+        gemini_token_endpoint: data.gemini_token_endpoint || null,
         enabled: config?.enabled ?? true,
       };
       await updateConfig(configToSave);
@@ -364,6 +377,38 @@ export function ProxySettingsDialog({
                     </FormItem>
                   )}
                 />
+
+                <FormField
+                  control={form.control}
+                  name="gemini_token_endpoint"
+                  render={({ field }) => (
+                    <FormItem>
+                      <FormLabel>
+                        {t("proxy.settings.fields.geminiTokenEndpoint.label", {
+                          defaultValue: "Gemini OAuth Token 端点",
+                        })}
+                      </FormLabel>
+                      <FormControl>
+                        <Input
+                          {...field}
+                          value={field.value ?? ""}
+                          placeholder="https://oauth2.googleapis.com/token"
+                          disabled={isLoading}
+                        />
+                      </FormControl>
+                      <FormDescription>
+                        {t(
+                          "proxy.settings.fields.geminiTokenEndpoint.description",
+                          {
+                            defaultValue:
+                              "刷新 Gemini OAuth 凭证时使用的地址，留空则使用凭证中的 token_uri 或 Google 默认端点",
+                          },
+                        )}
+                      </FormDescription>
+                      <FormMessage />
+                    </FormItem>
+                  )}
+                />
               </div>
 
               <FormField
@@ -20,11 +20,17 @@ export type {
   FileEntry,
   ClaudeInstallation,
   Agent,
+  AgentPermissions,
+  AgentPermissionPolicy,
   AgentExport,
   GitHubAgentFile,
   AgentRun,
   AgentRunMetrics,
   AgentRunWithMetrics,
+  AgentQueueLimits,
+  AgentQueueStatus,
+  AgentSchedule,
+  MissedRunPolicy,
   UsageEntry,
   ModelUsage,
   DailyUsage,
@@ -33,12 +39,24 @@ export type {
   Checkpoint,
   CheckpointMetadata,
   FileSnapshot,
+  CheckpointFileLimits,
+  CheckpointBackend,
   TimelineNode,
   SessionTimeline,
   CheckpointStrategy,
   CheckpointResult,
   CheckpointDiff,
   FileDiff,
+  DiffHunk,
+  RestoreFileSelection,
+  RestoreAction,
+  RestoreChange,
+  RestoreReport,
+  ArchiveManifest,
+  ExportSummary,
+  ImportSummary,
+  RetentionPolicy,
+  RetentionReport,
   MCPServer,
   ServerStatus,
   MCPProjectConfig,
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import type {
   UsageSummary,
   DailyStats,
@@ -9,6 +10,7 @@ import type {
   ModelPricing,
   ProviderLimitStatus,
   PaginatedLogs,
+  UsageAlert,
 } from "@/types/usage";
 import type { UsageResult } from "@/types";
 import type { AppId } from "./types";
@@ -109,4 +111,16 @@ export const usageApi = {
   ): Promise<ProviderLimitStatus> => {
     return invoke("check_provider_limits", { providerId, appType });
   },
+
+  acknowledgeUsageAlert: async (id: number): Promise<void> => {
+    return invoke("acknowledge_usage_alert", { id });
+  },
+
+  onAnomaly: async (
+    handler: (alert: UsageAlert) => void,
+  ): Promise<UnlistenFn> => {
+    return await listen("usage-anomaly", (event) => {
+      handler(event.payload as UsageAlert);
+    });
+  },
 };
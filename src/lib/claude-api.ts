@@ -1,5 +1,6 @@
 import { apiCall } from './apiAdapter';
 import type { HooksConfiguration } from '@/types/hooks';
+import type { AppId } from './api/types';
 
 /** Process type for tracking in ProcessRegistry */
 export type ProcessType = 
@@ -119,11 +120,37 @@ export interface Agent {
   system_prompt: string;
   default_task?: string;
   model: string;
+  enable_file_read?: boolean;
+  enable_file_write?: boolean;
+  enable_network?: boolean;
+  /** Extra tools granted on top of the flags, in `--allowedTools` syntax */
+  allowed_tools?: string[];
+  /** Tools always denied, overriding the flags and allowed_tools */
+  disallowed_tools?: string[];
+  /** Directories outside the project the agent may access */
+  allowed_directories?: string[];
   hooks?: string; // JSON string of HooksConfiguration
   created_at: string;
   updated_at: string;
 }
 
+/** Permission settings accepted when creating or updating an agent */
+export interface AgentPermissions {
+  enableFileRead?: boolean;
+  enableFileWrite?: boolean;
+  enableNetwork?: boolean;
+  allowedTools?: string[];
+  disallowedTools?: string[];
+  allowedDirectories?: string[];
+}
+
+/** Effective Claude Code permissions an agent run was launched with */
+export interface AgentPermissionPolicy {
+  allowed_tools: string[];
+  disallowed_tools: string[];
+  additional_directories: string[];
+}
+
 export interface AgentExport {
   version: number;
   exported_at: string;
@@ -133,6 +160,12 @@ export interface AgentExport {
     system_prompt: string;
     default_task?: string;
     model: string;
+    enable_file_read?: boolean;
+    enable_file_write?: boolean;
+    enable_network?: boolean;
+    allowed_tools?: string[];
+    disallowed_tools?: string[];
+    allowed_directories?: string[];
     hooks?: string;
   };
 }
@@ -154,11 +187,48 @@ export interface AgentRun {
   model: string;
   project_path: string;
   session_id: string;
-  status: string; // 'pending', 'running', 'completed', 'failed', 'cancelled'
+  status: string; // 'queued', 'pending', 'running', 'completed', 'failed', 'cancelled'
   pid?: number;
   process_started_at?: string;
   created_at: string;
   completed_at?: string;
+  permission_policy?: AgentPermissionPolicy;
+  queued_at?: string;
+  schedule_id?: number;
+  scheduled_for?: string;
+}
+
+/** Global and per-agent caps applied by the agent run queue */
+export interface AgentQueueLimits {
+  max_concurrent_runs: number;
+  max_runs_per_agent: number;
+  /** Per-agent overrides of max_runs_per_agent, keyed by agent id */
+  agent_limits: Record<number, number>;
+}
+
+export interface AgentQueueStatus {
+  queued: AgentRun[];
+  active: number;
+  limits: AgentQueueLimits;
+}
+
+/** What to do with occurrences missed while the app was closed */
+export type MissedRunPolicy = 'skip' | 'run_once';
+
+/** Cron schedule that queues an agent run on a project */
+export interface AgentSchedule {
+  id?: number;
+  agent_id: number;
+  project_path: string;
+  task?: string;
+  model?: string;
+  cron: string;
+  enabled: boolean;
+  missed_run_policy: MissedRunPolicy;
+  next_run_at?: string;
+  last_run_at?: string;
+  created_at: string;
+  updated_at: string;
 }
 
 export interface AgentRunMetrics {
@@ -177,13 +247,17 @@ export interface AgentRunWithMetrics {
   model: string;
   project_path: string;
   session_id: string;
-  status: string; // 'pending', 'running', 'completed', 'failed', 'cancelled'
+  status: string; // 'queued', 'pending', 'running', 'completed', 'failed', 'cancelled'
   pid?: number;
   duration_ms?: number;
   total_tokens?: number;
   process_started_at?: string;
   created_at: string;
   completed_at?: string;
+  permission_policy?: AgentPermissionPolicy;
+  queued_at?: string;
+  schedule_id?: number;
+  scheduled_for?: string;
   metrics?: AgentRunMetrics;
   output?: string; // Real-time JSONL content
 }
@@ -252,6 +326,7 @@ export interface Checkpoint {
   description?: string;
   parentCheckpointId?: string;
   metadata: CheckpointMetadata;
+  pinned?: boolean;
 }
 
 /**
@@ -263,6 +338,8 @@ export interface CheckpointMetadata {
   userPrompt: string;
   fileChanges: number;
   snapshotSize: number;
+  skippedFiles?: string[];
+  automatic?: boolean;
 }
 
 /**
@@ -271,11 +348,14 @@ export interface CheckpointMetadata {
 export interface FileSnapshot {
   checkpointId: string;
   filePath: string;
-  content: string;
+  content: number[];
+  isBinary: boolean;
   hash: string;
   isDeleted: boolean;
   permissions?: number;
   size: number;
+  modified?: string;
+  reused: boolean;
 }
 
 /**
@@ -297,12 +377,29 @@ export interface SessionTimeline {
   autoCheckpointEnabled: boolean;
   checkpointStrategy: CheckpointStrategy;
   totalCheckpoints: number;
+  fileLimits: CheckpointFileLimits;
+  storageBackend: CheckpointBackend;
+}
+
+/**
+ * Storage backend for checkpoint file snapshots
+ */
+export type CheckpointBackend =
+  | { type: "files" }
+  | { type: "git"; repoPath: string };
+
+/**
+ * Size limits for files collected into a checkpoint (0 disables a limit)
+ */
+export interface CheckpointFileLimits {
+  maxFileSize: number;
+  maxTotalSize: number;
 }
 
 /**
  * Strategy for automatic checkpoint creation
  */
-export type CheckpointStrategy = 'manual' | 'per_prompt' | 'per_tool_use' | 'smart';
+export type CheckpointStrategy = 'manual' | 'per_prompt' | 'per_tool_use' | 'smart' | 'watch';
 
 /**
  * Result of a checkpoint operation
@@ -318,7 +415,7 @@ export interface CheckpointResult {
  */
 export interface CheckpointDiff {
   fromCheckpointId: string;
-  toCheckpointId: string;
+  toCheckpointId?: string;
   modifiedFiles: FileDiff[];
   addedFiles: string[];
   deletedFiles: string[];
@@ -333,6 +430,116 @@ export interface FileDiff {
   additions: number;
   deletions: number;
   diffContent?: string;
+  isBinary: boolean;
+  hunks: DiffHunk[];
+}
+
+/**
+ * A hunk of a unified diff
+ */
+export interface DiffHunk {
+  oldStart: number;
+  oldLines: number;
+  newStart: number;
+  newLines: number;
+  lines: string[];
+}
+
+/**
+ * Files (and optionally hunks) to restore from a checkpoint
+ */
+export interface RestoreFileSelection {
+  /** Path relative to the project root */
+  path: string;
+  /** Hunk indices from getCheckpointDiff against the working tree; omit to restore the whole file */
+  hunks?: number[];
+}
+
+export type RestoreAction = "create" | "modify" | "delete";
+
+/**
+ * Planned or applied change for a single file
+ */
+export interface RestoreChange {
+  path: string;
+  action: RestoreAction;
+  additions: number;
+  deletions: number;
+  diffContent?: string;
+}
+
+/**
+ * Result of a selective restore
+ */
+export interface RestoreReport {
+  checkpointId: string;
+  dryRun: boolean;
+  changes: RestoreChange[];
+  warnings: string[];
+}
+
+/**
+ * Description of a checkpoint archive
+ */
+export interface ArchiveManifest {
+  formatVersion: number;
+  exportedAt: string;
+  sourceProjectId: string;
+  sourceSessionId: string;
+  sourceProjectPath?: string;
+  /** Parents before children; the last one is the head */
+  checkpoints: Checkpoint[];
+}
+
+/**
+ * Result of exporting a checkpoint archive
+ */
+export interface ExportSummary {
+  archivePath: string;
+  checkpoints: number;
+  files: number;
+  archiveSize: number;
+}
+
+/**
+ * Result of importing a checkpoint archive as a new session
+ */
+export interface ImportSummary {
+  projectId: string;
+  sessionId: string;
+  headCheckpointId: string;
+  checkpoints: number;
+  /** Null when the head checkpoint was not restored into the project */
+  filesRestored: number | null;
+}
+
+/**
+ * Policy deciding which checkpoints are kept; quotas of 0 are unlimited
+ */
+export interface RetentionPolicy {
+  /** Off until a policy is saved */
+  enabled: boolean;
+  keepAllHours: number;
+  keepHourlyHours: number;
+  keepDailyDays: number;
+  maxProjectBytes: number;
+  maxTotalBytes: number;
+}
+
+/**
+ * Outcome of applying the retention policy
+ */
+export interface RetentionReport {
+  sessionsScanned: number;
+  sessionsSkipped: number;
+  /** Git-backed sessions, whose contents live in the project repository and are not pruned */
+  gitSessionsExempt: number;
+  checkpointsRemoved: number;
+  contentFilesRemoved: number;
+  bytesBefore: number;
+  bytesAfter: number;
+  bytesReclaimed: number;
+  warnings: string[];
 }
 
 /**
@@ -700,6 +907,7 @@ export const api = {
    * @param default_task - Optional default task
    * @param model - Optional model (defaults to 'sonnet')
    * @param hooks - Optional hooks configuration as JSON string
+   * @param permissions - Optional permission flags, tool lists and allowed directories
    * @returns Promise resolving to the created agent
    */
   async createAgent(
@@ -708,7 +916,8 @@ export const api = {
     system_prompt: string, 
     default_task?: string, 
     model?: string,
-    hooks?: string
+    hooks?: string,
+    permissions?: AgentPermissions
   ): Promise<Agent> {
     try {
       return await apiCall<Agent>('create_agent', { 
@@ -717,7 +926,8 @@ export const api = {
         systemPrompt: system_prompt,
         defaultTask: default_task,
         model,
-        hooks
+        hooks,
+        ...permissions
       });
     } catch (error) {
       console.error("Failed to create agent:", error);
@@ -734,6 +944,7 @@ export const api = {
    * @param default_task - Optional default task
    * @param model - Optional model
    * @param hooks - Optional hooks configuration as JSON string
+   * @param permissions - Optional permission flags, tool lists and allowed directories
    * @returns Promise resolving to the updated agent
    */
   async updateAgent(
@@ -743,7 +954,8 @@ export const api = {
     system_prompt: string, 
     default_task?: string, 
     model?: string,
-    hooks?: string
+    hooks?: string,
+    permissions?: AgentPermissions
   ): Promise<Agent> {
     try {
       return await apiCall<Agent>('update_agent', { 
@@ -753,7 +965,8 @@ export const api = {
         systemPrompt: system_prompt,
         defaultTask: default_task,
         model,
-        hooks
+        hooks,
+        ...permissions
       });
     } catch (error) {
       console.error("Failed to update agent:", error);
@@ -832,12 +1045,12 @@ export const api = {
   },
 
   /**
-   * Executes an agent
+   * Queues an agent run; it starts once the run queue has a free slot
    * @param agentId - The agent ID to execute
    * @param projectPath - The project path to run the agent in
    * @param task - The task description
    * @param model - Optional model override
-   * @returns Promise resolving to the run ID when execution starts
+   * @returns Promise resolving to the run ID of the queued run
    */
   async executeAgent(agentId: number, projectPath: string, task: string, model?: string): Promise<number> {
     try {
@@ -849,6 +1062,152 @@ export const api = {
     }
   },
 
+  /**
+   * Gets the queued runs, the number of active runs and the queue limits
+   * @returns Promise resolving to the run queue status
+   */
+  async getAgentQueueStatus(): Promise<AgentQueueStatus> {
+    try {
+      return await apiCall<AgentQueueStatus>('get_agent_queue_status');
+    } catch (error) {
+      console.error("Failed to get agent queue status:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the agent run queue concurrency limits
+   * @returns Promise resolving to the limits
+   */
+  async getAgentQueueLimits(): Promise<AgentQueueLimits> {
+    try {
+      return await apiCall<AgentQueueLimits>('get_agent_queue_limits');
+    } catch (error) {
+      console.error("Failed to get agent queue limits:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the agent run queue concurrency limits
+   * @param limits - Global, per-agent default and per-agent override caps
+   */
+  async setAgentQueueLimits(limits: AgentQueueLimits): Promise<void> {
+    try {
+      return await apiCall<void>('set_agent_queue_limits', { limits });
+    } catch (error) {
+      console.error("Failed to set agent queue limits:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists agent schedules
+   * @param agentId - Optional agent ID to filter schedules
+   * @returns Promise resolving to an array of schedules
+   */
+  async listAgentSchedules(agentId?: number): Promise<AgentSchedule[]> {
+    try {
+      return await apiCall<AgentSchedule[]>('list_agent_schedules', { agentId });
+    } catch (error) {
+      console.error("Failed to list agent schedules:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Creates a cron schedule for an agent
+   * @param agentId - The agent to run
+   * @param projectPath - The project path to run it in
+   * @param cron - Five-field cron expression or @daily-style shortcut (local time)
+   * @param task - Optional task, defaults to the agent's default task
+   * @param model - Optional model override
+   * @param missedRunPolicy - What to do with runs missed while the app was closed
+   * @param enabled - Whether the schedule is active (default true)
+   * @returns Promise resolving to the created schedule
+   */
+  async createAgentSchedule(
+    agentId: number,
+    projectPath: string,
+    cron: string,
+    task?: string,
+    model?: string,
+    missedRunPolicy?: MissedRunPolicy,
+    enabled?: boolean
+  ): Promise<AgentSchedule> {
+    try {
+      return await apiCall<AgentSchedule>('create_agent_schedule', {
+        agentId,
+        projectPath,
+        cron,
+        task,
+        model,
+        missedRunPolicy,
+        enabled,
+      });
+    } catch (error) {
+      console.error("Failed to create agent schedule:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Updates an agent schedule; its next run is recomputed from now
+   * @returns Promise resolving to the updated schedule
+   */
+  async updateAgentSchedule(
+    id: number,
+    projectPath: string,
+    cron: string,
+    task: string | undefined,
+    model: string | undefined,
+    missedRunPolicy: MissedRunPolicy,
+    enabled: boolean
+  ): Promise<AgentSchedule> {
+    try {
+      return await apiCall<AgentSchedule>('update_agent_schedule', {
+        id,
+        projectPath,
+        cron,
+        task,
+        model,
+        missedRunPolicy,
+        enabled,
+      });
+    } catch (error) {
+      console.error("Failed to update agent schedule:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Deletes an agent schedule
+   * @param id - The schedule ID
+   */
+  async deleteAgentSchedule(id: number): Promise<void> {
+    try {
+      return await apiCall<void>('delete_agent_schedule', { id });
+    } catch (error) {
+      console.error("Failed to delete agent schedule:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Previews the next occurrences of a cron expression
+   * @param cron - The cron expression
+   * @param count - Number of occurrences (default 5)
+   * @returns Promise resolving to RFC 3339 timestamps
+   */
+  async previewAgentSchedule(cron: string, count?: number): Promise<string[]> {
+    try {
+      return await apiCall<string[]>('preview_agent_schedule', { cron, count });
+    } catch (error) {
+      console.error("Failed to preview agent schedule:", error);
+      throw error;
+    }
+  },
+
   /**
    * Lists agent runs without metrics (basic info only)
    * @param agentId - Optional agent ID to filter runs
@@ -921,7 +1280,7 @@ export const api = {
   },
 
   /**
-   * Kills a running agent session
+   * Kills a running agent session, or cancels it if it is still queued
    * @param runId - The run ID to kill
    * @returns Promise resolving to whether the session was successfully killed
    */
@@ -1159,14 +1518,16 @@ export const api = {
     projectId: string,
     projectPath: string,
     messageIndex?: number,
-    description?: string
+    description?: string,
+    appType?: AppId
   ): Promise<CheckpointResult> {
     return apiCall("create_checkpoint", {
       sessionId,
       projectId,
       projectPath,
       messageIndex,
-      description
+      description,
+      appType
     });
   },
 
@@ -1177,13 +1538,38 @@ export const api = {
     checkpointId: string,
     sessionId: string,
     projectId: string,
-    projectPath: string
+    projectPath: string,
+    appType?: AppId
   ): Promise<CheckpointResult> {
     return apiCall("restore_checkpoint", {
       checkpointId,
       sessionId,
       projectId,
-      projectPath
+      projectPath,
+      appType
+    });
+  },
+
+  /**
+   * Restores selected files or hunks from a checkpoint, optionally as a dry run
+   */
+  async restoreCheckpointFiles(
+    checkpointId: string,
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    files: RestoreFileSelection[] | null,
+    dryRun: boolean,
+    appType?: AppId
+  ): Promise<RestoreReport> {
+    return apiCall("restore_checkpoint_files", {
+      checkpointId,
+      sessionId,
+      projectId,
+      projectPath,
+      files,
+      dryRun,
+      appType
     });
   },
 
@@ -1193,12 +1579,14 @@ export const api = {
   async listCheckpoints(
     sessionId: string,
     projectId: string,
-    projectPath: string
+    projectPath: string,
+    appType?: AppId
   ): Promise<Checkpoint[]> {
     return apiCall("list_checkpoints", {
       sessionId,
       projectId,
-      projectPath
+      projectPath,
+      appType
     });
   },
 
@@ -1211,7 +1599,8 @@ export const api = {
     projectId: string,
     projectPath: string,
     newSessionId: string,
-    description?: string
+    description?: string,
+    appType?: AppId
   ): Promise<CheckpointResult> {
     return apiCall("fork_from_checkpoint", {
       checkpointId,
@@ -1219,7 +1608,8 @@ export const api = {
       projectId,
       projectPath,
       newSessionId,
-      description
+      description,
+      appType
     });
   },
 
@@ -1229,12 +1619,14 @@ export const api = {
   async getSessionTimeline(
     sessionId: string,
     projectId: string,
-    projectPath: string
+    projectPath: string,
+    appType?: AppId
   ): Promise<SessionTimeline> {
     return apiCall("get_session_timeline", {
       sessionId,
       projectId,
-      projectPath
+      projectPath,
+      appType
     });
   },
 
@@ -1246,14 +1638,21 @@ export const api = {
     projectId: string,
     projectPath: string,
     autoCheckpointEnabled: boolean,
-    checkpointStrategy: CheckpointStrategy
+    checkpointStrategy: CheckpointStrategy,
+    fileLimits?: Partial<CheckpointFileLimits>,
+    storageBackend?: CheckpointBackend["type"],
+    appType?: AppId
   ): Promise<void> {
     return apiCall("update_checkpoint_settings", {
       sessionId,
       projectId,
       projectPath,
       autoCheckpointEnabled,
-      checkpointStrategy
+      checkpointStrategy,
+      maxFileSize: fileLimits?.maxFileSize,
+      maxTotalSize: fileLimits?.maxTotalSize,
+      storageBackend,
+      appType
     });
   },
 
@@ -1262,16 +1661,20 @@ export const api = {
    */
   async getCheckpointDiff(
     fromCheckpointId: string,
-    toCheckpointId: string,
+    toCheckpointId: string | null,
     sessionId: string,
-    projectId: string
+    projectId: string,
+    projectPath?: string,
+    appType?: AppId
   ): Promise<CheckpointDiff> {
     try {
       return await apiCall<CheckpointDiff>("get_checkpoint_diff", {
         fromCheckpointId,
         toCheckpointId,
         sessionId,
-        projectId
+        projectId,
+        projectPath,
+        appType
       });
     } catch (error) {
       console.error("Failed to get checkpoint diff:", error);
@@ -1286,14 +1689,16 @@ export const api = {
     sessionId: string,
     projectId: string,
     projectPath: string,
-    message: string
+    message: string,
+    appType?: AppId
   ): Promise<void> {
     try {
       await apiCall("track_checkpoint_message", {
         sessionId,
         projectId,
         projectPath,
-        message
+        message,
+        appType
       });
     } catch (error) {
       console.error("Failed to track checkpoint message:", error);
@@ -1308,14 +1713,16 @@ export const api = {
     sessionId: string,
     projectId: string,
     projectPath: string,
-    message: string
+    message: string,
+    appType?: AppId
   ): Promise<boolean> {
     try {
       return await apiCall<boolean>("check_auto_checkpoint", {
         sessionId,
         projectId,
         projectPath,
-        message
+        message,
+        appType
       });
     } catch (error) {
       console.error("Failed to check auto checkpoint:", error);
@@ -1330,14 +1737,16 @@ export const api = {
     sessionId: string,
     projectId: string,
     projectPath: string,
-    keepCount: number
+    keepCount: number,
+    appType?: AppId
   ): Promise<number> {
     try {
       return await apiCall<number>("cleanup_old_checkpoints", {
         sessionId,
         projectId,
         projectPath,
-        keepCount
+        keepCount,
+        appType
       });
     } catch (error) {
       console.error("Failed to cleanup old checkpoints:", error);
@@ -1345,13 +1754,136 @@ export const api = {
     }
   },
 
+  /**
+   * Exports a checkpoint, or its branch of the timeline, to a portable archive
+   */
+  async exportCheckpointArchive(
+    checkpointId: string,
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    includeAncestors: boolean,
+    outputPath: string,
+    appType?: AppId
+  ): Promise<ExportSummary> {
+    try {
+      return await apiCall<ExportSummary>("export_checkpoint_archive", {
+        checkpointId,
+        sessionId,
+        projectId,
+        projectPath,
+        includeAncestors,
+        outputPath,
+        appType
+      });
+    } catch (error) {
+      console.error("Failed to export checkpoint archive:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Reads the manifest of a checkpoint archive without importing it
+   */
+  async inspectCheckpointArchive(archivePath: string): Promise<ArchiveManifest> {
+    try {
+      return await apiCall<ArchiveManifest>("inspect_checkpoint_archive", { archivePath });
+    } catch (error) {
+      console.error("Failed to inspect checkpoint archive:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Imports a checkpoint archive as a new session of a local project
+   */
+  async importCheckpointArchive(
+    archivePath: string,
+    projectPath: string,
+    restoreFiles: boolean
+  ): Promise<ImportSummary> {
+    try {
+      return await apiCall<ImportSummary>("import_checkpoint_archive", {
+        archivePath,
+        projectPath,
+        restoreFiles
+      });
+    } catch (error) {
+      console.error("Failed to import checkpoint archive:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Pins or unpins a checkpoint so retention never removes it
+   */
+  async setCheckpointPinned(
+    checkpointId: string,
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    pinned: boolean,
+    appType?: AppId
+  ): Promise<Checkpoint> {
+    try {
+      return await apiCall<Checkpoint>("set_checkpoint_pinned", {
+        checkpointId,
+        sessionId,
+        projectId,
+        projectPath,
+        pinned,
+        appType
+      });
+    } catch (error) {
+      console.error("Failed to set checkpoint pinned:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the checkpoint retention policy
+   */
+  async getCheckpointRetentionPolicy(): Promise<RetentionPolicy> {
+    try {
+      return await apiCall<RetentionPolicy>("get_checkpoint_retention_policy");
+    } catch (error) {
+      console.error("Failed to get checkpoint retention policy:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the checkpoint retention policy
+   */
+  async updateCheckpointRetentionPolicy(policy: RetentionPolicy): Promise<void> {
+    try {
+      await apiCall("update_checkpoint_retention_policy", { policy });
+    } catch (error) {
+      console.error("Failed to update checkpoint retention policy:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Applies the retention policy now and reports the reclaimed space
+   */
+  async applyCheckpointRetention(): Promise<RetentionReport> {
+    try {
+      return await apiCall<RetentionReport>("apply_checkpoint_retention");
+    } catch (error) {
+      console.error("Failed to apply checkpoint retention:", error);
+      throw error;
+    }
+  },
+
   /**
    * Gets checkpoint settings for a session
    */
   async getCheckpointSettings(
     sessionId: string,
     projectId: string,
-    projectPath: string
+    projectPath: string,
+    appType?: AppId
   ): Promise<{
     auto_checkpoint_enabled: boolean;
     checkpoint_strategy: CheckpointStrategy;
@@ -1362,7 +1894,8 @@ export const api = {
       return await apiCall("get_checkpoint_settings", {
         sessionId,
         projectId,
-        projectPath
+        projectPath,
+        appType
       });
     } catch (error) {
       console.error("Failed to get checkpoint settings:", error);
@@ -1389,9 +1922,10 @@ export const api = {
     sessionId: string, 
     projectId: string, 
     projectPath: string, 
-    messages: string[]
+    messages: string[],
+    appType?: AppId
   ): Promise<void> =>
-    apiCall("track_session_messages", { sessionId, projectId, projectPath, messages }),
+    apiCall("track_session_messages", { sessionId, projectId, projectPath, messages, appType }),
 
   /**
    * Adds a new MCP server
@@ -96,6 +96,10 @@ export interface ProviderMeta {
   isPartner?: boolean;
   // 合作伙伴促销 key（用于后端识别 PackyCode 等）
   partnerPromotionKey?: string;
+  // 结算币种（ISO 4217，如 CNY；未设置时为 USD）
+  billingCurrency?: string;
+  // 固定结算汇率（1 USD 折合多少结算币种；未设置时按汇率表换算）
+  billingRate?: string;
 }
 
 // 应用设置类型（用于设置对话框与 Tauri API）
@@ -6,6 +6,35 @@ export interface ProxyConfig {
   request_timeout: number;
   enable_logging: boolean;
   live_takeover_active?: boolean;
+  max_concurrent_requests?: number;
+  priority_lanes?: PriorityLane[];
+  /** Gemini OAuth token 端点，为空时使用凭证中的 token_uri 或 Google 默认端点 */
+  gemini_token_endpoint?: string | null;
+}
+
+// 优先级通道
+export type LaneMatcher =
+  | { type: "route_prefix"; prefix: string }
+  | { type: "header"; name: string; value?: string | null }
+  | { type: "user_agent"; contains: string }
+  | { type: "virtual_key"; key: string };
+
+export interface PriorityLane {
+  name: string;
+  weight: number;
+  max_concurrency: number;
+  matchers: LaneMatcher[];
+}
+
+export interface LaneStatus {
+  name: string;
+  in_flight: number;
+  queued: number;
+  max_concurrency: number;
+  dispatched: number;
+  avg_wait_ms: number;
+  max_wait_ms: number;
+  oldest_wait_ms: number;
 }
 
 export interface ProxyStatus {
@@ -24,6 +53,8 @@ export interface ProxyStatus {
   last_error: string | null;
   failover_count: number;
   active_targets?: ActiveTarget[];
+  queued_requests?: number;
+  lanes?: LaneStatus[];
 }
 
 export interface ActiveTarget {
@@ -38,6 +69,14 @@ export interface ProxyServerInfo {
   started_at: string;
 }
 
+// 影子流量配置（每个应用一条）
+export interface ShadowConfig {
+  app_type: string;
+  provider_id: string;
+  sample_percent: number;
+  enabled: boolean;
+}
+
 export interface ProviderHealth {
   provider_id: string;
   app_type: string;
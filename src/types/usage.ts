@@ -7,6 +7,18 @@ export interface TokenUsage {
   cacheCreationTokens: number;
 }
 
+export type UsageSource = "proxy" | "session_log";
+
+export interface SessionUsageImportResult {
+  filesScanned: number;
+  filesUpdated: number;
+  imported: number;
+  duplicates: number;
+  /** Turns already recorded by the proxy, skipped to avoid double counting */
+  proxied: number;
+  unpriced: number;
+}
+
 export interface RequestLog {
   requestId: string;
   providerId: string;
@@ -28,6 +40,9 @@ export interface RequestLog {
   durationMs?: number;
   statusCode: number;
   errorMessage?: string;
+  sessionId?: string;
+  projectPath?: string;
+  source: UsageSource;
   createdAt: number;
 }
 
@@ -47,9 +62,72 @@ export interface ModelPricing {
   cacheCreationCostPerMillion: string;
 }
 
+export interface ModelPricingRule {
+  id?: number;
+  modelId: string;
+  minPromptTokens: number;
+  effectiveFrom?: number;
+  effectiveTo?: number;
+  timeStart?: string;
+  timeEnd?: string;
+  inputCostPerMillion: string;
+  outputCostPerMillion: string;
+  cacheReadCostPerMillion: string;
+  cacheCreationCostPerMillion: string;
+}
+
+export interface RecalculateCostsResult {
+  scanned: number;
+  updated: number;
+  unpriced: number;
+}
+
+export type PricingFormat = "litellm" | "csv" | "native";
+
+export type PricingSource =
+  | { type: "file"; path: string }
+  | { type: "url"; url: string }
+  | { type: "configured" };
+
+export interface ModelAlias {
+  alias: string;
+  modelId: string;
+}
+
+export interface PricingCatalog {
+  models: ModelPricing[];
+  aliases: ModelAlias[];
+}
+
+export interface PricingChange {
+  before: ModelPricing;
+  after: ModelPricing;
+}
+
+export interface PricingImportPreview {
+  format: PricingFormat;
+  added: ModelPricing[];
+  changed: PricingChange[];
+  unchanged: number;
+  aliases: ModelAlias[];
+  skipped: number;
+}
+
+export interface PricingImportResult {
+  upserted: number;
+  aliases: number;
+}
+
+export interface ExchangeRate {
+  currency: string;
+  effectiveDate: string;
+  rate: string;
+}
+
 export interface UsageSummary {
   totalRequests: number;
   totalCost: string;
+  currency: string;
   totalInputTokens: number;
   totalOutputTokens: number;
   totalCacheCreationTokens: number;
@@ -61,6 +139,7 @@ export interface DailyStats {
   date: string;
   requestCount: number;
   totalCost: string;
+  currency: string;
   totalTokens: number;
   totalInputTokens: number;
   totalOutputTokens: number;
@@ -74,8 +153,10 @@ export interface ProviderStats {
   requestCount: number;
   totalTokens: number;
   totalCost: string;
+  currency: string;
   successRate: number;
   avgLatencyMs: number;
+  isShadow?: boolean;
 }
 
 export interface ModelStats {
@@ -86,6 +167,80 @@ export interface ModelStats {
   avgCostPerRequest: string;
 }
 
+export type StatsGroupBy = "provider" | "model";
+
+export interface Percentiles {
+  samples: number;
+  p50: number;
+  p90: number;
+  p99: number;
+}
+
+export interface LatencyStats {
+  key: string;
+  name?: string;
+  appType?: string;
+  requestCount: number;
+  latencyMs?: Percentiles;
+  firstTokenMs?: Percentiles;
+  outputTokensPerSecond?: Percentiles;
+}
+
+export type FailureCategory =
+  | "rate_limit"
+  | "auth"
+  | "client_error"
+  | "overloaded"
+  | "timeout"
+  | "network"
+  | "server_error"
+  | "other";
+
+export interface HeatmapCell {
+  weekday: number;
+  hour: number;
+  requestCount: number;
+  errorCount: number;
+}
+
+export interface ErrorBreakdown {
+  totalRequests: number;
+  errorCount: number;
+  errorRate: number;
+  byStatus: { statusCode: number; count: number }[];
+  byCategory: { category: FailureCategory; count: number }[];
+  heatmap: HeatmapCell[];
+}
+
+export interface ProjectStats {
+  projectPath?: string;
+  sessionCount: number;
+  requestCount: number;
+  totalInputTokens: number;
+  totalOutputTokens: number;
+  totalCacheCreationTokens: number;
+  totalCacheReadTokens: number;
+  totalCost: string;
+  currency: string;
+  firstRequestAt: number;
+  lastRequestAt: number;
+}
+
+export interface SessionStats {
+  sessionId: string;
+  projectPath?: string;
+  requestCount: number;
+  totalInputTokens: number;
+  totalOutputTokens: number;
+  totalCacheCreationTokens: number;
+  totalCacheReadTokens: number;
+  totalCost: string;
+  currency: string;
+  models: string[];
+  firstRequestAt: number;
+  lastRequestAt: number;
+}
+
 export interface LogFilters {
   appType?: string;
   providerName?: string;
@@ -93,6 +248,9 @@ export interface LogFilters {
   statusCode?: number;
   startDate?: number;
   endDate?: number;
+  sessionId?: string;
+  projectPath?: string;
+  source?: UsageSource;
 }
 
 export interface ProviderLimitStatus {
@@ -112,3 +270,130 @@ export interface StatsFilters {
   providerId?: string;
   appType?: string;
 }
+
+export type ExportFormat = "csv" | "jsonl" | "parquet";
+
+export interface ExportResult {
+  path: string;
+  rows: number;
+}
+
+export type ReportFrequency = "daily" | "weekly";
+
+export interface UsageReportConfig {
+  daily: boolean;
+  weekly: boolean;
+  outputDir?: string;
+  currency?: string;
+  lastDailyPeriod?: string;
+  lastWeeklyPeriod?: string;
+}
+
+export interface LogRetentionConfig {
+  enabled: boolean;
+  retentionDays: number;
+  lastRunAt?: number;
+}
+
+export interface LogMaintenanceResult {
+  rolledUpRows: number;
+  deletedRows: number;
+  freedBytes: number;
+  runAt: number;
+}
+
+export interface DatabaseSizeInfo {
+  totalBytes: number;
+  freeBytes: number;
+  requestLogRows: number;
+  shadowLogRows: number;
+  rollupRows: number;
+  oldestRequestLogAt?: number;
+}
+
+export interface SpendRateRule {
+  enabled: boolean;
+  windowMinutes: number;
+  baselineHours: number;
+  multiplier: number;
+  minSpendUsd: number;
+}
+
+export interface SessionBurstRule {
+  enabled: boolean;
+  windowMinutes: number;
+  maxRequests: number;
+}
+
+export interface RequestLoopRule {
+  enabled: boolean;
+  windowMinutes: number;
+  maxRepeats: number;
+}
+
+export interface AnomalyRules {
+  enabled: boolean;
+  spendRate: SpendRateRule;
+  sessionBurst: SessionBurstRule;
+  requestLoop: RequestLoopRule;
+  autoPause: boolean;
+  cooldownMinutes: number;
+}
+
+export type AnomalyKind = "spend_rate" | "session_burst" | "request_loop";
+
+export interface UsageAlert {
+  id: number;
+  kind: AnomalyKind;
+  appType?: string;
+  providerId?: string;
+  sessionId?: string;
+  message: string;
+  observed: number;
+  threshold: number;
+  paused: boolean;
+  acknowledged: boolean;
+  createdAt: number;
+}
+
+export type PauseKind = "provider" | "session";
+
+export interface UsagePause {
+  kind: PauseKind;
+  target: string;
+  alertId?: number;
+  reason: string;
+  createdAt: number;
+}
+
+export interface BalanceForecast {
+  remaining: number;
+  unit?: string;
+  daysRemaining?: number;
+  exhaustedOn?: string;
+}
+
+export interface SpendForecast {
+  key: string;
+  appType?: string;
+  providerId?: string;
+  providerName?: string;
+  monthToDate: string;
+  projectedMonthEnd: string;
+  projectedLow: string;
+  projectedHigh: string;
+  dailyAverage: string;
+  trendPerDay: string;
+  monthlyLimit?: string;
+  limitReachedOn?: string;
+  balance?: BalanceForecast;
+}
+
+export interface BudgetForecast {
+  currency: string;
+  today: string;
+  monthEnd: string;
+  historyDays: number;
+  total: SpendForecast;
+  providers: SpendForecast[];
+}